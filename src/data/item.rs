@@ -0,0 +1,144 @@
+use crate::data::nx::{self, DataCache, NxFiles};
+use crate::game::inventory::{EquipStats, InventoryType, Item};
+use ::nx::{GenericNode, Node};
+use std::sync::Arc;
+
+const EQUIP_CATEGORIES: [&str; 15] = [
+    "Accessory", "Cap", "Cape", "Coat", "Face", "Glove", "Hair", "Longcoat", "Pants", "PetEquip",
+    "Ring", "Shield", "Shoes", "TamingMob", "Weapon",
+];
+
+const DEFAULT_SLOT_MAX: i16 = 100;
+
+pub struct EquipData {
+    pub required_level: i16,
+    /// Bit mask of job branches, 0 for every job and -1 for beginners only.
+    pub required_job: i16,
+    pub required_strength: i16,
+    pub required_dexterity: i16,
+    pub required_intelligence: i16,
+    pub required_luck: i16,
+    pub required_fame: i16,
+    pub stats: EquipStats,
+}
+
+pub struct ItemData {
+    pub item_id: i32,
+    pub slot_max: i16,
+    /// Mesos NPC shops pay for one of the item.
+    pub price: i32,
+    /// Mesos NPC shops charge or pay for each star or bullet of a rechargeable stack.
+    pub unit_price: f64,
+    pub untradeable: bool,
+    pub only_one: bool,
+    pub quest: bool,
+    pub cash: bool,
+    pub equip: Option<EquipData>,
+}
+
+static ITEM_DATA_CACHE: DataCache<ItemData> = DataCache::new();
+
+impl ItemData {
+    /// Looks the item up in the NX data, caching the result including unknown ids.
+    pub fn get(item_id: i32) -> Option<Arc<ItemData>> {
+        ITEM_DATA_CACHE.get_or_load(item_id, Self::load)
+    }
+
+    fn info_node(item_id: i32) -> Option<Node<'static>> {
+        let image_name = nx::image_name(item_id);
+
+        match InventoryType::of_item(item_id)? {
+            InventoryType::Equip => {
+                let character = NxFiles::root("Character")?;
+                EQUIP_CATEGORIES
+                    .iter()
+                    .find_map(|category| character.get(category).get(&image_name))
+                    .get("info")
+            }
+            inventory_type => {
+                let directory = match inventory_type {
+                    InventoryType::Use => "Consume",
+                    InventoryType::Setup => "Install",
+                    InventoryType::Etc => "Etc",
+                    _ => "Cash",
+                };
+                let item = NxFiles::root("Item")?;
+                match item_id / 10000 {
+                    500 => item.get("Pet").get(&format!("{}.img", item_id)).get("info"),
+                    prefix => item
+                        .get(directory)
+                        .get(&format!("{:04}.img", prefix))
+                        .get(&format!("{:08}", item_id))
+                        .get("info"),
+                }
+            }
+        }
+    }
+
+    fn load(item_id: i32) -> Option<ItemData> {
+        let info = Self::info_node(item_id)?;
+        let stat = |name: &str| nx::integer_or(info.get(name), 0) as i16;
+        let flag = |name: &str| nx::integer_or(info.get(name), 0) != 0;
+        let is_equip = InventoryType::of_item(item_id) == Some(InventoryType::Equip);
+
+        Some(ItemData {
+            item_id,
+            slot_max: match is_equip {
+                true => 1,
+                false => nx::integer_or(info.get("slotMax"), DEFAULT_SLOT_MAX as i64) as i16,
+            },
+            price: nx::integer_or(info.get("price"), 0) as i32,
+            unit_price: info.get("unitPrice").float().unwrap_or(0.0),
+            untradeable: flag("tradeBlock"),
+            only_one: flag("only"),
+            quest: flag("quest"),
+            cash: flag("cash"),
+            equip: match is_equip {
+                true => Some(EquipData {
+                    required_level: stat("reqLevel"),
+                    required_job: stat("reqJob"),
+                    required_strength: stat("reqSTR"),
+                    required_dexterity: stat("reqDEX"),
+                    required_intelligence: stat("reqINT"),
+                    required_luck: stat("reqLUK"),
+                    required_fame: stat("reqPOP"),
+                    stats: EquipStats {
+                        upgrade_slots: stat("tuc"),
+                        upgrades: 0,
+                        strength: stat("incSTR"),
+                        dexterity: stat("incDEX"),
+                        intelligence: stat("incINT"),
+                        luck: stat("incLUK"),
+                        hp: stat("incMHP"),
+                        mp: stat("incMMP"),
+                        weapon_attack: stat("incPAD"),
+                        magic_attack: stat("incMAD"),
+                        weapon_defense: stat("incPDD"),
+                        magic_defense: stat("incMDD"),
+                        accuracy: stat("incACC"),
+                        avoidability: stat("incEVA"),
+                        hands: stat("incCraft"),
+                        speed: stat("incSpeed"),
+                        jump: stat("incJump"),
+                    },
+                }),
+                false => None,
+            },
+        })
+    }
+
+    /// A new item of this kind, equips getting their base stats.
+    pub fn create_item(&self, quantity: i16) -> Item {
+        Item {
+            item_id: self.item_id,
+            quantity: match &self.equip {
+                Some(_) => 1,
+                None => quantity,
+            },
+            owner: String::new(),
+            flags: 0,
+            expiration_date: None,
+            equip: self.equip.as_ref().map(|equip| equip.stats.clone()),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::data::nx::{self, DataCache, NxFiles};
+use ::nx::{GenericNode, Node};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A mob placed in the map data, respawned after it dies.
+pub struct MobSpawn {
+    pub mob_id: i32,
+    pub position: (i16, i16),
+    pub foothold: i16,
+    pub flipped: bool,
+    /// Extra time before the mob comes back, mostly for bosses; `None` if it never does.
+    pub respawn_delay: Option<Duration>,
+}
+
+/// An NPC placed in the map data, which walks between `range`.
+pub struct NpcSpawn {
+    pub npc_id: i32,
+    pub position: (i16, i16),
+    pub foothold: i16,
+    pub flipped: bool,
+    pub range: (i16, i16),
+}
+
+/// A reactor placed in the map data, respawned after it was destroyed.
+pub struct ReactorSpawn {
+    pub reactor_id: i32,
+    pub position: (i16, i16),
+    pub flipped: bool,
+    /// How long the reactor stays destroyed; `None` if it never comes back.
+    pub respawn_delay: Option<Duration>,
+}
+
+/// A portal placed in the map data.
+pub struct PortalData {
+    /// Index of the portal, which characters entering the map are placed at.
+    pub id: i16,
+    pub name: String,
+    pub position: (i16, i16),
+    /// The map the portal leads to, if any.
+    pub target_map_id: Option<i32>,
+    /// Name of the portal of the target map the character comes out of.
+    pub target_name: String,
+    /// The script run when the portal is used, instead of following its target.
+    pub script: Option<String>,
+}
+
+/// Map id the data uses for no map, such as the return map of maps that do not send dead
+/// players elsewhere.
+const NO_MAP: i32 = 999999999;
+
+pub struct MapData {
+    pub mob_spawns: Vec<MobSpawn>,
+    pub npc_spawns: Vec<NpcSpawn>,
+    pub reactor_spawns: Vec<ReactorSpawn>,
+    /// Where players respawn after dying in this map; `None` to respawn in the map itself.
+    pub return_map_id: Option<i32>,
+    /// Players do not lose EXP when they die in towns.
+    pub town: bool,
+    pub portals: Vec<PortalData>,
+    /// The script run for every character entering the map.
+    pub on_user_enter: Option<String>,
+    /// The script run for a character entering the map while nobody else is in it.
+    pub on_first_user_enter: Option<String>,
+}
+
+/// Reads a script name, which the data leaves empty for no script.
+fn script_name(node: Option<Node>) -> Option<String> {
+    match node.string() {
+        Some(name) if !name.is_empty() => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+static MAP_DATA_CACHE: DataCache<MapData> = DataCache::new();
+
+impl MapData {
+    pub fn get(map_id: i32) -> Option<Arc<MapData>> {
+        MAP_DATA_CACHE.get_or_load(map_id, Self::load)
+    }
+
+    fn image(map_id: i32) -> Option<Node<'static>> {
+        NxFiles::root("Map")?
+            .get("Map")
+            .get(&format!("Map{}", map_id / 100000000))
+            .get(&nx::image_name(map_id))
+    }
+
+    fn load(map_id: i32) -> Option<MapData> {
+        let image = Self::image(map_id)?;
+
+        let mob_spawns = match image.get("life") {
+            Some(life) => life
+                .iter()
+                .filter(|entry| entry.get("type").string() == Some("m"))
+                .filter(|entry| nx::integer_or(entry.get("hide"), 0) == 0)
+                .filter_map(|entry| {
+                    let mob_time = nx::integer_or(entry.get("mobTime"), 0);
+                    Some(MobSpawn {
+                        mob_id: nx::integer(entry.get("id"))? as i32,
+                        position: (
+                            nx::integer_or(entry.get("x"), 0) as i16,
+                            nx::integer_or(entry.get("cy"), 0) as i16,
+                        ),
+                        foothold: nx::integer_or(entry.get("fh"), 0) as i16,
+                        flipped: nx::integer_or(entry.get("f"), 0) != 0,
+                        respawn_delay: match mob_time {
+                            mob_time if mob_time < 0 => None,
+                            mob_time => Some(Duration::from_secs(mob_time as u64)),
+                        },
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let npc_spawns = match image.get("life") {
+            Some(life) => life
+                .iter()
+                .filter(|entry| entry.get("type").string() == Some("n"))
+                .filter(|entry| nx::integer_or(entry.get("hide"), 0) == 0)
+                .filter_map(|entry| {
+                    Some(NpcSpawn {
+                        npc_id: nx::integer(entry.get("id"))? as i32,
+                        position: (
+                            nx::integer_or(entry.get("x"), 0) as i16,
+                            nx::integer_or(entry.get("cy"), 0) as i16,
+                        ),
+                        foothold: nx::integer_or(entry.get("fh"), 0) as i16,
+                        flipped: nx::integer_or(entry.get("f"), 0) != 0,
+                        range: (
+                            nx::integer_or(entry.get("rx0"), 0) as i16,
+                            nx::integer_or(entry.get("rx1"), 0) as i16,
+                        ),
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let reactor_spawns = match image.get("reactor") {
+            Some(reactors) => reactors
+                .iter()
+                .filter_map(|entry| {
+                    let reactor_time = nx::integer_or(entry.get("reactorTime"), 0);
+                    Some(ReactorSpawn {
+                        reactor_id: nx::integer(entry.get("id"))? as i32,
+                        position: (
+                            nx::integer_or(entry.get("x"), 0) as i16,
+                            nx::integer_or(entry.get("y"), 0) as i16,
+                        ),
+                        flipped: nx::integer_or(entry.get("f"), 0) != 0,
+                        respawn_delay: match reactor_time {
+                            reactor_time if reactor_time <= 0 => None,
+                            reactor_time => Some(Duration::from_secs(reactor_time as u64)),
+                        },
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let portals = match image.get("portal") {
+            Some(portals) => portals
+                .iter()
+                .filter_map(|portal| {
+                    Some(PortalData {
+                        id: portal.name().parse::<i16>().ok()?,
+                        name: portal.get("pn").string().unwrap_or_default().to_string(),
+                        position: (
+                            nx::integer_or(portal.get("x"), 0) as i16,
+                            nx::integer_or(portal.get("y"), 0) as i16,
+                        ),
+                        target_map_id: match nx::integer(portal.get("tm")) {
+                            Some(map_id) if map_id != NO_MAP as i64 => Some(map_id as i32),
+                            _ => None,
+                        },
+                        target_name: portal.get("tn").string().unwrap_or_default().to_string(),
+                        script: script_name(portal.get("script")),
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let info = image.get("info");
+        let return_map_id = match nx::integer(info.get("returnMap")) {
+            Some(return_map_id) if return_map_id != NO_MAP as i64 => Some(return_map_id as i32),
+            _ => None,
+        };
+
+        Some(MapData {
+            mob_spawns,
+            npc_spawns,
+            reactor_spawns,
+            return_map_id,
+            town: nx::integer_or(info.get("town"), 0) != 0,
+            portals,
+            on_user_enter: script_name(info.get("onUserEnter")),
+            on_first_user_enter: script_name(info.get("onFirstUserEnter")),
+        })
+    }
+
+    pub fn portal(&self, name: &str) -> Option<&PortalData> {
+        self.portals.iter().find(|portal| portal.name == name)
+    }
+}
@@ -0,0 +1,33 @@
+use crate::data::nx::{self, DataCache, NxFiles};
+use ::nx::GenericNode;
+use std::sync::Arc;
+
+pub struct MobData {
+    pub mob_id: i32,
+    pub level: i16,
+    pub max_hp: i32,
+    pub exp: i32,
+    pub max_mp: i32,
+}
+
+static MOB_DATA_CACHE: DataCache<MobData> = DataCache::new();
+
+impl MobData {
+    pub fn get(mob_id: i32) -> Option<Arc<MobData>> {
+        MOB_DATA_CACHE.get_or_load(mob_id, Self::load)
+    }
+
+    fn load(mob_id: i32) -> Option<MobData> {
+        let info = NxFiles::root("Mob")?
+            .get(&format!("{:07}.img", mob_id))
+            .get("info")?;
+
+        Some(MobData {
+            mob_id,
+            level: nx::integer_or(info.get("level"), 1) as i16,
+            max_hp: nx::integer_or(info.get("maxHP"), 1) as i32,
+            exp: nx::integer_or(info.get("exp"), 0) as i32,
+            max_mp: nx::integer_or(info.get("maxMP"), 0) as i32,
+        })
+    }
+}
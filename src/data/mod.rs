@@ -0,0 +1,8 @@
+pub mod item;
+pub mod map;
+pub mod mob;
+pub mod npc;
+pub mod nx;
+pub mod quest;
+pub mod reactor;
+pub mod skill;
@@ -0,0 +1,35 @@
+use crate::data::nx::{self, DataCache, NxFiles};
+use ::nx::GenericNode;
+use std::sync::Arc;
+
+/// What a storage keeper charges for each item stored or taken.
+#[derive(Clone, Copy)]
+pub struct StorageFees {
+    pub store: i32,
+    pub take: i32,
+}
+
+pub struct NpcData {
+    /// The fees of the storage the NPC opens, for storage keepers.
+    pub storage_fees: Option<StorageFees>,
+}
+
+static NPC_DATA_CACHE: DataCache<NpcData> = DataCache::new();
+
+impl NpcData {
+    pub fn get(npc_id: i32) -> Option<Arc<NpcData>> {
+        NPC_DATA_CACHE.get_or_load(npc_id, Self::load)
+    }
+
+    fn load(npc_id: i32) -> Option<NpcData> {
+        let info = NxFiles::root("Npc")?.get(&format!("{:07}.img", npc_id))?.get("info");
+
+        // Storage keepers are the NPCs with a fee for storing items, which may be free to take.
+        Some(NpcData {
+            storage_fees: nx::integer(info.get("trunkPut")).map(|store| StorageFees {
+                store: store as i32,
+                take: nx::integer_or(info.get("trunkGet"), 0) as i32,
+            }),
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use log::{info, warn};
+use nx::{GenericNode, Node};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// The NX files the channel server reads game data from.
+const NX_FILE_NAMES: [&str; 10] = [
+    "Character", "Etc", "Item", "Map", "Mob", "Npc", "Quest", "Reactor", "Skill", "String",
+];
+
+pub struct NxFiles {
+    files: HashMap<&'static str, nx::File>,
+}
+
+static NX_FILES_INSTANCE: OnceCell<NxFiles> = OnceCell::new();
+
+impl NxFiles {
+    /// Memory maps every known NX file found in `directory`. Missing files are only warned
+    /// about, so lookups into them simply find nothing.
+    pub fn init(directory: &Path) -> Result<(), Box<dyn Error>> {
+        let mut files = HashMap::new();
+
+        for file_name in NX_FILE_NAMES {
+            let path = directory.join(format!("{}.nx", file_name));
+            if !path.exists() {
+                warn!("NX file {} was not found", path.display());
+                continue;
+            }
+
+            // The files are produced by trusted conversion tools and never modified while mapped.
+            match unsafe { nx::File::open(&path) } {
+                Ok(file) => {
+                    info!("loaded {} ({} nodes)", path.display(), file.node_count());
+                    files.insert(file_name, file);
+                }
+                Err(error) => return Err(format!("Unable to open {} [{}]", path.display(), error).into()),
+            }
+        }
+
+        match NX_FILES_INSTANCE.set(NxFiles { files }) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("NX files already initialized".into()),
+        }
+    }
+
+    pub fn root(file_name: &str) -> Option<Node<'static>> {
+        NX_FILES_INSTANCE.get()?.files.get(file_name).map(|file| file.root())
+    }
+}
+
+/// Reads an integer value, which some converters store as a string.
+pub fn integer(node: Option<Node>) -> Option<i64> {
+    match node.integer() {
+        Some(value) => Some(value),
+        None => node.string()?.parse::<i64>().ok(),
+    }
+}
+
+pub fn integer_or(node: Option<Node>, default: i64) -> i64 {
+    integer(node).unwrap_or(default)
+}
+
+/// Name of the image node holding the data of `id`, such as `01302000.img`.
+pub fn image_name(id: i32) -> String {
+    format!("{:08}.img", id)
+}
+
+/// Data loaded from the NX files on first use, keyed by id. Unknown ids are cached as well, so
+/// repeated lookups of them do not walk the files again.
+pub struct DataCache<T> {
+    entries: OnceCell<RwLock<HashMap<i32, Option<Arc<T>>>>>,
+}
+
+impl<T> DataCache<T> {
+    pub const fn new() -> DataCache<T> {
+        DataCache {
+            entries: OnceCell::new(),
+        }
+    }
+
+    pub fn get_or_load(&self, id: i32, load: impl FnOnce(i32) -> Option<T>) -> Option<Arc<T>> {
+        let entries = self.entries.get_or_init(|| RwLock::new(HashMap::new()));
+
+        if let Ok(guard) = entries.read() {
+            if let Some(data) = guard.get(&id) {
+                return data.clone();
+            }
+        }
+
+        let data = load(id).map(Arc::new);
+        if let Ok(mut guard) = entries.write() {
+            guard.insert(id, data.clone());
+        }
+
+        data
+    }
+}
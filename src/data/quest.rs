@@ -0,0 +1,148 @@
+use crate::data::nx::{self, DataCache, NxFiles};
+use ::nx::{GenericNode, Node};
+use std::sync::Arc;
+
+/// What a character needs to start or complete a quest.
+#[derive(Default)]
+pub struct QuestRequirements {
+    /// The NPC the quest is started at or handed in to.
+    pub npc_id: Option<i32>,
+    pub min_level: Option<i16>,
+    pub max_level: Option<i16>,
+    /// Jobs allowed to take the quest; empty for every job.
+    pub jobs: Vec<i16>,
+    /// Item ids and the quantity of them that must be owned.
+    pub items: Vec<(i32, i32)>,
+    /// Mob ids and how many of them must be killed, in the order the quest record counts them.
+    pub mobs: Vec<(i32, i32)>,
+    /// Quest ids and the state they must be in.
+    pub quests: Vec<(i32, u8)>,
+    /// The script run instead of the regular dialog, for scripted quests.
+    pub script: Option<String>,
+}
+
+/// An item given by a quest, or taken when `count` is negative.
+pub struct QuestRewardItem {
+    pub item_id: i32,
+    pub count: i32,
+    /// Weight of the item when only one of the weighted items is given.
+    pub prop: i32,
+    /// 0 for male, 1 for female and 2 for both.
+    pub gender: i16,
+}
+
+#[derive(Default)]
+pub struct QuestRewards {
+    pub exp: i32,
+    pub meso: i32,
+    pub fame: i16,
+    pub items: Vec<QuestRewardItem>,
+    /// The quest started right after this one is completed.
+    pub next_quest: Option<i32>,
+}
+
+pub struct QuestData {
+    pub start: QuestRequirements,
+    pub complete: QuestRequirements,
+    pub start_rewards: QuestRewards,
+    pub complete_rewards: QuestRewards,
+}
+
+/// Children of `node` with an `id` and an amount under `amount_name`.
+fn id_amounts(node: Option<Node>, amount_name: &str) -> Vec<(i32, i32)> {
+    match node {
+        Some(node) => node
+            .iter()
+            .filter_map(|entry| {
+                Some((
+                    nx::integer(entry.get("id"))? as i32,
+                    nx::integer_or(entry.get(amount_name), 0) as i32,
+                ))
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+static QUEST_DATA_CACHE: DataCache<QuestData> = DataCache::new();
+
+impl QuestData {
+    pub fn get(quest_id: i32) -> Option<Arc<QuestData>> {
+        QUEST_DATA_CACHE.get_or_load(quest_id, Self::load)
+    }
+
+    fn node(image: &str, quest_id: i32) -> Option<Node<'static>> {
+        NxFiles::root("Quest")?.get(image).get(&quest_id.to_string())
+    }
+
+    fn requirements(node: Option<Node>, script_name: &str) -> QuestRequirements {
+        let node = match node {
+            Some(node) => node,
+            None => return QuestRequirements::default(),
+        };
+
+        QuestRequirements {
+            npc_id: nx::integer(node.get("npc")).map(|npc_id| npc_id as i32),
+            min_level: nx::integer(node.get("lvmin")).map(|level| level as i16),
+            max_level: nx::integer(node.get("lvmax")).map(|level| level as i16),
+            jobs: match node.get("job") {
+                Some(jobs) => jobs
+                    .iter()
+                    .filter_map(|job| nx::integer(Some(job)))
+                    .map(|job| job as i16)
+                    .collect(),
+                None => Vec::new(),
+            },
+            items: id_amounts(node.get("item"), "count"),
+            mobs: id_amounts(node.get("mob"), "count"),
+            quests: id_amounts(node.get("quest"), "state")
+                .into_iter()
+                .map(|(quest_id, state)| (quest_id, state as u8))
+                .collect(),
+            script: node.get(script_name).string().map(|script| script.to_string()),
+        }
+    }
+
+    fn rewards(node: Option<Node>) -> QuestRewards {
+        let node = match node {
+            Some(node) => node,
+            None => return QuestRewards::default(),
+        };
+
+        QuestRewards {
+            exp: nx::integer_or(node.get("exp"), 0) as i32,
+            meso: nx::integer_or(node.get("money"), 0) as i32,
+            fame: nx::integer_or(node.get("pop"), 0) as i16,
+            items: match node.get("item") {
+                Some(items) => items
+                    .iter()
+                    .filter_map(|item| {
+                        Some(QuestRewardItem {
+                            item_id: nx::integer(item.get("id"))? as i32,
+                            count: nx::integer_or(item.get("count"), 1) as i32,
+                            prop: nx::integer_or(item.get("prop"), 0) as i32,
+                            gender: nx::integer_or(item.get("gender"), 2) as i16,
+                        })
+                    })
+                    .collect(),
+                None => Vec::new(),
+            },
+            next_quest: nx::integer(node.get("nextQuest")).map(|quest_id| quest_id as i32),
+        }
+    }
+
+    fn load(quest_id: i32) -> Option<QuestData> {
+        let check = Self::node("Check.img", quest_id);
+        let act = Self::node("Act.img", quest_id);
+        if check.is_none() && act.is_none() {
+            return None;
+        }
+
+        Some(QuestData {
+            start: Self::requirements(check.get("0"), "startscript"),
+            complete: Self::requirements(check.get("1"), "endscript"),
+            start_rewards: Self::rewards(act.get("0")),
+            complete_rewards: Self::rewards(act.get("1")),
+        })
+    }
+}
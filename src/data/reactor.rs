@@ -0,0 +1,139 @@
+use crate::data::nx::{self, DataCache, NxFiles};
+use ::nx::{GenericNode, Node};
+use std::sync::Arc;
+
+/// Event types of the data that are triggered by hitting the reactor.
+const HIT_EVENT_TYPES: [i64; 3] = [0, 1, 2];
+/// Event type of the data that is triggered by dropping an item on the reactor.
+const ITEM_EVENT_TYPE: i64 = 100;
+
+pub enum ReactorEventKind {
+    Hit,
+    /// Dropping `quantity` of the item within `area`, relative to the reactor.
+    Item {
+        item_id: i32,
+        quantity: i16,
+        area: ((i16, i16), (i16, i16)),
+    },
+    /// Events the server does not trigger, such as skills or timeouts.
+    Other,
+}
+
+/// What moves a reactor on to another state.
+pub struct ReactorEvent {
+    pub kind: ReactorEventKind,
+    pub next_state: u8,
+}
+
+pub struct ReactorState {
+    pub events: Vec<ReactorEvent>,
+}
+
+pub struct ReactorData {
+    pub reactor_id: i32,
+    /// States indexed by their number; states without events are final.
+    pub states: Vec<ReactorState>,
+    /// Name of the script run when the reactor is destroyed; its id when the data has none.
+    pub action: Option<String>,
+}
+
+static REACTOR_DATA_CACHE: DataCache<ReactorData> = DataCache::new();
+
+impl ReactorData {
+    pub fn get(reactor_id: i32) -> Option<Arc<ReactorData>> {
+        REACTOR_DATA_CACHE.get_or_load(reactor_id, Self::load)
+    }
+
+    fn image(reactor_id: i32) -> Option<Node<'static>> {
+        NxFiles::root("Reactor")?.get(&format!("{:07}.img", reactor_id))
+    }
+
+    fn event(node: Node) -> Option<ReactorEvent> {
+        let event_type = nx::integer(node.get("type"))?;
+        let kind = match event_type {
+            event_type if HIT_EVENT_TYPES.contains(&event_type) => ReactorEventKind::Hit,
+            ITEM_EVENT_TYPE => {
+                let (left, top) = node.get("lt").vector().unwrap_or_default();
+                let (right, bottom) = node.get("rb").vector().unwrap_or_default();
+                ReactorEventKind::Item {
+                    item_id: nx::integer(node.get("0"))? as i32,
+                    quantity: nx::integer_or(node.get("1"), 1) as i16,
+                    area: ((left as i16, top as i16), (right as i16, bottom as i16)),
+                }
+            }
+            _ => ReactorEventKind::Other,
+        };
+
+        Some(ReactorEvent {
+            kind,
+            next_state: nx::integer_or(node.get("state"), 0) as u8,
+        })
+    }
+
+    fn load(reactor_id: i32) -> Option<ReactorData> {
+        let image = Self::image(reactor_id)?;
+        let action = image
+            .get("action")
+            .string()
+            .filter(|action| !action.is_empty())
+            .map(|action| action.to_string());
+
+        // Reactors that only differ in looks take their states from the one they link to.
+        let states_image = match nx::integer(image.get("info").get("link")) {
+            Some(link) => Self::image(link as i32)?,
+            None => image,
+        };
+
+        let mut states = Vec::new();
+        while let Some(state) = states_image.get(&states.len().to_string()) {
+            states.push(ReactorState {
+                events: match state.get("event") {
+                    Some(events) => events
+                        .iter()
+                        .filter(|event| event.name().parse::<u8>().is_ok())
+                        .filter_map(Self::event)
+                        .collect(),
+                    None => Vec::new(),
+                },
+            });
+        }
+
+        Some(ReactorData {
+            reactor_id,
+            states,
+            action,
+        })
+    }
+
+    pub fn script_name(&self) -> String {
+        match &self.action {
+            Some(action) => action.clone(),
+            None => self.reactor_id.to_string(),
+        }
+    }
+
+    pub fn is_final(&self, state: u8) -> bool {
+        self.states
+            .get(state as usize)
+            .is_none_or(|state| state.events.is_empty())
+    }
+
+    /// The state hitting the reactor in `state` moves it to, if a hit does anything.
+    pub fn hit(&self, state: u8) -> Option<u8> {
+        self.states
+            .get(state as usize)?
+            .events
+            .iter()
+            .find(|event| matches!(event.kind, ReactorEventKind::Hit))
+            .map(|event| event.next_state)
+    }
+
+    /// The item event of the reactor in `state`, if it waits for an item to be dropped on it.
+    pub fn item_event(&self, state: u8) -> Option<&ReactorEvent> {
+        self.states
+            .get(state as usize)?
+            .events
+            .iter()
+            .find(|event| matches!(event.kind, ReactorEventKind::Item { .. }))
+    }
+}
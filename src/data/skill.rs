@@ -0,0 +1,120 @@
+use crate::data::nx::{self, DataCache, NxFiles};
+use ::nx::{GenericNode, Node};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct SkillLevelData {
+    /// Damage of each line in percent of a regular attack.
+    pub damage: i32,
+    /// Spell attack of magic skills.
+    pub magic_attack: i32,
+    pub attack_count: u8,
+    pub mob_count: u8,
+    pub mp_cost: i16,
+    pub hp_cost: i16,
+    /// Item id and quantity used up by casting the skill.
+    pub item_cost: Option<(i32, i16)>,
+    /// How long the buff of the skill lasts, if it has one.
+    pub duration: Option<Duration>,
+    pub cooldown: Option<Duration>,
+    pub weapon_attack: i16,
+    pub weapon_defense: i16,
+    pub magic_defense: i16,
+    pub accuracy: i16,
+    pub avoidability: i16,
+    pub speed: i16,
+    pub jump: i16,
+}
+
+pub struct SkillData {
+    /// Index 0 holds level 1.
+    pub levels: Vec<SkillLevelData>,
+    /// Skills and levels that must be learned before this skill.
+    pub requirements: Vec<(i32, u8)>,
+    /// Fourth job skills can only be raised up to the master level the character unlocked.
+    pub needs_mastery: bool,
+}
+
+/// Reads a positive amount of seconds; zero means the skill has no such timer.
+fn seconds(node: Option<Node>) -> Option<Duration> {
+    match nx::integer(node)? {
+        seconds if seconds > 0 => Some(Duration::from_secs(seconds as u64)),
+        _ => None,
+    }
+}
+
+static SKILL_DATA_CACHE: DataCache<SkillData> = DataCache::new();
+
+impl SkillData {
+    pub fn get(skill_id: i32) -> Option<Arc<SkillData>> {
+        SKILL_DATA_CACHE.get_or_load(skill_id, Self::load)
+    }
+
+    /// The job a skill belongs to, encoded in the leading digits of its id.
+    pub fn job_of(skill_id: i32) -> i16 {
+        (skill_id / 10000) as i16
+    }
+
+    fn node(skill_id: i32) -> Option<Node<'static>> {
+        NxFiles::root("Skill")?
+            .get(&format!("{:03}.img", Self::job_of(skill_id)))
+            .get("skill")
+            .get(&format!("{:07}", skill_id))
+    }
+
+    fn load(skill_id: i32) -> Option<SkillData> {
+        let node = Self::node(skill_id)?;
+        let level_nodes = node.get("level")?;
+
+        let levels = (1..)
+            .map_while(|level| level_nodes.get(&level.to_string()))
+            .map(|level| SkillLevelData {
+                damage: nx::integer_or(level.get("damage"), 100) as i32,
+                magic_attack: nx::integer_or(level.get("mad"), 0) as i32,
+                attack_count: nx::integer_or(level.get("attackCount"), 1) as u8,
+                mob_count: nx::integer_or(level.get("mobCount"), 1) as u8,
+                mp_cost: nx::integer_or(level.get("mpCon"), 0) as i16,
+                hp_cost: nx::integer_or(level.get("hpCon"), 0) as i16,
+                item_cost: nx::integer(level.get("itemCon")).map(|item_id| {
+                    (item_id as i32, nx::integer_or(level.get("itemConNo"), 1) as i16)
+                }),
+                duration: seconds(level.get("time")),
+                cooldown: seconds(level.get("cooltime")),
+                weapon_attack: nx::integer_or(level.get("pad"), 0) as i16,
+                weapon_defense: nx::integer_or(level.get("pdd"), 0) as i16,
+                magic_defense: nx::integer_or(level.get("mdd"), 0) as i16,
+                accuracy: nx::integer_or(level.get("acc"), 0) as i16,
+                avoidability: nx::integer_or(level.get("eva"), 0) as i16,
+                speed: nx::integer_or(level.get("speed"), 0) as i16,
+                jump: nx::integer_or(level.get("jump"), 0) as i16,
+            })
+            .collect();
+
+        let requirements = match node.get("req") {
+            Some(requirements) => requirements
+                .iter()
+                .filter_map(|requirement| {
+                    Some((
+                        requirement.name().parse::<i32>().ok()?,
+                        nx::integer(Some(requirement))? as u8,
+                    ))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Some(SkillData {
+            levels,
+            requirements,
+            needs_mastery: node.get("masterLevel").is_some(),
+        })
+    }
+
+    pub fn max_level(&self) -> u8 {
+        self.levels.len() as u8
+    }
+
+    pub fn level(&self, level: u8) -> Option<&SkillLevelData> {
+        self.levels.get((level as usize).checked_sub(1)?)
+    }
+}
@@ -0,0 +1,138 @@
+use crate::db::db;
+use crate::db::schema::buddies;
+use diesel::prelude::*;
+use std::error::Error;
+
+/// A character on the buddy list of another, or a buddy request it has yet to answer.
+#[derive(Queryable, Identifiable)]
+#[diesel(table_name = buddies)]
+pub struct Buddy {
+    pub id: i32,
+    pub character_id: i32,
+    pub buddy_id: i32,
+    pub buddy_name: String,
+    pub group_name: String,
+    /// Whether `buddy_id` asked to be added and `character_id` has not accepted yet.
+    pub pending: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = buddies)]
+pub struct NewBuddy {
+    pub character_id: i32,
+    pub buddy_id: i32,
+    pub buddy_name: String,
+    pub group_name: String,
+    pub pending: bool,
+}
+
+impl Buddy {
+    pub fn get_by_character(character_id: i32) -> Result<Vec<Buddy>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match buddies::table
+            .filter(buddies::character_id.eq(character_id))
+            .order(buddies::id)
+            .load::<Buddy>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn get(character_id: i32, buddy_id: i32) -> Result<Option<Buddy>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match buddies::table
+            .filter(buddies::character_id.eq(character_id))
+            .filter(buddies::buddy_id.eq(buddy_id))
+            .first::<Buddy>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Counts the buddies of the character, leaving out requests it has yet to accept.
+    pub fn count_accepted(character_id: i32) -> Result<i64, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match buddies::table
+            .filter(buddies::character_id.eq(character_id))
+            .filter(buddies::pending.eq(false))
+            .count()
+            .get_result::<i64>(&mut db_connection)
+        {
+            Ok(count) => Ok(count),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn create(new_buddies: &[NewBuddy]) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::insert_into(buddies::table)
+            .values(new_buddies)
+            .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn accept(character_id: i32, buddy_id: i32) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::update(
+            buddies::table
+                .filter(buddies::character_id.eq(character_id))
+                .filter(buddies::buddy_id.eq(buddy_id)),
+        )
+        .set(buddies::pending.eq(false))
+        .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn set_group(character_id: i32, buddy_id: i32, group_name: &str) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::update(
+            buddies::table
+                .filter(buddies::character_id.eq(character_id))
+                .filter(buddies::buddy_id.eq(buddy_id)),
+        )
+        .set(buddies::group_name.eq(group_name))
+        .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Removes `buddy_id` from the buddy list of the character, along with the request the
+    /// character sent it if it was never answered.
+    pub fn delete(character_id: i32, buddy_id: i32) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::delete(
+            buddies::table.filter(
+                buddies::character_id
+                    .eq(character_id)
+                    .and(buddies::buddy_id.eq(buddy_id))
+                    .or(buddies::character_id
+                        .eq(buddy_id)
+                        .and(buddies::buddy_id.eq(character_id))
+                        .and(buddies::pending.eq(true))),
+            ),
+        )
+        .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::db::db;
+use crate::db::model::inventory_item::{InventoryItem, NewInventoryItem};
+use crate::db::model::quest_status::{NewQuestStatus, QuestStatus};
+use crate::db::model::skill::{NewSkill, Skill};
+use crate::db::schema::characters;
+use diesel::prelude::*;
+use std::error::Error;
+
+#[derive(Queryable, Identifiable, AsChangeset)]
+#[diesel(table_name = characters)]
+pub struct Character {
+    pub id: i32,
+    pub user_id: i32,
+    pub world_id: i16,
+    pub name: String,
+    pub gm_level: i16,
+    pub gender: i16,
+    pub skin: i16,
+    pub face: i32,
+    pub hair: i32,
+    pub level: i16,
+    pub job: i16,
+    pub strength: i16,
+    pub dexterity: i16,
+    pub intelligence: i16,
+    pub luck: i16,
+    pub hp: i16,
+    pub max_hp: i16,
+    pub mp: i16,
+    pub max_mp: i16,
+    pub ap: i16,
+    pub sp: i16,
+    pub exp: i32,
+    pub fame: i16,
+    pub meso: i32,
+    pub map_id: i32,
+    pub spawn_point: i16,
+    pub equip_slots: i16,
+    pub use_slots: i16,
+    pub setup_slots: i16,
+    pub etc_slots: i16,
+    pub cash_slots: i16,
+    pub buddy_capacity: i16,
+}
+
+/// The mesos of a character and, when items changed hands, its inventory, stored in the
+/// transaction of whatever it traded with.
+pub struct CharacterUpdate {
+    pub character_id: i32,
+    pub meso: i32,
+    pub inventory: Option<Vec<NewInventoryItem>>,
+}
+
+impl CharacterUpdate {
+    /// Stores the update on its own, for changes that involve nothing else.
+    pub fn store(&self) -> Result<(), Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<(), diesel::result::Error, _>(|connection| self.save(connection)) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self, connection: &mut PgConnection) -> QueryResult<()> {
+        diesel::update(characters::table.filter(characters::id.eq(self.character_id)))
+            .set(characters::meso.eq(self.meso))
+            .execute(connection)?;
+        if let Some(inventory) = &self.inventory {
+            InventoryItem::replace(connection, self.character_id, inventory)?;
+        }
+        Ok(())
+    }
+}
+
+impl Character {
+    pub fn get_by_id(character_id: i32) -> Result<Option<Character>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match characters::table
+            .filter(characters::id.eq(character_id))
+            .first::<Character>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn get_by_name(character_name: &str) -> Result<Option<Character>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match characters::table
+            .filter(characters::name.eq(character_name))
+            .first::<Character>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Gives mesos to a character that is not loaded, such as a refund owed after it logged out.
+    pub fn add_meso(character_id: i32, amount: i32) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::update(characters::table.filter(characters::id.eq(character_id)))
+            .set(characters::meso.eq(characters::meso + amount))
+            .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Stores the character along with its inventory, skills and quests in a single
+    /// transaction, leaving out those given as `None`.
+    pub fn save(
+        &self,
+        inventory: Option<&[NewInventoryItem]>,
+        skills: Option<&[NewSkill]>,
+        quests: Option<&[NewQuestStatus]>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<(), diesel::result::Error, _>(|connection| {
+            diesel::update(self).set(self).execute(connection)?;
+            if let Some(inventory) = inventory {
+                InventoryItem::replace(connection, self.id, inventory)?;
+            }
+            if let Some(skills) = skills {
+                Skill::replace(connection, self.id, skills)?;
+            }
+            if let Some(quests) = quests {
+                QuestStatus::replace(connection, self.id, quests)?;
+            }
+            Ok(())
+        }) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::db::db;
+use crate::db::schema::cheat_flags;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CheatKind {
+    /// Damage above what the character's stats, weapon and skill allow.
+    Damage = 0,
+    /// HP or MP recovered faster or in larger amounts than the client allows.
+    Recovery = 1,
+}
+
+#[derive(Queryable, Identifiable)]
+pub struct CheatFlag {
+    pub id: i32,
+    pub character_id: i32,
+    pub kind: i16,
+    pub description: String,
+    pub flag_date: SystemTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = cheat_flags)]
+pub struct NewCheatFlag {
+    pub character_id: i32,
+    pub kind: i16,
+    pub description: String,
+    pub flag_date: SystemTime,
+}
+
+impl CheatFlag {
+    pub fn create(new_cheat_flag: NewCheatFlag) -> Result<CheatFlag, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::insert_into(cheat_flags::table)
+            .values(&new_cheat_flag)
+            .get_result::<CheatFlag>(&mut db_connection)
+        {
+            Ok(cheat_flag) => Ok(cheat_flag),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
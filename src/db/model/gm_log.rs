@@ -0,0 +1,39 @@
+use crate::db::db;
+use crate::db::schema::gm_logs;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+#[derive(Queryable, Identifiable)]
+pub struct GmLog {
+    pub id: i32,
+    pub character_id: i32,
+    pub command: String,
+    pub arguments: String,
+    pub succeeded: bool,
+    pub execution_date: SystemTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = gm_logs)]
+pub struct NewGmLog {
+    pub character_id: i32,
+    pub command: String,
+    pub arguments: String,
+    pub succeeded: bool,
+    pub execution_date: SystemTime,
+}
+
+impl GmLog {
+    pub fn create(new_gm_log: NewGmLog) -> Result<GmLog, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::insert_into(gm_logs::table)
+            .values(&new_gm_log)
+            .get_result::<GmLog>(&mut db_connection)
+        {
+            Ok(gm_log) => Ok(gm_log),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::db::db;
+use crate::db::schema::{guild_members, guilds};
+use diesel::prelude::*;
+use std::error::Error;
+
+#[derive(Queryable, Identifiable, AsChangeset)]
+#[diesel(table_name = guilds)]
+pub struct Guild {
+    pub id: i32,
+    pub world_id: i16,
+    pub name: String,
+    pub leader_id: i32,
+    /// The titles of the five ranks, from the master down, separated by commas.
+    pub rank_titles: String,
+    pub capacity: i16,
+    pub emblem_background: i16,
+    pub emblem_background_color: i16,
+    pub emblem_logo: i16,
+    pub emblem_logo_color: i16,
+    pub notice: String,
+    pub points: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = guilds)]
+pub struct NewGuild {
+    pub world_id: i16,
+    pub name: String,
+    pub leader_id: i32,
+    pub rank_titles: String,
+    pub capacity: i16,
+}
+
+#[derive(Queryable, Identifiable)]
+#[diesel(table_name = guild_members)]
+pub struct GuildMember {
+    pub id: i32,
+    pub guild_id: i32,
+    pub character_id: i32,
+    pub name: String,
+    pub job: i16,
+    pub level: i16,
+    pub rank: i16,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = guild_members)]
+pub struct NewGuildMember {
+    pub guild_id: i32,
+    pub character_id: i32,
+    pub name: String,
+    pub job: i16,
+    pub level: i16,
+    pub rank: i16,
+}
+
+impl Guild {
+    pub fn get_by_id(guild_id: i32) -> Result<Option<Guild>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match guilds::table
+            .filter(guilds::id.eq(guild_id))
+            .first::<Guild>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Creates the guild with `leader` as its only member, unless the world already has a guild
+    /// by that name, whatever its case.
+    pub fn create(new_guild: &NewGuild, leader: NewGuildMember) -> Result<Option<Guild>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<Option<Guild>, diesel::result::Error, _>(|connection| {
+            let taken = guilds::table
+                .filter(guilds::world_id.eq(new_guild.world_id))
+                .filter(guilds::name.ilike(&new_guild.name))
+                .count()
+                .get_result::<i64>(connection)?
+                > 0;
+            if taken {
+                return Ok(None);
+            }
+
+            let guild = diesel::insert_into(guilds::table)
+                .values(new_guild)
+                .get_result::<Guild>(connection)?;
+            diesel::insert_into(guild_members::table)
+                .values(NewGuildMember {
+                    guild_id: guild.id,
+                    ..leader
+                })
+                .execute(connection)?;
+            Ok(Some(guild))
+        }) {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::update(self).set(self).execute(&mut db_connection) {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Deletes the guild along with its members.
+    pub fn delete(guild_id: i32) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<usize, diesel::result::Error, _>(|connection| {
+            diesel::delete(guild_members::table.filter(guild_members::guild_id.eq(guild_id))).execute(connection)?;
+            diesel::delete(guilds::table.filter(guilds::id.eq(guild_id))).execute(connection)
+        }) {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+impl GuildMember {
+    pub fn get_by_guild(guild_id: i32) -> Result<Vec<GuildMember>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match guild_members::table
+            .filter(guild_members::guild_id.eq(guild_id))
+            .order((guild_members::rank, guild_members::id))
+            .load::<GuildMember>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn get_by_character(character_id: i32) -> Result<Option<GuildMember>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match guild_members::table
+            .filter(guild_members::character_id.eq(character_id))
+            .first::<GuildMember>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn create(new_member: &NewGuildMember) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::insert_into(guild_members::table)
+            .values(new_member)
+            .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Updates the job, level and rank of the member.
+    pub fn update(character_id: i32, job: i16, level: i16, rank: i16) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::update(guild_members::table.filter(guild_members::character_id.eq(character_id)))
+            .set((
+                guild_members::job.eq(job),
+                guild_members::level.eq(level),
+                guild_members::rank.eq(rank),
+            ))
+            .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn delete(character_id: i32) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::delete(guild_members::table.filter(guild_members::character_id.eq(character_id)))
+            .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
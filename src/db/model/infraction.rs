@@ -0,0 +1,209 @@
+use crate::db::db;
+use crate::db::schema::infractions;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InfractionKind {
+    Ban = 0,
+    Mute = 1,
+}
+
+/// What an infraction is recorded against; an account, an IP address or network, or a machine.
+#[derive(Default)]
+pub struct InfractionSubject {
+    pub user_id: Option<i32>,
+    pub ip_address: Option<String>,
+    pub mac_address: Option<String>,
+    pub hardware_id: Option<String>,
+}
+
+#[derive(Queryable, Identifiable, Clone)]
+pub struct Infraction {
+    pub id: i32,
+    pub kind: i16,
+    pub user_id: Option<i32>,
+    pub ip_address: Option<String>,
+    pub mac_address: Option<String>,
+    pub hardware_id: Option<String>,
+    pub reason: i16,
+    pub description: String,
+    pub issuer_id: Option<i32>,
+    pub issue_date: SystemTime,
+    pub expiry_date: Option<SystemTime>,
+    pub revoker_id: Option<i32>,
+    pub revocation_date: Option<SystemTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = infractions)]
+pub struct NewInfraction {
+    pub kind: i16,
+    pub user_id: Option<i32>,
+    pub ip_address: Option<String>,
+    pub mac_address: Option<String>,
+    pub hardware_id: Option<String>,
+    pub reason: i16,
+    pub description: String,
+    pub issuer_id: Option<i32>,
+    pub issue_date: SystemTime,
+    pub expiry_date: Option<SystemTime>,
+}
+
+impl Infraction {
+    pub fn create(new_infraction: NewInfraction) -> Result<Infraction, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::insert_into(infractions::table)
+            .values(&new_infraction)
+            .get_result::<Infraction>(&mut db_connection)
+        {
+            Ok(infraction) => Ok(infraction),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Finds the longest lasting infraction of `kind` that is neither revoked nor expired and
+    /// applies to the given account or IP address.
+    pub fn get_active(
+        kind: InfractionKind,
+        user_id: Option<i32>,
+        ip_address: Option<&str>,
+    ) -> Result<Option<Infraction>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        let mut query = infractions::table
+            .filter(infractions::kind.eq(kind as i16))
+            .filter(infractions::revocation_date.is_null())
+            .filter(
+                infractions::expiry_date
+                    .is_null()
+                    .or(infractions::expiry_date.gt(SystemTime::now())),
+            )
+            .into_boxed();
+
+        query = match (user_id, ip_address) {
+            (Some(user_id), Some(ip_address)) => query.filter(
+                infractions::user_id
+                    .eq(user_id)
+                    .or(infractions::ip_address.eq(ip_address.to_string())),
+            ),
+            (Some(user_id), None) => query.filter(infractions::user_id.eq(user_id)),
+            (None, Some(ip_address)) => {
+                query.filter(infractions::ip_address.eq(ip_address.to_string()))
+            }
+            (None, None) => return Ok(None),
+        };
+
+        match query
+            .order(infractions::expiry_date.desc().nulls_first())
+            .first::<Infraction>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn get_by_user(user_id: i32) -> Result<Vec<Infraction>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match infractions::table
+            .filter(infractions::user_id.eq(user_id))
+            .order(infractions::issue_date.desc())
+            .load::<Infraction>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Active bans that target an IP address or network, a MAC address or a hardware id,
+    /// which are checked before an account is known.
+    pub fn get_active_machine_bans() -> Result<Vec<Infraction>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match infractions::table
+            .filter(infractions::kind.eq(InfractionKind::Ban as i16))
+            .filter(infractions::revocation_date.is_null())
+            .filter(
+                infractions::expiry_date
+                    .is_null()
+                    .or(infractions::expiry_date.gt(SystemTime::now())),
+            )
+            .filter(
+                infractions::ip_address
+                    .is_not_null()
+                    .or(infractions::mac_address.is_not_null())
+                    .or(infractions::hardware_id.is_not_null()),
+            )
+            .load::<Infraction>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Marks every active infraction of `kind` on the subject as revoked, keeping the records
+    /// for the history.
+    pub fn revoke_active(
+        kind: InfractionKind,
+        subject: &InfractionSubject,
+        revoker_id: Option<i32>,
+    ) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        let mut query = infractions::table
+            .select(infractions::id)
+            .filter(infractions::kind.eq(kind as i16))
+            .filter(infractions::revocation_date.is_null())
+            .filter(
+                infractions::expiry_date
+                    .is_null()
+                    .or(infractions::expiry_date.gt(SystemTime::now())),
+            )
+            .into_boxed();
+
+        query = match subject {
+            InfractionSubject {
+                user_id: Some(user_id),
+                ..
+            } => query.filter(infractions::user_id.eq(*user_id)),
+            InfractionSubject {
+                ip_address: Some(ip_address),
+                ..
+            } => query.filter(infractions::ip_address.eq(ip_address.clone())),
+            InfractionSubject {
+                mac_address: Some(mac_address),
+                ..
+            } => query.filter(infractions::mac_address.eq(mac_address.clone())),
+            InfractionSubject {
+                hardware_id: Some(hardware_id),
+                ..
+            } => query.filter(infractions::hardware_id.eq(hardware_id.clone())),
+            _ => return Ok(0),
+        };
+
+        let active_ids = query.load::<i32>(&mut db_connection)?;
+
+        match diesel::update(infractions::table.filter(infractions::id.eq_any(active_ids)))
+            .set((
+                infractions::revoker_id.eq(revoker_id),
+                infractions::revocation_date.eq(Some(SystemTime::now())),
+            ))
+            .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.revocation_date.is_none()
+            && match self.expiry_date {
+                Some(expiry_date) => expiry_date > SystemTime::now(),
+                None => true,
+            }
+    }
+}
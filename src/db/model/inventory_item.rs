@@ -0,0 +1,97 @@
+use crate::db::db;
+use crate::db::schema::inventory_items;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+#[derive(Queryable, Identifiable)]
+pub struct InventoryItem {
+    pub id: i32,
+    pub character_id: i32,
+    pub inventory_type: i16,
+    pub position: i16,
+    pub item_id: i32,
+    pub quantity: i16,
+    pub owner: String,
+    pub flags: i16,
+    pub expiration_date: Option<SystemTime>,
+    pub upgrade_slots: i16,
+    pub upgrades: i16,
+    pub strength: i16,
+    pub dexterity: i16,
+    pub intelligence: i16,
+    pub luck: i16,
+    pub hp: i16,
+    pub mp: i16,
+    pub weapon_attack: i16,
+    pub magic_attack: i16,
+    pub weapon_defense: i16,
+    pub magic_defense: i16,
+    pub accuracy: i16,
+    pub avoidability: i16,
+    pub hands: i16,
+    pub speed: i16,
+    pub jump: i16,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = inventory_items)]
+pub struct NewInventoryItem {
+    pub character_id: i32,
+    pub inventory_type: i16,
+    pub position: i16,
+    pub item_id: i32,
+    pub quantity: i16,
+    pub owner: String,
+    pub flags: i16,
+    pub expiration_date: Option<SystemTime>,
+    pub upgrade_slots: i16,
+    pub upgrades: i16,
+    pub strength: i16,
+    pub dexterity: i16,
+    pub intelligence: i16,
+    pub luck: i16,
+    pub hp: i16,
+    pub mp: i16,
+    pub weapon_attack: i16,
+    pub magic_attack: i16,
+    pub weapon_defense: i16,
+    pub magic_defense: i16,
+    pub accuracy: i16,
+    pub avoidability: i16,
+    pub hands: i16,
+    pub speed: i16,
+    pub jump: i16,
+}
+
+impl InventoryItem {
+    pub fn get_by_character(character_id: i32) -> Result<Vec<InventoryItem>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match inventory_items::table
+            .filter(inventory_items::character_id.eq(character_id))
+            .order((inventory_items::inventory_type, inventory_items::position))
+            .load::<InventoryItem>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Replaces every stored item of the character with `items` within a transaction the caller
+    /// runs.
+    pub fn replace(
+        connection: &mut PgConnection,
+        character_id: i32,
+        items: &[NewInventoryItem],
+    ) -> QueryResult<usize> {
+        diesel::delete(
+            inventory_items::table.filter(inventory_items::character_id.eq(character_id)),
+        )
+        .execute(connection)?;
+
+        diesel::insert_into(inventory_items::table)
+            .values(items)
+            .execute(connection)
+    }
+}
@@ -0,0 +1,301 @@
+use crate::db::db;
+use crate::db::model::character::CharacterUpdate;
+use crate::db::schema::{merchant_items, merchants};
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+/// A hired merchant, which keeps its unsold items and the mesos it made once closed, until its
+/// owner retrieves them from Fredrick.
+#[derive(Queryable, Identifiable)]
+pub struct Merchant {
+    pub id: i32,
+    pub character_id: i32,
+    pub world_id: i16,
+    pub channel_id: i16,
+    pub map_id: i32,
+    pub title: String,
+    pub meso: i32,
+    pub open: bool,
+    pub opening_date: SystemTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = merchants)]
+pub struct NewMerchant {
+    pub character_id: i32,
+    pub world_id: i16,
+    pub channel_id: i16,
+    pub map_id: i32,
+    pub title: String,
+    pub meso: i32,
+    pub open: bool,
+    pub opening_date: SystemTime,
+}
+
+/// Items sold by a merchant come in `bundles` of `quantity` for `price` each.
+#[derive(Queryable, Identifiable)]
+pub struct MerchantItem {
+    pub id: i32,
+    pub merchant_id: i32,
+    pub position: i16,
+    pub price: i32,
+    pub bundles: i16,
+    pub item_id: i32,
+    pub quantity: i16,
+    pub owner: String,
+    pub flags: i16,
+    pub expiration_date: Option<SystemTime>,
+    pub upgrade_slots: i16,
+    pub upgrades: i16,
+    pub strength: i16,
+    pub dexterity: i16,
+    pub intelligence: i16,
+    pub luck: i16,
+    pub hp: i16,
+    pub mp: i16,
+    pub weapon_attack: i16,
+    pub magic_attack: i16,
+    pub weapon_defense: i16,
+    pub magic_defense: i16,
+    pub accuracy: i16,
+    pub avoidability: i16,
+    pub hands: i16,
+    pub speed: i16,
+    pub jump: i16,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = merchant_items)]
+pub struct NewMerchantItem {
+    pub merchant_id: i32,
+    pub position: i16,
+    pub price: i32,
+    pub bundles: i16,
+    pub item_id: i32,
+    pub quantity: i16,
+    pub owner: String,
+    pub flags: i16,
+    pub expiration_date: Option<SystemTime>,
+    pub upgrade_slots: i16,
+    pub upgrades: i16,
+    pub strength: i16,
+    pub dexterity: i16,
+    pub intelligence: i16,
+    pub luck: i16,
+    pub hp: i16,
+    pub mp: i16,
+    pub weapon_attack: i16,
+    pub magic_attack: i16,
+    pub weapon_defense: i16,
+    pub magic_defense: i16,
+    pub accuracy: i16,
+    pub avoidability: i16,
+    pub hands: i16,
+    pub speed: i16,
+    pub jump: i16,
+}
+
+impl Merchant {
+    /// The merchant of the character, whether it is still open or waiting at Fredrick.
+    pub fn get_by_character(character_id: i32) -> Result<Option<Merchant>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        // What waits at Fredrick comes first, as a character may have that and an open merchant.
+        match merchants::table
+            .filter(merchants::character_id.eq(character_id))
+            .order(merchants::open.asc())
+            .first::<Merchant>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn get_items(merchant_id: i32) -> Result<Vec<MerchantItem>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match merchant_items::table
+            .filter(merchant_items::merchant_id.eq(merchant_id))
+            .order(merchant_items::position)
+            .load::<MerchantItem>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Opens the merchant with `items`, storing the inventory of the owner they were taken out
+    /// of in the same transaction.
+    pub fn open(
+        new_merchant: &NewMerchant,
+        items: Vec<NewMerchantItem>,
+        owner: &CharacterUpdate,
+    ) -> Result<Merchant, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<Merchant, diesel::result::Error, _>(|connection| {
+            let merchant = diesel::insert_into(merchants::table)
+                .values(new_merchant)
+                .get_result::<Merchant>(connection)?;
+            let items: Vec<NewMerchantItem> = items
+                .into_iter()
+                .map(|item| NewMerchantItem {
+                    merchant_id: merchant.id,
+                    ..item
+                })
+                .collect();
+            diesel::insert_into(merchant_items::table)
+                .values(&items)
+                .execute(connection)?;
+            owner.save(connection)?;
+            Ok(merchant)
+        }) {
+            Ok(merchant) => Ok(merchant),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Stores the mesos and the items left in the merchant, along with the character that bought
+    /// from it or took an item back.
+    pub fn update(
+        merchant_id: i32,
+        meso: i32,
+        items: &[NewMerchantItem],
+        character: &CharacterUpdate,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<(), diesel::result::Error, _>(|connection| {
+            diesel::update(merchants::table.filter(merchants::id.eq(merchant_id)))
+                .set(merchants::meso.eq(meso))
+                .execute(connection)?;
+            diesel::delete(merchant_items::table.filter(merchant_items::merchant_id.eq(merchant_id)))
+                .execute(connection)?;
+            diesel::insert_into(merchant_items::table)
+                .values(items)
+                .execute(connection)?;
+            character.save(connection)
+        }) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Closes the merchant, leaving what it holds to Fredrick.
+    pub fn close(merchant_id: i32) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::update(merchants::table.filter(merchants::id.eq(merchant_id)))
+            .set(merchants::open.eq(false))
+            .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Closes the merchants a previous run of the channel left open.
+    pub fn close_channel(world_id: i16, channel_id: i16) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::update(
+            merchants::table
+                .filter(merchants::world_id.eq(world_id))
+                .filter(merchants::channel_id.eq(channel_id))
+                .filter(merchants::open.eq(true)),
+        )
+        .set(merchants::open.eq(false))
+        .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Removes the closed merchant once its owner took what it held, storing the owner in the
+    /// same transaction. Returns whether the merchant was still there to take from.
+    pub fn retrieve(merchant_id: i32, owner: &CharacterUpdate) -> Result<bool, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<bool, diesel::result::Error, _>(|connection| {
+            diesel::delete(merchant_items::table.filter(merchant_items::merchant_id.eq(merchant_id)))
+                .execute(connection)?;
+            let removed = diesel::delete(
+                merchants::table
+                    .filter(merchants::id.eq(merchant_id))
+                    .filter(merchants::open.eq(false)),
+            )
+            .execute(connection)?;
+            if removed == 0 {
+                return Err(diesel::result::Error::RollbackTransaction);
+            }
+            owner.save(connection)?;
+            Ok(true)
+        }) {
+            Ok(retrieved) => Ok(retrieved),
+            Err(diesel::result::Error::RollbackTransaction) => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Leaves mesos and items the character could not be given with Fredrick, adding them to
+    /// what already waits there, and stores the character with what it was given in the same
+    /// transaction. The owner is `None` only when the character is not loaded anywhere. The
+    /// items are given with a `merchant_id` and `position` of 0.
+    pub fn stash(
+        new_merchant: &NewMerchant,
+        items: Vec<NewMerchantItem>,
+        owner: Option<&CharacterUpdate>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<(), diesel::result::Error, _>(|connection| {
+            let closed = merchants::table
+                .filter(merchants::character_id.eq(new_merchant.character_id))
+                .filter(merchants::open.eq(false))
+                .for_update()
+                .first::<Merchant>(connection)
+                .optional()?;
+            let merchant_id = match closed {
+                Some(merchant) => {
+                    diesel::update(merchants::table.filter(merchants::id.eq(merchant.id)))
+                        .set(merchants::meso.eq(merchants::meso + new_merchant.meso))
+                        .execute(connection)?;
+                    merchant.id
+                }
+                None => {
+                    diesel::insert_into(merchants::table)
+                        .values(new_merchant)
+                        .get_result::<Merchant>(connection)?
+                        .id
+                }
+            };
+            let last_position = merchant_items::table
+                .filter(merchant_items::merchant_id.eq(merchant_id))
+                .select(diesel::dsl::max(merchant_items::position))
+                .first::<Option<i16>>(connection)?
+                .unwrap_or(0);
+            let items: Vec<NewMerchantItem> = items
+                .into_iter()
+                .zip(last_position + 1..)
+                .map(|(item, position)| NewMerchantItem {
+                    merchant_id,
+                    position,
+                    ..item
+                })
+                .collect();
+            diesel::insert_into(merchant_items::table)
+                .values(&items)
+                .execute(connection)?;
+            if let Some(owner) = owner {
+                owner.save(connection)?;
+            }
+            Ok(())
+        }) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
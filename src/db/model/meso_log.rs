@@ -0,0 +1,56 @@
+use crate::db::db;
+use crate::db::schema::meso_logs;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+/// Where mesos a character gained or spent came from or went to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MesoReason {
+    ShopBuy = 0,
+    ShopSell = 1,
+    ShopRecharge = 2,
+    Drop = 3,
+    PickUp = 4,
+    Script = 5,
+    Quest = 6,
+    Guild = 7,
+    Trade = 8,
+    Storage = 9,
+    PlayerShop = 10,
+    Merchant = 11,
+}
+
+#[derive(Queryable, Identifiable)]
+pub struct MesoLog {
+    pub id: i32,
+    pub character_id: i32,
+    pub amount: i32,
+    pub reason: i16,
+    pub description: String,
+    pub log_date: SystemTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = meso_logs)]
+pub struct NewMesoLog {
+    pub character_id: i32,
+    pub amount: i32,
+    pub reason: i16,
+    pub description: String,
+    pub log_date: SystemTime,
+}
+
+impl MesoLog {
+    pub fn create(new_meso_log: NewMesoLog) -> Result<MesoLog, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::insert_into(meso_logs::table)
+            .values(&new_meso_log)
+            .get_result::<MesoLog>(&mut db_connection)
+        {
+            Ok(meso_log) => Ok(meso_log),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
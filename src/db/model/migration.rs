@@ -0,0 +1,67 @@
+use crate::db::db;
+use crate::db::schema::character_migrations;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::{Duration, SystemTime};
+
+/// How long the channel server accepts a handoff after the character was selected.
+const MIGRATION_DURATION: Duration = Duration::from_secs(60);
+
+/// The handoff the login server leaves for the channel server once a character was selected,
+/// so only the account that selected it can enter the channel with it, from the same address.
+#[derive(Queryable, Identifiable)]
+pub struct CharacterMigration {
+    pub id: i32,
+    pub user_id: i32,
+    pub character_id: i32,
+    pub ip_address: String,
+    pub creation_date: SystemTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = character_migrations)]
+pub struct NewCharacterMigration {
+    pub user_id: i32,
+    pub character_id: i32,
+    pub ip_address: String,
+    pub creation_date: SystemTime,
+}
+
+impl CharacterMigration {
+    /// Stores the handoff, replacing any the account still had waiting.
+    pub fn create(new_migration: &NewCharacterMigration) -> Result<(), Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<(), diesel::result::Error, _>(|connection| {
+            diesel::delete(
+                character_migrations::table.filter(character_migrations::user_id.eq(new_migration.user_id)),
+            )
+            .execute(connection)?;
+            diesel::insert_into(character_migrations::table)
+                .values(new_migration)
+                .execute(connection)?;
+            Ok(())
+        }) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Takes the handoff left for the character from `ip_address`, if it has not expired yet.
+    /// A handoff can only be taken once.
+    pub fn consume(character_id: i32, ip_address: &str) -> Result<Option<CharacterMigration>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::delete(
+            character_migrations::table
+                .filter(character_migrations::character_id.eq(character_id))
+                .filter(character_migrations::ip_address.eq(ip_address))
+                .filter(character_migrations::creation_date.gt(SystemTime::now() - MIGRATION_DURATION)),
+        )
+        .get_results::<CharacterMigration>(&mut db_connection)
+        {
+            Ok(mut migrations) => Ok(migrations.pop()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::db::db;
+use crate::db::schema::minigame_records;
+use diesel::prelude::*;
+use std::error::Error;
+
+/// How a character fared in one kind of mini-game.
+#[derive(Queryable, Identifiable, Clone, Copy, Default)]
+pub struct MinigameRecord {
+    pub id: i32,
+    pub character_id: i32,
+    pub game: i16,
+    pub wins: i32,
+    pub ties: i32,
+    pub losses: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = minigame_records)]
+pub struct NewMinigameRecord {
+    pub character_id: i32,
+    pub game: i16,
+    pub wins: i32,
+    pub ties: i32,
+    pub losses: i32,
+}
+
+impl MinigameRecord {
+    /// The record of the character in `game`, empty when it never finished one.
+    pub fn get(character_id: i32, game: i16) -> Result<MinigameRecord, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match minigame_records::table
+            .filter(minigame_records::character_id.eq(character_id))
+            .filter(minigame_records::game.eq(game))
+            .first::<MinigameRecord>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(diesel::result::Error::NotFound) => Ok(MinigameRecord {
+                character_id,
+                game,
+                ..Default::default()
+            }),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Adds a finished game to the record of the character, creating the record the first time.
+    pub fn add(character_id: i32, game: i16, wins: i32, ties: i32, losses: i32) -> Result<(), Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<(), diesel::result::Error, _>(|connection| {
+            let updated = diesel::update(
+                minigame_records::table
+                    .filter(minigame_records::character_id.eq(character_id))
+                    .filter(minigame_records::game.eq(game)),
+            )
+            .set((
+                minigame_records::wins.eq(minigame_records::wins + wins),
+                minigame_records::ties.eq(minigame_records::ties + ties),
+                minigame_records::losses.eq(minigame_records::losses + losses),
+            ))
+            .execute(connection)?;
+            if updated == 0 {
+                diesel::insert_into(minigame_records::table)
+                    .values(NewMinigameRecord {
+                        character_id,
+                        game,
+                        wins,
+                        ties,
+                        losses,
+                    })
+                    .execute(connection)?;
+            }
+            Ok(())
+        }) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -1 +1,19 @@
+pub mod buddy;
+pub mod character;
+pub mod cheat_flag;
+pub mod gm_log;
+pub mod guild;
+pub mod infraction;
+pub mod inventory_item;
+pub mod merchant;
+pub mod meso_log;
+pub mod migration;
+pub mod minigame_record;
+pub mod quest_status;
+pub mod reactor_drop;
+pub mod session;
+pub mod shop;
+pub mod skill;
+pub mod storage;
+pub mod trade_log;
 pub mod user;
\ No newline at end of file
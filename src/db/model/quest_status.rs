@@ -0,0 +1,53 @@
+use crate::db::db;
+use crate::db::schema::quest_status;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+#[derive(Queryable, Identifiable)]
+#[diesel(table_name = quest_status)]
+pub struct QuestStatus {
+    pub id: i32,
+    pub character_id: i32,
+    pub quest_id: i32,
+    pub status: i16,
+    /// The quest record the client shows, holding the mob kill counts of started quests.
+    pub progress: String,
+    pub completed_at: Option<SystemTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = quest_status)]
+pub struct NewQuestStatus {
+    pub character_id: i32,
+    pub quest_id: i32,
+    pub status: i16,
+    pub progress: String,
+    pub completed_at: Option<SystemTime>,
+}
+
+impl QuestStatus {
+    pub fn get_by_character(character_id: i32) -> Result<Vec<QuestStatus>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match quest_status::table
+            .filter(quest_status::character_id.eq(character_id))
+            .order(quest_status::quest_id)
+            .load::<QuestStatus>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Replaces every stored quest of the character with `statuses` within a transaction the
+    /// caller runs.
+    pub fn replace(connection: &mut PgConnection, character_id: i32, statuses: &[NewQuestStatus]) -> QueryResult<usize> {
+        diesel::delete(quest_status::table.filter(quest_status::character_id.eq(character_id)))
+            .execute(connection)?;
+
+        diesel::insert_into(quest_status::table)
+            .values(statuses)
+            .execute(connection)
+    }
+}
@@ -0,0 +1,28 @@
+use crate::db::db;
+use crate::db::schema::reactor_drops;
+use diesel::prelude::*;
+use std::error::Error;
+
+/// An item dropped by a reactor when it is destroyed.
+#[derive(Queryable, Identifiable)]
+pub struct ReactorDrop {
+    pub id: i32,
+    pub reactor_id: i32,
+    pub item_id: i32,
+    /// The item drops with a chance of one in `chance`.
+    pub chance: i32,
+}
+
+impl ReactorDrop {
+    pub fn get_by_reactor(reactor_id: i32) -> Result<Vec<ReactorDrop>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match reactor_drops::table
+            .filter(reactor_drops::reactor_id.eq(reactor_id))
+            .load::<ReactorDrop>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::db::db;
+use crate::db::schema::sessions;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+/// The character an account has in game, and the channel it is on. An account has at most one.
+#[derive(Queryable, Identifiable)]
+#[diesel(primary_key(user_id))]
+pub struct Session {
+    pub user_id: i32,
+    pub character_id: i32,
+    pub world_id: i16,
+    pub channel_id: i16,
+    pub login_date: SystemTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+pub struct NewSession {
+    pub user_id: i32,
+    pub character_id: i32,
+    pub world_id: i16,
+    pub channel_id: i16,
+    pub login_date: SystemTime,
+}
+
+impl Session {
+    /// Stores the session, returning false if the account already has one on any channel.
+    pub fn begin(new_session: &NewSession) -> Result<bool, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::insert_into(sessions::table)
+            .values(new_session)
+            .on_conflict_do_nothing()
+            .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows == 1),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn end(user_id: i32) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::delete(sessions::table.filter(sessions::user_id.eq(user_id))).execute(&mut db_connection) {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Ends the sessions a channel left behind when it stopped without logging its players out.
+    pub fn end_channel(world_id: i16, channel_id: i16) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match diesel::delete(
+            sessions::table
+                .filter(sessions::world_id.eq(world_id))
+                .filter(sessions::channel_id.eq(channel_id)),
+        )
+        .execute(&mut db_connection)
+        {
+            Ok(affected_rows) => Ok(affected_rows),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::db::db;
+use crate::db::schema::{shop_items, shops};
+use diesel::prelude::*;
+use std::error::Error;
+
+#[derive(Queryable, Identifiable)]
+pub struct Shop {
+    pub id: i32,
+    pub npc_id: i32,
+}
+
+#[derive(Queryable, Identifiable)]
+pub struct ShopItem {
+    pub id: i32,
+    pub shop_id: i32,
+    pub item_id: i32,
+    pub price: i32,
+    pub position: i16,
+}
+
+impl Shop {
+    pub fn get_by_npc(npc_id: i32) -> Result<Option<Shop>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match shops::table
+            .filter(shops::npc_id.eq(npc_id))
+            .first::<Shop>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+impl ShopItem {
+    pub fn get_by_shop(shop_id: i32) -> Result<Vec<ShopItem>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match shop_items::table
+            .filter(shop_items::shop_id.eq(shop_id))
+            .order(shop_items::position)
+            .load::<ShopItem>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::db::db;
+use crate::db::schema::skills;
+use diesel::prelude::*;
+use std::error::Error;
+
+#[derive(Queryable, Identifiable)]
+pub struct Skill {
+    pub id: i32,
+    pub character_id: i32,
+    pub skill_id: i32,
+    pub level: i16,
+    pub master_level: i16,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = skills)]
+pub struct NewSkill {
+    pub character_id: i32,
+    pub skill_id: i32,
+    pub level: i16,
+    pub master_level: i16,
+}
+
+impl Skill {
+    pub fn get_by_character(character_id: i32) -> Result<Vec<Skill>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match skills::table
+            .filter(skills::character_id.eq(character_id))
+            .order(skills::skill_id)
+            .load::<Skill>(&mut db_connection)
+        {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Replaces every stored skill of the character with `new_skills` within a transaction
+    /// the caller runs.
+    pub fn replace(connection: &mut PgConnection, character_id: i32, new_skills: &[NewSkill]) -> QueryResult<usize> {
+        diesel::delete(skills::table.filter(skills::character_id.eq(character_id))).execute(connection)?;
+
+        diesel::insert_into(skills::table)
+            .values(new_skills)
+            .execute(connection)
+    }
+}
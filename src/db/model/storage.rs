@@ -0,0 +1,166 @@
+use crate::db::db;
+use crate::db::model::character::CharacterUpdate;
+use crate::db::schema::{storage, storage_items};
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+/// The storage an account shares between its characters in one world.
+#[derive(Queryable, Identifiable, AsChangeset)]
+#[diesel(table_name = storage)]
+pub struct Storage {
+    pub id: i32,
+    pub user_id: i32,
+    pub world_id: i16,
+    pub slots: i16,
+    pub meso: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = storage)]
+pub struct NewStorage {
+    pub user_id: i32,
+    pub world_id: i16,
+    pub slots: i16,
+    pub meso: i32,
+}
+
+#[derive(Queryable, Identifiable)]
+pub struct StorageItem {
+    pub id: i32,
+    pub storage_id: i32,
+    pub position: i16,
+    pub item_id: i32,
+    pub quantity: i16,
+    pub owner: String,
+    pub flags: i16,
+    pub expiration_date: Option<SystemTime>,
+    pub upgrade_slots: i16,
+    pub upgrades: i16,
+    pub strength: i16,
+    pub dexterity: i16,
+    pub intelligence: i16,
+    pub luck: i16,
+    pub hp: i16,
+    pub mp: i16,
+    pub weapon_attack: i16,
+    pub magic_attack: i16,
+    pub weapon_defense: i16,
+    pub magic_defense: i16,
+    pub accuracy: i16,
+    pub avoidability: i16,
+    pub hands: i16,
+    pub speed: i16,
+    pub jump: i16,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = storage_items)]
+pub struct NewStorageItem {
+    pub storage_id: i32,
+    pub position: i16,
+    pub item_id: i32,
+    pub quantity: i16,
+    pub owner: String,
+    pub flags: i16,
+    pub expiration_date: Option<SystemTime>,
+    pub upgrade_slots: i16,
+    pub upgrades: i16,
+    pub strength: i16,
+    pub dexterity: i16,
+    pub intelligence: i16,
+    pub luck: i16,
+    pub hp: i16,
+    pub mp: i16,
+    pub weapon_attack: i16,
+    pub magic_attack: i16,
+    pub weapon_defense: i16,
+    pub magic_defense: i16,
+    pub accuracy: i16,
+    pub avoidability: i16,
+    pub hands: i16,
+    pub speed: i16,
+    pub jump: i16,
+}
+
+/// What a change to a storage stores along with the storage itself.
+pub struct StorageUpdate {
+    pub items: Vec<NewStorageItem>,
+    /// The character that stored or took something.
+    pub character: Option<CharacterUpdate>,
+}
+
+impl Storage {
+    /// Loads the storage of the account in the world with its items in position order, creating
+    /// an empty one with `slots` slots the first time.
+    pub fn get_or_create(
+        user_id: i32,
+        world_id: i16,
+        slots: i16,
+    ) -> Result<(Storage, Vec<StorageItem>), Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<(Storage, Vec<StorageItem>), diesel::result::Error, _>(|connection| {
+            let existing = storage::table
+                .filter(storage::user_id.eq(user_id))
+                .filter(storage::world_id.eq(world_id))
+                .order(storage::id)
+                .first::<Storage>(connection)
+                .optional()?;
+            let storage = match existing {
+                Some(storage) => storage,
+                None => diesel::insert_into(storage::table)
+                    .values(NewStorage {
+                        user_id,
+                        world_id,
+                        slots,
+                        meso: 0,
+                    })
+                    .get_result::<Storage>(connection)?,
+            };
+            let items = Self::items(connection, storage.id)?;
+            Ok((storage, items))
+        }) {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Runs `change` on the storage and its items while its row is locked, so characters of the
+    /// account on other channels wait for the change to be stored before reading it. What the
+    /// change returns replaces the items and updates the character in the same transaction.
+    pub fn update<T>(
+        storage_id: i32,
+        change: impl FnOnce(&mut Storage, Vec<StorageItem>) -> Result<(T, StorageUpdate), Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        db_connection.transaction::<T, Box<dyn Error>, _>(|connection| {
+            let mut storage = storage::table
+                .filter(storage::id.eq(storage_id))
+                .for_update()
+                .first::<Storage>(connection)?;
+            let items = Self::items(connection, storage_id)?;
+
+            let (result, update) = change(&mut storage, items)?;
+            diesel::update(&storage).set(&storage).execute(connection)?;
+            diesel::delete(storage_items::table.filter(storage_items::storage_id.eq(storage_id)))
+                .execute(connection)?;
+            diesel::insert_into(storage_items::table)
+                .values(&update.items)
+                .execute(connection)?;
+
+            if let Some(character) = update.character {
+                character.save(connection)?;
+            }
+            Ok(result)
+        })
+    }
+
+    fn items(connection: &mut PgConnection, storage_id: i32) -> QueryResult<Vec<StorageItem>> {
+        storage_items::table
+            .filter(storage_items::storage_id.eq(storage_id))
+            .order(storage_items::position)
+            .load::<StorageItem>(connection)
+    }
+}
@@ -0,0 +1,47 @@
+use crate::db::db;
+use crate::db::model::character::CharacterUpdate;
+use crate::db::schema::trade_logs;
+use diesel::prelude::*;
+use std::error::Error;
+use std::time::SystemTime;
+
+/// What one side of a completed trade gave its partner.
+#[derive(Queryable, Identifiable)]
+pub struct TradeLog {
+    pub id: i32,
+    pub character_id: i32,
+    pub partner_id: i32,
+    pub meso: i32,
+    /// The items given, as `item_id x quantity` separated by commas.
+    pub items: String,
+    pub log_date: SystemTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = trade_logs)]
+pub struct NewTradeLog {
+    pub character_id: i32,
+    pub partner_id: i32,
+    pub meso: i32,
+    pub items: String,
+    pub log_date: SystemTime,
+}
+
+impl TradeLog {
+    /// Stores both sides of a completed trade in a single transaction: the characters with
+    /// their new mesos and inventories, and the logs of what each side gave.
+    pub fn save_exchange(characters: [&CharacterUpdate; 2], logs: &[NewTradeLog]) -> Result<(), Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match db_connection.transaction::<(), diesel::result::Error, _>(|connection| {
+            for character in characters {
+                character.save(connection)?;
+            }
+            diesel::insert_into(trade_logs::table).values(logs).execute(connection)?;
+            Ok(())
+        }) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use crate::db::{db, schema};
-use crate::db::schema::users::{self, pin_code, id};
+use crate::db::schema::users::{self, pin_code, id, last_ip_address, mac_addresses, hardware_id, pin_attempts, pin_locked_until};
 use bcrypt;
 use diesel::prelude::*;
 use std::error::Error;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 #[derive(Queryable, Identifiable, AsChangeset)]
 pub struct User {
@@ -16,10 +16,12 @@ pub struct User {
     pub salt: Vec<u8>,
     pub pin_code: Option<String>,
     pub creation_date: SystemTime,
-    pub ban_reason: i16,
-    pub ban_reset_date: SystemTime,
-    pub mute_reason: i16,
-    pub mute_reset_date: SystemTime,
+    pub gm_level: i16,
+    pub last_ip_address: Option<String>,
+    pub mac_addresses: Option<String>,
+    pub hardware_id: Option<String>,
+    pub pin_attempts: i16,
+    pub pin_locked_until: Option<SystemTime>,
 }
 
 #[derive(Insertable)]
@@ -33,13 +35,28 @@ pub struct NewUser {
     pub salt: Vec<u8>,
     pub pin_code: Option<String>,
     pub creation_date: SystemTime,
-    pub ban_reason: i16,
-    pub ban_reset_date: SystemTime,
-    pub mute_reason: i16,
-    pub mute_reset_date: SystemTime,
+    pub gm_level: i16,
+    pub last_ip_address: Option<String>,
+    pub mac_addresses: Option<String>,
+    pub hardware_id: Option<String>,
+    pub pin_attempts: i16,
+    pub pin_locked_until: Option<SystemTime>,
 }
 
 impl User {
+    pub fn get_by_id(user_id: i32) -> Result<Option<User>, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+
+        match users::table
+            .filter(users::id.eq(user_id))
+            .first::<User>(&mut db_connection)
+        {
+            Ok(result) => Ok(Some(result)),
+            Err(diesel::result::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
     pub fn get_by_username(username: &str) -> Result<Option<User>, Box<dyn Error>> {
         let mut db_connection = db::DBPool::get()?.connection()?;
 
@@ -69,11 +86,75 @@ impl User {
         bcrypt::verify(password, &self.password)
     }
 
-    pub fn update_pin_code(&mut self, new_pin_code: String) -> Result<usize, Box<dyn Error>> {
+    /// Stores the PIN hashed the same way as the password.
+    pub fn update_pin_code(&mut self, new_pin_code: &str) -> Result<usize, Box<dyn Error>> {
+        let hashed_pin_code = bcrypt::hash(new_pin_code, bcrypt::DEFAULT_COST)?;
+        let mut db_connection = db::DBPool::get()?.connection()?;
+        match diesel::update(schema::users::dsl::users).filter(id.eq(self.id)).set(pin_code.eq(Some(&hashed_pin_code))).execute(&mut db_connection) {
+            Ok(affected_rows) => {
+                self.pin_code = Some(hashed_pin_code);
+                Ok(affected_rows)
+            },
+            Err(error) => Err(error.into())
+        }
+    }
+
+    /// PINs stored before they were hashed are compared as they are, see `has_legacy_pin_code`.
+    pub fn verify_pin_code(&self, candidate_pin_code: &str) -> bcrypt::BcryptResult<bool> {
+        match &self.pin_code {
+            Some(_) if self.has_legacy_pin_code() => Ok(self.pin_code.as_deref() == Some(candidate_pin_code)),
+            Some(hashed_pin_code) => bcrypt::verify(candidate_pin_code, hashed_pin_code),
+            None => Ok(false),
+        }
+    }
+
+    pub fn has_legacy_pin_code(&self) -> bool {
+        match &self.pin_code {
+            Some(stored_pin_code) => !stored_pin_code.starts_with("$2"),
+            None => false,
+        }
+    }
+
+    pub fn is_pin_locked(&self) -> bool {
+        match self.pin_locked_until {
+            Some(locked_until) => locked_until > SystemTime::now(),
+            None => false,
+        }
+    }
+
+    /// Counts a wrong PIN, locking PIN entry for `lock_duration` once `max_attempts` are reached.
+    pub fn record_pin_failure(&mut self, max_attempts: i16, lock_duration: Duration) -> Result<usize, Box<dyn Error>> {
+        let (new_pin_attempts, new_pin_locked_until) = match self.pin_attempts + 1 >= max_attempts {
+            true => (0, Some(SystemTime::now() + lock_duration)),
+            false => (self.pin_attempts + 1, self.pin_locked_until),
+        };
+        self.update_pin_attempts(new_pin_attempts, new_pin_locked_until)
+    }
+
+    pub fn reset_pin_failures(&mut self) -> Result<usize, Box<dyn Error>> {
+        self.update_pin_attempts(0, None)
+    }
+
+    fn update_pin_attempts(&mut self, new_pin_attempts: i16, new_pin_locked_until: Option<SystemTime>) -> Result<usize, Box<dyn Error>> {
+        let mut db_connection = db::DBPool::get()?.connection()?;
+        match diesel::update(schema::users::dsl::users).filter(id.eq(self.id)).set((pin_attempts.eq(new_pin_attempts), pin_locked_until.eq(new_pin_locked_until))).execute(&mut db_connection) {
+            Ok(affected_rows) => {
+                self.pin_attempts = new_pin_attempts;
+                self.pin_locked_until = new_pin_locked_until;
+                Ok(affected_rows)
+            },
+            Err(error) => Err(error.into())
+        }
+    }
+
+    /// Records the machine the account last selected a character from, so its identifiers can be banned.
+    pub fn update_machine_identifiers(&mut self, new_ip_address: Option<String>, new_mac_addresses: String, new_hardware_id: Option<String>) -> Result<usize, Box<dyn Error>> {
         let mut db_connection = db::DBPool::get()?.connection()?;
-        match diesel::update(schema::users::dsl::users).filter(id.eq(self.id)).set(pin_code.eq(Some(&new_pin_code))).execute(&mut db_connection) {
+        match diesel::update(schema::users::dsl::users).filter(id.eq(self.id)).set((last_ip_address.eq(&new_ip_address), mac_addresses.eq(Some(&new_mac_addresses)), hardware_id.eq(&new_hardware_id))).execute(&mut db_connection) {
             Ok(affected_rows) => {
-                self.pin_code = Some(new_pin_code);
+                self.last_ip_address = new_ip_address;
+                self.mac_addresses = Some(new_mac_addresses);
+                self.hardware_id = new_hardware_id;
                 Ok(affected_rows)
             },
             Err(error) => Err(error.into())
@@ -11,9 +11,343 @@ table! {
         salt -> Bytea,
         pin_code -> Nullable<Varchar>,
         creation_date -> Timestamp,
-        ban_reason -> SmallInt,
-        ban_reset_date -> Timestamp,
-        mute_reason -> SmallInt,
-        mute_reset_date -> Timestamp,
+        gm_level -> SmallInt,
+        last_ip_address -> Nullable<Varchar>,
+        mac_addresses -> Nullable<Varchar>,
+        hardware_id -> Nullable<Varchar>,
+        pin_attempts -> SmallInt,
+        pin_locked_until -> Nullable<Timestamp>,
     }
-}
\ No newline at end of file
+}
+
+table! {
+    characters(id) {
+        id -> Integer,
+        user_id -> Integer,
+        world_id -> SmallInt,
+        name -> Varchar,
+        gm_level -> SmallInt,
+        gender -> SmallInt,
+        skin -> SmallInt,
+        face -> Integer,
+        hair -> Integer,
+        level -> SmallInt,
+        job -> SmallInt,
+        strength -> SmallInt,
+        dexterity -> SmallInt,
+        intelligence -> SmallInt,
+        luck -> SmallInt,
+        hp -> SmallInt,
+        max_hp -> SmallInt,
+        mp -> SmallInt,
+        max_mp -> SmallInt,
+        ap -> SmallInt,
+        sp -> SmallInt,
+        exp -> Integer,
+        fame -> SmallInt,
+        meso -> Integer,
+        map_id -> Integer,
+        spawn_point -> SmallInt,
+        equip_slots -> SmallInt,
+        use_slots -> SmallInt,
+        setup_slots -> SmallInt,
+        etc_slots -> SmallInt,
+        cash_slots -> SmallInt,
+        buddy_capacity -> SmallInt,
+    }
+}
+
+table! {
+    gm_logs(id) {
+        id -> Integer,
+        character_id -> Integer,
+        command -> Varchar,
+        arguments -> Varchar,
+        succeeded -> Bool,
+        execution_date -> Timestamp,
+    }
+}
+
+table! {
+    inventory_items(id) {
+        id -> Integer,
+        character_id -> Integer,
+        inventory_type -> SmallInt,
+        position -> SmallInt,
+        item_id -> Integer,
+        quantity -> SmallInt,
+        owner -> Varchar,
+        flags -> SmallInt,
+        expiration_date -> Nullable<Timestamp>,
+        upgrade_slots -> SmallInt,
+        upgrades -> SmallInt,
+        strength -> SmallInt,
+        dexterity -> SmallInt,
+        intelligence -> SmallInt,
+        luck -> SmallInt,
+        hp -> SmallInt,
+        mp -> SmallInt,
+        weapon_attack -> SmallInt,
+        magic_attack -> SmallInt,
+        weapon_defense -> SmallInt,
+        magic_defense -> SmallInt,
+        accuracy -> SmallInt,
+        avoidability -> SmallInt,
+        hands -> SmallInt,
+        speed -> SmallInt,
+        jump -> SmallInt,
+    }
+}
+
+table! {
+    infractions(id) {
+        id -> Integer,
+        kind -> SmallInt,
+        user_id -> Nullable<Integer>,
+        ip_address -> Nullable<Varchar>,
+        mac_address -> Nullable<Varchar>,
+        hardware_id -> Nullable<Varchar>,
+        reason -> SmallInt,
+        description -> Varchar,
+        issuer_id -> Nullable<Integer>,
+        issue_date -> Timestamp,
+        expiry_date -> Nullable<Timestamp>,
+        revoker_id -> Nullable<Integer>,
+        revocation_date -> Nullable<Timestamp>,
+    }
+}
+table! {
+    cheat_flags(id) {
+        id -> Integer,
+        character_id -> Integer,
+        kind -> SmallInt,
+        description -> Varchar,
+        flag_date -> Timestamp,
+    }
+}
+
+table! {
+    skills(id) {
+        id -> Integer,
+        character_id -> Integer,
+        skill_id -> Integer,
+        level -> SmallInt,
+        master_level -> SmallInt,
+    }
+}
+
+table! {
+    shops(id) {
+        id -> Integer,
+        npc_id -> Integer,
+    }
+}
+
+table! {
+    shop_items(id) {
+        id -> Integer,
+        shop_id -> Integer,
+        item_id -> Integer,
+        price -> Integer,
+        position -> SmallInt,
+    }
+}
+
+table! {
+    meso_logs(id) {
+        id -> Integer,
+        character_id -> Integer,
+        amount -> Integer,
+        reason -> SmallInt,
+        description -> Varchar,
+        log_date -> Timestamp,
+    }
+}
+
+table! {
+    quest_status(id) {
+        id -> Integer,
+        character_id -> Integer,
+        quest_id -> Integer,
+        status -> SmallInt,
+        progress -> Varchar,
+        completed_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    reactor_drops(id) {
+        id -> Integer,
+        reactor_id -> Integer,
+        item_id -> Integer,
+        chance -> Integer,
+    }
+}
+
+table! {
+    buddies(id) {
+        id -> Integer,
+        character_id -> Integer,
+        buddy_id -> Integer,
+        buddy_name -> Varchar,
+        group_name -> Varchar,
+        pending -> Bool,
+    }
+}
+
+table! {
+    guilds(id) {
+        id -> Integer,
+        world_id -> SmallInt,
+        name -> Varchar,
+        leader_id -> Integer,
+        rank_titles -> Varchar,
+        capacity -> SmallInt,
+        emblem_background -> SmallInt,
+        emblem_background_color -> SmallInt,
+        emblem_logo -> SmallInt,
+        emblem_logo_color -> SmallInt,
+        notice -> Varchar,
+        points -> Integer,
+    }
+}
+
+table! {
+    guild_members(id) {
+        id -> Integer,
+        guild_id -> Integer,
+        character_id -> Integer,
+        name -> Varchar,
+        job -> SmallInt,
+        level -> SmallInt,
+        rank -> SmallInt,
+    }
+}
+
+table! {
+    trade_logs(id) {
+        id -> Integer,
+        character_id -> Integer,
+        partner_id -> Integer,
+        meso -> Integer,
+        items -> Varchar,
+        log_date -> Timestamp,
+    }
+}
+
+table! {
+    storage(id) {
+        id -> Integer,
+        user_id -> Integer,
+        world_id -> SmallInt,
+        slots -> SmallInt,
+        meso -> Integer,
+    }
+}
+
+table! {
+    storage_items(id) {
+        id -> Integer,
+        storage_id -> Integer,
+        position -> SmallInt,
+        item_id -> Integer,
+        quantity -> SmallInt,
+        owner -> Varchar,
+        flags -> SmallInt,
+        expiration_date -> Nullable<Timestamp>,
+        upgrade_slots -> SmallInt,
+        upgrades -> SmallInt,
+        strength -> SmallInt,
+        dexterity -> SmallInt,
+        intelligence -> SmallInt,
+        luck -> SmallInt,
+        hp -> SmallInt,
+        mp -> SmallInt,
+        weapon_attack -> SmallInt,
+        magic_attack -> SmallInt,
+        weapon_defense -> SmallInt,
+        magic_defense -> SmallInt,
+        accuracy -> SmallInt,
+        avoidability -> SmallInt,
+        hands -> SmallInt,
+        speed -> SmallInt,
+        jump -> SmallInt,
+    }
+}
+
+table! {
+    merchants(id) {
+        id -> Integer,
+        character_id -> Integer,
+        world_id -> SmallInt,
+        channel_id -> SmallInt,
+        map_id -> Integer,
+        title -> Varchar,
+        meso -> Integer,
+        open -> Bool,
+        opening_date -> Timestamp,
+    }
+}
+
+table! {
+    merchant_items(id) {
+        id -> Integer,
+        merchant_id -> Integer,
+        position -> SmallInt,
+        price -> Integer,
+        bundles -> SmallInt,
+        item_id -> Integer,
+        quantity -> SmallInt,
+        owner -> Varchar,
+        flags -> SmallInt,
+        expiration_date -> Nullable<Timestamp>,
+        upgrade_slots -> SmallInt,
+        upgrades -> SmallInt,
+        strength -> SmallInt,
+        dexterity -> SmallInt,
+        intelligence -> SmallInt,
+        luck -> SmallInt,
+        hp -> SmallInt,
+        mp -> SmallInt,
+        weapon_attack -> SmallInt,
+        magic_attack -> SmallInt,
+        weapon_defense -> SmallInt,
+        magic_defense -> SmallInt,
+        accuracy -> SmallInt,
+        avoidability -> SmallInt,
+        hands -> SmallInt,
+        speed -> SmallInt,
+        jump -> SmallInt,
+    }
+}
+
+table! {
+    minigame_records(id) {
+        id -> Integer,
+        character_id -> Integer,
+        game -> SmallInt,
+        wins -> Integer,
+        ties -> Integer,
+        losses -> Integer,
+    }
+}
+
+table! {
+    character_migrations(id) {
+        id -> Integer,
+        user_id -> Integer,
+        character_id -> Integer,
+        ip_address -> Varchar,
+        creation_date -> Timestamp,
+    }
+}
+
+table! {
+    sessions(user_id) {
+        user_id -> Integer,
+        character_id -> Integer,
+        world_id -> SmallInt,
+        channel_id -> SmallInt,
+        login_date -> Timestamp,
+    }
+}
@@ -1,11 +1,25 @@
 pub const DEFAULT_CLIENT_WORKERS: usize = 10;
 pub const DEFAULT_CLIENTS_THREADS: usize = 100;
 pub const DEFAULT_HEADER_LENGTH: usize = 4;
+pub const DEFAULT_BAN_LIST_RELOAD_SECONDS: u64 = 300;
+pub const DEFAULT_MAX_CONNECTIONS: usize = 1000;
+pub const DEFAULT_MAX_CONNECTIONS_PER_IP: usize = 10;
+pub const DEFAULT_CONNECTIONS_PER_IP_PER_MINUTE: usize = 30;
+pub const DEFAULT_MAX_LOGIN_ATTEMPTS: u32 = 5;
+pub const DEFAULT_LOGIN_BLOCK_SECONDS: u64 = 300;
+pub const DEFAULT_PIN_MAX_ATTEMPTS: i16 = 5;
+pub const DEFAULT_PIN_LOCK_SECONDS: u64 = 900;
+pub const DEFAULT_INTERSERVER_ADDRESS: &str = "127.0.0.1";
+pub const DEFAULT_INTERSERVER_PORT: u16 = 8600;
 
 // constants
 pub const MAPLESTORY_LOCALE: u8 = 8;
 pub const MAPLESTORY_VERSION: u16 = 62;
 pub const MAPLESTORY_SUBVERSION: &str = "1";
+pub const MAP_UPDATE_INTERVAL_MILLISECONDS: u64 = 1000;
+pub const BUFF_UPDATE_INTERVAL_MILLISECONDS: u64 = 1000;
+pub const MERCHANT_UPDATE_INTERVAL_MILLISECONDS: u64 = 60000;
 pub const USER_SEQUENCE_SIZE: usize = 4;
+pub const PIN_CODE_LENGTH: usize = 4;
 pub const AES_KEY_SIZE: usize = 32;
 pub const AES_BLOCK_SIZE: usize = 16;
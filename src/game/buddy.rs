@@ -0,0 +1,268 @@
+use crate::db::model::buddy::{Buddy, NewBuddy};
+use crate::db::model::character::Character;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::net::client::{Client, PacketSender};
+use crate::net::interserver::{ChannelMessage, WorldLink};
+use crate::net::packet::buddy as buddy_packet;
+use log::warn;
+use std::error::Error;
+
+/// Group buddies are put in when the client names none.
+pub const DEFAULT_GROUP: &str = "Default Group";
+
+/// Outcomes of buddy list changes the player is told about, by their code in the packet.
+#[derive(Clone, Copy)]
+pub enum BuddyResult {
+    ListFull = 11,
+    TargetListFull = 12,
+    AlreadyRegistered = 13,
+    CannotAddGm = 14,
+    NotFound = 15,
+}
+
+pub struct BuddyEntry {
+    pub character_id: i32,
+    pub name: String,
+    pub group: String,
+    /// Whether the character asked to be added and is waiting for an answer.
+    pub pending: bool,
+    /// The channel the buddy plays on; `None` while offline or while it does not have the
+    /// player on its own list.
+    pub channel_id: Option<u8>,
+}
+
+/// The buddy list of a character, along with the buddy requests it has yet to answer.
+pub struct BuddyList {
+    pub capacity: usize,
+    pub entries: Vec<BuddyEntry>,
+}
+
+impl BuddyList {
+    pub fn load(character: &Character) -> Result<BuddyList, Box<dyn Error>> {
+        Ok(BuddyList {
+            capacity: character.buddy_capacity.max(0) as usize,
+            entries: Buddy::get_by_character(character.id)?
+                .into_iter()
+                .map(|buddy| BuddyEntry {
+                    character_id: buddy.buddy_id,
+                    name: buddy.buddy_name,
+                    group: buddy.group_name,
+                    pending: buddy.pending,
+                    channel_id: None,
+                })
+                .collect(),
+        })
+    }
+
+    pub fn get_mut(&mut self, character_id: i32) -> Option<&mut BuddyEntry> {
+        self.entries.iter_mut().find(|entry| entry.character_id == character_id)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.entries.iter().filter(|entry| !entry.pending).count() >= self.capacity
+    }
+
+    /// The ids of the buddies the character accepted.
+    pub fn accepted_ids(&self) -> Vec<i32> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.pending)
+            .map(|entry| entry.character_id)
+            .collect()
+    }
+
+    fn next_request(&self) -> Option<&BuddyEntry> {
+        self.entries.iter().find(|entry| entry.pending)
+    }
+}
+
+/// Shows the buddy list to a player that just logged in, along with the first request it has
+/// yet to answer, and tells the world server about its buddies.
+pub fn enter(sender: &PacketSender, character_id: i32, buddies: &BuddyList) {
+    sender.send(buddy_packet::create_buddy_list(buddies));
+    if let Some(request) = buddies.next_request() {
+        sender.send(buddy_packet::create_buddy_request(request.character_id, &request.name, None));
+    }
+    report(character_id, buddies);
+}
+
+/// Tells the world server who the character has on its buddy list, which shows them to each
+/// other while both have the other on their list.
+pub fn report(character_id: i32, buddies: &BuddyList) {
+    let result = WorldLink::get()
+        .and_then(|link| link.send(&ChannelMessage::Buddies(character_id, buddies.accepted_ids())));
+    if let Err(error) = result {
+        warn!("Unable to report buddies of character {} to the world server [{}]", character_id, error);
+    }
+}
+
+/// Adds the named character to the buddy list, asking it to add the player in return unless it
+/// already did. Adding a character that asked first accepts its request, and adding a buddy
+/// again moves it to `group`.
+pub fn add(
+    client: &Client,
+    character: &Character,
+    buddies: &mut BuddyList,
+    name: &str,
+    group: String,
+) -> Result<(), Box<dyn Error>> {
+    let target = match Character::get_by_name(name)? {
+        Some(target) if target.world_id == character.world_id && target.id != character.id => target,
+        _ => {
+            client.send(buddy_packet::create_buddy_result(BuddyResult::NotFound));
+            return Ok(());
+        }
+    };
+
+    match buddies.get_mut(target.id) {
+        Some(entry) if entry.pending => return accept(client, character, buddies, target.id),
+        Some(entry) if entry.group == group => {
+            client.send(buddy_packet::create_buddy_result(BuddyResult::AlreadyRegistered));
+            return Ok(());
+        }
+        Some(entry) => {
+            Buddy::set_group(character.id, target.id, &group)?;
+            entry.group = group;
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let result = match buddies.is_full() {
+        true => Some(BuddyResult::ListFull),
+        false if target.gm_level > 0 && character.gm_level == 0 => Some(BuddyResult::CannotAddGm),
+        false => None,
+    };
+    if let Some(result) = result {
+        client.send(buddy_packet::create_buddy_result(result));
+        return Ok(());
+    }
+
+    let mut new_buddies = vec![NewBuddy {
+        character_id: character.id,
+        buddy_id: target.id,
+        buddy_name: target.name.clone(),
+        group_name: group.clone(),
+        pending: false,
+    }];
+    // A character that already has the player on its list only needs to see it come online.
+    let requested = Buddy::get(target.id, character.id)?.is_none();
+    if requested {
+        if Buddy::count_accepted(target.id)? >= target.buddy_capacity as i64 {
+            client.send(buddy_packet::create_buddy_result(BuddyResult::TargetListFull));
+            return Ok(());
+        }
+        new_buddies.push(NewBuddy {
+            character_id: target.id,
+            buddy_id: character.id,
+            buddy_name: character.name.clone(),
+            group_name: DEFAULT_GROUP.to_string(),
+            pending: true,
+        });
+    }
+    Buddy::create(&new_buddies)?;
+
+    buddies.entries.push(BuddyEntry {
+        character_id: target.id,
+        name: target.name,
+        group,
+        pending: false,
+        channel_id: None,
+    });
+    client.send(buddy_packet::create_buddy_list(buddies));
+    report(character.id, buddies);
+
+    if requested {
+        WorldLink::get()?.send(&ChannelMessage::BuddyRequest {
+            character_id: target.id,
+            from_id: character.id,
+            from_name: character.name.clone(),
+        })?;
+    }
+    Ok(())
+}
+
+/// Accepts the request of `buddy_id`, adding it to the buddy list.
+pub fn accept(client: &Client, character: &Character, buddies: &mut BuddyList, buddy_id: i32) -> Result<(), Box<dyn Error>> {
+    let full = buddies.is_full();
+    let entry = match buddies.get_mut(buddy_id) {
+        Some(entry) if entry.pending => entry,
+        _ => return Err(format!("{} has no buddy request of character {}", character.name, buddy_id).into()),
+    };
+    if full {
+        client.send(buddy_packet::create_buddy_result(BuddyResult::ListFull));
+        return Ok(());
+    }
+
+    Buddy::accept(character.id, buddy_id)?;
+    entry.pending = false;
+    client.send(buddy_packet::create_buddy_list(buddies));
+    report(character.id, buddies);
+    show_next_request(client, buddies);
+    Ok(())
+}
+
+/// Removes `buddy_id` from the buddy list, which also turns down its request if it is pending.
+pub fn delete(client: &Client, character: &Character, buddies: &mut BuddyList, buddy_id: i32) -> Result<(), Box<dyn Error>> {
+    let pending = match buddies.get_mut(buddy_id) {
+        Some(entry) => entry.pending,
+        None => return Err(format!("{} has no buddy {}", character.name, buddy_id).into()),
+    };
+
+    Buddy::delete(character.id, buddy_id)?;
+    buddies.entries.retain(|entry| entry.character_id != buddy_id);
+    client.send(buddy_packet::create_buddy_list(buddies));
+    match pending {
+        true => show_next_request(client, buddies),
+        false => report(character.id, buddies),
+    }
+    Ok(())
+}
+
+fn show_next_request(client: &Client, buddies: &BuddyList) {
+    if let Some(request) = buddies.next_request() {
+        client.send(buddy_packet::create_buddy_request(request.character_id, &request.name, None));
+    }
+}
+
+/// Shows the request of `from_name` to `character_id`, if it plays on this channel.
+pub fn request(character_id: i32, from_id: i32, from_name: String, channel_id: u8) -> Result<(), Box<dyn Error>> {
+    let player = match Channel::get()?.player(character_id) {
+        Some(player) => player,
+        None => return Ok(()),
+    };
+
+    character::with_character(&player.client, |client, _| {
+        let mut buddies = character::lock_buddies(client)?;
+        if buddies.get_mut(from_id).is_some() {
+            return Ok(());
+        }
+
+        client.send(buddy_packet::create_buddy_request(from_id, &from_name, Some(channel_id)));
+        buddies.entries.push(BuddyEntry {
+            character_id: from_id,
+            name: from_name,
+            group: DEFAULT_GROUP.to_string(),
+            pending: true,
+            channel_id: None,
+        });
+        Ok(())
+    })
+}
+
+/// Shows `buddy_id` on the buddy list of `character_id` as playing on `channel_id`, or offline.
+pub fn update_channel(character_id: i32, buddy_id: i32, channel_id: Option<u8>) -> Result<(), Box<dyn Error>> {
+    let player = match Channel::get()?.player(character_id) {
+        Some(player) => player,
+        None => return Ok(()),
+    };
+
+    character::with_character(&player.client, |client, _| {
+        if let Some(entry) = character::lock_buddies(client)?.get_mut(buddy_id) {
+            entry.channel_id = channel_id;
+            client.send(buddy_packet::create_update_buddy_channel(buddy_id, channel_id));
+        }
+        Ok(())
+    })
+}
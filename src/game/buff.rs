@@ -0,0 +1,155 @@
+use crate::data::skill::SkillLevelData;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::net::client::Client;
+use crate::net::packet::buff;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Stats a buff can raise, by the bit the client knows them as. Declared in mask order, which
+/// is also the order their values are written in.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BuffStat {
+    WeaponAttack = 0x1,
+    WeaponDefense = 0x2,
+    MagicAttack = 0x4,
+    MagicDefense = 0x8,
+    Accuracy = 0x10,
+    Avoidability = 0x20,
+    Speed = 0x80,
+    Jump = 0x100,
+}
+
+pub struct Buff {
+    pub skill_id: i32,
+    pub stats: Vec<(BuffStat, i16)>,
+    pub duration: Duration,
+    expires_at: Instant,
+}
+
+impl Buff {
+    /// The buff a skill gives at `level`, if it lasts for a while and raises any stat.
+    pub fn from_skill(skill_id: i32, level: &SkillLevelData, now: Instant) -> Option<Buff> {
+        let duration = level.duration?;
+        let stats: Vec<(BuffStat, i16)> = [
+            (BuffStat::WeaponAttack, level.weapon_attack),
+            (BuffStat::WeaponDefense, level.weapon_defense),
+            (BuffStat::MagicAttack, level.magic_attack as i16),
+            (BuffStat::MagicDefense, level.magic_defense),
+            (BuffStat::Accuracy, level.accuracy),
+            (BuffStat::Avoidability, level.avoidability),
+            (BuffStat::Speed, level.speed),
+            (BuffStat::Jump, level.jump),
+        ]
+        .iter()
+        .copied()
+        .filter(|(_, value)| *value != 0)
+        .collect();
+
+        match stats.is_empty() {
+            true => None,
+            false => Some(Buff {
+                skill_id,
+                stats,
+                duration,
+                expires_at: now + duration,
+            }),
+        }
+    }
+
+    pub fn mask(&self) -> u64 {
+        self.stats.iter().fold(0, |mask, (stat, _)| mask | *stat as u64)
+    }
+}
+
+/// The buffs, skill cooldowns and recovery timer of a character, which only last while it is
+/// online.
+#[derive(Default)]
+pub struct CharacterBuffs {
+    buffs: Vec<Buff>,
+    cooldowns: HashMap<i32, Instant>,
+    last_recovery: Option<Instant>,
+}
+
+impl CharacterBuffs {
+    /// The highest raise of `stat` among the active buffs.
+    pub fn stat(&self, stat: BuffStat) -> i16 {
+        self.buffs
+            .iter()
+            .flat_map(|buff| buff.stats.iter())
+            .filter(|(buff_stat, _)| *buff_stat == stat)
+            .map(|(_, value)| *value)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Adds `buff`, replacing an earlier cast of the same skill.
+    pub fn give(&mut self, buff: Buff) {
+        self.buffs.retain(|active| active.skill_id != buff.skill_id);
+        self.buffs.push(buff);
+    }
+
+    pub fn cancel(&mut self, skill_id: i32) -> Option<Buff> {
+        let index = self.buffs.iter().position(|buff| buff.skill_id == skill_id)?;
+        Some(self.buffs.remove(index))
+    }
+
+    pub fn take_all(&mut self) -> Vec<Buff> {
+        self.buffs.drain(..).collect()
+    }
+
+    fn take_expired(&mut self, now: Instant) -> Vec<Buff> {
+        let (expired, active) = self.buffs.drain(..).partition(|buff| buff.expires_at <= now);
+        self.buffs = active;
+        expired
+    }
+
+    pub fn is_cooling_down(&self, skill_id: i32, now: Instant) -> bool {
+        self.cooldowns.get(&skill_id).is_some_and(|ready_at| *ready_at > now)
+    }
+
+    /// Starts a natural HP/MP recovery unless the previous one was less than `interval` ago.
+    pub fn start_recovery(&mut self, now: Instant, interval: Duration) -> bool {
+        match self.last_recovery {
+            Some(last_recovery) if now < last_recovery + interval => false,
+            _ => {
+                self.last_recovery = Some(now);
+                true
+            }
+        }
+    }
+
+    pub fn start_cooldown(&mut self, skill_id: i32, now: Instant, cooldown: Duration) {
+        self.cooldowns.retain(|_, ready_at| *ready_at > now);
+        self.cooldowns.insert(skill_id, now + cooldown);
+    }
+}
+
+/// Removes `buff` from the character, telling its client and the players in its map.
+pub fn cancel(client: &Client, character_id: i32, map_id: i32, buff: &Buff) -> Result<(), Box<dyn Error>> {
+    client.send(buff::create_cancel_buff(buff.mask()));
+
+    let map = Channel::get()?.map(map_id)?;
+    match map.lock() {
+        Ok(map_guard) => map_guard.broadcast(
+            &buff::create_cancel_foreign_buff(character_id, buff.mask()),
+            Some(character_id),
+        ),
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+
+    Ok(())
+}
+
+/// Cancels the buffs of the player whose time ran out.
+pub fn expire(client: &Arc<Mutex<Client>>) -> Result<(), Box<dyn Error>> {
+    character::with_character(client, |client, character| {
+        let expired = character::lock_buffs(client)?.take_expired(Instant::now());
+        for buff in expired {
+            cancel(client, character.id, character.map_id, &buff)?;
+        }
+        Ok(())
+    })
+}
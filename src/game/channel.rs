@@ -0,0 +1,386 @@
+use crate::game::buff;
+use crate::game::guild::Guild;
+use crate::game::map::Map;
+use crate::game::merchant;
+use crate::game::party::Party;
+use crate::game::room::Room;
+use crate::game::trade::Trade;
+use crate::net::client::{Client, PacketSender};
+use log::warn;
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct Player {
+    pub character_id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub client: Arc<Mutex<Client>>,
+    pub sender: PacketSender,
+}
+
+/// Multipliers of the world, applied to EXP, meso and drop chances of mobs.
+#[derive(Clone, Copy)]
+pub struct Rates {
+    pub exp: u32,
+    pub meso: u32,
+    pub drop: u32,
+}
+
+pub struct Channel {
+    world_id: u8,
+    channel_id: u8,
+    rates: Rates,
+    players: RwLock<HashMap<i32, Player>>,
+    maps: Mutex<HashMap<i32, Arc<Mutex<Map>>>>,
+    /// Copies of the parties with members on this channel, as last sent by the world server.
+    parties: RwLock<HashMap<i32, Party>>,
+    /// Copies of the guilds with members online, as last sent by the world server.
+    guilds: RwLock<HashMap<i32, Guild>>,
+    /// The trade of every character that opened or joined one. Each trade is locked after the
+    /// inventories of its traders.
+    trades: Mutex<HashMap<i32, Arc<Mutex<Trade>>>>,
+    /// Player shops, hired merchants and mini-game rooms by room id. Each room is locked after
+    /// the inventories of the characters in it.
+    rooms: Mutex<HashMap<i32, Arc<Mutex<Room>>>>,
+    /// The room of every character that opened or visits one.
+    room_visits: Mutex<HashMap<i32, i32>>,
+    next_room_id: AtomicI32,
+    /// Mesos characters paid for a guild request the world server has not answered yet.
+    guild_fees: Mutex<HashMap<i32, i32>>,
+}
+
+/// Room ids are handed out from here, far above the character ids trades are known by, which
+/// share the packets that visit rooms.
+const FIRST_ROOM_ID: i32 = 0x40000000;
+
+static CHANNEL_INSTANCE: OnceCell<Channel> = OnceCell::new();
+
+impl Channel {
+    pub fn get() -> Result<&'static Channel, Box<dyn Error>> {
+        match CHANNEL_INSTANCE.get() {
+            Some(channel) => Ok(channel),
+            None => Err("Channel not initialized".into()),
+        }
+    }
+
+    pub fn init(world_id: u8, channel_id: u8, rates: Rates) -> Result<(), Box<dyn Error>> {
+        match CHANNEL_INSTANCE.set(Channel {
+            world_id,
+            channel_id,
+            rates,
+            players: RwLock::new(HashMap::new()),
+            maps: Mutex::new(HashMap::new()),
+            parties: RwLock::new(HashMap::new()),
+            guilds: RwLock::new(HashMap::new()),
+            trades: Mutex::new(HashMap::new()),
+            rooms: Mutex::new(HashMap::new()),
+            room_visits: Mutex::new(HashMap::new()),
+            next_room_id: AtomicI32::new(FIRST_ROOM_ID),
+            guild_fees: Mutex::new(HashMap::new()),
+        }) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Channel already initialized".into()),
+        }
+    }
+
+    pub fn world_id(&self) -> u8 {
+        self.world_id
+    }
+
+    pub fn channel_id(&self) -> u8 {
+        self.channel_id
+    }
+
+    pub fn rates(&self) -> Rates {
+        self.rates
+    }
+
+    pub fn add_player(&self, player: Player) -> Result<(), Box<dyn Error>> {
+        match self.players.write() {
+            Ok(mut players) => {
+                players.insert(player.character_id, player);
+                Ok(())
+            }
+            Err(error) => Err(format!("Unable to lock players RwLock [{}]", error).into()),
+        }
+    }
+
+    pub fn remove_player(&self, character_id: i32) -> Option<Player> {
+        match self.players.write() {
+            Ok(mut players) => players.remove(&character_id),
+            Err(_) => None,
+        }
+    }
+
+    pub fn player(&self, character_id: i32) -> Option<Player> {
+        match self.players.read() {
+            Ok(players) => players.get(&character_id).cloned(),
+            Err(_) => None,
+        }
+    }
+
+    pub fn player_by_name(&self, name: &str) -> Option<Player> {
+        match self.players.read() {
+            Ok(players) => players
+                .values()
+                .find(|player| player.name.eq_ignore_ascii_case(name))
+                .cloned(),
+            Err(_) => None,
+        }
+    }
+
+    pub fn players(&self) -> Vec<Player> {
+        match self.players.read() {
+            Ok(players) => players.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn broadcast(&self, buffer: &[u8]) {
+        for player in self.players() {
+            player.sender.send(buffer.to_vec());
+        }
+    }
+
+    /// Sends the packet to the characters among `character_ids` that play on this channel.
+    pub fn send_to(&self, character_ids: &[i32], buffer: &[u8]) {
+        for character_id in character_ids {
+            if let Some(player) = self.player(*character_id) {
+                player.sender.send(buffer.to_vec());
+            }
+        }
+    }
+
+    pub fn update_party(&self, party: Party) -> Result<(), Box<dyn Error>> {
+        match self.parties.write() {
+            Ok(mut parties) => {
+                parties.insert(party.id, party);
+                Ok(())
+            }
+            Err(error) => Err(format!("Unable to lock parties RwLock [{}]", error).into()),
+        }
+    }
+
+    pub fn remove_party(&self, party_id: i32) -> Result<(), Box<dyn Error>> {
+        match self.parties.write() {
+            Ok(mut parties) => {
+                parties.remove(&party_id);
+                Ok(())
+            }
+            Err(error) => Err(format!("Unable to lock parties RwLock [{}]", error).into()),
+        }
+    }
+
+    pub fn party_of(&self, character_id: i32) -> Option<Party> {
+        match self.parties.read() {
+            Ok(parties) => parties
+                .values()
+                .find(|party| party.member(character_id).is_some())
+                .cloned(),
+            Err(_) => None,
+        }
+    }
+
+    pub fn party_id(&self, character_id: i32) -> Option<i32> {
+        self.party_of(character_id).map(|party| party.id)
+    }
+
+    pub fn update_guild(&self, guild: Guild) -> Result<(), Box<dyn Error>> {
+        match self.guilds.write() {
+            Ok(mut guilds) => {
+                guilds.insert(guild.id, guild);
+                Ok(())
+            }
+            Err(error) => Err(format!("Unable to lock guilds RwLock [{}]", error).into()),
+        }
+    }
+
+    pub fn remove_guild(&self, guild_id: i32) -> Result<(), Box<dyn Error>> {
+        match self.guilds.write() {
+            Ok(mut guilds) => {
+                guilds.remove(&guild_id);
+                Ok(())
+            }
+            Err(error) => Err(format!("Unable to lock guilds RwLock [{}]", error).into()),
+        }
+    }
+
+    pub fn guild_of(&self, character_id: i32) -> Option<Guild> {
+        match self.guilds.read() {
+            Ok(guilds) => guilds
+                .values()
+                .find(|guild| guild.member(character_id).is_some())
+                .cloned(),
+            Err(_) => None,
+        }
+    }
+
+    pub fn add_trade(&self, character_id: i32, trade: Arc<Mutex<Trade>>) -> Result<(), Box<dyn Error>> {
+        match self.trades.lock() {
+            Ok(mut trades) => {
+                trades.insert(character_id, trade);
+                Ok(())
+            }
+            Err(error) => Err(format!("Unable to lock trades Mutex [{}]", error).into()),
+        }
+    }
+
+    pub fn remove_trade(&self, character_id: i32) -> Option<Arc<Mutex<Trade>>> {
+        match self.trades.lock() {
+            Ok(mut trades) => trades.remove(&character_id),
+            Err(_) => None,
+        }
+    }
+
+    pub fn trade(&self, character_id: i32) -> Option<Arc<Mutex<Trade>>> {
+        match self.trades.lock() {
+            Ok(trades) => trades.get(&character_id).cloned(),
+            Err(_) => None,
+        }
+    }
+
+    /// Remembers the fee the character paid, returning false if it still waits on another.
+    pub fn add_guild_fee(&self, character_id: i32, fee: i32) -> Result<bool, Box<dyn Error>> {
+        match self.guild_fees.lock() {
+            Ok(mut guild_fees) => match guild_fees.contains_key(&character_id) {
+                true => Ok(false),
+                false => {
+                    guild_fees.insert(character_id, fee);
+                    Ok(true)
+                }
+            },
+            Err(error) => Err(format!("Unable to lock guild fees Mutex [{}]", error).into()),
+        }
+    }
+
+    pub fn take_guild_fee(&self, character_id: i32) -> Option<i32> {
+        match self.guild_fees.lock() {
+            Ok(mut guild_fees) => guild_fees.remove(&character_id),
+            Err(_) => None,
+        }
+    }
+
+    pub fn next_room_id(&self) -> i32 {
+        self.next_room_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn add_room(&self, room_id: i32, room: Arc<Mutex<Room>>) -> Result<(), Box<dyn Error>> {
+        match self.rooms.lock() {
+            Ok(mut rooms) => {
+                rooms.insert(room_id, room);
+                Ok(())
+            }
+            Err(error) => Err(format!("Unable to lock rooms Mutex [{}]", error).into()),
+        }
+    }
+
+    pub fn remove_room(&self, room_id: i32) -> Option<Arc<Mutex<Room>>> {
+        match self.rooms.lock() {
+            Ok(mut rooms) => rooms.remove(&room_id),
+            Err(_) => None,
+        }
+    }
+
+    pub fn room(&self, room_id: i32) -> Option<Arc<Mutex<Room>>> {
+        match self.rooms.lock() {
+            Ok(rooms) => rooms.get(&room_id).cloned(),
+            Err(_) => None,
+        }
+    }
+
+    pub fn rooms(&self) -> Vec<Arc<Mutex<Room>>> {
+        match self.rooms.lock() {
+            Ok(rooms) => rooms.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn enter_room(&self, character_id: i32, room_id: i32) -> Result<(), Box<dyn Error>> {
+        match self.room_visits.lock() {
+            Ok(mut room_visits) => {
+                room_visits.insert(character_id, room_id);
+                Ok(())
+            }
+            Err(error) => Err(format!("Unable to lock room visits Mutex [{}]", error).into()),
+        }
+    }
+
+    pub fn exit_room(&self, character_id: i32) -> Option<i32> {
+        match self.room_visits.lock() {
+            Ok(mut room_visits) => room_visits.remove(&character_id),
+            Err(_) => None,
+        }
+    }
+
+    /// The room the character opened or visits.
+    pub fn room_of(&self, character_id: i32) -> Option<Arc<Mutex<Room>>> {
+        let room_id = match self.room_visits.lock() {
+            Ok(room_visits) => *room_visits.get(&character_id)?,
+            Err(_) => return None,
+        };
+        self.room(room_id)
+    }
+
+    /// Returns the instance of `map_id` in this channel, creating it on first use.
+    pub fn map(&self, map_id: i32) -> Result<Arc<Mutex<Map>>, Box<dyn Error>> {
+        match self.maps.lock() {
+            Ok(mut maps) => Ok(maps
+                .entry(map_id)
+                .or_insert_with(|| Arc::new(Mutex::new(Map::new(map_id))))
+                .clone()),
+            Err(error) => Err(format!("Unable to lock maps Mutex [{}]", error).into()),
+        }
+    }
+
+    /// Updates every instantiated map each `interval`, running their timed events.
+    pub fn spawn_map_updater(&'static self, interval: Duration) {
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            let maps: Vec<Arc<Mutex<Map>>> = match self.maps.lock() {
+                Ok(maps) => maps.values().cloned().collect(),
+                Err(error) => {
+                    warn!("Unable to lock maps Mutex [{}]", error);
+                    continue;
+                }
+            };
+
+            for map in maps {
+                match map.lock() {
+                    Ok(mut map_guard) => map_guard.update(),
+                    Err(error) => warn!("Unable to lock Map Mutex [{}]", error),
+                }
+            }
+        });
+    }
+
+    /// Expires the buffs of every player each `interval`.
+    pub fn spawn_buff_updater(&'static self, interval: Duration) {
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            for player in self.players() {
+                if let Err(error) = buff::expire(&player.client) {
+                    warn!("Unable to expire buffs of {} [{}]", player.name, error);
+                }
+            }
+        });
+    }
+
+    /// Closes the hired merchants that stayed open for too long each `interval`.
+    pub fn spawn_merchant_updater(&'static self, interval: Duration) {
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            if let Err(error) = merchant::close_expired() {
+                warn!("Unable to close expired merchants [{}]", error);
+            }
+        });
+    }
+}
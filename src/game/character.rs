@@ -0,0 +1,259 @@
+use crate::data::map::MapData;
+use crate::db::model::character::Character;
+use crate::game::buddy::BuddyList;
+use crate::game::buff::CharacterBuffs;
+use crate::game::channel::Channel;
+use crate::game::inventory::{CharacterInventory, Inventory, InventoryType};
+use crate::game::map::MapPlayer;
+use crate::game::party;
+use crate::game::quest::CharacterQuests;
+use crate::game::skill::CharacterSkills;
+use crate::net::client::{Client, PacketSender};
+use crate::net::packet::field;
+use crate::script;
+use std::error::Error;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// How far from a portal, in pixels along both axes, a character may be to use it.
+const MAX_PORTAL_DISTANCE: i32 = 300;
+
+pub fn enter_map(
+    sender: &PacketSender,
+    character: &Character,
+    equipped: &Inventory,
+) -> Result<(), Box<dyn Error>> {
+    let map = Channel::get()?.map(character.map_id)?;
+    let mut map_guard = match map.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+
+    let guild = Channel::get()?.guild_of(character.id);
+    let first = map_guard.player_count() == 0;
+    map_guard.add_player(MapPlayer {
+        character_id: character.id,
+        sender: sender.clone(),
+        spawn_packet: field::create_spawn_player(character, equipped, (0, 0), 0, 0, guild.as_ref()),
+        position: (0, 0),
+        hp: (character.hp, character.max_hp),
+    });
+
+    if let Some(data) = MapData::get(character.map_id) {
+        let mut scripts = Vec::new();
+        if let (true, Some(name)) = (first, &data.on_first_user_enter) {
+            scripts.push(("map/onFirstUserEnter", name.clone()));
+        }
+        if let Some(name) = &data.on_user_enter {
+            scripts.push(("map/onUserEnter", name.clone()));
+        }
+        script::field::enter_map(character.id, scripts);
+    }
+    drop(map_guard);
+
+    party::report(character);
+    party::update_hp(character, true)
+}
+
+pub fn leave_map(character: &Character) -> Result<(), Box<dyn Error>> {
+    let map = Channel::get()?.map(character.map_id)?;
+    let mut map_guard = match map.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+
+    map_guard.remove_player(character.id);
+    Ok(())
+}
+
+pub fn change_map(
+    client: &Client,
+    character: &mut Character,
+    map_id: i32,
+    spawn_point: i16,
+) -> Result<(), Box<dyn Error>> {
+    let sender = match client.sender() {
+        Some(sender) => sender,
+        None => return Err("Unable to change map of a disconnected client".into()),
+    };
+
+    leave_map(character)?;
+
+    character.map_id = map_id;
+    character.spawn_point = spawn_point;
+    sender.send(field::create_change_map(
+        Channel::get()?.channel_id(),
+        character,
+    ));
+
+    let inventory = lock_inventory(client)?;
+    enter_map(&sender, character, inventory.get(InventoryType::Equipped))
+}
+
+/// Uses the portal named `name` in the map of the character, warping it to the target of the
+/// portal. Portals that run a script instead return its name, to be run once the client is
+/// unlocked.
+pub fn use_portal(client: &Client, character: &mut Character, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let data = match MapData::get(character.map_id) {
+        Some(data) => data,
+        None => return Err(format!("Unknown map {}", character.map_id).into()),
+    };
+    let portal = match data.portal(name) {
+        Some(portal) => portal,
+        None => return Err(format!("No portal {} in map {}", name, character.map_id).into()),
+    };
+
+    let map = Channel::get()?.map(character.map_id)?;
+    let position = match map.lock() {
+        Ok(map_guard) => map_guard.player(character.id).map(|player| player.position),
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+    if let Some(position) = position {
+        let distance = (position.0 as i32 - portal.position.0 as i32).abs()
+            + (position.1 as i32 - portal.position.1 as i32).abs();
+        if distance > MAX_PORTAL_DISTANCE {
+            return Err(format!("{} is {} away from portal {}", character.name, distance, name).into());
+        }
+    }
+    if let Some(script) = &portal.script {
+        return Ok(Some(script.clone()));
+    }
+
+    let target_map_id = match portal.target_map_id {
+        Some(target_map_id) => target_map_id,
+        None => return Err(format!("Portal {} of map {} leads nowhere", name, character.map_id).into()),
+    };
+    let spawn_point = MapData::get(target_map_id)
+        .and_then(|target| target.portal(&portal.target_name).map(|target_portal| target_portal.id))
+        .unwrap_or(0);
+
+    change_map(client, character, target_map_id, spawn_point)?;
+    Ok(None)
+}
+
+/// Shows the new look of the character to the players in its map, after equips changed.
+pub fn update_look(character: &Character, equipped: &Inventory) -> Result<(), Box<dyn Error>> {
+    let map = Channel::get()?.map(character.map_id)?;
+    let mut map_guard = match map.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+
+    let position = match map_guard.player(character.id) {
+        Some(player) => player.position,
+        None => return Ok(()),
+    };
+
+    map_guard.update_spawn_packet(
+        character.id,
+        field::create_spawn_player(
+            character,
+            equipped,
+            position,
+            0,
+            0,
+            Channel::get()?.guild_of(character.id).as_ref(),
+        ),
+    );
+    map_guard.broadcast(
+        &field::create_update_look(character, equipped),
+        Some(character.id),
+    );
+
+    Ok(())
+}
+
+/// Locks a client on its own, for when two clients are locked at once. They are always locked
+/// in the order of their character ids.
+pub fn lock_client(client: &Mutex<Client>) -> Result<MutexGuard<'_, Client>, Box<dyn Error>> {
+    match client.lock() {
+        Ok(guard) => Ok(guard),
+        Err(error) => Err(format!("Unable to lock Client Mutex [{}]", error).into()),
+    }
+}
+
+pub fn lock_character(client: &Client) -> Result<MutexGuard<'_, Character>, Box<dyn Error>> {
+    match &client.character {
+        Some(character_mutex) => match character_mutex.lock() {
+            Ok(character) => Ok(character),
+            Err(error) => Err(format!("Unable to lock Character Mutex [{}]", error).into()),
+        },
+        None => Err("Client has no character".into()),
+    }
+}
+
+/// Locks the inventory of an already locked client; the character must be locked before it.
+pub fn lock_inventory(client: &Client) -> Result<MutexGuard<'_, CharacterInventory>, Box<dyn Error>> {
+    match &client.inventory {
+        Some(inventory_mutex) => match inventory_mutex.lock() {
+            Ok(inventory) => Ok(inventory),
+            Err(error) => Err(format!("Unable to lock CharacterInventory Mutex [{}]", error).into()),
+        },
+        None => Err("Client has no inventory".into()),
+    }
+}
+
+/// Locks the client and its character and runs `action` on them.
+pub fn with_character<T>(
+    client: &Arc<Mutex<Client>>,
+    action: impl FnOnce(&Client, &mut Character) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    let client_guard = match client.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Client Mutex [{}]", error).into()),
+    };
+
+    let mut character = match &client_guard.character {
+        Some(character_mutex) => match character_mutex.lock() {
+            Ok(character) => character,
+            Err(error) => return Err(format!("Unable to lock Character Mutex [{}]", error).into()),
+        },
+        None => return Err("Client has no character".into()),
+    };
+
+    action(&client_guard, &mut character)
+}
+
+/// Locks the skills of an already locked client, after its character and inventory.
+pub fn lock_skills(client: &Client) -> Result<MutexGuard<'_, CharacterSkills>, Box<dyn Error>> {
+    match &client.skills {
+        Some(skills_mutex) => match skills_mutex.lock() {
+            Ok(skills) => Ok(skills),
+            Err(error) => Err(format!("Unable to lock CharacterSkills Mutex [{}]", error).into()),
+        },
+        None => Err("Client has no skills".into()),
+    }
+}
+
+/// Locks the buffs of an already locked client, after its character, inventory and skills.
+pub fn lock_buffs(client: &Client) -> Result<MutexGuard<'_, CharacterBuffs>, Box<dyn Error>> {
+    match &client.buffs {
+        Some(buffs_mutex) => match buffs_mutex.lock() {
+            Ok(buffs) => Ok(buffs),
+            Err(error) => Err(format!("Unable to lock CharacterBuffs Mutex [{}]", error).into()),
+        },
+        None => Err("Client has no buffs".into()),
+    }
+}
+
+/// Locks the quests of an already locked client, after every other part of its character but its
+/// buddies.
+pub fn lock_quests(client: &Client) -> Result<MutexGuard<'_, CharacterQuests>, Box<dyn Error>> {
+    match &client.quests {
+        Some(quests_mutex) => match quests_mutex.lock() {
+            Ok(quests) => Ok(quests),
+            Err(error) => Err(format!("Unable to lock CharacterQuests Mutex [{}]", error).into()),
+        },
+        None => Err("Client has no quests".into()),
+    }
+}
+
+/// Locks the buddies of an already locked client, after every other part of its character.
+pub fn lock_buddies(client: &Client) -> Result<MutexGuard<'_, BuddyList>, Box<dyn Error>> {
+    match &client.buddies {
+        Some(buddies_mutex) => match buddies_mutex.lock() {
+            Ok(buddies) => Ok(buddies),
+            Err(error) => Err(format!("Unable to lock BuddyList Mutex [{}]", error).into()),
+        },
+        None => Err("Client has no buddies".into()),
+    }
+}
@@ -0,0 +1,18 @@
+use crate::db::model::cheat_flag::{CheatFlag, CheatKind, NewCheatFlag};
+use crate::db::model::character::Character;
+use log::warn;
+use std::time::SystemTime;
+
+/// Records that `character` did something its client cannot legitimately do, for GMs to review.
+pub fn flag(character: &Character, kind: CheatKind, description: &str) {
+    warn!("Flagged {} for cheating: {}", character.name, description);
+
+    if let Err(error) = CheatFlag::create(NewCheatFlag {
+        character_id: character.id,
+        kind: kind as i16,
+        description: description.to_string(),
+        flag_date: SystemTime::now(),
+    }) {
+        warn!("Unable to insert new row to database [{}]", error);
+    }
+}
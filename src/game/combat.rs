@@ -0,0 +1,163 @@
+use crate::data::skill::{SkillData, SkillLevelData};
+use crate::db::model::character::Character;
+use crate::game::buff::{BuffStat, CharacterBuffs};
+use crate::game::inventory::Inventory;
+use crate::game::skill::CharacterSkills;
+use std::sync::Arc;
+
+/// The highest damage a single line can deal in this version.
+pub const MAX_LINE_DAMAGE: i32 = 99999;
+/// Head room over the calculated maximum for critical hits, buffs and rounding in the client.
+const DAMAGE_TOLERANCE: f64 = 2.0;
+/// Calculated maximums below this are raised to it, so weak characters are not flagged for luck.
+const MIN_DAMAGE_CAP: i32 = 200;
+
+const WEAPON_SLOT: i16 = -11;
+const THIEF_JOB_BRANCH: i16 = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AttackKind {
+    CloseRange,
+    Ranged,
+    Magic,
+}
+
+pub struct AttackTarget {
+    pub object_id: i32,
+    pub damage: Vec<i32>,
+}
+
+/// What hurt a player, as reported by its client.
+#[derive(Clone, Copy)]
+pub enum DamageSource {
+    /// Falling objects and other hazards of the map.
+    Map,
+    /// `attack` is -1 for touching the mob, or the index of the attack it used.
+    Mob {
+        attack: i8,
+        mob_id: i32,
+        object_id: i32,
+        direction: u8,
+    },
+}
+
+/// The attack index the client sends for damage from the map itself.
+pub const MAP_DAMAGE_SOURCE: i8 = -2;
+
+pub struct Attack {
+    pub kind: AttackKind,
+    pub skill_id: i32,
+    pub display: u8,
+    pub direction: u8,
+    pub speed: u8,
+    pub lines: u8,
+    pub targets: Vec<AttackTarget>,
+}
+
+impl Attack {
+    /// The skill of the attack along with the level the character learned it at.
+    pub fn skill(&self, skills: &CharacterSkills) -> Option<(Arc<SkillData>, u8)> {
+        match self.skill_id {
+            0 => None,
+            skill_id => SkillData::get(skill_id).map(|skill| (skill, skills.level(skill_id))),
+        }
+    }
+}
+
+/// Maximum damage of a regular line, from the main stats, weapon and buffs of the character.
+fn max_physical_damage(character: &Character, equipped: &Inventory, buffs: &CharacterBuffs) -> f64 {
+    let weapon_id = equipped.get(WEAPON_SLOT).map_or(0, |weapon| weapon.item_id);
+    let weapon_attack: i32 = equipped
+        .items()
+        .filter_map(|(_, item)| item.equip.as_ref())
+        .map(|stats| stats.weapon_attack as i32)
+        .sum::<i32>()
+        + buffs.stat(BuffStat::WeaponAttack) as i32;
+
+    let strength = character.strength as f64;
+    let dexterity = character.dexterity as f64;
+    let luck = character.luck as f64;
+
+    let (multiplier, primary, secondary) = match weapon_id / 10000 {
+        130 => (4.0, strength, dexterity),
+        131 | 132 => (4.4, strength, dexterity),
+        133 if character.job / 100 == THIEF_JOB_BRANCH => (3.6, luck, strength + dexterity),
+        133 => (4.0, strength, dexterity),
+        137 | 138 => (3.6, strength, dexterity),
+        140 => (4.6, strength, dexterity),
+        141 | 142 => (4.8, strength, dexterity),
+        143 | 144 => (5.0, strength, dexterity),
+        145 => (3.4, dexterity, strength),
+        146 => (3.6, dexterity, strength),
+        147 => (3.6, luck, strength + dexterity),
+        _ => (1.2, strength, dexterity),
+    };
+
+    (primary * multiplier + secondary) / 100.0 * weapon_attack.max(1) as f64
+}
+
+/// Maximum damage of a spell, from the intelligence, magic attack and buffs of the character.
+fn max_magic_damage(character: &Character, equipped: &Inventory, buffs: &CharacterBuffs, spell_attack: i32) -> f64 {
+    let intelligence = character.intelligence as f64;
+    let magic_attack = intelligence
+        + equipped
+            .items()
+            .filter_map(|(_, item)| item.equip.as_ref())
+            .map(|stats| stats.magic_attack as f64)
+            .sum::<f64>()
+        + buffs.stat(BuffStat::MagicAttack) as f64;
+
+    ((magic_attack * magic_attack / 1000.0 + magic_attack) / 30.0 + intelligence / 200.0)
+        * spell_attack.max(1) as f64
+}
+
+/// Checks an attack against what the character could possibly deal, returning the reason it
+/// is impossible.
+pub fn validate(
+    character: &Character,
+    equipped: &Inventory,
+    skills: &CharacterSkills,
+    buffs: &CharacterBuffs,
+    attack: &Attack,
+) -> Result<(), String> {
+    let skill = attack.skill(skills);
+    let skill_level: Option<&SkillLevelData> = match (&skill, attack.skill_id) {
+        (_, 0) => None,
+        (Some((skill, level)), _) if *level > 0 => skill.level(*level),
+        (_, skill_id) => return Err(format!("used skill {} it has not learned", skill_id)),
+    };
+
+    let (attack_count, mob_count) = skill_level.map_or((1, 1), |level| (level.attack_count, level.mob_count));
+    if attack.lines > attack_count.max(1) || attack.targets.len() > mob_count.max(1) as usize {
+        return Err(format!(
+            "hit {} mobs {} times with skill {}",
+            attack.targets.len(),
+            attack.lines,
+            attack.skill_id
+        ));
+    }
+
+    let max_damage = match attack.kind {
+        AttackKind::Magic => max_magic_damage(
+            character,
+            equipped,
+            buffs,
+            skill_level.map_or(0, |level| level.magic_attack),
+        ),
+        _ => max_physical_damage(character, equipped, buffs) * skill_level.map_or(100, |level| level.damage) as f64 / 100.0,
+    };
+    let damage_cap = ((max_damage * DAMAGE_TOLERANCE) as i32).clamp(MIN_DAMAGE_CAP, MAX_LINE_DAMAGE);
+
+    match attack
+        .targets
+        .iter()
+        .flat_map(|target| target.damage.iter())
+        .find(|damage| **damage < 0 || **damage > damage_cap)
+    {
+        Some(damage) => Err(format!(
+            "dealt {} damage with skill {} where at most {} is possible",
+            damage, attack.skill_id, damage_cap
+        )),
+        None => Ok(()),
+    }
+}
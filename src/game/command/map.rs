@@ -0,0 +1,207 @@
+use crate::data::item::ItemData;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::command::{self, Command, CommandArguments, CommandContext, CommandError};
+use crate::game::map::{DropContent, DropOwnership, Map};
+use std::error::Error;
+
+const MAX_SPAWN_COUNT: u16 = 100;
+
+pub struct WarpCommand;
+
+impl Command for WarpCommand {
+    fn name(&self) -> &'static str {
+        "warp"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_INTERN
+    }
+
+    fn usage(&self) -> &'static str {
+        "<map id> [character name]"
+    }
+
+    fn description(&self) -> &'static str {
+        "Warps you or another player in the channel to a map"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let map_id = arguments.get::<i32>(0)?;
+        let target = command::target_client(context, arguments, 1)?;
+
+        let name = character::with_character(&target, |client, character| {
+            character::change_map(client, character, map_id, 0)?;
+            Ok(character.name.clone())
+        })?;
+
+        context.reply(&format!("Warped {} to map {}.", name, map_id));
+        Ok(())
+    }
+}
+
+pub struct SpawnCommand;
+
+impl Command for SpawnCommand {
+    fn name(&self) -> &'static str {
+        "spawn"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_GAME_MASTER
+    }
+
+    fn usage(&self) -> &'static str {
+        "<mob id> [count]"
+    }
+
+    fn description(&self) -> &'static str {
+        "Spawns monsters at your position"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let mob_id = arguments.get::<i32>(0)?;
+        let count = arguments.get_optional::<u16>(1)?.unwrap_or(1);
+        if !(1..=MAX_SPAWN_COUNT).contains(&count) {
+            return Err(CommandError::Failed(format!(
+                "Count must be between 1 and {}.",
+                MAX_SPAWN_COUNT
+            )));
+        }
+
+        with_map(context, |map, position| {
+            for _ in 0..count {
+                map.spawn_mob(mob_id, position, 0, false, None)?;
+            }
+            Ok(())
+        })?;
+
+        context.reply(&format!("Spawned {} of mob {}.", count, mob_id));
+        Ok(())
+    }
+}
+
+pub struct KillAllCommand;
+
+impl Command for KillAllCommand {
+    fn name(&self) -> &'static str {
+        "killall"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_GAME_MASTER
+    }
+
+    fn usage(&self) -> &'static str {
+        ""
+    }
+
+    fn description(&self) -> &'static str {
+        "Kills every monster in your map without rewards"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        _arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let count = with_map(context, |map, _position| {
+            let mob_ids = map.mob_ids();
+            for object_id in &mob_ids {
+                map.kill_mob(*object_id, true);
+            }
+            Ok(mob_ids.len())
+        })?;
+
+        context.reply(&format!("Killed {} monsters.", count));
+        Ok(())
+    }
+}
+
+pub struct DropCommand;
+
+impl Command for DropCommand {
+    fn name(&self) -> &'static str {
+        "drop"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_ADMIN
+    }
+
+    fn usage(&self) -> &'static str {
+        "<item id> [quantity]"
+    }
+
+    fn description(&self) -> &'static str {
+        "Drops an item at your position, owned by you until its ownership ends"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let item_id = arguments.get::<i32>(0)?;
+        let quantity = arguments.get_optional::<i16>(1)?.unwrap_or(1);
+        if quantity < 1 {
+            return Err(CommandError::Failed(
+                "Quantity must be at least 1.".to_string(),
+            ));
+        }
+
+        let item_data = match ItemData::get(item_id) {
+            Some(item_data) => item_data,
+            None => {
+                return Err(CommandError::Failed(format!(
+                    "Item {} does not exist.",
+                    item_id
+                )))
+            }
+        };
+
+        with_map(context, |map, position| {
+            map.spawn_drop(
+                DropContent::Item(item_data.create_item(quantity)),
+                DropOwnership::of_character(context.character_id, None),
+                context.character_id,
+                position,
+                position,
+            );
+            Ok(())
+        })?;
+
+        context.reply(&format!("Dropped {} of item {}.", quantity, item_id));
+        Ok(())
+    }
+}
+
+/// Runs `action` on the map of the command's issuer, along with their position in it.
+fn with_map<T>(
+    context: &CommandContext,
+    action: impl FnOnce(&mut Map, (i16, i16)) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    let map_id = character::with_character(&context.client, |_client, character| {
+        Ok(character.map_id)
+    })?;
+    let map = Channel::get()?.map(map_id)?;
+    let mut map_guard = match map.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+
+    let position = match map_guard.player(context.character_id) {
+        Some(player) => player.position,
+        None => return Err("You are not in a map.".into()),
+    };
+
+    action(&mut map_guard, position)
+}
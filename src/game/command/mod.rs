@@ -0,0 +1,245 @@
+mod map;
+mod moderation;
+mod player;
+mod server;
+
+use crate::db::model::character::Character;
+use crate::db::model::gm_log::{GmLog, NewGmLog};
+use crate::db::model::user::User;
+use crate::game::channel::Channel;
+use crate::net::client::{Client, PacketSender};
+use crate::net::packet::message::{self, ServerMessageType};
+use log::{error, info, warn};
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+pub const GM_LEVEL_INTERN: i16 = 1;
+pub const GM_LEVEL_GAME_MASTER: i16 = 2;
+pub const GM_LEVEL_ADMIN: i16 = 3;
+
+pub const COMMAND_PREFIX: char = '!';
+
+pub enum CommandError {
+    Usage,
+    Failed(String),
+}
+
+impl From<Box<dyn Error>> for CommandError {
+    fn from(error: Box<dyn Error>) -> Self {
+        CommandError::Failed(error.to_string())
+    }
+}
+
+pub struct CommandContext {
+    pub client: Arc<Mutex<Client>>,
+    pub character_id: i32,
+    pub character_name: String,
+    pub gm_level: i16,
+    sender: PacketSender,
+}
+
+impl CommandContext {
+    pub fn reply(&self, message: &str) {
+        self.sender.send(message::create_server_message(
+            ServerMessageType::LightBlueText,
+            message,
+        ));
+    }
+}
+
+pub struct CommandArguments<'a> {
+    arguments: Vec<&'a str>,
+}
+
+impl<'a> CommandArguments<'a> {
+    pub fn get<T: FromStr>(&self, index: usize) -> Result<T, CommandError> {
+        match self.get_optional(index)? {
+            Some(value) => Ok(value),
+            None => Err(CommandError::Usage),
+        }
+    }
+
+    pub fn get_optional<T: FromStr>(&self, index: usize) -> Result<Option<T>, CommandError> {
+        match self.arguments.get(index) {
+            Some(argument) => match argument.parse::<T>() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => Err(CommandError::Usage),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Joins every argument from `index` onwards, for free text such as notices and reasons.
+    pub fn rest(&self, index: usize) -> Result<String, CommandError> {
+        match self.arguments.len() > index {
+            true => Ok(self.arguments[index..].join(" ")),
+            false => Err(CommandError::Usage),
+        }
+    }
+}
+
+pub trait Command: Sync {
+    fn name(&self) -> &'static str;
+    fn min_gm_level(&self) -> i16;
+    fn usage(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError>;
+}
+
+static COMMANDS: [&dyn Command; 15] = [
+    &server::HelpCommand,
+    &server::OnlineCommand,
+    &server::NoticeCommand,
+    &map::WarpCommand,
+    &map::SpawnCommand,
+    &map::KillAllCommand,
+    &map::DropCommand,
+    &player::LevelCommand,
+    &player::ItemCommand,
+    &moderation::KickCommand,
+    &moderation::MuteCommand,
+    &moderation::UnmuteCommand,
+    &moderation::BanCommand,
+    &moderation::UnbanCommand,
+    &moderation::InfractionsCommand,
+];
+
+pub fn get_command_by_name(command_name: &str) -> Option<&'static dyn Command> {
+    COMMANDS
+        .iter()
+        .find(|command| command.name().eq_ignore_ascii_case(command_name))
+        .copied()
+}
+
+pub fn gm_level(user: &User, character: &Character) -> i16 {
+    match user.is_admin {
+        true => GM_LEVEL_ADMIN,
+        false => user.gm_level.max(character.gm_level),
+    }
+}
+
+/// Parses and runs a `!command args` chat line on behalf of the client's character.
+/// Every command that passes the GM level check is audited, whether it succeeded or not.
+pub fn execute(client: Arc<Mutex<Client>>, text: &str) {
+    let mut tokens = text.trim_start_matches(COMMAND_PREFIX).split_whitespace();
+    let command_name = match tokens.next() {
+        Some(name) => name.to_lowercase(),
+        None => return,
+    };
+    let arguments = CommandArguments {
+        arguments: tokens.collect(),
+    };
+
+    let context = {
+        let client_guard = match client.lock() {
+            Ok(guard) => guard,
+            Err(error) => {
+                error!("Unable to lock Client Mutex [{}]", error);
+                return;
+            }
+        };
+
+        let sender = match client_guard.sender() {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        let (user_mutex, character_mutex) = match (&client_guard.user, &client_guard.character) {
+            (Some(user_mutex), Some(character_mutex)) => (user_mutex, character_mutex),
+            _ => {
+                error!("Received command from a client without a character");
+                return;
+            }
+        };
+
+        let context = match (user_mutex.lock(), character_mutex.lock()) {
+            (Ok(user), Ok(character)) => CommandContext {
+                client: client.clone(),
+                character_id: character.id,
+                character_name: character.name.clone(),
+                gm_level: gm_level(&user, &character),
+                sender,
+            },
+            _ => {
+                error!("Unable to lock User or Character Mutex");
+                return;
+            }
+        };
+        context
+    };
+
+    let command = match get_command_by_name(&command_name) {
+        Some(command) if command.min_gm_level() <= context.gm_level => command,
+        Some(_) => {
+            warn!(
+                "{} tried to use `{}` without the required GM level",
+                context.character_name, command_name
+            );
+            context.reply("You are not allowed to use this command.");
+            return;
+        }
+        None => {
+            context.reply(&format!("Unknown command `{}`, try !help.", command_name));
+            return;
+        }
+    };
+
+    let succeeded = match command.execute(&context, &arguments) {
+        Ok(()) => true,
+        Err(CommandError::Usage) => {
+            context.reply(&format!("Usage: !{} {}", command.name(), command.usage()));
+            false
+        }
+        Err(CommandError::Failed(reason)) => {
+            context.reply(&reason);
+            false
+        }
+    };
+
+    info!(
+        "GM command `{}` [{}] executed by {} ({})",
+        command.name(),
+        arguments.arguments.join(" "),
+        context.character_name,
+        match succeeded {
+            true => "succeeded",
+            false => "failed",
+        }
+    );
+
+    match GmLog::create(NewGmLog {
+        character_id: context.character_id,
+        command: command.name().to_string(),
+        arguments: arguments.arguments.join(" "),
+        succeeded,
+        execution_date: SystemTime::now(),
+    }) {
+        Ok(_) => {}
+        Err(error) => warn!("Unable to audit GM command [{}]", error),
+    };
+}
+
+/// Resolves the optional character name argument at `index` to an online player's client,
+/// falling back to the command's invoker.
+pub fn target_client(
+    context: &CommandContext,
+    arguments: &CommandArguments,
+    index: usize,
+) -> Result<Arc<Mutex<Client>>, CommandError> {
+    match arguments.get_optional::<String>(index)? {
+        Some(character_name) => match Channel::get()?.player_by_name(&character_name) {
+            Some(player) => Ok(player.client),
+            None => Err(CommandError::Failed(format!(
+                "{} is not online in this channel.",
+                character_name
+            ))),
+        },
+        None => Ok(context.client.clone()),
+    }
+}
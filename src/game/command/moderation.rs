@@ -0,0 +1,296 @@
+use crate::game::channel::Channel;
+use crate::game::command::{self, Command, CommandArguments, CommandContext, CommandError};
+use crate::game::infraction::{self, InfractionTarget};
+use std::time::{Duration, UNIX_EPOCH};
+
+const DEFAULT_REASON: i16 = 1;
+
+/// Reads the optional reason code and free text description that follow a target and duration.
+fn reason_and_description(
+    arguments: &CommandArguments,
+    index: usize,
+) -> Result<(i16, String), CommandError> {
+    let reason = arguments
+        .get_optional::<i16>(index)?
+        .unwrap_or(DEFAULT_REASON);
+    let description = arguments.rest(index + 1).unwrap_or_default();
+
+    Ok((reason, description))
+}
+
+/// A zero duration means the infraction never expires.
+fn duration_from(amount: u64, unit_seconds: u64) -> Option<Duration> {
+    match amount {
+        0 => None,
+        _ => Some(Duration::from_secs(amount * unit_seconds)),
+    }
+}
+
+pub struct KickCommand;
+
+impl Command for KickCommand {
+    fn name(&self) -> &'static str {
+        "kick"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_GAME_MASTER
+    }
+
+    fn usage(&self) -> &'static str {
+        "<character name>"
+    }
+
+    fn description(&self) -> &'static str {
+        "Disconnects a player from the channel"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let character_name = arguments.get::<String>(0)?;
+        let player = match Channel::get()?.player_by_name(&character_name) {
+            Some(player) => player,
+            None => {
+                return Err(CommandError::Failed(format!(
+                    "{} is not online in this channel.",
+                    character_name
+                )))
+            }
+        };
+
+        match player.client.lock() {
+            Ok(client_guard) => client_guard.disconnect(),
+            Err(error) => {
+                return Err(CommandError::Failed(format!(
+                    "Unable to lock Client Mutex [{}]",
+                    error
+                )))
+            }
+        };
+
+        context.reply(&format!("{} has been kicked.", player.name));
+        Ok(())
+    }
+}
+
+pub struct MuteCommand;
+
+impl Command for MuteCommand {
+    fn name(&self) -> &'static str {
+        "mute"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_GAME_MASTER
+    }
+
+    fn usage(&self) -> &'static str {
+        "<character | account:name | ip:address> <minutes, 0 for ever> [reason code] [description]"
+    }
+
+    fn description(&self) -> &'static str {
+        "Prevents a player from chatting for the given duration"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let target = InfractionTarget::parse(&arguments.get::<String>(0)?);
+        let minutes = arguments.get::<u64>(1)?;
+        let (reason, description) = reason_and_description(arguments, 2)?;
+
+        infraction::mute(
+            &target,
+            reason,
+            &description,
+            duration_from(minutes, 60),
+            Some(context.character_id),
+        )?;
+
+        context.reply(&format!("{} has been muted.", target));
+        Ok(())
+    }
+}
+
+pub struct UnmuteCommand;
+
+impl Command for UnmuteCommand {
+    fn name(&self) -> &'static str {
+        "unmute"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_GAME_MASTER
+    }
+
+    fn usage(&self) -> &'static str {
+        "<character | account:name | ip:address>"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lifts the active mutes of a player"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let target = InfractionTarget::parse(&arguments.get::<String>(0)?);
+
+        match infraction::unmute(&target, Some(context.character_id))? {
+            0 => Err(CommandError::Failed(format!("{} is not muted.", target))),
+            _ => {
+                context.reply(&format!("{} has been unmuted.", target));
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct BanCommand;
+
+impl Command for BanCommand {
+    fn name(&self) -> &'static str {
+        "ban"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_GAME_MASTER
+    }
+
+    fn usage(&self) -> &'static str {
+        "<character | account:name | ip:address[/prefix] | mac:address | hwid:id> <days, 0 for ever> [reason code] [description]"
+    }
+
+    fn description(&self) -> &'static str {
+        "Bans an account or IP address and disconnects it"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let target = InfractionTarget::parse(&arguments.get::<String>(0)?);
+        let days = arguments.get::<u64>(1)?;
+        let (reason, description) = reason_and_description(arguments, 2)?;
+
+        infraction::ban(
+            &target,
+            reason,
+            &description,
+            duration_from(days, 24 * 60 * 60),
+            Some(context.character_id),
+        )?;
+
+        context.reply(&format!("{} has been banned.", target));
+        Ok(())
+    }
+}
+
+pub struct UnbanCommand;
+
+impl Command for UnbanCommand {
+    fn name(&self) -> &'static str {
+        "unban"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_ADMIN
+    }
+
+    fn usage(&self) -> &'static str {
+        "<character | account:name | ip:address[/prefix] | mac:address | hwid:id>"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lifts the active bans of an account or IP address"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let target = InfractionTarget::parse(&arguments.get::<String>(0)?);
+
+        match infraction::unban(&target, Some(context.character_id))? {
+            0 => Err(CommandError::Failed(format!("{} is not banned.", target))),
+            _ => {
+                context.reply(&format!("{} has been unbanned.", target));
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct InfractionsCommand;
+
+impl Command for InfractionsCommand {
+    fn name(&self) -> &'static str {
+        "infractions"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_GAME_MASTER
+    }
+
+    fn usage(&self) -> &'static str {
+        "<character | account:name>"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shows the ban and mute history of an account"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let target = InfractionTarget::parse(&arguments.get::<String>(0)?);
+        let history = infraction::history(&target)?;
+
+        context.reply(&format!("{} has {} infractions.", target, history.len()));
+        for record in history {
+            let issued = record
+                .issue_date
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+
+            context.reply(&format!(
+                "#{} {} reason {} issued at {} by {}{}{}",
+                record.id,
+                match record.kind {
+                    0 => "ban",
+                    _ => "mute",
+                },
+                record.reason,
+                issued,
+                record
+                    .issuer_id
+                    .map_or("the server".to_string(), |issuer_id| format!(
+                        "character {}",
+                        issuer_id
+                    )),
+                match record.is_active() {
+                    true => " (active)",
+                    false => "",
+                },
+                match record.description.is_empty() {
+                    true => String::new(),
+                    false => format!(": {}", record.description),
+                },
+            ));
+        }
+
+        Ok(())
+    }
+}
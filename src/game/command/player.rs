@@ -0,0 +1,107 @@
+use crate::data::item::ItemData;
+use crate::game::{character, item};
+use crate::game::command::{self, Command, CommandArguments, CommandContext, CommandError};
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::item::create_modify_inventory;
+
+pub struct LevelCommand;
+
+impl Command for LevelCommand {
+    fn name(&self) -> &'static str {
+        "level"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_ADMIN
+    }
+
+    fn usage(&self) -> &'static str {
+        "<level> [character name]"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sets the level of you or another player in the channel"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let level = arguments.get::<i16>(0)?;
+        if !(1..=200).contains(&level) {
+            return Err(CommandError::Failed(
+                "Level must be between 1 and 200.".to_string(),
+            ));
+        }
+
+        let target = command::target_client(context, arguments, 1)?;
+        let name = character::with_character(&target, |client, character| {
+            character.level = level;
+            character.exp = 0;
+            client.send(create_update_stats(
+                character,
+                &[Stat::Level, Stat::Exp],
+                false,
+            ));
+            Ok(character.name.clone())
+        })?;
+
+        context.reply(&format!("{} is now level {}.", name, level));
+        Ok(())
+    }
+}
+
+pub struct ItemCommand;
+
+impl Command for ItemCommand {
+    fn name(&self) -> &'static str {
+        "item"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_ADMIN
+    }
+
+    fn usage(&self) -> &'static str {
+        "<item id> [quantity]"
+    }
+
+    fn description(&self) -> &'static str {
+        "Creates an item in your inventory"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let item_id = arguments.get::<i32>(0)?;
+        let quantity = arguments.get_optional::<i16>(1)?.unwrap_or(1);
+        if quantity < 1 {
+            return Err(CommandError::Failed(
+                "Quantity must be at least 1.".to_string(),
+            ));
+        }
+
+        let item_data = match ItemData::get(item_id) {
+            Some(item_data) => item_data,
+            None => {
+                return Err(CommandError::Failed(format!(
+                    "Item {} does not exist.",
+                    item_id
+                )))
+            }
+        };
+
+        character::with_character(&context.client, |client, _character| {
+            let mut inventory = character::lock_inventory(client)?;
+            let operations = item::add_item(&mut inventory, item_data.create_item(quantity))?;
+            client.send(create_modify_inventory(&operations, false));
+            Ok(())
+        })?;
+
+        context.reply(&format!("Created {} of item {}.", quantity, item_id));
+        Ok(())
+    }
+}
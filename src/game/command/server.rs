@@ -0,0 +1,131 @@
+use crate::game::channel::Channel;
+use crate::game::command::{self, Command, CommandArguments, CommandContext, CommandError};
+use crate::net::packet::message::{self, ServerMessageType};
+
+pub struct HelpCommand;
+
+impl Command for HelpCommand {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_INTERN
+    }
+
+    fn usage(&self) -> &'static str {
+        "[command]"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists the available commands or describes a single one"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        match arguments.get_optional::<String>(0)? {
+            Some(command_name) => match command::get_command_by_name(&command_name) {
+                Some(found) if found.min_gm_level() <= context.gm_level => {
+                    context.reply(&format!(
+                        "!{} {} - {}",
+                        found.name(),
+                        found.usage(),
+                        found.description()
+                    ));
+                    Ok(())
+                }
+                _ => Err(CommandError::Failed(format!(
+                    "Unknown command `{}`.",
+                    command_name
+                ))),
+            },
+            None => {
+                let names: Vec<String> = command::COMMANDS
+                    .iter()
+                    .filter(|available| available.min_gm_level() <= context.gm_level)
+                    .map(|available| format!("!{}", available.name()))
+                    .collect();
+
+                context.reply(&format!("Available commands: {}", names.join(", ")));
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct OnlineCommand;
+
+impl Command for OnlineCommand {
+    fn name(&self) -> &'static str {
+        "online"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_INTERN
+    }
+
+    fn usage(&self) -> &'static str {
+        ""
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists the players connected to this channel"
+    }
+
+    fn execute(
+        &self,
+        context: &CommandContext,
+        _arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let mut names: Vec<String> = Channel::get()?
+            .players()
+            .into_iter()
+            .map(|player| player.name)
+            .collect();
+        names.sort();
+
+        context.reply(&format!(
+            "{} players online: {}",
+            names.len(),
+            names.join(", ")
+        ));
+        Ok(())
+    }
+}
+
+pub struct NoticeCommand;
+
+impl Command for NoticeCommand {
+    fn name(&self) -> &'static str {
+        "notice"
+    }
+
+    fn min_gm_level(&self) -> i16 {
+        command::GM_LEVEL_GAME_MASTER
+    }
+
+    fn usage(&self) -> &'static str {
+        "<message>"
+    }
+
+    fn description(&self) -> &'static str {
+        "Broadcasts a notice to every player in the channel"
+    }
+
+    fn execute(
+        &self,
+        _context: &CommandContext,
+        arguments: &CommandArguments,
+    ) -> Result<(), CommandError> {
+        let notice = arguments.rest(0)?;
+
+        Channel::get()?.broadcast(&message::create_server_message(
+            ServerMessageType::Notice,
+            &notice,
+        ));
+        Ok(())
+    }
+}
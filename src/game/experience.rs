@@ -0,0 +1,163 @@
+use crate::db::model::character::Character;
+use crate::game::channel::Channel;
+use crate::game::mob::Mob;
+use crate::game::party;
+use crate::game::stat::MAX_HP_MP;
+use crate::net::client::Client;
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::field::{self, Effect};
+use crate::net::packet::message;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use std::error::Error;
+
+pub const MAX_LEVEL: i16 = 200;
+const AP_PER_LEVEL: i16 = 5;
+const SP_PER_LEVEL: i16 = 3;
+/// Beginners get a single SP per level, up to this level.
+const BEGINNER_SP_LEVEL: i16 = 10;
+/// Share of the EXP needed for the next level that is lost on death outside of towns.
+const DEATH_EXP_LOSS: f64 = 0.1;
+/// Thieves get away with less, thanks to their luck.
+const THIEF_DEATH_EXP_LOSS: f64 = 0.05;
+
+/// EXP needed to advance from levels 1 to 50; later levels each need 5.48% more than the last.
+const EXP_TABLE_START: [i32; 50] = [
+    15, 34, 57, 92, 135, 372, 560, 840, 1242, 1716, 2360, 3216, 4200, 5460, 7050, 8840, 11040,
+    13716, 16680, 20216, 24402, 28980, 34320, 40512, 47216, 54900, 63666, 73080, 83720, 95700,
+    108480, 122760, 138666, 155540, 174216, 194832, 216600, 240500, 266682, 294216, 324240, 356916,
+    391160, 428280, 468450, 510420, 555680, 604416, 655200, 709716,
+];
+
+static EXP_TABLE: Lazy<Vec<i32>> = Lazy::new(|| {
+    let mut table = EXP_TABLE_START.to_vec();
+    while table.len() < MAX_LEVEL as usize - 1 {
+        let previous = table[table.len() - 1];
+        table.push((previous as f64 * 1.0548) as i32);
+    }
+    table
+});
+
+/// EXP needed to advance from `level` to the next one.
+pub fn exp_to_level_up(level: i16) -> i32 {
+    match level {
+        level if !(1..MAX_LEVEL).contains(&level) => i32::MAX,
+        level => EXP_TABLE[level as usize - 1],
+    }
+}
+
+/// Splits the EXP of a killed mob among its attackers by the share of damage each dealt.
+/// The top attacker gets the white text the client shows for the killing blow.
+pub fn kill_rewards(mob: &Mob, exp_rate: u32) -> Vec<(i32, i32, bool)> {
+    let top_attacker = mob.top_attacker();
+    let total_damage = mob.damage_by.values().sum::<i64>().max(1);
+    let exp = mob.data.exp as i64 * exp_rate as i64;
+
+    mob.damage_by
+        .iter()
+        .map(|(character_id, damage)| {
+            let share = (exp * damage / total_damage).clamp(0, i32::MAX as i64) as i32;
+            (*character_id, share, top_attacker == Some(*character_id))
+        })
+        .filter(|(_, share, _)| *share > 0)
+        .collect()
+}
+
+/// HP and MP a character of `job` gains on level up.
+fn level_up_gains(job: i16) -> (i16, i16) {
+    let mut rng = rand::thread_rng();
+
+    match job / 100 {
+        1 => (rng.gen_range(24..=28), rng.gen_range(4..=6)),
+        2 => (rng.gen_range(10..=14), rng.gen_range(22..=24)),
+        3 | 4 => (rng.gen_range(20..=24), rng.gen_range(14..=16)),
+        9 => (500, 500),
+        _ => (rng.gen_range(12..=16), rng.gen_range(10..=12)),
+    }
+}
+
+fn level_up(character: &mut Character) {
+    let (hp_gain, mp_gain) = level_up_gains(character.job);
+
+    character.level += 1;
+    character.max_hp = character.max_hp.saturating_add(hp_gain).min(MAX_HP_MP);
+    character.max_mp = character.max_mp.saturating_add(mp_gain).min(MAX_HP_MP);
+    character.hp = character.max_hp;
+    character.mp = character.max_mp;
+    character.ap += AP_PER_LEVEL;
+    character.sp += match character.job {
+        0 if character.level <= BEGINNER_SP_LEVEL => 1,
+        0 => 0,
+        _ => SP_PER_LEVEL,
+    };
+}
+
+/// Gives `amount` EXP to the character, levelling it up as many times as it reaches the next
+/// level, and shows the level ups to the players in its map.
+pub fn gain_exp(
+    client: &Client,
+    character: &mut Character,
+    amount: i32,
+    white: bool,
+) -> Result<(), Box<dyn Error>> {
+    if amount <= 0 || character.level >= MAX_LEVEL {
+        return Ok(());
+    }
+
+    client.send(message::create_exp_gain(amount, white, false));
+
+    let level = character.level;
+    character.exp = character.exp.saturating_add(amount);
+    while character.level < MAX_LEVEL && character.exp >= exp_to_level_up(character.level) {
+        character.exp -= exp_to_level_up(character.level);
+        level_up(character);
+    }
+    if character.level >= MAX_LEVEL {
+        character.exp = 0;
+    }
+
+    if character.level == level {
+        client.send(create_update_stats(character, &[Stat::Exp], false));
+        return Ok(());
+    }
+
+    client.send(create_update_stats(
+        character,
+        &[
+            Stat::Level,
+            Stat::Hp,
+            Stat::MaxHp,
+            Stat::Mp,
+            Stat::MaxMp,
+            Stat::Ap,
+            Stat::Sp,
+            Stat::Exp,
+        ],
+        false,
+    ));
+    client.send(field::create_show_own_effect(Effect::LevelUp));
+
+    let map = Channel::get()?.map(character.map_id)?;
+    match map.lock() {
+        Ok(map_guard) => map_guard.broadcast(
+            &field::create_show_foreign_effect(character.id, Effect::LevelUp),
+            Some(character.id),
+        ),
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+
+    party::report(character);
+    party::update_hp(character, false)
+}
+
+/// Takes the EXP a character loses by dying; beginners and characters dying in towns keep it.
+pub fn lose_exp_on_death(character: &mut Character, town: bool) {
+    let loss_rate = match character.job / 100 {
+        _ if town || character.job == 0 || character.level >= MAX_LEVEL => return,
+        4 => THIEF_DEATH_EXP_LOSS,
+        _ => DEATH_EXP_LOSS,
+    };
+
+    let loss = (exp_to_level_up(character.level) as f64 * loss_rate) as i32;
+    character.exp = (character.exp - loss).max(0);
+}
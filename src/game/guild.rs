@@ -0,0 +1,408 @@
+use crate::db::model::character::{Character, CharacterUpdate};
+use crate::db::model::guild as guild_model;
+use crate::db::model::meso_log::MesoReason;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::inventory::InventoryType;
+use crate::game::meso;
+use crate::net::client::Client;
+use crate::net::interserver::{ChannelMessage, WorldLink};
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::guild as guild_packet;
+use log::warn;
+use std::error::Error;
+
+/// Mesos the leader pays for creating a guild.
+pub const GUILD_CREATION_COST: i32 = 1_500_000;
+/// Mesos the master pays for changing the emblem of the guild.
+pub const EMBLEM_CHANGE_COST: i32 = 5_000_000;
+/// Most characters a new guild holds.
+pub const DEFAULT_CAPACITY: u8 = 10;
+
+pub const MASTER_RANK: u8 = 1;
+pub const JR_MASTER_RANK: u8 = 2;
+/// The lowest rank, which new members start at.
+pub const MEMBER_RANK: u8 = 5;
+
+pub const DEFAULT_RANK_TITLES: [&str; 5] = ["Master", "Jr. Master", "Member", "Member", "Member"];
+
+const MIN_NAME_LENGTH: usize = 4;
+const MAX_NAME_LENGTH: usize = 12;
+/// Longest notice the client lets the guild write.
+pub const MAX_NOTICE_LENGTH: usize = 100;
+
+#[derive(Clone, Copy, Default)]
+pub struct GuildEmblem {
+    pub background: i16,
+    pub background_color: u8,
+    pub logo: i16,
+    pub logo_color: u8,
+}
+
+#[derive(Clone)]
+pub struct GuildMember {
+    pub character_id: i32,
+    pub name: String,
+    pub job: i16,
+    pub level: i16,
+    /// From 1 for the master down to 5.
+    pub rank: u8,
+    pub online: bool,
+}
+
+/// A guild as stored by the world server, which channel servers keep a copy of while its members
+/// are online.
+#[derive(Clone)]
+pub struct Guild {
+    pub id: i32,
+    pub world_id: i16,
+    pub name: String,
+    pub leader_id: i32,
+    pub rank_titles: Vec<String>,
+    pub capacity: u8,
+    pub emblem: GuildEmblem,
+    pub notice: String,
+    pub points: i32,
+    pub members: Vec<GuildMember>,
+}
+
+impl Guild {
+    /// Loads the guild and its members from the database.
+    pub fn load(guild_id: i32) -> Result<Option<Guild>, Box<dyn Error>> {
+        let record = match guild_model::Guild::get_by_id(guild_id)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        let members = guild_model::GuildMember::get_by_guild(guild_id)?
+            .into_iter()
+            .map(|member| GuildMember {
+                character_id: member.character_id,
+                name: member.name,
+                job: member.job,
+                level: member.level,
+                rank: member.rank as u8,
+                online: false,
+            })
+            .collect();
+        Ok(Some(Guild::from_record(record, members)))
+    }
+
+    pub fn from_record(record: guild_model::Guild, members: Vec<GuildMember>) -> Guild {
+        Guild {
+            id: record.id,
+            world_id: record.world_id,
+            name: record.name,
+            leader_id: record.leader_id,
+            rank_titles: record.rank_titles.split(',').map(|title| title.to_string()).collect(),
+            capacity: record.capacity as u8,
+            emblem: GuildEmblem {
+                background: record.emblem_background,
+                background_color: record.emblem_background_color as u8,
+                logo: record.emblem_logo,
+                logo_color: record.emblem_logo_color as u8,
+            },
+            notice: record.notice,
+            points: record.points,
+            members,
+        }
+    }
+
+    /// Stores everything about the guild but its members, which are stored as they change.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        guild_model::Guild {
+            id: self.id,
+            world_id: self.world_id,
+            name: self.name.clone(),
+            leader_id: self.leader_id,
+            rank_titles: self.rank_titles.join(","),
+            capacity: self.capacity as i16,
+            emblem_background: self.emblem.background,
+            emblem_background_color: self.emblem.background_color as i16,
+            emblem_logo: self.emblem.logo,
+            emblem_logo_color: self.emblem.logo_color as i16,
+            notice: self.notice.clone(),
+            points: self.points,
+        }
+        .save()?;
+        Ok(())
+    }
+
+    pub fn member(&self, character_id: i32) -> Option<&GuildMember> {
+        self.members.iter().find(|member| member.character_id == character_id)
+    }
+
+    pub fn member_mut(&mut self, character_id: i32) -> Option<&mut GuildMember> {
+        self.members.iter_mut().find(|member| member.character_id == character_id)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.members.len() >= self.capacity as usize
+    }
+
+    fn online_ids(&self) -> Vec<i32> {
+        self.members
+            .iter()
+            .filter(|member| member.online)
+            .map(|member| member.character_id)
+            .collect()
+    }
+}
+
+/// Whether the client would accept `name` for a guild.
+pub fn is_valid_name(name: &str) -> bool {
+    (MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// What changed in a guild, which decides the message its members see.
+pub enum GuildChange {
+    Created,
+    /// The member logged in or out.
+    MemberOnline(i32, bool),
+    /// The job or level of the member changed.
+    MemberUpdated(i32),
+    Joined(i32),
+    /// A member left or, when `expelled`, was expelled. It is no longer in the guild.
+    Left {
+        character_id: i32,
+        name: String,
+        expelled: bool,
+    },
+    Disbanded,
+    RankTitlesChanged,
+    RankChanged(i32),
+    EmblemChanged,
+    NoticeChanged,
+    PointsChanged,
+}
+
+/// Outcomes of guild requests the requester is told about.
+pub enum GuildResult {
+    NameInUse,
+    AlreadyJoined,
+    NotFound,
+    Full,
+    /// The named character turned the invitation down.
+    Denied(String),
+}
+
+/// Guild requests of players, applied by the world server.
+pub enum GuildRequest {
+    Create(String),
+    Invite(String),
+    Accept(i32),
+    /// Turns down the invitation of the named character.
+    Deny(String),
+    Leave,
+    Expel(i32),
+    ChangeRankTitles(Vec<String>),
+    ChangeRank(i32, u8),
+    ChangeEmblem(GuildEmblem),
+    ChangeNotice(String),
+    Disband,
+    GainPoints(i32),
+}
+
+/// Asks the world server to apply a guild request of `character_id`.
+pub fn request(character_id: i32, request: GuildRequest) -> Result<(), Box<dyn Error>> {
+    WorldLink::get()?.send(&ChannelMessage::Guild(character_id, request))
+}
+
+/// Sends a guild chat message of `character_id` to the other members of its guild.
+pub fn say(character_id: i32, text: String) -> Result<(), Box<dyn Error>> {
+    WorldLink::get()?.send(&ChannelMessage::GuildChat(character_id, text))
+}
+
+/// The rank of the character in its guild, or 0 when it has none.
+pub fn rank(character_id: i32) -> Result<u8, Box<dyn Error>> {
+    Ok(Channel::get()?
+        .guild_of(character_id)
+        .and_then(|guild| guild.member(character_id).map(|member| member.rank))
+        .unwrap_or(0))
+}
+
+/// Shows the guild change to the members playing on this channel, and to a member that just
+/// left it, and keeps the copy of the guild up to date.
+pub fn update(guild: Guild, change: GuildChange) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let members = guild.online_ids();
+    let others = |character_id: i32| -> Vec<i32> {
+        members.iter().copied().filter(|id| *id != character_id).collect()
+    };
+
+    match &change {
+        GuildChange::Created => {
+            // The fee was taken with the request and is now kept.
+            channel.take_guild_fee(guild.leader_id);
+            channel.send_to(&[guild.leader_id], &guild_packet::create_guild_info(Some(&guild)));
+        }
+        GuildChange::MemberOnline(character_id, online) => {
+            let packet = guild_packet::create_member_online(guild.id, *character_id, *online);
+            channel.send_to(&others(*character_id), &packet);
+            if *online {
+                channel.send_to(&[*character_id], &guild_packet::create_guild_info(Some(&guild)));
+            }
+        }
+        GuildChange::MemberUpdated(character_id) => {
+            if let Some(member) = guild.member(*character_id) {
+                channel.send_to(&members, &guild_packet::create_update_member(guild.id, member));
+            }
+        }
+        GuildChange::Joined(character_id) => {
+            if let Some(member) = guild.member(*character_id) {
+                channel.send_to(&others(*character_id), &guild_packet::create_new_member(guild.id, member));
+            }
+            channel.send_to(&[*character_id], &guild_packet::create_guild_info(Some(&guild)));
+        }
+        GuildChange::Left {
+            character_id,
+            name,
+            expelled,
+        } => {
+            let packet = guild_packet::create_member_left(guild.id, *character_id, name, *expelled);
+            channel.send_to(&members, &packet);
+            channel.send_to(&[*character_id], &packet);
+        }
+        GuildChange::Disbanded => channel.send_to(&members, &guild_packet::create_disband_guild(guild.id)),
+        GuildChange::RankTitlesChanged => {
+            channel.send_to(&members, &guild_packet::create_change_rank_titles(guild.id, &guild.rank_titles))
+        }
+        GuildChange::RankChanged(character_id) => {
+            if let Some(member) = guild.member(*character_id) {
+                channel.send_to(&members, &guild_packet::create_change_rank(guild.id, member));
+            }
+        }
+        GuildChange::EmblemChanged => {
+            channel.take_guild_fee(guild.leader_id);
+            channel.send_to(&members, &guild_packet::create_change_emblem(guild.id, guild.emblem));
+        }
+        GuildChange::NoticeChanged => {
+            channel.send_to(&members, &guild_packet::create_change_notice(guild.id, &guild.notice))
+        }
+        GuildChange::PointsChanged => {
+            channel.send_to(&members, &guild_packet::create_update_points(guild.id, guild.points))
+        }
+    }
+
+    // Characters whose guild name or emblem others see changed.
+    let shown: Vec<i32> = match change {
+        GuildChange::Created => vec![guild.leader_id],
+        GuildChange::MemberOnline(character_id, true) | GuildChange::Joined(character_id) => vec![character_id],
+        GuildChange::Left { character_id, .. } => vec![character_id],
+        GuildChange::Disbanded | GuildChange::EmblemChanged => members,
+        _ => Vec::new(),
+    };
+
+    match change {
+        GuildChange::Disbanded => channel.remove_guild(guild.id)?,
+        _ => channel.update_guild(guild)?,
+    }
+
+    for character_id in shown {
+        if let Some(player) = channel.player(character_id) {
+            let result = character::with_character(&player.client, |client, character| {
+                character::update_look(character, character::lock_inventory(client)?.get(InventoryType::Equipped))
+            });
+            if let Err(error) = result {
+                warn!("Unable to show the guild of character {} [{}]", character_id, error);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Takes the fee of a guild request, storing it right away, then asks the world server to apply
+/// the request. The fee is given back if the world server turns the request down.
+pub fn request_paid(
+    client: &Client,
+    character: &mut Character,
+    guild_request: GuildRequest,
+    cost: i32,
+    description: &str,
+) -> Result<(), Box<dyn Error>> {
+    if character.meso < cost {
+        return Err(format!("{} cannot pay {} mesos for a {}", character.name, cost, description).into());
+    }
+    let channel = Channel::get()?;
+    if !channel.add_guild_fee(character.id, cost)? {
+        return Err(format!("{} already waits on a paid guild request", character.name).into());
+    }
+
+    let meso = character.meso - cost;
+    let character_update = CharacterUpdate {
+        character_id: character.id,
+        meso,
+        inventory: None,
+    };
+    if let Err(error) = character_update.store() {
+        channel.take_guild_fee(character.id);
+        return Err(error);
+    }
+    character.meso = meso;
+    meso::log(character, -cost, MesoReason::Guild, description);
+    client.send(create_update_stats(character, &[Stat::Meso], false));
+
+    if let Err(error) = request(character.id, guild_request) {
+        if let Some(fee) = channel.take_guild_fee(character.id) {
+            give_back_fee(client, character, fee)?;
+        }
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Gives back the fee the character paid for a guild request the world server turned down.
+fn refund(character_id: i32) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let fee = match channel.take_guild_fee(character_id) {
+        Some(fee) => fee,
+        None => return Ok(()),
+    };
+
+    match channel.player(character_id) {
+        Some(player) => character::with_character(&player.client, |client, character| {
+            give_back_fee(client, character, fee)
+        }),
+        None => {
+            Character::add_meso(character_id, fee)?;
+            Ok(())
+        }
+    }
+}
+
+fn give_back_fee(client: &Client, character: &mut Character, fee: i32) -> Result<(), Box<dyn Error>> {
+    let meso = match character.meso.checked_add(fee) {
+        Some(meso) => meso,
+        None => return Err(format!("{} has no room for a guild fee of {} mesos", character.name, fee).into()),
+    };
+    CharacterUpdate {
+        character_id: character.id,
+        meso,
+        inventory: None,
+    }
+    .store()?;
+    character.meso = meso;
+    meso::log(character, fee, MesoReason::Guild, "refunded guild fee");
+    client.send(create_update_stats(character, &[Stat::Meso], false));
+    Ok(())
+}
+
+/// Shows the invitation of `inviter_name` to their guild to the invited character.
+pub fn invite(character_id: i32, guild_id: i32, inviter_name: &str) -> Result<(), Box<dyn Error>> {
+    Channel::get()?.send_to(&[character_id], &guild_packet::create_guild_invite(guild_id, inviter_name));
+    Ok(())
+}
+
+/// Tells the character how its guild request went.
+pub fn result(character_id: i32, result: &GuildResult) -> Result<(), Box<dyn Error>> {
+    if let GuildResult::NameInUse | GuildResult::AlreadyJoined = result {
+        refund(character_id)?;
+    }
+    Channel::get()?.send_to(&[character_id], &guild_packet::create_guild_result(result));
+    Ok(())
+}
+
+/// Gives back what the character paid for a guild request the world server rejected.
+pub fn failed(character_id: i32) -> Result<(), Box<dyn Error>> {
+    refund(character_id)
+}
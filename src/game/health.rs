@@ -0,0 +1,85 @@
+use crate::data::map::MapData;
+use crate::db::model::character::Character;
+use crate::game::buff::{self, CharacterBuffs};
+use crate::game::character;
+use crate::game::experience;
+use crate::game::party;
+use crate::net::client::Client;
+use crate::net::packet::character::{create_update_stats, Stat};
+use log::warn;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+/// The client asks for natural recovery every 10 seconds, or every 5 while sitting.
+const MIN_RECOVERY_INTERVAL: Duration = Duration::from_secs(4);
+/// Room for recovery skills and chairs over the regular amount.
+const MAX_RECOVERY_AMOUNT: i16 = 300;
+/// HP a dead character comes back with.
+const RESPAWN_HP: i16 = 50;
+
+/// Checks a natural HP/MP recovery against the amounts and pace the client allows, returning
+/// the reason it is impossible.
+pub fn validate_recovery(buffs: &mut CharacterBuffs, hp: i16, mp: i16) -> Result<(), String> {
+    if !(0..=MAX_RECOVERY_AMOUNT).contains(&hp) || !(0..=MAX_RECOVERY_AMOUNT).contains(&mp) {
+        return Err(format!("recovered {} HP and {} MP at once", hp, mp));
+    }
+    match buffs.start_recovery(Instant::now(), MIN_RECOVERY_INTERVAL) {
+        true => Ok(()),
+        false => Err("recovered HP or MP too soon after the last time".to_string()),
+    }
+}
+
+/// Applies a validated natural recovery; dead characters do not recover.
+pub fn recover(client: &Client, character: &mut Character, hp: i16, mp: i16) {
+    if character.hp <= 0 {
+        return;
+    }
+
+    character.hp = character.hp.saturating_add(hp).min(character.max_hp);
+    character.mp = character.mp.saturating_add(mp).min(character.max_mp);
+    client.send(create_update_stats(character, &[Stat::Hp, Stat::Mp], true));
+    if let Err(error) = party::update_hp(character, false) {
+        warn!("Unable to show HP of {} to its party [{}]", character.name, error);
+    }
+}
+
+/// Takes `damage` HP from the character, killing it once none is left.
+pub fn take_damage(client: &Client, character: &mut Character, damage: i32) -> Result<(), Box<dyn Error>> {
+    if character.hp <= 0 {
+        return Ok(());
+    }
+
+    character.hp = (character.hp as i32 - damage).max(0) as i16;
+    if character.hp > 0 {
+        client.send(create_update_stats(character, &[Stat::Hp], true));
+        return party::update_hp(character, false);
+    }
+
+    let town = MapData::get(character.map_id).is_some_and(|data| data.town);
+    experience::lose_exp_on_death(character, town);
+    client.send(create_update_stats(character, &[Stat::Hp, Stat::Exp], true));
+    party::update_hp(character, false)?;
+
+    let expired = character::lock_buffs(client)?.take_all();
+    for expired_buff in expired {
+        buff::cancel(client, character.id, character.map_id, &expired_buff)?;
+    }
+
+    Ok(())
+}
+
+/// Brings a dead character back to life in the return map of the map it died in.
+pub fn respawn(client: &Client, character: &mut Character) -> Result<(), Box<dyn Error>> {
+    if character.hp > 0 {
+        return Err(format!("{} is not dead", character.name).into());
+    }
+
+    let map_id = MapData::get(character.map_id)
+        .and_then(|data| data.return_map_id)
+        .unwrap_or(character.map_id);
+
+    character.hp = RESPAWN_HP.min(character.max_hp);
+    character::change_map(client, character, map_id, 0)?;
+    client.send(create_update_stats(character, &[Stat::Hp], true));
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use crate::db::model::character::Character;
+use crate::db::model::infraction::{Infraction, InfractionKind, InfractionSubject, NewInfraction};
+use crate::db::model::user::User;
+use crate::game::channel::{Channel, Player};
+use crate::net::ban_list::{self, BanList, IpNetwork};
+use log::{info, warn};
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// Who an infraction applies to, as written by a GM: `account:<username>`, `ip:<address or network>`,
+/// `mac:<address>`, `hwid:<hardware id>` or a plain character name.
+pub enum InfractionTarget {
+    Account(String),
+    Character(String),
+    Ip(String),
+    Mac(String),
+    HardwareId(String),
+}
+
+impl InfractionTarget {
+    pub fn parse(value: &str) -> InfractionTarget {
+        match value.split_once(':') {
+            Some(("account", username)) => InfractionTarget::Account(username.to_string()),
+            Some(("ip", ip_address)) => InfractionTarget::Ip(ip_address.to_string()),
+            Some(("mac", mac_address)) => InfractionTarget::Mac(mac_address.to_string()),
+            Some(("hwid", hardware_id)) => InfractionTarget::HardwareId(hardware_id.to_string()),
+            _ => InfractionTarget::Character(value.to_string()),
+        }
+    }
+
+    /// Resolves the target to what an infraction is recorded against.
+    fn resolve(&self) -> Result<InfractionSubject, Box<dyn Error>> {
+        match self {
+            InfractionTarget::Account(username) => match User::get_by_username(username)? {
+                Some(user) => Ok(InfractionSubject {
+                    user_id: Some(user.id),
+                    ..Default::default()
+                }),
+                None => Err(format!("Account {} does not exist", username).into()),
+            },
+            InfractionTarget::Character(name) => match Character::get_by_name(name)? {
+                Some(character) => Ok(InfractionSubject {
+                    user_id: Some(character.user_id),
+                    ..Default::default()
+                }),
+                None => Err(format!("Character {} does not exist", name).into()),
+            },
+            InfractionTarget::Ip(ip_address) => match ip_address.parse::<IpNetwork>() {
+                Ok(_) => Ok(InfractionSubject {
+                    ip_address: Some(ip_address.clone()),
+                    ..Default::default()
+                }),
+                Err(error) => Err(format!("Invalid IP address {} [{}]", ip_address, error).into()),
+            },
+            InfractionTarget::Mac(mac_address) => match ban_list::normalize_mac_address(mac_address) {
+                normalized if normalized.len() == 12 => Ok(InfractionSubject {
+                    mac_address: Some(normalized),
+                    ..Default::default()
+                }),
+                _ => Err(format!("Invalid MAC address {}", mac_address).into()),
+            },
+            InfractionTarget::HardwareId(hardware_id) => Ok(InfractionSubject {
+                hardware_id: Some(hardware_id.clone()),
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl fmt::Display for InfractionTarget {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InfractionTarget::Account(username) => write!(formatter, "account {}", username),
+            InfractionTarget::Character(name) => write!(formatter, "{}", name),
+            InfractionTarget::Ip(ip_address) => write!(formatter, "IP {}", ip_address),
+            InfractionTarget::Mac(mac_address) => write!(formatter, "MAC {}", mac_address),
+            InfractionTarget::HardwareId(hardware_id) => write!(formatter, "hardware id {}", hardware_id),
+        }
+    }
+}
+
+/// Players connected to this process that the subject applies to.
+fn online_players(subject: &InfractionSubject) -> Vec<Player> {
+    let channel = match Channel::get() {
+        Ok(channel) => channel,
+        Err(_) => return Vec::new(),
+    };
+    let network = subject
+        .ip_address
+        .as_ref()
+        .and_then(|ip_address| ip_address.parse::<IpNetwork>().ok());
+
+    channel
+        .players()
+        .into_iter()
+        .filter(|player| {
+            if Some(player.user_id) == subject.user_id {
+                return true;
+            }
+
+            let client_guard = match player.client.lock() {
+                Ok(guard) => guard,
+                Err(_) => return false,
+            };
+
+            let address_matches = match (&network, client_guard.address()) {
+                (Some(network), Some(address)) => network.contains(address),
+                _ => false,
+            };
+
+            address_matches
+                || match (&client_guard.user, &subject.mac_address, &subject.hardware_id) {
+                    (Some(user_mutex), Some(_), _) | (Some(user_mutex), _, Some(_)) => match user_mutex.lock() {
+                        Ok(user) => {
+                            user.mac_addresses.as_ref().is_some_and(|mac_addresses| {
+                                mac_addresses
+                                    .split(',')
+                                    .any(|mac_address| Some(ban_list::normalize_mac_address(mac_address)) == subject.mac_address)
+                            }) || (subject.hardware_id.is_some() && user.hardware_id == subject.hardware_id)
+                        }
+                        Err(_) => false,
+                    },
+                    _ => false,
+                }
+        })
+        .collect()
+}
+
+fn record(
+    kind: InfractionKind,
+    target: &InfractionTarget,
+    reason: i16,
+    description: &str,
+    duration: Option<Duration>,
+    issuer_id: Option<i32>,
+) -> Result<(Infraction, Vec<Player>), Box<dyn Error>> {
+    let subject = target.resolve()?;
+    let now = SystemTime::now();
+
+    let infraction = Infraction::create(NewInfraction {
+        kind: kind as i16,
+        user_id: subject.user_id,
+        ip_address: subject.ip_address.clone(),
+        mac_address: subject.mac_address.clone(),
+        hardware_id: subject.hardware_id.clone(),
+        reason,
+        description: description.to_string(),
+        issuer_id,
+        issue_date: now,
+        expiry_date: duration.map(|duration| now + duration),
+    })?;
+
+    Ok((infraction, online_players(&subject)))
+}
+
+/// Bans the target for `duration` (forever when `None`) and disconnects its online players.
+pub fn ban(
+    target: &InfractionTarget,
+    reason: i16,
+    description: &str,
+    duration: Option<Duration>,
+    issuer_id: Option<i32>,
+) -> Result<Infraction, Box<dyn Error>> {
+    let (infraction, players) = record(InfractionKind::Ban, target, reason, description, duration, issuer_id)?;
+    BanList::insert(&infraction);
+
+    for player in players {
+        if let Ok(client_guard) = player.client.lock() {
+            client_guard.disconnect();
+        }
+    }
+
+    info!("{} has been banned (infraction {})", target, infraction.id);
+    Ok(infraction)
+}
+
+/// Mutes the target for `duration` (forever when `None`), taking effect on its online players
+/// without requiring them to reconnect.
+pub fn mute(
+    target: &InfractionTarget,
+    reason: i16,
+    description: &str,
+    duration: Option<Duration>,
+    issuer_id: Option<i32>,
+) -> Result<Infraction, Box<dyn Error>> {
+    let (infraction, players) = record(InfractionKind::Mute, target, reason, description, duration, issuer_id)?;
+
+    for player in players {
+        if let Ok(mut client_guard) = player.client.lock() {
+            client_guard.mute = Some(infraction.clone());
+        }
+    }
+
+    info!("{} has been muted (infraction {})", target, infraction.id);
+    Ok(infraction)
+}
+
+pub fn unban(target: &InfractionTarget, revoker_id: Option<i32>) -> Result<usize, Box<dyn Error>> {
+    let revoked = Infraction::revoke_active(InfractionKind::Ban, &target.resolve()?, revoker_id)?;
+
+    if let Err(error) = BanList::reload() {
+        warn!("Unable to reload ban list [{}]", error);
+    }
+
+    Ok(revoked)
+}
+
+pub fn unmute(target: &InfractionTarget, revoker_id: Option<i32>) -> Result<usize, Box<dyn Error>> {
+    let subject = target.resolve()?;
+    let revoked = Infraction::revoke_active(InfractionKind::Mute, &subject, revoker_id)?;
+
+    for player in online_players(&subject) {
+        if let Ok(mut client_guard) = player.client.lock() {
+            client_guard.mute = None;
+        }
+    }
+
+    Ok(revoked)
+}
+
+pub fn history(target: &InfractionTarget) -> Result<Vec<Infraction>, Box<dyn Error>> {
+    match target.resolve()? {
+        InfractionSubject {
+            user_id: Some(user_id),
+            ..
+        } => Infraction::get_by_user(user_id),
+        _ => Err("Infraction history is kept per account".into()),
+    }
+}
@@ -0,0 +1,318 @@
+use crate::db::model::character::Character;
+use crate::db::model::inventory_item::{InventoryItem, NewInventoryItem};
+use crate::db::model::merchant::{MerchantItem, NewMerchantItem};
+use crate::db::model::storage::{NewStorageItem, StorageItem};
+use log::warn;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::time::SystemTime;
+
+/// Positions of equipped cash items are offset by this much from the slot they cover.
+pub const CASH_EQUIP_OFFSET: i16 = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InventoryType {
+    Equipped = -1,
+    Equip = 1,
+    Use = 2,
+    Setup = 3,
+    Etc = 4,
+    Cash = 5,
+}
+
+impl InventoryType {
+    pub fn from_i16(value: i16) -> Option<InventoryType> {
+        match value {
+            -1 => Some(InventoryType::Equipped),
+            1 => Some(InventoryType::Equip),
+            2 => Some(InventoryType::Use),
+            3 => Some(InventoryType::Setup),
+            4 => Some(InventoryType::Etc),
+            5 => Some(InventoryType::Cash),
+            _ => None,
+        }
+    }
+
+    /// The tab an item is kept in, which is encoded in the first digit of its id.
+    pub fn of_item(item_id: i32) -> Option<InventoryType> {
+        match item_id / 1000000 {
+            1 => Some(InventoryType::Equip),
+            2 => Some(InventoryType::Use),
+            3 => Some(InventoryType::Setup),
+            4 => Some(InventoryType::Etc),
+            5 => Some(InventoryType::Cash),
+            _ => None,
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            InventoryType::Equipped => 0,
+            InventoryType::Equip => 1,
+            InventoryType::Use => 2,
+            InventoryType::Setup => 3,
+            InventoryType::Etc => 4,
+            InventoryType::Cash => 5,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct EquipStats {
+    pub upgrade_slots: i16,
+    pub upgrades: i16,
+    pub strength: i16,
+    pub dexterity: i16,
+    pub intelligence: i16,
+    pub luck: i16,
+    pub hp: i16,
+    pub mp: i16,
+    pub weapon_attack: i16,
+    pub magic_attack: i16,
+    pub weapon_defense: i16,
+    pub magic_defense: i16,
+    pub accuracy: i16,
+    pub avoidability: i16,
+    pub hands: i16,
+    pub speed: i16,
+    pub jump: i16,
+}
+
+/// Builds an item from a row of any of the item tables, which all store an item in the same
+/// columns.
+macro_rules! item_from_row {
+    ($row:expr) => {{
+        let row = $row;
+        Item {
+            item_id: row.item_id,
+            quantity: row.quantity,
+            owner: row.owner.clone(),
+            flags: row.flags,
+            expiration_date: row.expiration_date,
+            equip: match InventoryType::of_item(row.item_id) {
+                Some(InventoryType::Equip) => Some(EquipStats {
+                    upgrade_slots: row.upgrade_slots,
+                    upgrades: row.upgrades,
+                    strength: row.strength,
+                    dexterity: row.dexterity,
+                    intelligence: row.intelligence,
+                    luck: row.luck,
+                    hp: row.hp,
+                    mp: row.mp,
+                    weapon_attack: row.weapon_attack,
+                    magic_attack: row.magic_attack,
+                    weapon_defense: row.weapon_defense,
+                    magic_defense: row.magic_defense,
+                    accuracy: row.accuracy,
+                    avoidability: row.avoidability,
+                    hands: row.hands,
+                    speed: row.speed,
+                    jump: row.jump,
+                }),
+                _ => None,
+            },
+        }
+    }};
+}
+
+/// Builds a row of one of the item tables from an item, along with the columns named after the
+/// given variables, which place the item in that table.
+macro_rules! item_row {
+    ($item:expr, $row:ident { $($column:ident),* $(,)? }) => {{
+        let item: &Item = $item;
+        let stats = item.equip.clone().unwrap_or_default();
+
+        $row {
+            $($column,)*
+            item_id: item.item_id,
+            quantity: item.quantity,
+            owner: item.owner.clone(),
+            flags: item.flags,
+            expiration_date: item.expiration_date,
+            upgrade_slots: stats.upgrade_slots,
+            upgrades: stats.upgrades,
+            strength: stats.strength,
+            dexterity: stats.dexterity,
+            intelligence: stats.intelligence,
+            luck: stats.luck,
+            hp: stats.hp,
+            mp: stats.mp,
+            weapon_attack: stats.weapon_attack,
+            magic_attack: stats.magic_attack,
+            weapon_defense: stats.weapon_defense,
+            magic_defense: stats.magic_defense,
+            accuracy: stats.accuracy,
+            avoidability: stats.avoidability,
+            hands: stats.hands,
+            speed: stats.speed,
+            jump: stats.jump,
+        }
+    }};
+}
+
+#[derive(Clone)]
+pub struct Item {
+    pub item_id: i32,
+    pub quantity: i16,
+    pub owner: String,
+    pub flags: i16,
+    pub expiration_date: Option<SystemTime>,
+    pub equip: Option<EquipStats>,
+}
+
+impl Item {
+    /// Throwing stars and bullets, which are recharged instead of stacked.
+    pub fn is_rechargeable(&self) -> bool {
+        matches!(self.item_id / 10000, 207 | 233)
+    }
+
+    pub fn is_stackable(&self) -> bool {
+        self.equip.is_none() && !self.is_rechargeable()
+    }
+
+    fn from_row(row: &InventoryItem) -> Item {
+        item_from_row!(row)
+    }
+
+    fn to_row(&self, character_id: i32, inventory_type: InventoryType, position: i16) -> NewInventoryItem {
+        let inventory_type = inventory_type as i16;
+        item_row!(self, NewInventoryItem { character_id, inventory_type, position })
+    }
+
+    pub fn from_storage_row(row: &StorageItem) -> Item {
+        item_from_row!(row)
+    }
+
+    pub fn to_storage_row(&self, storage_id: i32, position: i16) -> NewStorageItem {
+        item_row!(self, NewStorageItem { storage_id, position })
+    }
+
+    pub fn from_merchant_row(row: &MerchantItem) -> Item {
+        item_from_row!(row)
+    }
+
+    pub fn to_merchant_row(&self, merchant_id: i32, position: i16, price: i32, bundles: i16) -> NewMerchantItem {
+        item_row!(self, NewMerchantItem { merchant_id, position, price, bundles })
+    }
+}
+
+/// One inventory tab. Slots start at 1, except for equipped items which use the negative
+/// equip slot positions the client expects.
+#[derive(Clone)]
+pub struct Inventory {
+    inventory_type: InventoryType,
+    slot_limit: i16,
+    items: BTreeMap<i16, Item>,
+}
+
+impl Inventory {
+    pub fn new(inventory_type: InventoryType, slot_limit: i16) -> Inventory {
+        Inventory {
+            inventory_type,
+            slot_limit,
+            items: BTreeMap::new(),
+        }
+    }
+
+    pub fn inventory_type(&self) -> InventoryType {
+        self.inventory_type
+    }
+
+    pub fn slot_limit(&self) -> i16 {
+        self.slot_limit
+    }
+
+    pub fn get(&self, position: i16) -> Option<&Item> {
+        self.items.get(&position)
+    }
+
+    pub fn get_mut(&mut self, position: i16) -> Option<&mut Item> {
+        self.items.get_mut(&position)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = (i16, &Item)> {
+        self.items.iter().map(|(position, item)| (*position, item))
+    }
+
+    /// Puts `item` at `position`, returning the item that was there.
+    pub fn insert(&mut self, position: i16, item: Item) -> Option<Item> {
+        self.items.insert(position, item)
+    }
+
+    pub fn remove(&mut self, position: i16) -> Option<Item> {
+        self.items.remove(&position)
+    }
+
+    /// Takes every item out of the tab, in position order.
+    pub fn drain(&mut self) -> Vec<(i16, Item)> {
+        std::mem::take(&mut self.items).into_iter().collect()
+    }
+
+    pub fn is_valid_slot(&self, position: i16) -> bool {
+        position >= 1 && position <= self.slot_limit
+    }
+
+    pub fn free_slots(&self) -> usize {
+        (self.slot_limit.max(0) as usize).saturating_sub(self.items.len())
+    }
+
+    pub fn next_free_slot(&self) -> Option<i16> {
+        (1..=self.slot_limit).find(|position| !self.items.contains_key(position))
+    }
+}
+
+#[derive(Clone)]
+pub struct CharacterInventory {
+    inventories: [Inventory; 6],
+}
+
+impl CharacterInventory {
+    pub fn load(character: &Character) -> Result<CharacterInventory, Box<dyn Error>> {
+        let mut inventory = CharacterInventory {
+            inventories: [
+                Inventory::new(InventoryType::Equipped, 0),
+                Inventory::new(InventoryType::Equip, character.equip_slots),
+                Inventory::new(InventoryType::Use, character.use_slots),
+                Inventory::new(InventoryType::Setup, character.setup_slots),
+                Inventory::new(InventoryType::Etc, character.etc_slots),
+                Inventory::new(InventoryType::Cash, character.cash_slots),
+            ],
+        };
+
+        for row in InventoryItem::get_by_character(character.id)? {
+            match InventoryType::from_i16(row.inventory_type) {
+                Some(inventory_type) => {
+                    inventory
+                        .get_mut(inventory_type)
+                        .insert(row.position, Item::from_row(&row));
+                }
+                None => warn!(
+                    "Ignoring item {} of character {} with unknown inventory type {}",
+                    row.id, character.id, row.inventory_type
+                ),
+            }
+        }
+
+        Ok(inventory)
+    }
+
+    /// The rows the inventory is stored as.
+    pub fn rows(&self, character_id: i32) -> Vec<NewInventoryItem> {
+        self.inventories
+            .iter()
+            .flat_map(|inventory| {
+                inventory.items().map(move |(position, item)| {
+                    item.to_row(character_id, inventory.inventory_type(), position)
+                })
+            })
+            .collect()
+    }
+
+    pub fn get(&self, inventory_type: InventoryType) -> &Inventory {
+        &self.inventories[inventory_type.index()]
+    }
+
+    pub fn get_mut(&mut self, inventory_type: InventoryType) -> &mut Inventory {
+        &mut self.inventories[inventory_type.index()]
+    }
+}
@@ -0,0 +1,526 @@
+use crate::data::item::{EquipData, ItemData};
+use crate::db::model::character::Character;
+use crate::game::inventory::{CharacterInventory, Inventory, InventoryType, Item, CASH_EQUIP_OFFSET};
+use std::error::Error;
+
+const TOP_SLOT: i16 = 5;
+const PANTS_SLOT: i16 = 6;
+const SHIELD_SLOT: i16 = 10;
+const WEAPON_SLOT: i16 = 11;
+
+const GM_JOB_BRANCH: i16 = 9;
+
+/// A change to the client's inventory, sent with MODIFY_INVENTORY.
+pub enum InventoryOperation {
+    Add(InventoryType, i16, Item),
+    Quantity(InventoryType, i16, i16),
+    Move(InventoryType, i16, i16),
+    Remove(InventoryType, i16),
+}
+
+/// The equip slots an item may be worn in, derived from its category.
+fn equip_slots(item_id: i32) -> &'static [i16] {
+    match item_id / 10000 {
+        100 => &[1],
+        101 => &[2],
+        102 => &[3],
+        103 => &[4],
+        104 | 105 => &[TOP_SLOT],
+        106 => &[PANTS_SLOT],
+        107 => &[7],
+        108 => &[8],
+        109 => &[SHIELD_SLOT],
+        110 => &[9],
+        111 => &[12, 13, 15, 16],
+        112 => &[17],
+        130..=170 => &[WEAPON_SLOT],
+        180 => &[14],
+        190 => &[18],
+        191 => &[19],
+        _ => &[],
+    }
+}
+
+fn is_overall(item_id: i32) -> bool {
+    item_id / 10000 == 105
+}
+
+fn is_two_handed(item_id: i32) -> bool {
+    matches!(item_id / 10000, 140..=149)
+}
+
+/// Checks the level, fame, base stats and job branch of the character against the equip.
+pub fn meets_requirements(character: &Character, equip: &EquipData) -> bool {
+    let job_branch = character.job / 100;
+    if job_branch == GM_JOB_BRANCH {
+        return true;
+    }
+
+    let job_allowed = match equip.required_job {
+        0 => true,
+        -1 => job_branch == 0,
+        mask => (1..=5).contains(&job_branch) && mask & (1 << (job_branch - 1)) != 0,
+    };
+
+    job_allowed
+        && character.level >= equip.required_level
+        && character.fame >= equip.required_fame
+        && character.strength >= equip.required_strength
+        && character.dexterity >= equip.required_dexterity
+        && character.intelligence >= equip.required_intelligence
+        && character.luck >= equip.required_luck
+}
+
+fn slot_max(item_id: i32) -> Result<i16, Box<dyn Error>> {
+    match ItemData::get(item_id) {
+        Some(item_data) => Ok(item_data.slot_max.max(1)),
+        None => Err(format!("Unknown item {}", item_id).into()),
+    }
+}
+
+/// Handles the move packet, which equips, unequips, merges, splits and swaps items.
+/// Drops, which have a destination of 0, go through `take_for_drop`.
+pub fn move_item(
+    character: &Character,
+    inventory: &mut CharacterInventory,
+    inventory_type: InventoryType,
+    source: i16,
+    destination: i16,
+    quantity: i16,
+) -> Result<Vec<InventoryOperation>, Box<dyn Error>> {
+    if source < 0 && destination > 0 {
+        unequip(inventory, source, destination)
+    } else if destination < 0 {
+        equip(character, inventory, source, destination)
+    } else if inventory_type == InventoryType::Equipped {
+        Err("Cannot move items within the equipped items".into())
+    } else {
+        move_within(inventory, inventory_type, source, destination, quantity)
+    }
+}
+
+fn equip(
+    character: &Character,
+    inventory: &mut CharacterInventory,
+    source: i16,
+    destination: i16,
+) -> Result<Vec<InventoryOperation>, Box<dyn Error>> {
+    let item_id = match inventory.get(InventoryType::Equip).get(source) {
+        Some(item) => item.item_id,
+        None => return Err(format!("No equip at position {}", source).into()),
+    };
+    let item_data = match ItemData::get(item_id) {
+        Some(item_data) => item_data,
+        None => return Err(format!("Unknown item {}", item_id).into()),
+    };
+    let equip_data = match &item_data.equip {
+        Some(equip_data) => equip_data,
+        None => return Err(format!("Item {} is not an equip", item_id).into()),
+    };
+
+    let is_cash_slot = destination <= -CASH_EQUIP_OFFSET;
+    let slot = match is_cash_slot {
+        true => -destination - CASH_EQUIP_OFFSET,
+        false => -destination,
+    };
+
+    if is_cash_slot != item_data.cash || !equip_slots(item_id).contains(&slot) {
+        return Err(format!("Item {} cannot be worn at {}", item_id, destination).into());
+    }
+
+    if !meets_requirements(character, equip_data) {
+        return Err(format!("{} does not meet the requirements of {}", character.name, item_id).into());
+    }
+
+    let mut operations = Vec::new();
+    let equipped = inventory.get(InventoryType::Equipped);
+    let equipped_id = |slot: i16| equipped.get(-slot).map(|item| item.item_id);
+
+    let conflicting_slot = match (is_cash_slot, slot) {
+        (false, TOP_SLOT) if is_overall(item_id) => Some(PANTS_SLOT),
+        (false, PANTS_SLOT) if equipped_id(TOP_SLOT).is_some_and(is_overall) => Some(TOP_SLOT),
+        (false, SHIELD_SLOT) if equipped_id(WEAPON_SLOT).is_some_and(is_two_handed) => Some(WEAPON_SLOT),
+        (false, WEAPON_SLOT) if is_two_handed(item_id) => Some(SHIELD_SLOT),
+        _ => None,
+    };
+
+    if let Some(conflicting_slot) = conflicting_slot.filter(|slot| equipped_id(*slot).is_some()) {
+        let free_slot = match inventory.get(InventoryType::Equip).next_free_slot() {
+            Some(free_slot) => free_slot,
+            None => return Err("Equip inventory is full".into()),
+        };
+        operations.append(&mut unequip(inventory, -conflicting_slot, free_slot)?);
+    }
+
+    if let Some(item) = inventory.get_mut(InventoryType::Equip).remove(source) {
+        if let Some(previous_item) = inventory.get_mut(InventoryType::Equipped).insert(destination, item) {
+            inventory.get_mut(InventoryType::Equip).insert(source, previous_item);
+        }
+    }
+
+    operations.push(InventoryOperation::Move(InventoryType::Equip, source, destination));
+    Ok(operations)
+}
+
+fn unequip(
+    inventory: &mut CharacterInventory,
+    source: i16,
+    destination: i16,
+) -> Result<Vec<InventoryOperation>, Box<dyn Error>> {
+    let equip_inventory = inventory.get(InventoryType::Equip);
+    if !equip_inventory.is_valid_slot(destination) || equip_inventory.get(destination).is_some() {
+        return Err(format!("Cannot unequip to position {}", destination).into());
+    }
+
+    match inventory.get_mut(InventoryType::Equipped).remove(source) {
+        Some(item) => inventory.get_mut(InventoryType::Equip).insert(destination, item),
+        None => return Err(format!("Nothing is equipped at {}", source).into()),
+    };
+
+    Ok(vec![InventoryOperation::Move(InventoryType::Equip, source, destination)])
+}
+
+fn move_within(
+    inventory: &mut CharacterInventory,
+    inventory_type: InventoryType,
+    source: i16,
+    destination: i16,
+    quantity: i16,
+) -> Result<Vec<InventoryOperation>, Box<dyn Error>> {
+    let tab = inventory.get_mut(inventory_type);
+    if !tab.is_valid_slot(source) || !tab.is_valid_slot(destination) || source == destination {
+        return Err(format!("Invalid move from {} to {}", source, destination).into());
+    }
+
+    let item = match tab.get(source) {
+        Some(item) => item.clone(),
+        None => return Err(format!("No item at position {}", source).into()),
+    };
+
+    let target = tab.get(destination).map(|target| (target.item_id, target.quantity));
+    match target {
+        None if item.is_stackable() && quantity > 0 && quantity < item.quantity => {
+            if let Some(source_item) = tab.get_mut(source) {
+                source_item.quantity -= quantity;
+            }
+            let remaining = item.quantity - quantity;
+            let split_item = Item { quantity, ..item };
+            tab.insert(destination, split_item.clone());
+
+            Ok(vec![
+                InventoryOperation::Quantity(inventory_type, source, remaining),
+                InventoryOperation::Add(inventory_type, destination, split_item),
+            ])
+        }
+        Some((target_id, target_quantity)) if target_id == item.item_id && item.is_stackable() => {
+            let moved = item.quantity.min(slot_max(item.item_id)? - target_quantity).max(0);
+            if moved == 0 {
+                return Ok(swap(tab, inventory_type, source, destination));
+            }
+
+            if let Some(target_item) = tab.get_mut(destination) {
+                target_item.quantity += moved;
+            }
+
+            if moved == item.quantity {
+                tab.remove(source);
+                Ok(vec![
+                    InventoryOperation::Remove(inventory_type, source),
+                    InventoryOperation::Quantity(inventory_type, destination, target_quantity + moved),
+                ])
+            } else {
+                if let Some(source_item) = tab.get_mut(source) {
+                    source_item.quantity -= moved;
+                }
+                Ok(vec![
+                    InventoryOperation::Quantity(inventory_type, source, item.quantity - moved),
+                    InventoryOperation::Quantity(inventory_type, destination, target_quantity + moved),
+                ])
+            }
+        }
+        _ => Ok(swap(tab, inventory_type, source, destination)),
+    }
+}
+
+fn swap(
+    tab: &mut Inventory,
+    inventory_type: InventoryType,
+    source: i16,
+    destination: i16,
+) -> Vec<InventoryOperation> {
+    if let Some(item) = tab.remove(source) {
+        if let Some(previous_item) = tab.insert(destination, item) {
+            tab.insert(source, previous_item);
+        }
+    }
+
+    vec![InventoryOperation::Move(inventory_type, source, destination)]
+}
+
+/// Takes `quantity` of the item at `source` out of the inventory to drop it on the map.
+/// Untradeable and quest items vanish instead, so no item is returned for them.
+pub fn take_for_drop(
+    inventory: &mut CharacterInventory,
+    inventory_type: InventoryType,
+    source: i16,
+    quantity: i16,
+) -> Result<(Vec<InventoryOperation>, Option<Item>), Box<dyn Error>> {
+    let (operations, item) = take(inventory, inventory_type, source, quantity)?;
+    let vanishes = !is_tradeable(&item);
+
+    Ok((
+        operations,
+        match vanishes {
+            true => None,
+            false => Some(item),
+        },
+    ))
+}
+
+/// Whether the item may change hands, which untradeable and quest items may not.
+pub fn is_tradeable(item: &Item) -> bool {
+    match ItemData::get(item.item_id) {
+        Some(item_data) => !item_data.untradeable && !item_data.quest,
+        None => true,
+    }
+}
+
+/// Takes `quantity` of the item at `source` out of the inventory, or the whole item when it
+/// does not stack.
+pub fn take(
+    inventory: &mut CharacterInventory,
+    inventory_type: InventoryType,
+    source: i16,
+    quantity: i16,
+) -> Result<(Vec<InventoryOperation>, Item), Box<dyn Error>> {
+    let tab = inventory.get_mut(inventory_type);
+    if inventory_type == InventoryType::Equipped || !tab.is_valid_slot(source) {
+        return Err(format!("Cannot take from position {}", source).into());
+    }
+
+    let item = match tab.get(source) {
+        Some(item) => item.clone(),
+        None => return Err(format!("No item at position {}", source).into()),
+    };
+
+    let quantity = match item.is_stackable() {
+        true => quantity,
+        false => item.quantity,
+    };
+    if quantity < 1 || quantity > item.quantity {
+        return Err(format!("Cannot take {} of {} items", quantity, item.quantity).into());
+    }
+
+    let operation = match quantity == item.quantity {
+        true => {
+            tab.remove(source);
+            InventoryOperation::Remove(inventory_type, source)
+        }
+        false => {
+            if let Some(source_item) = tab.get_mut(source) {
+                source_item.quantity -= quantity;
+            }
+            InventoryOperation::Quantity(inventory_type, source, item.quantity - quantity)
+        }
+    };
+
+    Ok((vec![operation], Item { quantity, ..item }))
+}
+
+/// Merges the stacks of a tab and moves its items to the first slots, ordered by item id when
+/// `sort` is set and by position otherwise.
+pub fn rearrange(
+    inventory: &mut CharacterInventory,
+    inventory_type: InventoryType,
+    sort: bool,
+) -> Result<Vec<InventoryOperation>, Box<dyn Error>> {
+    if inventory_type == InventoryType::Equipped {
+        return Err("Cannot rearrange the equipped items".into());
+    }
+
+    let tab = inventory.get_mut(inventory_type);
+    let mut items = tab.drain();
+    let mut operations: Vec<InventoryOperation> = items
+        .iter()
+        .map(|(position, _)| InventoryOperation::Remove(inventory_type, *position))
+        .collect();
+
+    if sort {
+        items.sort_by_key(|(_, item)| item.item_id);
+    }
+
+    let mut arranged: Vec<Item> = Vec::new();
+    for (_, mut item) in items {
+        if item.is_stackable() {
+            let slot_max = slot_max(item.item_id).unwrap_or(item.quantity);
+            if let Some(last) = arranged.last_mut().filter(|last| last.item_id == item.item_id) {
+                let moved = item.quantity.min(slot_max - last.quantity).max(0);
+                last.quantity += moved;
+                item.quantity -= moved;
+            }
+        }
+
+        if item.quantity > 0 {
+            arranged.push(item);
+        }
+    }
+
+    for (position, item) in (1..).zip(arranged) {
+        operations.push(InventoryOperation::Add(inventory_type, position, item.clone()));
+        tab.insert(position, item);
+    }
+
+    Ok(operations)
+}
+
+/// Puts `item` into the character's inventory, topping up existing stacks first.
+/// Nothing is changed when the whole quantity does not fit.
+pub fn add_item(inventory: &mut CharacterInventory, item: Item) -> Result<Vec<InventoryOperation>, Box<dyn Error>> {
+    let inventory_type = match InventoryType::of_item(item.item_id) {
+        Some(inventory_type) => inventory_type,
+        None => return Err(format!("Unknown item {}", item.item_id).into()),
+    };
+    let item_data = match ItemData::get(item.item_id) {
+        Some(item_data) => item_data,
+        None => return Err(format!("Unknown item {}", item.item_id).into()),
+    };
+
+    if item_data.only_one && has_item(inventory, item.item_id) {
+        return Err(format!("Only one {} may be owned", item.item_id).into());
+    }
+
+    let tab = inventory.get_mut(inventory_type);
+    let slot_max = item_data.slot_max.max(1);
+    let stacks: Vec<(i16, i16)> = match item.is_stackable() {
+        true => tab
+            .items()
+            .filter(|(_, stack)| stack.item_id == item.item_id && stack.quantity < slot_max)
+            .map(|(position, stack)| (position, stack.quantity))
+            .collect(),
+        false => Vec::new(),
+    };
+    let free_slots: Vec<i16> = (1..=tab.slot_limit())
+        .filter(|position| tab.get(*position).is_none())
+        .collect();
+
+    let stack_room: i32 = stacks.iter().map(|(_, quantity)| (slot_max - quantity) as i32).sum();
+    let slot_room = match item.is_stackable() {
+        true => free_slots.len() as i32 * slot_max as i32,
+        false => free_slots.len().min(1) as i32 * item.quantity as i32,
+    };
+    if item.quantity < 1 || stack_room + slot_room < item.quantity as i32 {
+        return Err("Inventory is full".into());
+    }
+
+    let mut operations = Vec::new();
+    let mut remaining = item.quantity;
+
+    for (position, quantity) in stacks {
+        let moved = remaining.min(slot_max - quantity);
+        if let Some(stack) = tab.get_mut(position) {
+            stack.quantity += moved;
+        }
+        operations.push(InventoryOperation::Quantity(inventory_type, position, quantity + moved));
+        remaining -= moved;
+        if remaining == 0 {
+            return Ok(operations);
+        }
+    }
+
+    for position in free_slots {
+        let quantity = match item.is_stackable() {
+            true => remaining.min(slot_max),
+            false => remaining,
+        };
+        let new_item = Item {
+            quantity,
+            ..item.clone()
+        };
+        tab.insert(position, new_item.clone());
+        operations.push(InventoryOperation::Add(inventory_type, position, new_item));
+        remaining -= quantity;
+        if remaining == 0 {
+            break;
+        }
+    }
+
+    Ok(operations)
+}
+
+pub fn has_item(inventory: &CharacterInventory, item_id: i32) -> bool {
+    let tabs: &[InventoryType] = match InventoryType::of_item(item_id) {
+        Some(InventoryType::Equip) => &[InventoryType::Equip, InventoryType::Equipped],
+        Some(InventoryType::Use) => &[InventoryType::Use],
+        Some(InventoryType::Setup) => &[InventoryType::Setup],
+        Some(InventoryType::Etc) => &[InventoryType::Etc],
+        Some(InventoryType::Cash) => &[InventoryType::Cash],
+        _ => &[],
+    };
+
+    tabs.iter().any(|inventory_type| {
+        inventory
+            .get(*inventory_type)
+            .items()
+            .any(|(_, item)| item.item_id == item_id)
+    })
+}
+
+/// How many of `item_id` the character owns in its tab.
+pub fn count_item(inventory: &CharacterInventory, item_id: i32) -> i32 {
+    match InventoryType::of_item(item_id) {
+        Some(inventory_type) => inventory
+            .get(inventory_type)
+            .items()
+            .filter(|(_, item)| item.item_id == item_id)
+            .map(|(_, item)| item.quantity as i32)
+            .sum(),
+        None => 0,
+    }
+}
+
+/// Takes `quantity` of `item_id` out of its tab, emptying the lowest slots first. Nothing is
+/// taken unless the whole quantity is owned.
+pub fn remove_item(
+    inventory: &mut CharacterInventory,
+    item_id: i32,
+    quantity: i16,
+) -> Result<Vec<InventoryOperation>, Box<dyn Error>> {
+    let inventory_type = match InventoryType::of_item(item_id) {
+        Some(inventory_type) => inventory_type,
+        None => return Err(format!("Unknown item {}", item_id).into()),
+    };
+
+    let tab = inventory.get_mut(inventory_type);
+    let stacks: Vec<(i16, i16)> = tab
+        .items()
+        .filter(|(_, item)| item.item_id == item_id)
+        .map(|(position, item)| (position, item.quantity))
+        .collect();
+    let owned: i32 = stacks.iter().map(|(_, owned)| *owned as i32).sum();
+    if owned < quantity as i32 {
+        return Err(format!("Only {} of the {} needed {} are owned", owned, quantity, item_id).into());
+    }
+
+    let mut remaining = quantity;
+    let mut operations = Vec::new();
+    for (position, owned) in stacks {
+        if remaining <= 0 {
+            break;
+        }
+
+        let taken = owned.min(remaining);
+        remaining -= taken;
+        match tab.get_mut(position) {
+            Some(item) if taken < owned => {
+                item.quantity -= taken;
+                operations.push(InventoryOperation::Quantity(inventory_type, position, item.quantity));
+            }
+            _ => {
+                tab.remove(position);
+                operations.push(InventoryOperation::Remove(inventory_type, position));
+            }
+        }
+    }
+
+    Ok(operations)
+}
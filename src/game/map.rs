@@ -0,0 +1,603 @@
+use crate::data::map::MapData;
+use crate::data::mob::MobData;
+use crate::data::reactor::ReactorData;
+use crate::game::inventory::Item;
+use crate::game::mob::Mob;
+use crate::game::movement::Movement;
+use crate::game::reactor::Reactor;
+use crate::net::client::PacketSender;
+use crate::net::packet::drop::{self, DropAnimation, RemoveDropAnimation};
+use crate::net::packet::field;
+use crate::net::packet::mob::{self as mob_packet, MobAppearance};
+use crate::net::packet::npc as npc_packet;
+use crate::net::packet::reactor as reactor_packet;
+use log::warn;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long only the owner of a drop, or their party, may pick it up.
+const DROP_OWNERSHIP_DURATION: Duration = Duration::from_secs(15);
+/// How long drops stay on the ground before they vanish.
+const DROP_EXPIRY_DURATION: Duration = Duration::from_secs(180);
+/// How long a spawn point stays empty after its mob died, before its own delay is added.
+const MOB_RESPAWN_DURATION: Duration = Duration::from_secs(10);
+
+pub struct MapPlayer {
+    pub character_id: i32,
+    pub sender: PacketSender,
+    pub spawn_packet: Vec<u8>,
+    pub position: (i16, i16),
+    /// HP and max HP, shown to party members in the map.
+    pub hp: (i16, i16),
+}
+
+pub enum DropContent {
+    Item(Item),
+    Meso(i32),
+}
+
+/// Who may pick a drop up before its ownership window ends.
+#[derive(Clone, Copy)]
+pub enum DropOwnership {
+    Character(i32),
+    Party(i32),
+    FreeForAll,
+}
+
+impl DropOwnership {
+    /// Drops earned by a character belong to their party if they have one.
+    pub fn of_character(character_id: i32, party_id: Option<i32>) -> DropOwnership {
+        match party_id {
+            Some(party_id) => DropOwnership::Party(party_id),
+            None => DropOwnership::Character(character_id),
+        }
+    }
+}
+
+pub struct MapDrop {
+    pub object_id: i32,
+    pub content: DropContent,
+    pub ownership: DropOwnership,
+    pub dropper_id: i32,
+    pub position: (i16, i16),
+    pub dropped_from: (i16, i16),
+    pub dropped_at: Instant,
+}
+
+impl MapDrop {
+    pub fn can_pick_up(&self, character_id: i32, party_id: Option<i32>) -> bool {
+        if self.dropped_at.elapsed() >= DROP_OWNERSHIP_DURATION {
+            return true;
+        }
+
+        match self.ownership {
+            DropOwnership::Character(owner_id) => owner_id == character_id,
+            DropOwnership::Party(owner_party_id) => party_id == Some(owner_party_id),
+            DropOwnership::FreeForAll => true,
+        }
+    }
+}
+
+pub struct MapNpc {
+    pub object_id: i32,
+    pub npc_id: i32,
+    pub position: (i16, i16),
+    pub foothold: i16,
+    pub flipped: bool,
+    pub range: (i16, i16),
+}
+
+/// The state of one of the mob spawns in the map data.
+struct SpawnPoint {
+    mob_object_id: Option<i32>,
+    died_at: Instant,
+}
+
+/// The state of one of the reactor spawns in the map data.
+struct ReactorSpawnPoint {
+    reactor_object_id: Option<i32>,
+    destroyed_at: Instant,
+}
+
+pub struct Map {
+    data: Option<Arc<MapData>>,
+    players: HashMap<i32, MapPlayer>,
+    drops: HashMap<i32, MapDrop>,
+    mobs: HashMap<i32, Mob>,
+    npcs: HashMap<i32, MapNpc>,
+    reactors: HashMap<i32, Reactor>,
+    /// What shows the interaction rooms of the map, the balloons over their owners or the hired
+    /// merchants minding them, by room id.
+    rooms: HashMap<i32, Vec<u8>>,
+    spawn_points: Vec<SpawnPoint>,
+    reactor_spawn_points: Vec<ReactorSpawnPoint>,
+    next_object_id: i32,
+}
+
+impl Map {
+    /// Creates an instance of `map_id` with the mobs, NPCs and reactors of its map data spawned.
+    pub fn new(map_id: i32) -> Map {
+        let data = MapData::get(map_id);
+        let spawn_count = data.as_ref().map_or(0, |data| data.mob_spawns.len());
+        let reactor_spawn_count = data.as_ref().map_or(0, |data| data.reactor_spawns.len());
+
+        let mut map = Map {
+            data,
+            players: HashMap::new(),
+            drops: HashMap::new(),
+            mobs: HashMap::new(),
+            npcs: HashMap::new(),
+            reactors: HashMap::new(),
+            rooms: HashMap::new(),
+            spawn_points: (0..spawn_count)
+                .map(|_| SpawnPoint {
+                    mob_object_id: None,
+                    died_at: Instant::now(),
+                })
+                .collect(),
+            reactor_spawn_points: (0..reactor_spawn_count)
+                .map(|_| ReactorSpawnPoint {
+                    reactor_object_id: None,
+                    destroyed_at: Instant::now(),
+                })
+                .collect(),
+            next_object_id: 1,
+        };
+
+        for spawn_index in 0..spawn_count {
+            map.respawn(spawn_index);
+        }
+
+        for spawn_index in 0..reactor_spawn_count {
+            map.respawn_reactor(spawn_index);
+        }
+
+        if let Some(data) = map.data.clone() {
+            for npc_spawn in &data.npc_spawns {
+                let object_id = map.next_object_id();
+                map.npcs.insert(
+                    object_id,
+                    MapNpc {
+                        object_id,
+                        npc_id: npc_spawn.npc_id,
+                        position: npc_spawn.position,
+                        foothold: npc_spawn.foothold,
+                        flipped: npc_spawn.flipped,
+                        range: npc_spawn.range,
+                    },
+                );
+            }
+        }
+
+        map
+    }
+
+    /// Shows the players, rooms, NPCs, reactors, drops and mobs already in the map to the newcomer
+    /// and the newcomer to them. Mobs nobody controls yet are handed to the newcomer.
+    pub fn add_player(&mut self, player: MapPlayer) {
+        for existing_player in self.players.values() {
+            player.sender.send(existing_player.spawn_packet.clone());
+        }
+
+        for room_packet in self.rooms.values() {
+            player.sender.send(room_packet.clone());
+        }
+
+        for npc in self.npcs.values() {
+            player.sender.send(npc_packet::create_spawn_npc(npc));
+        }
+
+        for reactor in self.reactors.values() {
+            player.sender.send(reactor_packet::create_spawn_reactor(reactor));
+        }
+
+        for map_drop in self.drops.values() {
+            player
+                .sender
+                .send(drop::create_drop(map_drop, DropAnimation::Existing));
+        }
+
+        for mob in self.mobs.values_mut() {
+            player
+                .sender
+                .send(mob_packet::create_spawn_mob(mob, MobAppearance::Existing));
+            if mob.controller_id.is_none() {
+                mob.controller_id = Some(player.character_id);
+                player.sender.send(mob_packet::create_control_mob(mob));
+            }
+        }
+
+        self.broadcast(&player.spawn_packet, None);
+        self.players.insert(player.character_id, player);
+    }
+
+    /// Removes the player, handing the mobs it controlled to another player in the map.
+    pub fn remove_player(&mut self, character_id: i32) -> Option<MapPlayer> {
+        let removed_player = self.players.remove(&character_id);
+
+        if removed_player.is_some() {
+            self.broadcast(&field::create_remove_player(character_id), None);
+
+            let next_controller = self.players.values().next();
+            for mob in self.mobs.values_mut() {
+                if mob.controller_id == Some(character_id) {
+                    mob.controller_id = next_controller.map(|player| player.character_id);
+                    if let Some(player) = next_controller {
+                        player.sender.send(mob_packet::create_control_mob(mob));
+                    }
+                }
+            }
+        }
+
+        removed_player
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn player(&self, character_id: i32) -> Option<&MapPlayer> {
+        self.players.get(&character_id)
+    }
+
+    /// Replaces the packet newcomers see the player with, after their look changed.
+    pub fn update_spawn_packet(&mut self, character_id: i32, spawn_packet: Vec<u8>) {
+        if let Some(player) = self.players.get_mut(&character_id) {
+            player.spawn_packet = spawn_packet;
+        }
+    }
+
+    pub fn update_player_hp(&mut self, character_id: i32, hp: i16, max_hp: i16) {
+        if let Some(player) = self.players.get_mut(&character_id) {
+            player.hp = (hp, max_hp);
+        }
+    }
+
+    /// Shows the room to the map and to the players entering it from now on, replacing how it was
+    /// shown before.
+    pub fn show_room(&mut self, room_id: i32, room_packet: Vec<u8>) {
+        self.broadcast(&room_packet, None);
+        self.rooms.insert(room_id, room_packet);
+    }
+
+    /// Replaces how the room is shown to players entering the map, showing the change to the
+    /// players already there with `update_packet`.
+    pub fn update_room(&mut self, room_id: i32, room_packet: Vec<u8>, update_packet: &[u8]) {
+        match self.rooms.insert(room_id, room_packet) {
+            Some(_) => self.broadcast(update_packet, None),
+            None => {
+                if let Some(room_packet) = self.rooms.get(&room_id) {
+                    self.broadcast(room_packet, None);
+                }
+            }
+        }
+    }
+
+    pub fn hide_room(&mut self, room_id: i32, remove_packet: &[u8]) {
+        if self.rooms.remove(&room_id).is_some() {
+            self.broadcast(remove_packet, None);
+        }
+    }
+
+    fn next_object_id(&mut self) -> i32 {
+        let object_id = self.next_object_id;
+        self.next_object_id = self.next_object_id.wrapping_add(1).max(1);
+        object_id
+    }
+
+    /// Moves the player to the end of `movement` and shows the movement to the others.
+    pub fn move_player(&mut self, character_id: i32, movement: &Movement) {
+        if let Some(player) = self.players.get_mut(&character_id) {
+            player.position = movement.position;
+            self.broadcast(
+                &field::create_move_player(character_id, &movement.data),
+                Some(character_id),
+            );
+        }
+    }
+
+    /// Spawns a `mob_id` at `position`, controlled by one of the players in the map.
+    pub fn spawn_mob(
+        &mut self,
+        mob_id: i32,
+        position: (i16, i16),
+        foothold: i16,
+        flipped: bool,
+        spawn_index: Option<usize>,
+    ) -> Result<i32, Box<dyn Error>> {
+        let data = match MobData::get(mob_id) {
+            Some(data) => data,
+            None => return Err(format!("Unknown mob {}", mob_id).into()),
+        };
+
+        let object_id = self.next_object_id();
+        let mut mob = Mob::new(object_id, data, position, foothold, flipped, spawn_index);
+        self.broadcast(&mob_packet::create_spawn_mob(&mob, MobAppearance::New), None);
+
+        if let Some(controller) = self.players.values().next() {
+            mob.controller_id = Some(controller.character_id);
+            controller.sender.send(mob_packet::create_control_mob(&mob));
+        }
+
+        self.mobs.insert(object_id, mob);
+        Ok(object_id)
+    }
+
+    /// Removes the mob from the map, starting the respawn timer of its spawn point.
+    pub fn kill_mob(&mut self, object_id: i32, animate: bool) -> Option<Mob> {
+        let mob = self.mobs.remove(&object_id)?;
+        self.broadcast(&mob_packet::create_kill_mob(object_id, animate), None);
+
+        if let Some(spawn_point) = mob
+            .spawn_index
+            .and_then(|spawn_index| self.spawn_points.get_mut(spawn_index))
+        {
+            spawn_point.mob_object_id = None;
+            spawn_point.died_at = Instant::now();
+        }
+
+        Some(mob)
+    }
+
+    /// Applies the damage of an attack, killing the mob and returning it once its HP runs out.
+    pub fn damage_mob(&mut self, object_id: i32, character_id: i32, damage: i32) -> Option<Mob> {
+        let killed = self.mobs.get_mut(&object_id)?.damage(character_id, damage);
+        match killed {
+            true => self.kill_mob(object_id, true),
+            false => None,
+        }
+    }
+
+    pub fn mob(&self, object_id: i32) -> Option<&Mob> {
+        self.mobs.get(&object_id)
+    }
+
+    pub fn npc(&self, object_id: i32) -> Option<&MapNpc> {
+        self.npcs.get(&object_id)
+    }
+
+    pub fn mob_ids(&self) -> Vec<i32> {
+        self.mobs.keys().copied().collect()
+    }
+
+    /// Applies a movement reported by the controller of the mob and shows it to the others.
+    pub fn move_mob(
+        &mut self,
+        character_id: i32,
+        object_id: i32,
+        skill: &[u8],
+        start: (i16, i16),
+        movement: &Movement,
+    ) -> Result<&Mob, Box<dyn Error>> {
+        let mob = match self.mobs.get_mut(&object_id) {
+            Some(mob) => mob,
+            None => return Err(format!("Unknown mob object {}", object_id).into()),
+        };
+        if mob.controller_id != Some(character_id) {
+            return Err(format!("Character {} does not control mob {}", character_id, object_id).into());
+        }
+
+        mob.position = movement.position;
+        let packet = mob_packet::create_move_mob(object_id, skill, start, &movement.data);
+        for player in self.players.values() {
+            if player.character_id != character_id {
+                player.sender.send(packet.clone());
+            }
+        }
+
+        Ok(&self.mobs[&object_id])
+    }
+
+    fn respawn(&mut self, spawn_index: usize) {
+        let mob_spawn = match self.data.as_ref().and_then(|data| data.mob_spawns.get(spawn_index)) {
+            Some(mob_spawn) => mob_spawn,
+            None => return,
+        };
+        let (mob_id, position, foothold, flipped) =
+            (mob_spawn.mob_id, mob_spawn.position, mob_spawn.foothold, mob_spawn.flipped);
+
+        match self.spawn_mob(mob_id, position, foothold, flipped, Some(spawn_index)) {
+            Ok(object_id) => self.spawn_points[spawn_index].mob_object_id = Some(object_id),
+            Err(error) => warn!("Unable to spawn mob {} in map [{}]", mob_id, error),
+        }
+    }
+
+    fn respawn_reactor(&mut self, spawn_index: usize) {
+        let reactor_spawn = match self.data.as_ref().and_then(|data| data.reactor_spawns.get(spawn_index)) {
+            Some(reactor_spawn) => reactor_spawn,
+            None => return,
+        };
+        let (reactor_id, position, flipped) = (reactor_spawn.reactor_id, reactor_spawn.position, reactor_spawn.flipped);
+        let data = match ReactorData::get(reactor_id) {
+            Some(data) => data,
+            None => return warn!("Unable to spawn unknown reactor {} in map", reactor_id),
+        };
+
+        let object_id = self.next_object_id();
+        let reactor = Reactor::new(object_id, data, position, flipped, Some(spawn_index));
+        self.broadcast(&reactor_packet::create_spawn_reactor(&reactor), None);
+        self.reactors.insert(object_id, reactor);
+        self.reactor_spawn_points[spawn_index].reactor_object_id = Some(object_id);
+    }
+
+    pub fn reactor(&self, object_id: i32) -> Option<&Reactor> {
+        self.reactors.get(&object_id)
+    }
+
+    /// Moves the reactor on to `state`, returning it once that destroyed it.
+    fn change_reactor_state(&mut self, object_id: i32, state: u8, stance: i16) -> Option<Reactor> {
+        let reactor = self.reactors.get_mut(&object_id)?;
+        reactor.state = state;
+        if reactor.is_destroyed() {
+            return self.destroy_reactor(object_id);
+        }
+
+        let packet = reactor_packet::create_hit_reactor(reactor, stance);
+        self.broadcast(&packet, None);
+        None
+    }
+
+    /// Applies a hit to the reactor, returning it once the hit destroyed it.
+    pub fn hit_reactor(&mut self, object_id: i32, stance: i16) -> Result<Option<Reactor>, Box<dyn Error>> {
+        let reactor = match self.reactors.get(&object_id) {
+            Some(reactor) => reactor,
+            None => return Err(format!("Unknown reactor object {}", object_id).into()),
+        };
+
+        match reactor.data.hit(reactor.state) {
+            Some(state) => Ok(self.change_reactor_state(object_id, state, stance)),
+            None => Ok(None),
+        }
+    }
+
+    /// Feeds the item drop to the reactor waiting for it where it landed, if any, returning the
+    /// reactor once that destroyed it.
+    pub fn drop_on_reactor(&mut self, drop_object_id: i32) -> Option<Reactor> {
+        let (item_id, quantity, position) = match self.drops.get(&drop_object_id) {
+            Some(MapDrop {
+                content: DropContent::Item(item),
+                position,
+                ..
+            }) => (item.item_id, item.quantity, *position),
+            _ => return None,
+        };
+
+        let (object_id, state) = self
+            .reactors
+            .values()
+            .find_map(|reactor| Some((reactor.object_id, reactor.item_dropped(item_id, quantity, position)?)))?;
+
+        self.drops.remove(&drop_object_id);
+        self.broadcast(
+            &drop::create_remove_drop(drop_object_id, RemoveDropAnimation::Expired),
+            None,
+        );
+        self.change_reactor_state(object_id, state, 0)
+    }
+
+    /// Removes the reactor from the map, starting the respawn timer of its spawn point.
+    pub fn destroy_reactor(&mut self, object_id: i32) -> Option<Reactor> {
+        let reactor = self.reactors.remove(&object_id)?;
+        self.broadcast(&reactor_packet::create_destroy_reactor(&reactor), None);
+
+        if let Some(spawn_point) = reactor
+            .spawn_index
+            .and_then(|spawn_index| self.reactor_spawn_points.get_mut(spawn_index))
+        {
+            spawn_point.reactor_object_id = None;
+            spawn_point.destroyed_at = Instant::now();
+        }
+
+        Some(reactor)
+    }
+
+    /// Puts `content` on the ground at `position`, falling from `dropped_from`.
+    pub fn spawn_drop(
+        &mut self,
+        content: DropContent,
+        ownership: DropOwnership,
+        dropper_id: i32,
+        position: (i16, i16),
+        dropped_from: (i16, i16),
+    ) -> i32 {
+        let map_drop = MapDrop {
+            object_id: self.next_object_id(),
+            content,
+            ownership,
+            dropper_id,
+            position,
+            dropped_from,
+            dropped_at: Instant::now(),
+        };
+
+        self.broadcast(&drop::create_drop(&map_drop, DropAnimation::Dropping), None);
+        let object_id = map_drop.object_id;
+        self.drops.insert(object_id, map_drop);
+        object_id
+    }
+
+    pub fn map_drop(&self, object_id: i32) -> Option<&MapDrop> {
+        self.drops.get(&object_id)
+    }
+
+    /// Removes the drop after `character_id` picked it up, showing it fly to them.
+    pub fn pick_up_drop(&mut self, object_id: i32, character_id: i32) -> Option<MapDrop> {
+        let map_drop = self.drops.remove(&object_id)?;
+        self.broadcast(
+            &drop::create_remove_drop(object_id, RemoveDropAnimation::PickedUp(character_id)),
+            None,
+        );
+        Some(map_drop)
+    }
+
+    /// Runs the timed events of the map: respawning mobs and reactors and removing the drops that
+    /// expired.
+    pub fn update(&mut self) {
+        let respawns: Vec<usize> = match &self.data {
+            Some(data) => self
+                .spawn_points
+                .iter()
+                .zip(data.mob_spawns.iter())
+                .enumerate()
+                .filter(|(_, (spawn_point, mob_spawn))| {
+                    spawn_point.mob_object_id.is_none()
+                        && mob_spawn.respawn_delay.is_some_and(|respawn_delay| {
+                            spawn_point.died_at.elapsed() >= MOB_RESPAWN_DURATION + respawn_delay
+                        })
+                })
+                .map(|(spawn_index, _)| spawn_index)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        for spawn_index in respawns {
+            self.respawn(spawn_index);
+        }
+
+        let reactor_respawns: Vec<usize> = match &self.data {
+            Some(data) => self
+                .reactor_spawn_points
+                .iter()
+                .zip(data.reactor_spawns.iter())
+                .enumerate()
+                .filter(|(_, (spawn_point, reactor_spawn))| {
+                    spawn_point.reactor_object_id.is_none()
+                        && reactor_spawn
+                            .respawn_delay
+                            .is_some_and(|respawn_delay| spawn_point.destroyed_at.elapsed() >= respawn_delay)
+                })
+                .map(|(spawn_index, _)| spawn_index)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        for spawn_index in reactor_respawns {
+            self.respawn_reactor(spawn_index);
+        }
+
+        let expired_drops: Vec<i32> = self
+            .drops
+            .values()
+            .filter(|map_drop| map_drop.dropped_at.elapsed() >= DROP_EXPIRY_DURATION)
+            .map(|map_drop| map_drop.object_id)
+            .collect();
+
+        for object_id in expired_drops {
+            self.drops.remove(&object_id);
+            self.broadcast(
+                &drop::create_remove_drop(object_id, RemoveDropAnimation::Expired),
+                None,
+            );
+        }
+    }
+
+    pub fn broadcast(&self, buffer: &[u8], except: Option<i32>) {
+        for player in self.players.values() {
+            if Some(player.character_id) != except {
+                player.sender.send(buffer.to_vec());
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::data::item::ItemData;
+use crate::db::model::character::{Character, CharacterUpdate};
+use crate::db::model::merchant::{Merchant, NewMerchant};
+use crate::db::model::meso_log::MesoReason;
+use crate::game::channel::Channel;
+use crate::game::inventory::{CharacterInventory, Item};
+use crate::game::item;
+use crate::game::meso;
+use crate::game::player_shop::Shop;
+use crate::game::room::{self, RoomContent, RoomExit};
+use crate::net::client::Client;
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::item::create_modify_inventory;
+use std::error::Error;
+use std::time::{Duration, SystemTime};
+
+/// How long a hired merchant stays open before it closes on its own.
+const MERCHANT_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where the hired merchant of a character is, as Fredrick tells it.
+#[derive(Clone, Copy)]
+pub enum MerchantStatus {
+    None = 0,
+    Open = 1,
+    /// Closed, with its items and mesos waiting at Fredrick.
+    Closed = 2,
+}
+
+pub fn status(character_id: i32) -> Result<MerchantStatus, Box<dyn Error>> {
+    Ok(match Merchant::get_by_character(character_id)? {
+        Some(merchant) if merchant.open => MerchantStatus::Open,
+        Some(_) => MerchantStatus::Closed,
+        None => MerchantStatus::None,
+    })
+}
+
+/// Gives the character the items and mesos its closed merchant held, returning whether it had
+/// any waiting and room for them.
+pub fn retrieve(
+    client: &Client,
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+) -> Result<bool, Box<dyn Error>> {
+    let merchant = match Merchant::get_by_character(character.id)? {
+        Some(merchant) if !merchant.open => merchant,
+        _ => return Ok(false),
+    };
+    let meso = match character.meso.checked_add(merchant.meso) {
+        Some(meso) => meso,
+        None => return Ok(false),
+    };
+
+    let mut retrieved_inventory = inventory.clone();
+    let mut operations = Vec::new();
+    for row in Merchant::get_items(merchant.id)? {
+        for item in unbundle(Item::from_merchant_row(&row), row.bundles) {
+            match item::add_item(&mut retrieved_inventory, item) {
+                Ok(mut added) => operations.append(&mut added),
+                Err(_) => return Ok(false),
+            }
+        }
+    }
+    let character_update = CharacterUpdate {
+        character_id: character.id,
+        meso,
+        inventory: Some(retrieved_inventory.rows(character.id)),
+    };
+    if !Merchant::retrieve(merchant.id, &character_update)? {
+        return Ok(false);
+    }
+
+    *inventory = retrieved_inventory;
+    character.meso = meso;
+    client.send(create_modify_inventory(&operations, false));
+    client.send(create_update_stats(character, &[Stat::Meso], false));
+    if merchant.meso > 0 {
+        meso::log(
+            character,
+            merchant.meso,
+            MesoReason::Merchant,
+            &format!("retrieved merchant {} from Fredrick", merchant.id),
+        );
+    }
+    Ok(true)
+}
+
+/// Splits the `bundles` of an item left unsold into stacks that fit a slot each, as their total
+/// quantity may exceed what a single stack holds.
+fn unbundle(item: Item, bundles: i16) -> Vec<Item> {
+    let stack = match item.is_stackable() {
+        true => ItemData::get(item.item_id).map_or(1, |item_data| item_data.slot_max.max(1)),
+        false => item.quantity.max(1),
+    };
+    let mut remaining = item.quantity as i32 * bundles as i32;
+    let mut items = Vec::new();
+    while remaining > 0 {
+        let quantity = remaining.min(stack as i32) as i16;
+        items.push(Item {
+            quantity,
+            ..item.clone()
+        });
+        remaining -= quantity as i32;
+    }
+    items
+}
+
+/// Leaves what the character could not be given, such as the items of a shop that closed while
+/// its inventory was full, with Fredrick so it can be retrieved later. `owner` is what the
+/// character holds with the rest given to it, stored along with the stash.
+pub fn stash(
+    character_id: i32,
+    meso: i32,
+    items: Vec<Item>,
+    owner: Option<&CharacterUpdate>,
+) -> Result<(), Box<dyn Error>> {
+    if meso == 0 && items.is_empty() {
+        return match owner {
+            Some(owner) => owner.store(),
+            None => Ok(()),
+        };
+    }
+    let channel = Channel::get()?;
+    let new_merchant = NewMerchant {
+        character_id,
+        world_id: channel.world_id() as i16,
+        channel_id: channel.channel_id() as i16,
+        map_id: 0,
+        title: String::new(),
+        meso,
+        open: false,
+        opening_date: SystemTime::now(),
+    };
+    // Retrieving multiplies the quantity by the bundles, so each item is a single bundle.
+    let items = items.iter().map(|item| item.to_merchant_row(0, 0, 0, 1)).collect();
+    Merchant::stash(&new_merchant, items, owner)
+}
+
+/// Closes the hired merchants of the channel that stayed open for too long.
+pub fn close_expired() -> Result<(), Box<dyn Error>> {
+    for room_mutex in Channel::get()?.rooms() {
+        let mut room = room::lock_room(&room_mutex)?;
+        let expired = match &room.content {
+            RoomContent::Shop(Shop {
+                merchant: Some(merchant),
+                ..
+            }) => merchant.opened_at.elapsed() >= MERCHANT_DURATION,
+            _ => false,
+        };
+        if expired {
+            room::close(&mut room, RoomExit::Closed, None)?;
+        }
+    }
+    Ok(())
+}
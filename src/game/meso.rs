@@ -0,0 +1,19 @@
+use crate::db::model::character::Character;
+use crate::db::model::meso_log::{MesoLog, MesoReason, NewMesoLog};
+use log::{debug, warn};
+use std::time::SystemTime;
+
+/// Records mesos `character` gained, or lost when `amount` is negative, for economy monitoring.
+pub fn log(character: &Character, amount: i32, reason: MesoReason, description: &str) {
+    debug!("{} meso change of {}: {}", character.name, amount, description);
+
+    if let Err(error) = MesoLog::create(NewMesoLog {
+        character_id: character.id,
+        amount,
+        reason: reason as i16,
+        description: description.to_string(),
+        log_date: SystemTime::now(),
+    }) {
+        warn!("Unable to insert new row to database [{}]", error);
+    }
+}
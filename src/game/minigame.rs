@@ -0,0 +1,419 @@
+use crate::db::model::character::Character;
+use crate::db::model::minigame_record::MinigameRecord;
+use crate::game::inventory::CharacterInventory;
+use crate::game::item;
+use crate::game::room::{self, Room, RoomContent, RoomExit, RoomType};
+use crate::net::client::Client;
+use crate::net::packet::interaction as interaction_packet;
+use log::warn;
+use rand::seq::SliceRandom;
+use std::error::Error;
+use std::ops::RangeInclusive;
+
+/// The games records are kept for, by their id in the records.
+const OMOK_GAME: i16 = 1;
+const MATCH_CARD_GAME: i16 = 2;
+/// Rows and columns of the omok board.
+const OMOK_SIZE: i32 = 15;
+/// Pairs of cards on each size of board a match card room can be opened with.
+const MATCH_CARD_PAIRS: [u8; 3] = [6, 10, 15];
+/// The sets mini-game rooms are opened with, one omok set for each piece.
+const OMOK_SETS: RangeInclusive<i32> = 4080000..=4080011;
+const MATCH_CARD_SET: i32 = 4080100;
+
+/// How a game ended, with the position of the winner.
+#[derive(Clone, Copy)]
+pub enum GameResult {
+    Win(u8),
+    Tie,
+    Forfeit(u8),
+}
+
+/// A game in progress, with the position of the player whose turn it is.
+pub enum Play {
+    Omok {
+        /// The stones on the board, row by row, 0 where there is none and the position of its
+        /// player plus 1 where there is one.
+        board: Vec<u8>,
+        turn: u8,
+    },
+    MatchCard {
+        /// The cards in their shuffled order, each pair sharing a number.
+        cards: Vec<u8>,
+        matched: Vec<bool>,
+        /// The card turned face up first in this turn.
+        first_pick: Option<u8>,
+        turn: u8,
+        pairs: [u8; 2],
+    },
+}
+
+impl Play {
+    fn turn(&self) -> u8 {
+        match self {
+            Play::Omok { turn, .. } | Play::MatchCard { turn, .. } => *turn,
+        }
+    }
+
+    fn pass_turn(&mut self) {
+        match self {
+            Play::Omok { turn, .. } => *turn = 1 - *turn,
+            Play::MatchCard { turn, first_pick, .. } => {
+                *turn = 1 - *turn;
+                *first_pick = None;
+            }
+        }
+    }
+}
+
+/// An omok or match card room between its owner, at position 0, and one visitor.
+pub struct Minigame {
+    /// The omok piece, or the size of the match card board.
+    pub piece: u8,
+    /// The records of the players by position, as they stood when the game last ended.
+    pub records: [MinigameRecord; 2],
+    pub visitor_ready: bool,
+    pub play: Option<Play>,
+    /// Who moves first in the next game, the loser of the last one.
+    first: u8,
+    /// The player that offered a tie, until its opponent answers.
+    tie_offered: Option<u8>,
+    /// Players that asked to leave once the game ends.
+    leaving: [bool; 2],
+}
+
+/// The game `room_type` keeps records of.
+pub fn game_of(room_type: RoomType) -> i16 {
+    match room_type {
+        RoomType::Omok => OMOK_GAME,
+        _ => MATCH_CARD_GAME,
+    }
+}
+
+fn game_mut(room: &mut Room) -> Result<&mut Minigame, Box<dyn Error>> {
+    match &mut room.content {
+        RoomContent::Game(game) => Ok(game),
+        RoomContent::Shop(_) => Err(format!("Room {} is not a mini-game", room.room_id).into()),
+    }
+}
+
+/// Runs `action` on the mini-game room of the character with its position in it.
+fn with_game<T>(
+    character_id: i32,
+    action: impl FnOnce(&mut Room, u8) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    let room_mutex = room::joined_room(character_id)?;
+    let mut room = room::lock_room(&room_mutex)?;
+    match (room.position_of(character_id), &room.content) {
+        (Some(position), RoomContent::Game(_)) => action(&mut room, position),
+        _ => Err(format!("Character {} is not playing a mini-game", character_id).into()),
+    }
+}
+
+/// Opens an omok room with `piece`, or a match card room with the board size `piece`, for which
+/// the character needs the set of the game.
+#[allow(clippy::too_many_arguments)]
+pub fn create(
+    client: &Client,
+    character: &Character,
+    inventory: &CharacterInventory,
+    room_type: RoomType,
+    title: String,
+    password: Option<String>,
+    piece: u8,
+) -> Result<(), Box<dyn Error>> {
+    let has_set = match room_type {
+        RoomType::Omok => {
+            let set_id = *OMOK_SETS.start() + piece as i32;
+            OMOK_SETS.contains(&set_id) && item::has_item(inventory, set_id)
+        }
+        RoomType::MatchCard => (piece as usize) < MATCH_CARD_PAIRS.len() && item::has_item(inventory, MATCH_CARD_SET),
+        _ => return Err(format!("{:?} is not a mini-game", room_type).into()),
+    };
+    if !has_set {
+        return Err(format!("{} has no set for a {:?} room with piece {}", character.name, room_type, piece).into());
+    }
+
+    let mut records = [MinigameRecord::default(); 2];
+    records[0] = MinigameRecord::get(character.id, game_of(room_type))?;
+    let game = Minigame {
+        piece,
+        records,
+        visitor_ready: false,
+        play: None,
+        first: 0,
+        tie_offered: None,
+        leaving: [false; 2],
+    };
+    room::create(client, character, inventory, room_type, title, password, RoomContent::Game(game))
+}
+
+/// Tells the owner the visitor is ready to play, or no longer is.
+pub fn ready(character_id: i32, ready: bool) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        let game = game_mut(room)?;
+        if position != 1 || game.play.is_some() {
+            return Err(format!("Character {} cannot get ready in room {}", character_id, room.room_id).into());
+        }
+        game.visitor_ready = ready;
+        room.broadcast(&interaction_packet::create_game_ready(ready), None);
+        Ok(())
+    })
+}
+
+/// Starts a game once the visitor is ready, with the loser of the last game moving first.
+pub fn start(character_id: i32) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        let is_omok = room.room_type == RoomType::Omok;
+        let game = game_mut(room)?;
+        if position != 0 || !game.visitor_ready || game.play.is_some() {
+            return Err(format!("Character {} cannot start the game", character_id).into());
+        }
+
+        let play = match is_omok {
+            true => Play::Omok {
+                board: vec![0; (OMOK_SIZE * OMOK_SIZE) as usize],
+                turn: game.first,
+            },
+            false => {
+                let pairs = MATCH_CARD_PAIRS[game.piece as usize];
+                let mut cards: Vec<u8> = (0..pairs).flat_map(|card| [card, card]).collect();
+                cards.shuffle(&mut rand::thread_rng());
+                Play::MatchCard {
+                    matched: vec![false; cards.len()],
+                    cards,
+                    first_pick: None,
+                    turn: game.first,
+                    pairs: [0, 0],
+                }
+            }
+        };
+        let start_packet = interaction_packet::create_game_start(game.first, &play);
+        game.play = Some(play);
+        game.tie_offered = None;
+        game.leaving = [false; 2];
+        room.broadcast(&start_packet, None);
+        room.show()
+    })
+}
+
+/// Places a stone of the character at `x`, `y` of the omok board, which wins the game when it
+/// completes a line of five.
+pub fn move_omok(character_id: i32, x: i32, y: i32) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        let result = match &mut game_mut(room)?.play {
+            Some(Play::Omok { board, turn }) if *turn == position => {
+                if !(0..OMOK_SIZE).contains(&x) || !(0..OMOK_SIZE).contains(&y) {
+                    return Err(format!("Character {} placed a stone off the board", character_id).into());
+                }
+                let cell = (y * OMOK_SIZE + x) as usize;
+                if board[cell] != 0 {
+                    return Err(format!("Character {} placed a stone on another one", character_id).into());
+                }
+                board[cell] = position + 1;
+                *turn = 1 - *turn;
+
+                if completes_line(board, x, y) {
+                    Some(GameResult::Win(position))
+                } else if board.iter().all(|cell| *cell != 0) {
+                    Some(GameResult::Tie)
+                } else {
+                    None
+                }
+            }
+            _ => return Err(format!("Character {} cannot place a stone now", character_id).into()),
+        };
+
+        room.broadcast(&interaction_packet::create_omok_move(x, y, position + 1), None);
+        match result {
+            Some(result) => finish(room, result),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Whether the stone at `x`, `y` is part of five or more in a row.
+fn completes_line(board: &[u8], x: i32, y: i32) -> bool {
+    let stone = board[(y * OMOK_SIZE + x) as usize];
+    let stone_at = |x: i32, y: i32| {
+        (0..OMOK_SIZE).contains(&x) && (0..OMOK_SIZE).contains(&y) && board[(y * OMOK_SIZE + x) as usize] == stone
+    };
+
+    [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|(dx, dy)| {
+        let forward = (1..).take_while(|step| stone_at(x + dx * step, y + dy * step)).count();
+        let backward = (1..).take_while(|step| stone_at(x - dx * step, y - dy * step)).count();
+        forward + backward + 1 >= 5
+    })
+}
+
+/// Turns the card at `index` face up. The second card of a turn keeps the turn when it matches
+/// the first, and the game ends once every pair is matched.
+pub fn select_card(character_id: i32, first: bool, index: u8) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        let (packet, result) = match &mut game_mut(room)?.play {
+            Some(Play::MatchCard {
+                cards,
+                matched,
+                first_pick,
+                turn,
+                pairs,
+            }) if *turn == position && first == first_pick.is_none() => {
+                if matched.get(index as usize) != Some(&false) || *first_pick == Some(index) {
+                    return Err(format!("Character {} cannot turn card {}", character_id, index).into());
+                }
+
+                match first_pick.take() {
+                    None => {
+                        *first_pick = Some(index);
+                        let packet = interaction_packet::create_first_card(index);
+                        room.broadcast(&packet, Some(character_id));
+                        return Ok(());
+                    }
+                    Some(first_index) => {
+                        let is_match = cards[index as usize] == cards[first_index as usize];
+                        let packet = interaction_packet::create_second_card(index, first_index, position, is_match);
+                        if !is_match {
+                            *turn = 1 - *turn;
+                            (packet, None)
+                        } else {
+                            matched[index as usize] = true;
+                            matched[first_index as usize] = true;
+                            pairs[position as usize] += 1;
+                            let result = match matched.iter().all(|matched| *matched) {
+                                true if pairs[0] == pairs[1] => Some(GameResult::Tie),
+                                true => Some(GameResult::Win((pairs[1] > pairs[0]) as u8)),
+                                false => None,
+                            };
+                            (packet, result)
+                        }
+                    }
+                }
+            }
+            _ => return Err(format!("Character {} cannot turn a card now", character_id).into()),
+        };
+
+        room.broadcast(&packet, None);
+        match result {
+            Some(result) => finish(room, result),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Passes the turn of the character on to its opponent.
+pub fn skip(character_id: i32) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        match &mut game_mut(room)?.play {
+            Some(play) if play.turn() == position => play.pass_turn(),
+            _ => return Err(format!("Character {} cannot skip a turn now", character_id).into()),
+        }
+        room.broadcast(&interaction_packet::create_game_skip(position), None);
+        Ok(())
+    })
+}
+
+/// Offers the opponent of the character to end the game in a tie.
+pub fn offer_tie(character_id: i32) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        let game = game_mut(room)?;
+        if game.play.is_none() {
+            return Err(format!("Character {} offered a tie outside of a game", character_id).into());
+        }
+        game.tie_offered = Some(position);
+        room.send(1 - position, interaction_packet::create_tie_request());
+        Ok(())
+    })
+}
+
+pub fn answer_tie(character_id: i32, accepted: bool) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        let game = game_mut(room)?;
+        if game.play.is_none() || game.tie_offered != Some(1 - position) {
+            return Err(format!("Character {} answered a tie nobody offered", character_id).into());
+        }
+        game.tie_offered = None;
+        match accepted {
+            true => finish(room, GameResult::Tie),
+            false => {
+                room.send(1 - position, interaction_packet::create_tie_declined());
+                Ok(())
+            }
+        }
+    })
+}
+
+pub fn give_up(character_id: i32) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| match game_mut(room)?.play {
+        Some(_) => finish(room, GameResult::Forfeit(1 - position)),
+        None => Err(format!("Character {} gave up outside of a game", character_id).into()),
+    })
+}
+
+/// Makes the character leave the room once the game ends, or stay after all.
+pub fn leave_after_game(character_id: i32, leaving: bool) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        game_mut(room)?.leaving[position as usize] = leaving;
+        Ok(())
+    })
+}
+
+/// Sends the visitor out of the room of the character, which has to own it.
+pub fn expel(character_id: i32) -> Result<(), Box<dyn Error>> {
+    with_game(character_id, |room, position| {
+        if position != 0 || game_mut(room)?.play.is_some() {
+            return Err(format!("Character {} cannot expel anyone now", character_id).into());
+        }
+        room::remove_visitor(room, 1, Some(RoomExit::Expelled))
+    })
+}
+
+/// Ends the game in progress, if any, as lost by the player at `position`, who is leaving.
+pub fn forfeit(room: &mut Room, position: u8) -> Result<(), Box<dyn Error>> {
+    match game_mut(room)?.play {
+        Some(_) => finish(room, GameResult::Forfeit(1 - position)),
+        None => Ok(()),
+    }
+}
+
+/// Ends the game, adding it to the records of both players, and sends out those that asked to
+/// leave once it ended.
+fn finish(room: &mut Room, result: GameResult) -> Result<(), Box<dyn Error>> {
+    let character_ids = [0, 1].map(|position| room.visitor(position).map(|visitor| visitor.character_id));
+    let game_id = game_of(room.room_type);
+    let game = game_mut(room)?;
+    game.play = None;
+    game.tie_offered = None;
+    game.visitor_ready = false;
+
+    for (position, character_id) in character_ids.iter().enumerate() {
+        let (wins, ties, losses) = match result {
+            GameResult::Tie => (0, 1, 0),
+            GameResult::Win(winner) | GameResult::Forfeit(winner) if winner == position as u8 => (1, 0, 0),
+            _ => (0, 0, 1),
+        };
+        let record = &mut game.records[position];
+        record.wins += wins;
+        record.ties += ties;
+        record.losses += losses;
+        if let Some(character_id) = *character_id {
+            if let Err(error) = MinigameRecord::add(character_id, game_id, wins, ties, losses) {
+                warn!("Unable to store the mini-game record of character {} [{}]", character_id, error);
+            }
+        }
+    }
+    if let GameResult::Win(winner) | GameResult::Forfeit(winner) = result {
+        game.first = 1 - winner;
+    }
+
+    let leaving = std::mem::take(&mut game.leaving);
+    let result_packet = interaction_packet::create_game_result(result, &game.records);
+    room.broadcast(&result_packet, None);
+    if leaving[0] {
+        room::close(room, RoomExit::Closed, None)?;
+        return Ok(());
+    }
+    if leaving[1] {
+        room::remove_visitor(room, 1, Some(RoomExit::Closed))?;
+    }
+    room.show()
+}
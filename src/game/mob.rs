@@ -0,0 +1,69 @@
+use crate::data::mob::MobData;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Stance of a mob facing right; the lowest bit set makes it face left.
+const STANCE_RIGHT: u8 = 4;
+
+pub struct Mob {
+    pub object_id: i32,
+    pub data: Arc<MobData>,
+    pub hp: i32,
+    pub mp: i32,
+    pub position: (i16, i16),
+    pub foothold: i16,
+    pub stance: u8,
+    /// The player whose client moves the mob, if any is in the map.
+    pub controller_id: Option<i32>,
+    /// The spawn point of the map the mob came from, which brings it back after it dies.
+    pub spawn_index: Option<usize>,
+    /// Damage dealt by each character, which decides who is rewarded for the kill.
+    pub damage_by: HashMap<i32, i64>,
+}
+
+impl Mob {
+    pub fn new(
+        object_id: i32,
+        data: Arc<MobData>,
+        position: (i16, i16),
+        foothold: i16,
+        flipped: bool,
+        spawn_index: Option<usize>,
+    ) -> Mob {
+        Mob {
+            object_id,
+            hp: data.max_hp,
+            mp: data.max_mp,
+            data,
+            position,
+            foothold,
+            stance: match flipped {
+                true => STANCE_RIGHT,
+                false => STANCE_RIGHT | 1,
+            },
+            controller_id: None,
+            spawn_index,
+            damage_by: HashMap::new(),
+        }
+    }
+
+    pub fn mob_id(&self) -> i32 {
+        self.data.mob_id
+    }
+
+    /// Applies `damage` from `character_id`, returning whether the mob died.
+    pub fn damage(&mut self, character_id: i32, damage: i32) -> bool {
+        let dealt = damage.clamp(0, self.hp);
+        self.hp -= dealt;
+        *self.damage_by.entry(character_id).or_insert(0) += dealt as i64;
+        self.hp == 0
+    }
+
+    /// The character that dealt the most damage.
+    pub fn top_attacker(&self) -> Option<i32> {
+        self.damage_by
+            .iter()
+            .max_by_key(|(_, damage)| **damage)
+            .map(|(character_id, _)| *character_id)
+    }
+}
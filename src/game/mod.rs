@@ -0,0 +1,30 @@
+pub mod buddy;
+pub mod buff;
+pub mod channel;
+pub mod cheat;
+pub mod character;
+pub mod combat;
+pub mod command;
+pub mod experience;
+pub mod guild;
+pub mod health;
+pub mod infraction;
+pub mod inventory;
+pub mod item;
+pub mod map;
+pub mod merchant;
+pub mod meso;
+pub mod minigame;
+pub mod mob;
+pub mod movement;
+pub mod party;
+pub mod player_shop;
+pub mod quest;
+pub mod reactor;
+pub mod room;
+pub mod shop;
+pub mod skill;
+pub mod storage;
+pub mod stat;
+pub mod trade;
+pub mod world;
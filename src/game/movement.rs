@@ -0,0 +1,53 @@
+use bytes::Buf;
+use std::error::Error;
+
+/// A movement path as the client sends it; relayed as-is to the other players in the map.
+pub struct Movement {
+    pub data: Vec<u8>,
+    pub position: (i16, i16),
+}
+
+/// Reads a movement path starting at `start`, keeping track of where it ends.
+pub fn parse(buffer: &mut &[u8], start: (i16, i16)) -> Result<Movement, Box<dyn Error>> {
+    let data = buffer.to_vec();
+    let mut position = start;
+
+    if !buffer.has_remaining() {
+        return Err("Empty movement".into());
+    }
+
+    for _ in 0..buffer.get_u8() {
+        if !buffer.has_remaining() {
+            return Err("Truncated movement".into());
+        }
+
+        let command = buffer.get_u8();
+        let length = match command {
+            0 | 5 | 17 => 13,
+            1 | 2 | 6 | 12 | 13 | 16 => 7,
+            3 | 4 | 7 | 8 | 9 | 14 => 9,
+            10 => 1,
+            11 => 11,
+            15 => 15,
+            _ => return Err(format!("Unknown movement command {}", command).into()),
+        };
+
+        if buffer.remaining() < length {
+            return Err("Truncated movement".into());
+        }
+
+        let mut fragment = &buffer[..length];
+        position = match command {
+            10 => position,
+            1 | 2 | 6 | 12 | 13 | 16 => {
+                let x = fragment.get_i16_le();
+                let y = fragment.get_i16_le();
+                (position.0.wrapping_add(x), position.1.wrapping_add(y))
+            }
+            _ => (fragment.get_i16_le(), fragment.get_i16_le()),
+        };
+        buffer.advance(length);
+    }
+
+    Ok(Movement { data, position })
+}
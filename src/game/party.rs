@@ -0,0 +1,229 @@
+use crate::db::model::character::Character;
+use crate::game::channel::Channel;
+use crate::net::interserver::{ChannelMessage, PlayerStatus, WorldLink};
+use crate::net::packet::party as party_packet;
+use log::warn;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Most characters a party holds.
+pub const MAX_PARTY_MEMBERS: usize = 6;
+/// Extra EXP shared among a party for each of its members in the map besides the attacker.
+const PARTY_EXP_BONUS: f64 = 0.05;
+
+#[derive(Clone)]
+pub struct PartyMember {
+    pub character_id: i32,
+    pub name: String,
+    pub job: i16,
+    pub level: i16,
+    /// The channel the member plays on; `None` while offline.
+    pub channel_id: Option<u8>,
+    pub map_id: i32,
+}
+
+/// A party as stored by the world server, which channel servers keep a copy of.
+#[derive(Clone)]
+pub struct Party {
+    pub id: i32,
+    pub leader_id: i32,
+    pub members: Vec<PartyMember>,
+}
+
+impl Party {
+    pub fn member(&self, character_id: i32) -> Option<&PartyMember> {
+        self.members.iter().find(|member| member.character_id == character_id)
+    }
+
+    pub fn member_mut(&mut self, character_id: i32) -> Option<&mut PartyMember> {
+        self.members.iter_mut().find(|member| member.character_id == character_id)
+    }
+}
+
+/// What changed in a party, which decides the message its members see.
+pub enum PartyChange {
+    Created,
+    /// The channel, map, job or level of a member changed.
+    Silent,
+    Joined(i32),
+    /// A member left or, when `expelled`, was expelled. It is no longer in the party.
+    Left {
+        character_id: i32,
+        name: String,
+        expelled: bool,
+    },
+    /// The leader left, which breaks the party up.
+    Disbanded,
+    LeaderChanged,
+}
+
+/// Outcomes of party requests the requester is told about.
+pub enum PartyResult {
+    BeginnerCannotCreate,
+    NotInParty,
+    AlreadyJoined,
+    Full,
+    NotFound,
+    /// The named character turned the invitation down.
+    Denied(String),
+}
+
+/// Party requests of players, applied by the world server.
+pub enum PartyRequest {
+    Create,
+    Leave,
+    Accept(i32),
+    Invite(String),
+    Expel(i32),
+    ChangeLeader(i32),
+    /// Turns down the invitation of the named character.
+    Deny(String),
+}
+
+/// Asks the world server to apply a party request of `character_id`.
+pub fn request(character_id: i32, request: PartyRequest) -> Result<(), Box<dyn Error>> {
+    WorldLink::get()?.send(&ChannelMessage::Party(character_id, request))
+}
+
+/// Sends a party chat message of `character_id` to the other members of its party.
+pub fn say(character_id: i32, text: String) -> Result<(), Box<dyn Error>> {
+    WorldLink::get()?.send(&ChannelMessage::PartyChat(character_id, text))
+}
+
+/// Shows the party change to the members playing on this channel, and to a member that just
+/// left it, and keeps the copy of the party up to date.
+pub fn update(party: Party, change: PartyChange) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let packet = party_packet::create_update_party(channel.channel_id(), &party, &change);
+
+    let mut recipients: Vec<i32> = party.members.iter().map(|member| member.character_id).collect();
+    if let PartyChange::Left { character_id, .. } = change {
+        recipients.push(character_id);
+    }
+    for character_id in recipients {
+        if let Some(player) = channel.player(character_id) {
+            player.sender.send(packet.clone());
+        }
+    }
+
+    match change {
+        PartyChange::Disbanded => channel.remove_party(party.id),
+        _ => channel.update_party(party),
+    }
+}
+
+/// Shows the invitation of `inviter_name` to their party to the invited character.
+pub fn invite(character_id: i32, party_id: i32, inviter_name: &str) -> Result<(), Box<dyn Error>> {
+    if let Some(player) = Channel::get()?.player(character_id) {
+        player.sender.send(party_packet::create_party_invite(party_id, inviter_name));
+    }
+    Ok(())
+}
+
+/// Tells the character how its party request went.
+pub fn result(character_id: i32, result: &PartyResult) -> Result<(), Box<dyn Error>> {
+    if let Some(player) = Channel::get()?.player(character_id) {
+        player.sender.send(party_packet::create_party_result(result));
+    }
+    Ok(())
+}
+
+/// Tells the world server where the character is and how strong it is, which updates its party.
+pub fn report(character: &Character) {
+    let result = WorldLink::get().and_then(|link| {
+        link.send(&ChannelMessage::PlayerOnline(PlayerStatus {
+            character_id: character.id,
+            name: character.name.clone(),
+            job: character.job,
+            level: character.level,
+            channel_id: Channel::get()?.channel_id(),
+            map_id: character.map_id,
+        }))
+    });
+
+    if let Err(error) = result {
+        warn!("Unable to report {} to the world server [{}]", character.name, error);
+    }
+}
+
+/// Shows the HP of the character to its party members in the same map, and theirs to it when it
+/// just entered the map.
+pub fn update_hp(character: &Character, entered: bool) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let party = match channel.party_of(character.id) {
+        Some(party) => party,
+        None => return Ok(()),
+    };
+
+    let map = channel.map(character.map_id)?;
+    let mut map_guard = match map.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+    map_guard.update_player_hp(character.id, character.hp, character.max_hp);
+
+    let packet = party_packet::create_update_party_hp(character.id, character.hp, character.max_hp);
+    for member in &party.members {
+        if member.character_id == character.id {
+            continue;
+        }
+
+        if let Some(player) = map_guard.player(member.character_id) {
+            player.sender.send(packet.clone());
+            if entered {
+                if let Some(own) = map_guard.player(character.id) {
+                    own.sender.send(party_packet::create_update_party_hp(
+                        player.character_id,
+                        player.hp.0,
+                        player.hp.1,
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Shares the EXP each attacker earned with the members of its party for whom `in_map` holds,
+/// by their level, adding a bonus for each of them.
+pub fn share_exp(rewards: Vec<(i32, i32, bool)>, in_map: impl Fn(i32) -> bool) -> Vec<(i32, i32, bool)> {
+    let channel = match Channel::get() {
+        Ok(channel) => channel,
+        Err(_) => return rewards,
+    };
+
+    let mut shared: HashMap<i32, (i32, bool)> = HashMap::new();
+    for (character_id, amount, white) in rewards {
+        let members: Vec<PartyMember> = match channel.party_of(character_id) {
+            Some(party) => party
+                .members
+                .into_iter()
+                .filter(|member| member.character_id == character_id || in_map(member.character_id))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if members.len() < 2 {
+            let entry = shared.entry(character_id).or_insert((0, false));
+            entry.0 = entry.0.saturating_add(amount);
+            entry.1 |= white;
+            continue;
+        }
+
+        let bonus = 1.0 + PARTY_EXP_BONUS * (members.len() - 1) as f64;
+        let total = amount as f64 * bonus;
+        let total_level: i64 = members.iter().map(|member| member.level.max(1) as i64).sum();
+        for member in members {
+            let share = (total * member.level.max(1) as f64 / total_level as f64) as i32;
+            let entry = shared.entry(member.character_id).or_insert((0, false));
+            entry.0 = entry.0.saturating_add(share);
+            entry.1 |= white && member.character_id == character_id;
+        }
+    }
+
+    shared
+        .into_iter()
+        .filter(|(_, (amount, _))| *amount > 0)
+        .map(|(character_id, (amount, white))| (character_id, amount, white))
+        .collect()
+}
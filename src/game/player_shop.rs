@@ -0,0 +1,568 @@
+use crate::data::item::ItemData;
+use crate::db::model::character::{Character, CharacterUpdate};
+use crate::db::model::merchant::{Merchant, NewMerchant, NewMerchantItem};
+use crate::db::model::meso_log::MesoReason;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::inventory::{CharacterInventory, InventoryType, Item};
+use crate::game::item::{self, InventoryOperation};
+use crate::game::meso;
+use crate::game::room::{self, Room, RoomContent, RoomExit, RoomType};
+use crate::net::client::Client;
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::interaction as interaction_packet;
+use crate::net::packet::item::create_modify_inventory;
+use crate::net::packet::message::{create_server_message, ServerMessageType};
+use std::error::Error;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+/// How many items a shop can list.
+const MAX_LISTINGS: usize = 16;
+/// The Free Market rooms, the only maps shops and hired merchants can be opened in.
+const FREE_MARKET_ROOMS: RangeInclusive<i32> = 910000001..=910000022;
+/// Item ids of the permits shops are opened with, divided by 10000.
+const PLAYER_SHOP_PERMITS: i32 = 514;
+const MERCHANT_PERMITS: i32 = 503;
+
+/// An item a shop sells in `bundles` of its quantity, for `price` each.
+pub struct ShopListing {
+    pub item: Item,
+    pub bundles: i16,
+    pub price: i32,
+}
+
+impl ShopListing {
+    /// What is left of the listing, as the item its owner gets back.
+    pub fn remaining(&self) -> Option<Item> {
+        match self.bundles > 0 {
+            true => Some(Item {
+                quantity: self.item.quantity.saturating_mul(self.bundles),
+                ..self.item.clone()
+            }),
+            false => None,
+        }
+    }
+}
+
+/// The stored merchant minding a hired merchant room once it opened.
+pub struct HiredMerchant {
+    pub merchant_id: i32,
+    /// The mesos made, which the owner retrieves from Fredrick.
+    pub meso: i32,
+    pub position: (i16, i16),
+    pub opened_at: Instant,
+}
+
+/// The items of a player shop or hired merchant. Items are taken out of the inventory of the
+/// owner as they are listed, and bought items stay listed as sold out.
+pub struct Shop {
+    pub permit_id: i32,
+    pub listings: Vec<ShopListing>,
+    pub merchant: Option<HiredMerchant>,
+}
+
+impl Shop {
+    /// The items left to sell, as stored for the merchant.
+    fn rows(&self, merchant_id: i32) -> Vec<NewMerchantItem> {
+        self.listings
+            .iter()
+            .filter(|listing| listing.bundles > 0)
+            .enumerate()
+            .map(|(position, listing)| {
+                listing
+                    .item
+                    .to_merchant_row(merchant_id, position as i16, listing.price, listing.bundles)
+            })
+            .collect()
+    }
+
+    fn is_sold_out(&self) -> bool {
+        self.listings.iter().all(|listing| listing.bundles == 0)
+    }
+}
+
+fn shop_mut(room: &mut Room) -> Result<&mut Shop, Box<dyn Error>> {
+    match &mut room.content {
+        RoomContent::Shop(shop) => Ok(shop),
+        RoomContent::Game(_) => Err(format!("Room {} is not a shop", room.room_id).into()),
+    }
+}
+
+/// The shop the character owns and stands in.
+fn owned_shop(character_id: i32) -> Result<Arc<Mutex<Room>>, Box<dyn Error>> {
+    let room = room::joined_room(character_id)?;
+    let owned = {
+        let room = room::lock_room(&room)?;
+        room.owner_id == character_id && matches!(room.content, RoomContent::Shop(_))
+    };
+    match owned {
+        true => Ok(room),
+        false => Err(format!("Character {} does not own a shop", character_id).into()),
+    }
+}
+
+/// Stores the merchant of the shop as it is now, along with its owner.
+fn save_merchant(shop: &Shop, owner: &CharacterUpdate) -> Result<(), Box<dyn Error>> {
+    match &shop.merchant {
+        Some(merchant) => {
+            let items = shop.rows(merchant.merchant_id);
+            Merchant::update(merchant.merchant_id, merchant.meso, &items, owner)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Sets up a player shop or hired merchant with the permit at `slot` of the cash inventory.
+pub fn create(
+    client: &Client,
+    character: &Character,
+    inventory: &CharacterInventory,
+    room_type: RoomType,
+    title: String,
+    slot: i16,
+    permit_id: i32,
+) -> Result<(), Box<dyn Error>> {
+    let permits = match room_type {
+        RoomType::PlayerShop => PLAYER_SHOP_PERMITS,
+        RoomType::HiredMerchant => MERCHANT_PERMITS,
+        _ => return Err(format!("{:?} is not a shop", room_type).into()),
+    };
+    match inventory.get(InventoryType::Cash).get(slot) {
+        Some(item) if item.item_id == permit_id && permit_id / 10000 == permits => {}
+        _ => return Err(format!("{} has no permit {} at position {}", character.name, permit_id, slot).into()),
+    }
+    if !FREE_MARKET_ROOMS.contains(&character.map_id) {
+        client.send(create_server_message(
+            ServerMessageType::PinkText,
+            "You can only open a shop in the Free Market rooms.",
+        ));
+        return Ok(());
+    }
+    if room_type == RoomType::HiredMerchant && Merchant::get_by_character(character.id)?.is_some() {
+        client.send(create_server_message(
+            ServerMessageType::PinkText,
+            "Your hired merchant is still open, or its items are waiting for you at Fredrick.",
+        ));
+        return Ok(());
+    }
+
+    let shop = Shop {
+        permit_id,
+        listings: Vec::new(),
+        merchant: None,
+    };
+    room::create(client, character, inventory, room_type, title, None, RoomContent::Shop(shop))
+}
+
+/// Lists `bundles` of `quantity` of the item at `source` for `price` each, taking them out of
+/// the inventory. Player shops are stocked while they are set up, hired merchants also while
+/// their owner is back.
+#[allow(clippy::too_many_arguments)]
+pub fn put_item(
+    client: &Client,
+    character: &Character,
+    inventory: &mut CharacterInventory,
+    inventory_type: InventoryType,
+    source: i16,
+    quantity: i16,
+    bundles: i16,
+    price: i32,
+) -> Result<(), Box<dyn Error>> {
+    let room_mutex = owned_shop(character.id)?;
+    let mut room = room::lock_room(&room_mutex)?;
+    if room.open && room.room_type == RoomType::PlayerShop {
+        return Err(format!("{} cannot stock a shop that is open", character.name).into());
+    }
+    let total = match quantity.checked_mul(bundles) {
+        Some(total) if quantity > 0 && bundles > 0 && price > 0 => total,
+        _ => return Err(format!("{} cannot sell {} x {} for {}", character.name, bundles, quantity, price).into()),
+    };
+    match inventory.get(inventory_type).get(source) {
+        Some(item) if item::is_tradeable(item) => {}
+        Some(item) => return Err(format!("{} tried to sell untradeable item {}", character.name, item.item_id).into()),
+        None => return Err(format!("{} has no item at position {}", character.name, source).into()),
+    }
+
+    let shop = shop_mut(&mut room)?;
+    if shop.listings.len() >= MAX_LISTINGS {
+        return Err(format!("{} cannot list more than {} items", character.name, MAX_LISTINGS).into());
+    }
+    let mut stocked_inventory = inventory.clone();
+    let (operations, mut item) = item::take(&mut stocked_inventory, inventory_type, source, total)?;
+    // Items that do not stack are sold whole, one bundle of them.
+    let bundles = match item.is_stackable() {
+        true => bundles,
+        false => 1,
+    };
+    item.quantity /= bundles;
+    shop.listings.push(ShopListing { item, bundles, price });
+
+    let owner = CharacterUpdate {
+        character_id: character.id,
+        meso: character.meso,
+        inventory: Some(stocked_inventory.rows(character.id)),
+    };
+    if let Err(error) = save_merchant(shop, &owner) {
+        shop.listings.pop();
+        return Err(error);
+    }
+
+    *inventory = stocked_inventory;
+    client.send(create_modify_inventory(&operations, true));
+    let items_packet = interaction_packet::create_shop_items(shop);
+    room.broadcast(&items_packet, None);
+    Ok(())
+}
+
+/// Takes what is left of the item listed at `index` back into the inventory.
+pub fn remove_item(
+    client: &Client,
+    character: &Character,
+    inventory: &mut CharacterInventory,
+    index: i16,
+) -> Result<(), Box<dyn Error>> {
+    let room_mutex = owned_shop(character.id)?;
+    let mut room = room::lock_room(&room_mutex)?;
+    let shop = shop_mut(&mut room)?;
+    if index < 0 || index as usize >= shop.listings.len() {
+        return Err(format!("{} has no item listed at {}", character.name, index).into());
+    }
+
+    let mut restocked_inventory = inventory.clone();
+    let mut operations = Vec::new();
+    if let Some(item) = shop.listings[index as usize].remaining() {
+        operations = match item::add_item(&mut restocked_inventory, item) {
+            Ok(operations) => operations,
+            Err(_) => {
+                client.send(create_server_message(ServerMessageType::PinkText, "Your inventory is full."));
+                return Ok(());
+            }
+        };
+    }
+    let listing = shop.listings.remove(index as usize);
+
+    let owner = CharacterUpdate {
+        character_id: character.id,
+        meso: character.meso,
+        inventory: Some(restocked_inventory.rows(character.id)),
+    };
+    if let Err(error) = save_merchant(shop, &owner) {
+        shop.listings.insert(index as usize, listing);
+        return Err(error);
+    }
+
+    *inventory = restocked_inventory;
+    client.send(create_modify_inventory(&operations, true));
+    let items_packet = interaction_packet::create_shop_items(shop);
+    room.broadcast(&items_packet, None);
+    Ok(())
+}
+
+/// Opens the shop the character set up to visitors. A hired merchant is stored and spawned
+/// where its owner stands, and stays open once the owner leaves.
+pub fn open(character: &Character, inventory: &CharacterInventory) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let room_mutex = owned_shop(character.id)?;
+    let mut room = room::lock_room(&room_mutex)?;
+    if room.open {
+        return Err(format!("{} opened a shop that is already open", character.name).into());
+    }
+
+    if room.room_type == RoomType::HiredMerchant {
+        let position = match channel.map(character.map_id)?.lock() {
+            Ok(map_guard) => map_guard.player(character.id).map_or((0, 0), |player| player.position),
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+        let new_merchant = NewMerchant {
+            character_id: character.id,
+            world_id: channel.world_id() as i16,
+            channel_id: channel.channel_id() as i16,
+            map_id: room.map_id,
+            title: room.title.clone(),
+            meso: 0,
+            open: true,
+            opening_date: SystemTime::now(),
+        };
+        let owner = CharacterUpdate {
+            character_id: character.id,
+            meso: character.meso,
+            inventory: Some(inventory.rows(character.id)),
+        };
+
+        let shop = shop_mut(&mut room)?;
+        let merchant = Merchant::open(&new_merchant, shop.rows(0), &owner)?;
+        shop.merchant = Some(HiredMerchant {
+            merchant_id: merchant.id,
+            meso: 0,
+            position,
+            opened_at: Instant::now(),
+        });
+    }
+
+    room.open = true;
+    room.show()
+}
+
+/// What a buyer pays and ends up with, worked out before anything changes hands.
+struct Sale {
+    item_id: i32,
+    cost: i32,
+    inventory: CharacterInventory,
+    operations: Vec<InventoryOperation>,
+}
+
+/// Works out the sale of `bundles` of the item listed at `index` to the character, telling it
+/// when it cannot afford or hold them.
+fn prepare_sale(
+    client: &Client,
+    character: &Character,
+    inventory: &CharacterInventory,
+    shop: &Shop,
+    index: u8,
+    bundles: i16,
+) -> Result<Option<Sale>, Box<dyn Error>> {
+    let listing = match shop.listings.get(index as usize) {
+        Some(listing) if bundles > 0 && bundles <= listing.bundles => listing,
+        _ => return Err(format!("{} cannot buy {} bundles of listing {}", character.name, bundles, index).into()),
+    };
+    let (cost, quantity) = match (
+        listing.price.checked_mul(bundles as i32),
+        listing.item.quantity.checked_mul(bundles),
+    ) {
+        (Some(cost), Some(quantity)) => (cost, quantity),
+        _ => return Err(format!("{} cannot buy {} bundles of listing {}", character.name, bundles, index).into()),
+    };
+    if character.meso < cost {
+        client.send(create_server_message(ServerMessageType::PinkText, "You do not have enough mesos."));
+        return Ok(None);
+    }
+
+    let item_id = listing.item.item_id;
+    let only_one = ItemData::get(item_id).is_some_and(|item_data| item_data.only_one);
+    if only_one && item::has_item(inventory, item_id) {
+        client.send(create_server_message(
+            ServerMessageType::PinkText,
+            "You cannot hold more than one of this item.",
+        ));
+        return Ok(None);
+    }
+    let mut inventory = inventory.clone();
+    let item = Item {
+        quantity,
+        ..listing.item.clone()
+    };
+    match item::add_item(&mut inventory, item) {
+        Ok(operations) => Ok(Some(Sale {
+            item_id,
+            cost,
+            inventory,
+            operations,
+        })),
+        Err(_) => {
+            client.send(create_server_message(ServerMessageType::PinkText, "Your inventory is full."));
+            Ok(None)
+        }
+    }
+}
+
+/// Hands the bought items to the buyer and shows the sale in the shop.
+fn complete_sale(
+    room: &mut Room,
+    index: u8,
+    bundles: i16,
+    client: &Client,
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    sale: Sale,
+) -> Result<(), Box<dyn Error>> {
+    *inventory = sale.inventory;
+    character.meso -= sale.cost;
+    client.send(create_modify_inventory(&sale.operations, true));
+    client.send(create_update_stats(character, &[Stat::Meso], true));
+    let reason = match room.room_type {
+        RoomType::HiredMerchant => MesoReason::Merchant,
+        _ => MesoReason::PlayerShop,
+    };
+    meso::log(
+        character,
+        -sale.cost,
+        reason,
+        &format!("bought item {} from the shop of {}", sale.item_id, room.owner_name),
+    );
+
+    room.send(0, interaction_packet::create_shop_sold(index, bundles, &character.name));
+    let items_packet = interaction_packet::create_shop_items(shop_mut(room)?);
+    room.broadcast(&items_packet, None);
+    Ok(())
+}
+
+/// Buys `bundles` of the item listed at `index` in the shop the character visits. Nothing may be
+/// locked by the caller, as buying from a player shop locks its owner along with the buyer.
+pub fn buy(character_id: i32, index: u8, bundles: i16) -> Result<(), Box<dyn Error>> {
+    let room_mutex = room::joined_room(character_id)?;
+    let (owner_id, room_type) = {
+        let room = room::lock_room(&room_mutex)?;
+        (room.owner_id, room.room_type)
+    };
+    if owner_id == character_id {
+        return Err(format!("Character {} tried to buy from its own shop", character_id).into());
+    }
+
+    match room_type {
+        RoomType::PlayerShop => buy_from_player(&room_mutex, character_id, owner_id, index, bundles),
+        RoomType::HiredMerchant => buy_from_merchant(&room_mutex, character_id, index, bundles),
+        _ => Err(format!("Character {} tried to buy in a {:?} room", character_id, room_type).into()),
+    }
+}
+
+/// Sells to the buyer in a player shop, paying its owner right away. Both stay in memory as they
+/// are after the sale and are stored when they log out.
+fn buy_from_player(
+    room_mutex: &Mutex<Room>,
+    buyer_id: i32,
+    owner_id: i32,
+    index: u8,
+    bundles: i16,
+) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let mut character_ids = [buyer_id, owner_id];
+    // Both are locked in the order of their ids, the only order two clients are ever locked in.
+    character_ids.sort_unstable();
+    let players: Vec<_> = character_ids.iter().filter_map(|id| channel.player(*id)).collect();
+    if players.len() != 2 {
+        return Err(format!("The shop of character {} lost its owner", owner_id).into());
+    }
+
+    let first_client = character::lock_client(&players[0].client)?;
+    let second_client = character::lock_client(&players[1].client)?;
+    let mut first_character = character::lock_character(&first_client)?;
+    let mut second_character = character::lock_character(&second_client)?;
+    let ((buyer_client, buyer), (owner_client, owner)) = match first_character.id == buyer_id {
+        true => (
+            (&*first_client, &mut *first_character),
+            (&*second_client, &mut *second_character),
+        ),
+        false => (
+            (&*second_client, &mut *second_character),
+            (&*first_client, &mut *first_character),
+        ),
+    };
+    let mut inventory = character::lock_inventory(buyer_client)?;
+
+    let mut room = room::lock_room(room_mutex)?;
+    if room.owner_id != owner_id || room.position_of(buyer_id).is_none() {
+        // The shop closed in the meantime.
+        return Ok(());
+    }
+    let sale = match prepare_sale(buyer_client, buyer, &inventory, shop_mut(&mut room)?, index, bundles)? {
+        Some(sale) => sale,
+        None => return Ok(()),
+    };
+    let owner_meso = match owner.meso.checked_add(sale.cost) {
+        Some(meso) => meso,
+        None => {
+            buyer_client.send(create_server_message(
+                ServerMessageType::PinkText,
+                "The owner of the shop cannot hold any more mesos.",
+            ));
+            return Ok(());
+        }
+    };
+
+    shop_mut(&mut room)?.listings[index as usize].bundles -= bundles;
+    owner.meso = owner_meso;
+    owner_client.send(create_update_stats(owner, &[Stat::Meso], false));
+    meso::log(
+        owner,
+        sale.cost,
+        MesoReason::PlayerShop,
+        &format!("sold item {} to {}", sale.item_id, buyer.name),
+    );
+    complete_sale(&mut room, index, bundles, buyer_client, buyer, &mut inventory, sale)
+}
+
+/// Sells to the buyer in a hired merchant, storing the merchant and the buyer in one
+/// transaction before the sale shows. The merchant closes once it sold everything.
+fn buy_from_merchant(room_mutex: &Mutex<Room>, buyer_id: i32, index: u8, bundles: i16) -> Result<(), Box<dyn Error>> {
+    let player = match Channel::get()?.player(buyer_id) {
+        Some(player) => player,
+        None => return Err(format!("Character {} is not in the channel", buyer_id).into()),
+    };
+
+    character::with_character(&player.client, |client, character| {
+        let mut inventory = character::lock_inventory(client)?;
+        let mut room = room::lock_room(room_mutex)?;
+        if room.position_of(buyer_id).is_none() {
+            return Ok(());
+        }
+
+        let shop = shop_mut(&mut room)?;
+        let merchant_meso = match &shop.merchant {
+            Some(merchant) => merchant.meso,
+            None => return Err(format!("{} tried to buy from a merchant that is not open", character.name).into()),
+        };
+        let sale = match prepare_sale(client, character, &inventory, shop, index, bundles)? {
+            Some(sale) => sale,
+            None => return Ok(()),
+        };
+        let merchant_meso = match merchant_meso.checked_add(sale.cost) {
+            Some(meso) => meso,
+            None => {
+                client.send(create_server_message(
+                    ServerMessageType::PinkText,
+                    "The merchant cannot hold any more mesos.",
+                ));
+                return Ok(());
+            }
+        };
+
+        shop.listings[index as usize].bundles -= bundles;
+        if let Some(merchant) = &mut shop.merchant {
+            merchant.meso = merchant_meso;
+        }
+        let buyer = CharacterUpdate {
+            character_id: character.id,
+            meso: character.meso - sale.cost,
+            inventory: Some(sale.inventory.rows(character.id)),
+        };
+        if let Err(error) = save_merchant(shop, &buyer) {
+            shop.listings[index as usize].bundles += bundles;
+            if let Some(merchant) = &mut shop.merchant {
+                merchant.meso -= sale.cost;
+            }
+            return Err(error);
+        }
+
+        let sold_out = shop.is_sold_out();
+        complete_sale(&mut room, index, bundles, client, character, &mut inventory, sale)?;
+        if sold_out {
+            room::close(&mut room, RoomExit::Closed, None)?;
+        }
+        Ok(())
+    })
+}
+
+/// Closes the hired merchant of the character, which leaves what it holds to Fredrick. Closing it
+/// before it opened gives its items back right away.
+pub fn close_merchant(character_id: i32) -> Result<(), Box<dyn Error>> {
+    let room_mutex = owned_shop(character_id)?;
+    let mut room = room::lock_room(&room_mutex)?;
+    if room.room_type != RoomType::HiredMerchant {
+        return Err(format!("Character {} has no hired merchant to close", character_id).into());
+    }
+
+    let opened = room.open;
+    let items = room::close(&mut room, RoomExit::Closed, Some(character_id))?;
+    drop(room);
+    if opened {
+        if let Some(player) = Channel::get()?.player(character_id) {
+            player.sender.send(create_server_message(
+                ServerMessageType::PinkText,
+                "Your merchant has closed. Retrieve your items and mesos from Fredrick.",
+            ));
+        }
+    }
+    room::give_back(character_id, items)
+}
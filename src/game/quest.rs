@@ -0,0 +1,376 @@
+use crate::data::item::ItemData;
+use crate::data::quest::{QuestData, QuestRequirements, QuestRewardItem, QuestRewards};
+use crate::db::model::character::Character;
+use crate::db::model::meso_log::MesoReason;
+use crate::db::model::quest_status::{NewQuestStatus, QuestStatus};
+use crate::game::channel::Channel;
+use crate::game::experience;
+use crate::game::inventory::{CharacterInventory, InventoryType};
+use crate::game::item::{self, InventoryOperation};
+use crate::game::meso;
+use crate::net::client::Client;
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::field::{create_show_foreign_effect, create_show_own_effect, Effect};
+use crate::net::packet::item::create_modify_inventory;
+use crate::net::packet::quest::{create_quest_result, create_update_quest};
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::time::SystemTime;
+
+/// Digits the quest record spends on the kill count of each mob.
+const KILL_DIGITS: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum QuestState {
+    NotStarted = 0,
+    Started = 1,
+    Completed = 2,
+}
+
+impl QuestState {
+    pub fn from_i16(value: i16) -> Option<QuestState> {
+        match value {
+            0 => Some(QuestState::NotStarted),
+            1 => Some(QuestState::Started),
+            2 => Some(QuestState::Completed),
+            _ => None,
+        }
+    }
+}
+
+pub struct QuestEntry {
+    pub state: QuestState,
+    /// The quest record the client shows, holding the kill count of each mob the quest needs.
+    pub progress: String,
+    pub completed_at: Option<SystemTime>,
+}
+
+impl QuestEntry {
+    fn kills(&self, index: usize) -> i32 {
+        self.progress
+            .get(index * KILL_DIGITS..(index + 1) * KILL_DIGITS)
+            .and_then(|kills| kills.parse::<i32>().ok())
+            .unwrap_or(0)
+    }
+
+    fn set_kills(&mut self, index: usize, kills: i32) {
+        let length = (index + 1) * KILL_DIGITS;
+        if self.progress.len() < length {
+            let missing = length - self.progress.len();
+            self.progress.push_str(&"0".repeat(missing));
+        }
+        self.progress.replace_range(
+            index * KILL_DIGITS..length,
+            &format!("{:03}", kills.clamp(0, 999)),
+        );
+    }
+}
+
+/// Quests a character started or completed; quests it never started or forfeited are absent.
+pub struct CharacterQuests {
+    quests: BTreeMap<i32, QuestEntry>,
+}
+
+impl CharacterQuests {
+    pub fn load(character_id: i32) -> Result<CharacterQuests, Box<dyn Error>> {
+        let quests = QuestStatus::get_by_character(character_id)?
+            .into_iter()
+            .filter_map(|row| {
+                Some((
+                    row.quest_id,
+                    QuestEntry {
+                        state: QuestState::from_i16(row.status)?,
+                        progress: row.progress,
+                        completed_at: row.completed_at,
+                    },
+                ))
+            })
+            .collect();
+
+        Ok(CharacterQuests { quests })
+    }
+
+    /// The rows the quests are stored as.
+    pub fn rows(&self, character_id: i32) -> Vec<NewQuestStatus> {
+        self.quests
+            .iter()
+            .map(|(quest_id, entry)| NewQuestStatus {
+                character_id,
+                quest_id: *quest_id,
+                status: entry.state as i16,
+                progress: entry.progress.clone(),
+                completed_at: entry.completed_at,
+            })
+            .collect()
+    }
+
+    pub fn state(&self, quest_id: i32) -> QuestState {
+        self.quests
+            .get(&quest_id)
+            .map_or(QuestState::NotStarted, |entry| entry.state)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &QuestEntry)> {
+        self.quests.iter().map(|(quest_id, entry)| (*quest_id, entry))
+    }
+}
+
+/// Checks `requirements` against the character, returning why they are not met.
+fn check_requirements(
+    character: &Character,
+    inventory: &CharacterInventory,
+    quests: &CharacterQuests,
+    requirements: &QuestRequirements,
+    entry: Option<&QuestEntry>,
+) -> Result<(), String> {
+    if requirements.min_level.is_some_and(|level| character.level < level) {
+        return Err(format!("level {} is too low", character.level));
+    }
+    if requirements.max_level.is_some_and(|level| character.level > level) {
+        return Err(format!("level {} is too high", character.level));
+    }
+    if !requirements.jobs.is_empty() && !requirements.jobs.contains(&character.job) {
+        return Err(format!("job {} may not take it", character.job));
+    }
+
+    for (item_id, count) in &requirements.items {
+        let owned = item::count_item(inventory, *item_id);
+        // A count of zero asks for the item not to be owned at all.
+        if (*count > 0 && owned < *count) || (*count <= 0 && owned > 0) {
+            return Err(format!("owns {} of item {} rather than {}", owned, item_id, count));
+        }
+    }
+
+    for (quest_id, state) in &requirements.quests {
+        if quests.state(*quest_id) as u8 != *state {
+            return Err(format!("quest {} is not in state {}", quest_id, state));
+        }
+    }
+
+    for (index, (mob_id, count)) in requirements.mobs.iter().enumerate() {
+        let kills = entry.map_or(0, |entry| entry.kills(index));
+        if kills < *count {
+            return Err(format!("killed {} of the {} mobs {}", kills, count, mob_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// The items of `rewards` meant for the character: every unweighted item of its gender, and one
+/// of the weighted ones picked at random.
+fn reward_items<'a>(character: &Character, rewards: &'a QuestRewards) -> Vec<&'a QuestRewardItem> {
+    let items: Vec<&QuestRewardItem> = rewards
+        .items
+        .iter()
+        .filter(|item| item.gender == 2 || item.gender == character.gender)
+        .collect();
+
+    let total_prop: i32 = items.iter().map(|item| item.prop.max(0)).sum();
+    let mut roll = match total_prop > 0 {
+        true => rand::thread_rng().gen_range(0..total_prop),
+        false => 0,
+    };
+    let picked = items.iter().position(|item| {
+        roll -= item.prop.max(0);
+        item.prop > 0 && roll < 0
+    });
+
+    items
+        .iter()
+        .enumerate()
+        .filter(|(index, item)| item.prop <= 0 || Some(*index) == picked)
+        .map(|(_, item)| *item)
+        .collect()
+}
+
+/// Gives the EXP, mesos, fame and items of `rewards`, and takes the items it asks back. Nothing
+/// is given unless the inventory has room for every item and holds every item to take.
+fn give_rewards(
+    client: &Client,
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    rewards: &QuestRewards,
+    quest_id: i32,
+) -> Result<(), Box<dyn Error>> {
+    let items = reward_items(character, rewards);
+
+    let mut needed_slots: Vec<InventoryType> = Vec::new();
+    for reward in &items {
+        match reward.count > 0 {
+            true => match InventoryType::of_item(reward.item_id) {
+                Some(inventory_type) => needed_slots.push(inventory_type),
+                None => return Err(format!("Unknown item {}", reward.item_id).into()),
+            },
+            false => {
+                if item::count_item(inventory, reward.item_id) < -reward.count {
+                    return Err(format!("{} does not own {} of item {}", character.name, -reward.count, reward.item_id).into());
+                }
+            }
+        }
+    }
+    for inventory_type in &needed_slots {
+        let slots = needed_slots.iter().filter(|needed| *needed == inventory_type).count();
+        if inventory.get(*inventory_type).free_slots() < slots {
+            return Err(format!("{} has no room for the rewards of quest {}", character.name, quest_id).into());
+        }
+    }
+    let meso = match character.meso.checked_add(rewards.meso) {
+        Some(meso) if meso >= 0 => meso,
+        _ => return Err(format!("{} cannot hold {} more mesos", character.name, rewards.meso).into()),
+    };
+
+    let mut operations: Vec<InventoryOperation> = Vec::new();
+    for reward in items {
+        match reward.count > 0 {
+            true => {
+                let item_data = match ItemData::get(reward.item_id) {
+                    Some(item_data) => item_data,
+                    None => return Err(format!("Unknown item {}", reward.item_id).into()),
+                };
+                let quantity = reward.count.min(item_data.slot_max.max(1) as i32) as i16;
+                operations.extend(item::add_item(inventory, item_data.create_item(quantity))?);
+            }
+            false => operations.extend(item::remove_item(inventory, reward.item_id, -reward.count as i16)?),
+        }
+    }
+    if !operations.is_empty() {
+        client.send(create_modify_inventory(&operations, false));
+    }
+
+    let mut stats = Vec::new();
+    if rewards.meso != 0 {
+        character.meso = meso;
+        meso::log(character, rewards.meso, MesoReason::Quest, &format!("quest {}", quest_id));
+        stats.push(Stat::Meso);
+    }
+    if rewards.fame != 0 {
+        character.fame = character.fame.saturating_add(rewards.fame);
+        stats.push(Stat::Fame);
+    }
+    if !stats.is_empty() {
+        client.send(create_update_stats(character, &stats, false));
+    }
+
+    experience::gain_exp(client, character, rewards.exp, true)
+}
+
+/// Starts the quest for the character talking to `npc_id`, giving its start rewards.
+pub fn start(
+    client: &Client,
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    quests: &mut CharacterQuests,
+    quest_id: i32,
+    npc_id: i32,
+) -> Result<(), Box<dyn Error>> {
+    let data = match QuestData::get(quest_id) {
+        Some(data) => data,
+        None => return Err(format!("Unknown quest {}", quest_id).into()),
+    };
+    if quests.state(quest_id) != QuestState::NotStarted {
+        return Err(format!("{} already took quest {}", character.name, quest_id).into());
+    }
+    if data.start.npc_id.is_some_and(|required| required != npc_id) {
+        return Err(format!("Quest {} is not started at NPC {}", quest_id, npc_id).into());
+    }
+    if let Err(reason) = check_requirements(character, inventory, quests, &data.start, None) {
+        return Err(format!("{} cannot start quest {}: {}", character.name, quest_id, reason).into());
+    }
+
+    give_rewards(client, character, inventory, &data.start_rewards, quest_id)?;
+
+    let entry = QuestEntry {
+        state: QuestState::Started,
+        progress: "0".repeat(data.complete.mobs.len() * KILL_DIGITS),
+        completed_at: None,
+    };
+    client.send(create_update_quest(quest_id, Some(&entry)));
+    client.send(create_quest_result(quest_id, npc_id, None));
+    quests.quests.insert(quest_id, entry);
+    Ok(())
+}
+
+/// Hands the quest in to `npc_id`, giving its completion rewards.
+pub fn complete(
+    client: &Client,
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    quests: &mut CharacterQuests,
+    quest_id: i32,
+    npc_id: i32,
+) -> Result<(), Box<dyn Error>> {
+    let data = match QuestData::get(quest_id) {
+        Some(data) => data,
+        None => return Err(format!("Unknown quest {}", quest_id).into()),
+    };
+    let entry = match quests.quests.get(&quest_id) {
+        Some(entry) if entry.state == QuestState::Started => entry,
+        _ => return Err(format!("{} has not started quest {}", character.name, quest_id).into()),
+    };
+    if data.complete.npc_id.is_some_and(|required| required != npc_id) {
+        return Err(format!("Quest {} is not completed at NPC {}", quest_id, npc_id).into());
+    }
+    if let Err(reason) = check_requirements(character, inventory, quests, &data.complete, Some(entry)) {
+        return Err(format!("{} cannot complete quest {}: {}", character.name, quest_id, reason).into());
+    }
+
+    give_rewards(client, character, inventory, &data.complete_rewards, quest_id)?;
+
+    let entry = QuestEntry {
+        state: QuestState::Completed,
+        progress: String::new(),
+        completed_at: Some(SystemTime::now()),
+    };
+    client.send(create_update_quest(quest_id, Some(&entry)));
+    client.send(create_quest_result(quest_id, npc_id, data.complete_rewards.next_quest));
+    quests.quests.insert(quest_id, entry);
+
+    client.send(create_show_own_effect(Effect::QuestComplete));
+    let map = Channel::get()?.map(character.map_id)?;
+    match map.lock() {
+        Ok(map_guard) => map_guard.broadcast(
+            &create_show_foreign_effect(character.id, Effect::QuestComplete),
+            Some(character.id),
+        ),
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+    Ok(())
+}
+
+pub fn forfeit(client: &Client, quests: &mut CharacterQuests, quest_id: i32) -> Result<(), Box<dyn Error>> {
+    match quests.quests.get(&quest_id) {
+        Some(entry) if entry.state == QuestState::Started => {
+            quests.quests.remove(&quest_id);
+            client.send(create_update_quest(quest_id, None));
+            Ok(())
+        }
+        _ => Err(format!("Quest {} is not in progress", quest_id).into()),
+    }
+}
+
+/// Counts a kill of `mob_id` towards every started quest that still needs it.
+pub fn record_kill(client: &Client, quests: &mut CharacterQuests, mob_id: i32) {
+    for (quest_id, entry) in quests.quests.iter_mut() {
+        if entry.state != QuestState::Started {
+            continue;
+        }
+        let data = match QuestData::get(*quest_id) {
+            Some(data) => data,
+            None => continue,
+        };
+
+        let mut updated = false;
+        for (index, (required_mob_id, count)) in data.complete.mobs.iter().enumerate() {
+            let kills = entry.kills(index);
+            if *required_mob_id == mob_id && kills < *count {
+                entry.set_kills(index, kills + 1);
+                updated = true;
+            }
+        }
+        if updated {
+            client.send(create_update_quest(*quest_id, Some(entry)));
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::data::item::ItemData;
+use crate::data::reactor::{ReactorData, ReactorEventKind};
+use crate::db::model::reactor_drop::ReactorDrop;
+use crate::game::channel::Channel;
+use crate::game::inventory::Item;
+use crate::game::map::{DropContent, DropOwnership};
+use crate::net::client::Client;
+use crate::script;
+use rand::Rng;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// Horizontal distance between the items a destroyed reactor drops.
+const DROP_SPACING: i16 = 25;
+
+pub struct Reactor {
+    pub object_id: i32,
+    pub data: Arc<ReactorData>,
+    pub state: u8,
+    pub position: (i16, i16),
+    pub flipped: bool,
+    /// The reactor spawn of the map the reactor came from, which brings it back after it is
+    /// destroyed.
+    pub spawn_index: Option<usize>,
+}
+
+impl Reactor {
+    pub fn new(
+        object_id: i32,
+        data: Arc<ReactorData>,
+        position: (i16, i16),
+        flipped: bool,
+        spawn_index: Option<usize>,
+    ) -> Reactor {
+        Reactor {
+            object_id,
+            data,
+            state: 0,
+            position,
+            flipped,
+            spawn_index,
+        }
+    }
+
+    pub fn reactor_id(&self) -> i32 {
+        self.data.reactor_id
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.data.is_final(self.state)
+    }
+
+    /// The state the reactor moves on to when `quantity` of `item_id` lands at `position`, if
+    /// its current state waits for that item there.
+    pub fn item_dropped(&self, item_id: i32, quantity: i16, position: (i16, i16)) -> Option<u8> {
+        let event = self.data.item_event(self.state)?;
+        let ((left, top), (right, bottom)) = match event.kind {
+            ReactorEventKind::Item {
+                item_id: event_item_id,
+                quantity: event_quantity,
+                area,
+            } if event_item_id == item_id && quantity >= event_quantity => area,
+            _ => return None,
+        };
+
+        let (x, y) = (position.0 - self.position.0, position.1 - self.position.1);
+        match (left..=right).contains(&x) && (top..=bottom).contains(&y) {
+            true => Some(event.next_state),
+            false => None,
+        }
+    }
+}
+
+/// Rewards the character that destroyed the reactor with its drops and runs its script. No lock
+/// on the client may be held, since the script locks it.
+pub fn destroyed(
+    client: &Arc<Mutex<Client>>,
+    character_id: i32,
+    map_id: i32,
+    reactor: &Reactor,
+) -> Result<(), Box<dyn Error>> {
+    spawn_drops(character_id, map_id, reactor)?;
+    script::field::run_reactor(client, reactor)
+}
+
+/// Drops the items the reactor rolled, spread out around it and owned by `character_id`.
+fn spawn_drops(character_id: i32, map_id: i32, reactor: &Reactor) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let drop_rate = channel.rates().drop as f64;
+    let mut rng = rand::thread_rng();
+
+    let items: Vec<Item> = ReactorDrop::get_by_reactor(reactor.reactor_id())?
+        .iter()
+        .filter(|reactor_drop| rng.gen_bool((drop_rate / reactor_drop.chance.max(1) as f64).min(1.0)))
+        .filter_map(|reactor_drop| ItemData::get(reactor_drop.item_id).map(|item_data| item_data.create_item(1)))
+        .collect();
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    let map = channel.map(map_id)?;
+    let mut map_guard = match map.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+
+    let ownership = DropOwnership::of_character(character_id, channel.party_id(character_id));
+    let start = reactor.position.0 - DROP_SPACING * (items.len() as i16 - 1) / 2;
+    for (index, item) in items.into_iter().enumerate() {
+        map_guard.spawn_drop(
+            DropContent::Item(item),
+            ownership,
+            reactor.object_id,
+            (start + DROP_SPACING * index as i16, reactor.position.1),
+            reactor.position,
+        );
+    }
+    Ok(())
+}
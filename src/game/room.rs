@@ -0,0 +1,497 @@
+use crate::db::model::character::{Character, CharacterUpdate};
+use crate::db::model::merchant::Merchant;
+use crate::db::model::minigame_record::MinigameRecord;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::inventory::{CharacterInventory, InventoryType, Item};
+use crate::game::item;
+use crate::game::map::Map;
+use crate::game::merchant;
+use crate::game::minigame::{self, Minigame};
+use crate::game::player_shop::Shop;
+use crate::net::client::{Client, PacketSender};
+use crate::net::packet::interaction as interaction_packet;
+use crate::net::packet::item::create_modify_inventory;
+use crate::net::packet::message::{create_server_message, ServerMessageType};
+use std::error::Error;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The kinds of rooms, by their code in the interaction packets. Trades, code 3, are kept apart
+/// in `trade`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoomType {
+    Omok = 1,
+    MatchCard = 2,
+    PlayerShop = 4,
+    HiredMerchant = 5,
+}
+
+impl RoomType {
+    pub fn from_u8(value: u8) -> Option<RoomType> {
+        match value {
+            1 => Some(RoomType::Omok),
+            2 => Some(RoomType::MatchCard),
+            4 => Some(RoomType::PlayerShop),
+            5 => Some(RoomType::HiredMerchant),
+            _ => None,
+        }
+    }
+
+    /// How many players fit in the room, its owner included.
+    pub fn capacity(self) -> u8 {
+        match self {
+            RoomType::Omok | RoomType::MatchCard => 2,
+            RoomType::PlayerShop | RoomType::HiredMerchant => 4,
+        }
+    }
+}
+
+/// Why a player could not enter a room, by its code in the packet telling it.
+#[derive(Clone, Copy)]
+pub enum RoomError {
+    Closed = 1,
+    Full = 2,
+    Busy = 3,
+}
+
+/// Why the window of a room closed, by its code in the packet closing it.
+#[derive(Clone, Copy)]
+pub enum RoomExit {
+    Closed = 3,
+    Expelled = 5,
+}
+
+pub struct Visitor {
+    pub character_id: i32,
+    pub name: String,
+    /// The look and name of the character as the packets of the room show it.
+    pub look: Vec<u8>,
+    pub sender: PacketSender,
+}
+
+pub enum RoomContent {
+    Shop(Shop),
+    Game(Minigame),
+}
+
+/// A player shop, hired merchant or mini-game room, which the players in its map visit through
+/// the balloon over its owner or the merchant minding it.
+pub struct Room {
+    pub room_id: i32,
+    pub room_type: RoomType,
+    pub owner_id: i32,
+    pub owner_name: String,
+    pub map_id: i32,
+    pub title: String,
+    pub password: Option<String>,
+    /// Whether others can visit the room, which shops cannot while their owner sets them up.
+    pub open: bool,
+    pub content: RoomContent,
+    /// The players in the room by position. The owner takes position 0, which the owner of a
+    /// hired merchant leaves empty while away.
+    visitors: Vec<Option<Visitor>>,
+    /// Characters expelled from a shop, who may not visit it again.
+    banned: Vec<i32>,
+}
+
+impl Room {
+    pub fn visitors(&self) -> impl Iterator<Item = (u8, &Visitor)> {
+        self.visitors
+            .iter()
+            .enumerate()
+            .filter_map(|(position, visitor)| visitor.as_ref().map(|visitor| (position as u8, visitor)))
+    }
+
+    pub fn visitor(&self, position: u8) -> Option<&Visitor> {
+        self.visitors.get(position as usize)?.as_ref()
+    }
+
+    pub fn position_of(&self, character_id: i32) -> Option<u8> {
+        self.visitors()
+            .find(|(_, visitor)| visitor.character_id == character_id)
+            .map(|(position, _)| position)
+    }
+
+    /// The piece of a mini-game, or the kind of permit a shop was opened with, which its balloon
+    /// is drawn with.
+    pub fn kind(&self) -> u8 {
+        match &self.content {
+            RoomContent::Game(game) => game.piece,
+            RoomContent::Shop(shop) => (shop.permit_id % 10) as u8,
+        }
+    }
+
+    pub fn send(&self, position: u8, packet: Vec<u8>) {
+        if let Some(visitor) = self.visitor(position) {
+            visitor.sender.send(packet);
+        }
+    }
+
+    pub fn broadcast(&self, packet: &[u8], except: Option<i32>) {
+        for (_, visitor) in self.visitors() {
+            if Some(visitor.character_id) != except {
+                visitor.sender.send(packet.to_vec());
+            }
+        }
+    }
+
+    /// Shows the room in its map as it is now, through the balloon over its owner or the hired
+    /// merchant minding it.
+    pub fn show(&self) -> Result<(), Box<dyn Error>> {
+        match &self.content {
+            RoomContent::Shop(Shop {
+                merchant: Some(merchant),
+                ..
+            }) => {
+                let spawn_packet = interaction_packet::create_spawn_merchant(self, merchant.position);
+                let update_packet = interaction_packet::create_update_merchant(self);
+                with_map(self.map_id, |map| map.update_room(self.room_id, spawn_packet, &update_packet))
+            }
+            _ => {
+                let balloon_packet = interaction_packet::create_room_balloon(self);
+                with_map(self.map_id, |map| map.show_room(self.room_id, balloon_packet))
+            }
+        }
+    }
+
+    fn hide(&self) -> Result<(), Box<dyn Error>> {
+        let remove_packet = match &self.content {
+            RoomContent::Shop(Shop { merchant: Some(_), .. }) => {
+                interaction_packet::create_remove_merchant(self.owner_id)
+            }
+            _ => interaction_packet::create_remove_room_balloon(self.owner_id),
+        };
+        with_map(self.map_id, |map| map.hide_room(self.room_id, &remove_packet))
+    }
+}
+
+pub fn lock_room(room: &Mutex<Room>) -> Result<MutexGuard<'_, Room>, Box<dyn Error>> {
+    match room.lock() {
+        Ok(guard) => Ok(guard),
+        Err(error) => Err(format!("Unable to lock Room Mutex [{}]", error).into()),
+    }
+}
+
+/// The room the character opened or visits.
+pub fn joined_room(character_id: i32) -> Result<Arc<Mutex<Room>>, Box<dyn Error>> {
+    match Channel::get()?.room_of(character_id) {
+        Some(room) => Ok(room),
+        None => Err(format!("Character {} is not in a room", character_id).into()),
+    }
+}
+
+fn with_map(map_id: i32, action: impl FnOnce(&mut Map)) -> Result<(), Box<dyn Error>> {
+    let map = Channel::get()?.map(map_id)?;
+    let mut map_guard = match map.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+    action(&mut map_guard);
+    Ok(())
+}
+
+/// Whether the character is trading or in a room, and cannot enter another one.
+pub fn is_busy(character_id: i32) -> Result<bool, Box<dyn Error>> {
+    let channel = Channel::get()?;
+    Ok(channel.room_of(character_id).is_some() || channel.trade(character_id).is_some())
+}
+
+/// Opens a room owned by the character with `content`. Mini-games are shown in the map right
+/// away, shops once their owner set them up.
+pub fn create(
+    client: &Client,
+    character: &Character,
+    inventory: &CharacterInventory,
+    room_type: RoomType,
+    title: String,
+    password: Option<String>,
+    content: RoomContent,
+) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    if is_busy(character.id)? {
+        client.send(interaction_packet::create_room_error(RoomError::Busy));
+        return Ok(());
+    }
+    let sender = match client.sender() {
+        Some(sender) => sender,
+        None => return Err("Unable to open a room for a disconnected client".into()),
+    };
+
+    let room_id = channel.next_room_id();
+    let mut visitors: Vec<Option<Visitor>> = (0..room_type.capacity()).map(|_| None).collect();
+    visitors[0] = Some(Visitor {
+        character_id: character.id,
+        name: character.name.clone(),
+        look: interaction_packet::encode_visitor(character, inventory.get(InventoryType::Equipped)),
+        sender,
+    });
+    let room = Room {
+        room_id,
+        room_type,
+        owner_id: character.id,
+        owner_name: character.name.clone(),
+        map_id: character.map_id,
+        title,
+        password,
+        open: matches!(content, RoomContent::Game(_)),
+        content,
+        visitors,
+        banned: Vec::new(),
+    };
+
+    client.send(interaction_packet::create_room(&room, 0));
+    if room.open {
+        room.show()?;
+    }
+    channel.add_room(room_id, Arc::new(Mutex::new(room)))?;
+    channel.enter_room(character.id, room_id)
+}
+
+/// Enters the room through its balloon, with the password of the room if it is locked. The
+/// owner of a hired merchant takes its own place back.
+pub fn visit(
+    client: &Client,
+    character: &Character,
+    inventory: &CharacterInventory,
+    room_id: i32,
+    password: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let room_mutex = match channel.room(room_id) {
+        Some(room) => room,
+        None => {
+            client.send(interaction_packet::create_room_error(RoomError::Closed));
+            return Ok(());
+        }
+    };
+    if is_busy(character.id)? {
+        client.send(interaction_packet::create_room_error(RoomError::Busy));
+        return Ok(());
+    }
+    let sender = match client.sender() {
+        Some(sender) => sender,
+        None => return Err("Unable to visit a room with a disconnected client".into()),
+    };
+
+    let mut room = lock_room(&room_mutex)?;
+    if room.map_id != character.map_id {
+        return Err(format!("{} tried to visit room {} from another map", character.name, room_id).into());
+    }
+    let returning_owner = room.room_type == RoomType::HiredMerchant && room.owner_id == character.id;
+    if !room.open {
+        client.send(interaction_packet::create_room_error(RoomError::Closed));
+        return Ok(());
+    }
+    if room.banned.contains(&character.id) {
+        client.send(create_server_message(
+            ServerMessageType::PinkText,
+            "You have been expelled from this shop.",
+        ));
+        return Ok(());
+    }
+    if !returning_owner && room.password.is_some() && room.password != password {
+        client.send(create_server_message(ServerMessageType::PinkText, "The password is incorrect."));
+        return Ok(());
+    }
+
+    let position = match returning_owner {
+        true => Some(0).filter(|_| room.visitors[0].is_none()),
+        false => (1..room.room_type.capacity()).find(|position| room.visitors[*position as usize].is_none()),
+    };
+    let position = match position {
+        Some(position) => position,
+        None => {
+            client.send(interaction_packet::create_room_error(RoomError::Full));
+            return Ok(());
+        }
+    };
+
+    let room_type = room.room_type;
+    if let RoomContent::Game(game) = &mut room.content {
+        game.records[position as usize] = MinigameRecord::get(character.id, minigame::game_of(room_type))?;
+    }
+    room.visitors[position as usize] = Some(Visitor {
+        character_id: character.id,
+        name: character.name.clone(),
+        look: interaction_packet::encode_visitor(character, inventory.get(InventoryType::Equipped)),
+        sender,
+    });
+
+    client.send(interaction_packet::create_room(&room, position));
+    room.broadcast(&interaction_packet::create_room_visit(&room, position), Some(character.id));
+    room.show()?;
+    channel.enter_room(character.id, room_id)
+}
+
+/// Shows a chat message of the character to everyone in its room.
+pub fn chat(character_id: i32, text: &str) -> Result<(), Box<dyn Error>> {
+    let room = joined_room(character_id)?;
+    let room = lock_room(&room)?;
+    if let Some((position, visitor)) = room.visitors().find(|(_, visitor)| visitor.character_id == character_id) {
+        room.broadcast(&interaction_packet::create_room_chat(position, &visitor.name, text), None);
+    }
+    Ok(())
+}
+
+/// Leaves the room of the character, if it is in one, forfeiting a game in progress. Player
+/// shops and mini-games close when their owner leaves, and the items of a shop go back to it;
+/// hired merchants stay open. Nothing may be locked by the caller, as the owner is locked to
+/// get its items back.
+pub fn leave(character_id: i32) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let room_mutex = match channel.room_of(character_id) {
+        Some(room) => room,
+        None => return Ok(()),
+    };
+
+    let mut room = lock_room(&room_mutex)?;
+    let position = match room.position_of(character_id) {
+        Some(position) => position,
+        None => {
+            channel.exit_room(character_id);
+            return Ok(());
+        }
+    };
+    if let RoomContent::Game(_) = room.content {
+        minigame::forfeit(&mut room, position)?;
+        // The owner may have asked to leave once the game ended, closing the room already.
+        if room.position_of(character_id).is_none() {
+            return Ok(());
+        }
+    }
+
+    let open_merchant = matches!(&room.content, RoomContent::Shop(Shop { merchant: Some(_), .. }));
+    match position == 0 && !open_merchant {
+        true => {
+            let items = close(&mut room, RoomExit::Closed, Some(character_id))?;
+            drop(room);
+            give_back(character_id, items)
+        }
+        false => remove_visitor(&mut room, position, None),
+    }
+}
+
+/// Sends the visitor at `position` out of the room, telling it why when it did not leave by
+/// itself.
+pub fn remove_visitor(room: &mut Room, position: u8, exit: Option<RoomExit>) -> Result<(), Box<dyn Error>> {
+    let visitor = match room.visitors.get_mut(position as usize).and_then(Option::take) {
+        Some(visitor) => visitor,
+        None => return Ok(()),
+    };
+    Channel::get()?.exit_room(visitor.character_id);
+    if let Some(exit) = exit {
+        visitor.sender.send(interaction_packet::create_room_exit(position, exit));
+    }
+    room.broadcast(&interaction_packet::create_room_leave(position), None);
+
+    if let RoomContent::Game(game) = &mut room.content {
+        game.visitor_ready = false;
+    }
+    match room.open {
+        true => room.show(),
+        false => Ok(()),
+    }
+}
+
+/// Expels the visitor named `name` from the shop of the character, which it may not visit
+/// again.
+pub fn ban(character_id: i32, name: &str) -> Result<(), Box<dyn Error>> {
+    let room = joined_room(character_id)?;
+    let mut room = lock_room(&room)?;
+    if room.owner_id != character_id || !matches!(room.content, RoomContent::Shop(_)) {
+        return Err(format!("Character {} cannot ban visitors of room {}", character_id, room.room_id).into());
+    }
+
+    let banned = room
+        .visitors()
+        .find(|(position, visitor)| *position != 0 && visitor.name == name)
+        .map(|(position, visitor)| (position, visitor.character_id));
+    match banned {
+        Some((position, banned_id)) => {
+            room.banned.push(banned_id);
+            remove_visitor(&mut room, position, Some(RoomExit::Expelled))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Closes the room, sending everyone in it but `except` out, and hides it from its map. Returns
+/// what is left of the items of a shop for its owner; hired merchants leave them to Fredrick.
+pub fn close(room: &mut Room, exit: RoomExit, except: Option<i32>) -> Result<Vec<Item>, Box<dyn Error>> {
+    let channel = Channel::get()?;
+    channel.remove_room(room.room_id);
+    for (position, visitor) in room.visitors.iter_mut().enumerate() {
+        if let Some(visitor) = visitor.take() {
+            channel.exit_room(visitor.character_id);
+            if Some(visitor.character_id) != except {
+                visitor.sender.send(interaction_packet::create_room_exit(position as u8, exit));
+            }
+        }
+    }
+    room.open = false;
+    room.hide()?;
+
+    match &mut room.content {
+        RoomContent::Shop(shop) => match &shop.merchant {
+            Some(merchant) => {
+                Merchant::close(merchant.merchant_id)?;
+                Ok(Vec::new())
+            }
+            None => Ok(shop
+                .listings
+                .drain(..)
+                .filter_map(|listing| listing.remaining())
+                .collect()),
+        },
+        RoomContent::Game(_) => Ok(Vec::new()),
+    }
+}
+
+/// Returns the items of a closed shop to its owner. What does not fit in its inventory, or all
+/// of it when the owner already left, waits at Fredrick instead, stored in the same transaction
+/// as the owner.
+pub fn give_back(owner_id: i32, items: Vec<Item>) -> Result<(), Box<dyn Error>> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    let player = match Channel::get()?.player(owner_id) {
+        Some(player) => player,
+        None => return merchant::stash(owner_id, 0, items, None),
+    };
+
+    character::with_character(&player.client, |client, character| {
+        let mut inventory = character::lock_inventory(client)?;
+        let mut returned_inventory = inventory.clone();
+        let mut operations = Vec::new();
+        let mut unreturned = Vec::new();
+        for item in items {
+            match item::add_item(&mut returned_inventory, item.clone()) {
+                Ok(mut added) => operations.append(&mut added),
+                Err(_) => unreturned.push(item),
+            }
+        }
+
+        // What fits is returned even if the rest cannot be stashed.
+        let stashing = !unreturned.is_empty();
+        let stashed = match stashing {
+            true => {
+                let owner = CharacterUpdate {
+                    character_id: character.id,
+                    meso: character.meso,
+                    inventory: Some(returned_inventory.rows(character.id)),
+                };
+                merchant::stash(character.id, 0, unreturned, Some(&owner))
+            }
+            false => Ok(()),
+        };
+        *inventory = returned_inventory;
+        client.send(create_modify_inventory(&operations, true));
+        if stashing && stashed.is_ok() {
+            client.send(create_server_message(
+                ServerMessageType::PinkText,
+                "Your inventory is full. The items left in your shop wait at Fredrick.",
+            ));
+        }
+        stashed
+    })
+}
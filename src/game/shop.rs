@@ -0,0 +1,200 @@
+use crate::data::item::ItemData;
+use crate::db::model::character::Character;
+use crate::db::model::meso_log::MesoReason;
+use crate::db::model::shop::{Shop, ShopItem};
+use crate::game::inventory::{CharacterInventory, InventoryType, Item};
+use crate::game::item::{self, InventoryOperation};
+use crate::game::meso;
+use std::error::Error;
+
+/// Outcomes of a shop transaction the client shows a message for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ShopResult {
+    Bought = 0,
+    NotEnoughMesos = 2,
+    InventoryFull = 3,
+    /// Selling and recharging share the same confirmation.
+    Sold = 8,
+}
+
+/// The shop of an NPC, as opened by a player talking to it.
+pub struct NpcShop {
+    pub npc_id: i32,
+    pub items: Vec<ShopItem>,
+}
+
+impl NpcShop {
+    pub fn load(npc_id: i32) -> Result<Option<NpcShop>, Box<dyn Error>> {
+        match Shop::get_by_npc(npc_id)? {
+            Some(shop) => Ok(Some(NpcShop {
+                npc_id,
+                items: ShopItem::get_by_shop(shop.id)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Shops recharge the kinds of projectiles they sell.
+    fn recharges(&self, item_id: i32) -> bool {
+        self.items
+            .iter()
+            .any(|shop_item| shop_item.item_id / 10000 == item_id / 10000)
+    }
+}
+
+/// Buys `quantity` of the item listed at `position` in the shop. Rechargeable items are sold as
+/// a full stack for the listed price.
+pub fn buy(
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    shop: &NpcShop,
+    position: i16,
+    item_id: i32,
+    quantity: i16,
+) -> Result<(ShopResult, Vec<InventoryOperation>), Box<dyn Error>> {
+    let shop_item = match shop.items.get(position as usize) {
+        Some(shop_item) if shop_item.item_id == item_id => shop_item,
+        _ => return Err(format!("Shop of NPC {} does not sell {} at {}", shop.npc_id, item_id, position).into()),
+    };
+    let item_data = match ItemData::get(item_id) {
+        Some(item_data) => item_data,
+        None => return Err(format!("Unknown item {}", item_id).into()),
+    };
+
+    let mut item = item_data.create_item(quantity);
+    if item.is_rechargeable() {
+        item.quantity = item_data.slot_max;
+    } else if quantity < 1 || quantity > item_data.slot_max.max(1) {
+        return Err(format!("Cannot buy {} of item {}", quantity, item_id).into());
+    }
+
+    let cost = match item.is_rechargeable() {
+        true => shop_item.price as i64,
+        false => shop_item.price as i64 * quantity as i64,
+    };
+    if cost > character.meso as i64 {
+        return Ok((ShopResult::NotEnoughMesos, Vec::new()));
+    }
+
+    let operations = match item::add_item(inventory, item) {
+        Ok(operations) => operations,
+        Err(_) => return Ok((ShopResult::InventoryFull, Vec::new())),
+    };
+
+    character.meso -= cost as i32;
+    meso::log(
+        character,
+        -cost as i32,
+        MesoReason::ShopBuy,
+        &format!("bought {} of item {} from NPC {}", quantity, item_id, shop.npc_id),
+    );
+    Ok((ShopResult::Bought, operations))
+}
+
+/// Sells `quantity` of the item at `slot`; rechargeable stacks are always sold whole.
+pub fn sell(
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    shop: &NpcShop,
+    slot: i16,
+    item_id: i32,
+    quantity: i16,
+) -> Result<(ShopResult, Vec<InventoryOperation>), Box<dyn Error>> {
+    let inventory_type = match InventoryType::of_item(item_id) {
+        Some(inventory_type) => inventory_type,
+        None => return Err(format!("Unknown item {}", item_id).into()),
+    };
+    let item_data = match ItemData::get(item_id) {
+        Some(item_data) => item_data,
+        None => return Err(format!("Unknown item {}", item_id).into()),
+    };
+
+    let tab = inventory.get_mut(inventory_type);
+    let item: &Item = match tab.get(slot) {
+        Some(item) if item.item_id == item_id => item,
+        _ => return Err(format!("No item {} at position {}", item_id, slot).into()),
+    };
+
+    let quantity = match item.is_stackable() {
+        true => quantity,
+        false => item.quantity,
+    };
+    if quantity < 1 || quantity > item.quantity {
+        return Err(format!("Cannot sell {} of {} items", quantity, item.quantity).into());
+    }
+
+    let earnings = match item.is_rechargeable() {
+        true => item_data.price as i64 + (item_data.unit_price * quantity as f64) as i64,
+        false => item_data.price as i64 * quantity as i64,
+    };
+    if character.meso as i64 + earnings > i32::MAX as i64 {
+        return Err(format!("{} cannot hold {} more mesos", character.name, earnings).into());
+    }
+
+    let operation = match quantity == item.quantity {
+        true => {
+            tab.remove(slot);
+            InventoryOperation::Remove(inventory_type, slot)
+        }
+        false => {
+            let remaining = item.quantity - quantity;
+            if let Some(item) = tab.get_mut(slot) {
+                item.quantity = remaining;
+            }
+            InventoryOperation::Quantity(inventory_type, slot, remaining)
+        }
+    };
+
+    character.meso += earnings as i32;
+    meso::log(
+        character,
+        earnings as i32,
+        MesoReason::ShopSell,
+        &format!("sold {} of item {} to NPC {}", quantity, item_id, shop.npc_id),
+    );
+    Ok((ShopResult::Sold, vec![operation]))
+}
+
+/// Refills the throwing stars or bullets at `slot` up to a full stack.
+pub fn recharge(
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    shop: &NpcShop,
+    slot: i16,
+) -> Result<(ShopResult, Vec<InventoryOperation>), Box<dyn Error>> {
+    let tab = inventory.get_mut(InventoryType::Use);
+    let item = match tab.get_mut(slot) {
+        Some(item) if item.is_rechargeable() => item,
+        _ => return Err(format!("No rechargeable item at position {}", slot).into()),
+    };
+    if !shop.recharges(item.item_id) {
+        return Err(format!("Shop of NPC {} does not recharge item {}", shop.npc_id, item.item_id).into());
+    }
+    let item_data = match ItemData::get(item.item_id) {
+        Some(item_data) => item_data,
+        None => return Err(format!("Unknown item {}", item.item_id).into()),
+    };
+
+    let missing = item_data.slot_max - item.quantity;
+    if missing <= 0 {
+        return Err(format!("Item {} at position {} is already full", item.item_id, slot).into());
+    }
+
+    let cost = (item_data.unit_price * missing as f64).ceil() as i32;
+    if cost > character.meso {
+        return Ok((ShopResult::NotEnoughMesos, Vec::new()));
+    }
+
+    item.quantity = item_data.slot_max;
+    let operation = InventoryOperation::Quantity(InventoryType::Use, slot, item.quantity);
+    let item_id = item.item_id;
+
+    character.meso -= cost;
+    meso::log(
+        character,
+        -cost,
+        MesoReason::ShopRecharge,
+        &format!("recharged {} of item {} at NPC {}", missing, item_id, shop.npc_id),
+    );
+    Ok((ShopResult::Sold, vec![operation]))
+}
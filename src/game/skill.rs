@@ -0,0 +1,120 @@
+use crate::data::skill::SkillData;
+use crate::db::model::character::Character;
+use crate::db::model::skill::{NewSkill, Skill};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+#[derive(Clone, Copy, Default)]
+pub struct SkillEntry {
+    pub level: u8,
+    /// Highest level a skill that needs mastery can be raised to.
+    pub master_level: u8,
+}
+
+pub struct CharacterSkills {
+    skills: BTreeMap<i32, SkillEntry>,
+}
+
+impl CharacterSkills {
+    pub fn load(character_id: i32) -> Result<CharacterSkills, Box<dyn Error>> {
+        let skills = Skill::get_by_character(character_id)?
+            .into_iter()
+            .map(|row| {
+                (
+                    row.skill_id,
+                    SkillEntry {
+                        level: row.level as u8,
+                        master_level: row.master_level as u8,
+                    },
+                )
+            })
+            .collect();
+
+        Ok(CharacterSkills { skills })
+    }
+
+    /// The rows the skills are stored as.
+    pub fn rows(&self, character_id: i32) -> Vec<NewSkill> {
+        self.skills
+            .iter()
+            .map(|(skill_id, entry)| NewSkill {
+                character_id,
+                skill_id: *skill_id,
+                level: entry.level as i16,
+                master_level: entry.master_level as i16,
+            })
+            .collect()
+    }
+
+    pub fn get(&self, skill_id: i32) -> SkillEntry {
+        self.skills.get(&skill_id).copied().unwrap_or_default()
+    }
+
+    pub fn level(&self, skill_id: i32) -> u8 {
+        self.get(skill_id).level
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (i32, SkillEntry)> + '_ {
+        self.skills.iter().map(|(skill_id, entry)| (*skill_id, *entry))
+    }
+}
+
+/// Whether the skill belongs to the job of the character or to one it advanced from.
+pub fn has_skill_job(character: &Character, skill_id: i32) -> bool {
+    let skill_job = SkillData::job_of(skill_id);
+    let job = character.job;
+
+    skill_job == 0
+        || skill_job == job
+        || (skill_job % 100 == 0 && skill_job / 100 == job / 100)
+        || (skill_job / 10 == job / 10 && skill_job % 10 < job % 10)
+}
+
+/// Spends one SP of the character on `skill_id`, returning the new entry of the skill.
+pub fn add_skill_point(
+    character: &mut Character,
+    skills: &mut CharacterSkills,
+    skill_id: i32,
+) -> Result<SkillEntry, Box<dyn Error>> {
+    if character.sp <= 0 {
+        return Err(format!("{} has no SP left", character.name).into());
+    }
+    if !has_skill_job(character, skill_id) {
+        return Err(format!("{} cannot learn skill {} of another job", character.name, skill_id).into());
+    }
+
+    let data = match SkillData::get(skill_id) {
+        Some(data) => data,
+        None => return Err(format!("Skill {} does not exist", skill_id).into()),
+    };
+
+    let entry = skills.get(skill_id);
+    let max_level = match data.needs_mastery {
+        true => entry.master_level.min(data.max_level()),
+        false => data.max_level(),
+    };
+    if entry.level >= max_level {
+        return Err(format!("Skill {} is already at its highest level {}", skill_id, max_level).into());
+    }
+
+    if let Some((required_id, required_level)) = data
+        .requirements
+        .iter()
+        .find(|(required_id, required_level)| skills.level(*required_id) < *required_level)
+    {
+        return Err(format!(
+            "Skill {} needs skill {} at level {}",
+            skill_id, required_id, required_level
+        )
+        .into());
+    }
+
+    let entry = SkillEntry {
+        level: entry.level + 1,
+        ..entry
+    };
+    skills.skills.insert(skill_id, entry);
+    character.sp -= 1;
+
+    Ok(entry)
+}
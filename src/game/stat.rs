@@ -0,0 +1,57 @@
+use crate::db::model::character::Character;
+use crate::net::packet::character::Stat;
+use rand::Rng;
+use std::error::Error;
+
+/// Highest value strength, dexterity, intelligence and luck can be raised to with AP.
+const MAX_BASE_STAT: i16 = 999;
+pub const MAX_HP_MP: i16 = 30000;
+
+/// HP or MP a character of `job` gains from a single AP spent on it.
+fn ap_gain(job: i16, stat: Stat) -> i16 {
+    let mut rng = rand::thread_rng();
+
+    match (job / 100, stat) {
+        (9, _) => 500,
+        (1, Stat::MaxHp) => rng.gen_range(20..=24),
+        (1, _) => rng.gen_range(2..=4),
+        (2, Stat::MaxHp) => rng.gen_range(6..=10),
+        (2, _) => rng.gen_range(18..=20),
+        (3 | 4, Stat::MaxHp) => rng.gen_range(16..=20),
+        (3 | 4, _) => rng.gen_range(10..=12),
+        (_, Stat::MaxHp) => rng.gen_range(8..=12),
+        (_, _) => rng.gen_range(6..=8),
+    }
+}
+
+/// Spends `amount` AP of the character on `stat`.
+pub fn add_ability_points(character: &mut Character, stat: Stat, amount: i16) -> Result<(), Box<dyn Error>> {
+    if amount <= 0 || amount > character.ap {
+        return Err(format!("{} cannot spend {} of its {} AP", character.name, amount, character.ap).into());
+    }
+
+    let job = character.job;
+    let (value, max_value) = match stat {
+        Stat::Strength => (&mut character.strength, MAX_BASE_STAT),
+        Stat::Dexterity => (&mut character.dexterity, MAX_BASE_STAT),
+        Stat::Intelligence => (&mut character.intelligence, MAX_BASE_STAT),
+        Stat::Luck => (&mut character.luck, MAX_BASE_STAT),
+        Stat::MaxHp => (&mut character.max_hp, MAX_HP_MP),
+        Stat::MaxMp => (&mut character.max_mp, MAX_HP_MP),
+        _ => return Err(format!("AP cannot be spent on stat {:#x}", stat as u32).into()),
+    };
+
+    match stat {
+        Stat::MaxHp | Stat::MaxMp if *value < max_value => {
+            for _ in 0..amount {
+                *value = value.saturating_add(ap_gain(job, stat)).min(max_value);
+            }
+        }
+        Stat::MaxHp | Stat::MaxMp => return Err(format!("Stat {:#x} is already at {}", stat as u32, max_value).into()),
+        _ if *value as i32 + amount as i32 <= max_value as i32 => *value += amount,
+        _ => return Err(format!("Stat {:#x} would exceed {}", stat as u32, max_value).into()),
+    }
+    character.ap -= amount;
+
+    Ok(())
+}
@@ -0,0 +1,323 @@
+use crate::data::item::ItemData;
+use crate::data::npc::StorageFees;
+use crate::db::model::character::{Character, CharacterUpdate};
+use crate::db::model::meso_log::MesoReason;
+use crate::db::model::storage::{Storage, StorageUpdate};
+use crate::game::inventory::{CharacterInventory, InventoryType, Item};
+use crate::game::item::{self, InventoryOperation};
+use crate::game::meso;
+use std::error::Error;
+
+/// Slots of a storage that was never expanded.
+pub const DEFAULT_STORAGE_SLOTS: i16 = 4;
+pub const MAX_STORAGE_SLOTS: i16 = 48;
+
+/// Outcomes of a storage action, by the code of the packet that shows them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StorageResult {
+    Took = 0x09,
+    InventoryFull = 0x0A,
+    NotEnoughMesos = 0x0B,
+    OneOfAKind = 0x0C,
+    Stored = 0x0D,
+    Arranged = 0x0F,
+    StorageFull = 0x11,
+    MesoMoved = 0x13,
+}
+
+/// The storage of an account in a world, as opened by one of its characters from a storage
+/// keeper. It only mirrors the stored storage, which every action reads and writes anew, so
+/// characters of the account on other channels never work on stale contents.
+pub struct AccountStorage {
+    pub storage_id: i32,
+    pub npc_id: i32,
+    pub fees: StorageFees,
+    pub slots: i16,
+    pub meso: i32,
+    /// Grouped by tab, each tab in the order its items were stored.
+    pub items: Vec<Item>,
+}
+
+impl AccountStorage {
+    pub fn load(character: &Character, npc_id: i32, fees: StorageFees) -> Result<AccountStorage, Box<dyn Error>> {
+        let (storage, rows) = Storage::get_or_create(character.user_id, character.world_id, DEFAULT_STORAGE_SLOTS)?;
+
+        Ok(AccountStorage {
+            storage_id: storage.id,
+            npc_id,
+            fees,
+            slots: storage.slots,
+            meso: storage.meso,
+            items: rows.iter().map(Item::from_storage_row).collect(),
+        })
+    }
+
+    /// The items shown in the tab of `inventory_type`, in the order the client indexes them.
+    pub fn items_of(&self, inventory_type: InventoryType) -> impl Iterator<Item = &Item> {
+        self.items
+            .iter()
+            .filter(move |item| InventoryType::of_item(item.item_id) == Some(inventory_type))
+    }
+
+    /// Runs `change` on the storage as stored, with the other channels locked out of it, and
+    /// stores what it changed along with the character it returns. The storage shows the
+    /// stored contents afterwards, whatever the change did.
+    fn change<T>(
+        &mut self,
+        change: impl FnOnce(&mut Storage, &mut Vec<Item>) -> Result<(T, Option<CharacterUpdate>), Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let (result, slots, meso, items) = Storage::update(self.storage_id, |storage, rows| {
+            let mut items: Vec<Item> = rows.iter().map(Item::from_storage_row).collect();
+            let (result, character) = change(storage, &mut items)?;
+
+            let update = StorageUpdate {
+                items: items
+                    .iter()
+                    .enumerate()
+                    .map(|(position, item)| item.to_storage_row(storage.id, position as i16))
+                    .collect(),
+                character,
+            };
+            Ok(((result, storage.slots, storage.meso, items), update))
+        })?;
+
+        self.slots = slots;
+        self.meso = meso;
+        self.items = items;
+        Ok(result)
+    }
+}
+
+/// Orders items by the tab they are shown in.
+fn tab_order(item: &Item) -> i16 {
+    InventoryType::of_item(item.item_id).map_or(0, |inventory_type| inventory_type as i16)
+}
+
+/// Position in `items` of the item `index` in the tab of `inventory_type`.
+fn tab_position(items: &[Item], inventory_type: InventoryType, index: u8) -> Option<usize> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| InventoryType::of_item(item.item_id) == Some(inventory_type))
+        .nth(index as usize)
+        .map(|(position, _)| position)
+}
+
+/// Stores `quantity` of the item at `source` for the fee of the storage keeper. The item leaves
+/// the inventory only once the storage holding it is stored.
+pub fn store(
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    storage: &mut AccountStorage,
+    source: i16,
+    item_id: i32,
+    quantity: i16,
+) -> Result<(StorageResult, Vec<InventoryOperation>), Box<dyn Error>> {
+    let inventory_type = match InventoryType::of_item(item_id) {
+        Some(inventory_type) => inventory_type,
+        None => return Err(format!("Unknown item {}", item_id).into()),
+    };
+    match inventory.get(inventory_type).get(source) {
+        Some(item) if item.item_id == item_id => {}
+        _ => return Err(format!("{} has no item {} at position {}", character.name, item_id, source).into()),
+    }
+    if ItemData::get(item_id).is_some_and(|item_data| item_data.quest) {
+        return Err(format!("{} tried to store quest item {}", character.name, item_id).into());
+    }
+
+    let fee = storage.fees.store;
+    if character.meso < fee {
+        return Ok((StorageResult::NotEnoughMesos, Vec::new()));
+    }
+
+    let mut stored_inventory = inventory.clone();
+    let (operations, item) = item::take(&mut stored_inventory, inventory_type, source, quantity)?;
+    let character_update = CharacterUpdate {
+        character_id: character.id,
+        meso: character.meso - fee,
+        inventory: Some(stored_inventory.rows(character.id)),
+    };
+
+    let result = storage.change(|stored, items| {
+        if items.len() >= stored.slots.max(0) as usize {
+            return Ok((StorageResult::StorageFull, None));
+        }
+        let position = items
+            .iter()
+            .rposition(|stored_item| tab_order(stored_item) <= inventory_type as i16)
+            .map_or(0, |position| position + 1);
+        items.insert(position, item);
+        Ok((StorageResult::Stored, Some(character_update)))
+    })?;
+    if result != StorageResult::Stored {
+        return Ok((result, Vec::new()));
+    }
+
+    *inventory = stored_inventory;
+    character.meso -= fee;
+    if fee > 0 {
+        meso::log(
+            character,
+            -fee,
+            MesoReason::Storage,
+            &format!("stored item {} at NPC {}", item_id, storage.npc_id),
+        );
+    }
+    Ok((StorageResult::Stored, operations))
+}
+
+/// Takes the item at `index` of the tab of `inventory_type` for the fee of the storage keeper.
+pub fn take(
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    storage: &mut AccountStorage,
+    inventory_type: InventoryType,
+    index: u8,
+) -> Result<(StorageResult, Vec<InventoryOperation>), Box<dyn Error>> {
+    // The item the player saw, which a character of the account on another channel may have
+    // taken since.
+    let shown_item_id = match tab_position(&storage.items, inventory_type, index) {
+        Some(position) => storage.items[position].item_id,
+        None => {
+            return Err(format!("{} has no stored item at {} of {:?}", character.name, index, inventory_type).into())
+        }
+    };
+
+    let fee = storage.fees.take;
+    if character.meso < fee {
+        return Ok((StorageResult::NotEnoughMesos, Vec::new()));
+    }
+
+    let mut taken_inventory = inventory.clone();
+    let (character_id, meso) = (character.id, character.meso - fee);
+    let (result, operations) = storage.change(|_, items| {
+        let position = match tab_position(items, inventory_type, index) {
+            Some(position) if items[position].item_id == shown_item_id => position,
+            // Showing the tab again is all there is to do.
+            _ => return Ok(((StorageResult::Took, None), None)),
+        };
+
+        let item_id = items[position].item_id;
+        let only_one = ItemData::get(item_id).is_some_and(|item_data| item_data.only_one);
+        if only_one && item::has_item(&taken_inventory, item_id) {
+            return Ok(((StorageResult::OneOfAKind, None), None));
+        }
+        let operations = match item::add_item(&mut taken_inventory, items[position].clone()) {
+            Ok(operations) => operations,
+            Err(_) => return Ok(((StorageResult::InventoryFull, None), None)),
+        };
+        items.remove(position);
+
+        let character_update = CharacterUpdate {
+            character_id,
+            meso,
+            inventory: Some(taken_inventory.rows(character_id)),
+        };
+        Ok(((StorageResult::Took, Some((item_id, operations))), Some(character_update)))
+    })?;
+
+    let (item_id, operations) = match operations {
+        Some(taken) => taken,
+        None => return Ok((result, Vec::new())),
+    };
+    *inventory = taken_inventory;
+    character.meso -= fee;
+    if fee > 0 {
+        meso::log(
+            character,
+            -fee,
+            MesoReason::Storage,
+            &format!("took item {} at NPC {}", item_id, storage.npc_id),
+        );
+    }
+    Ok((result, operations))
+}
+
+/// Sorts the items of every tab by item id.
+pub fn arrange(storage: &mut AccountStorage) -> Result<StorageResult, Box<dyn Error>> {
+    storage.change(|_, items| {
+        items.sort_by_key(|item| (tab_order(item), item.item_id));
+        Ok((StorageResult::Arranged, None))
+    })
+}
+
+/// Takes `amount` mesos out of the storage, or stores them when it is negative.
+pub fn move_meso(
+    character: &mut Character,
+    storage: &mut AccountStorage,
+    amount: i32,
+) -> Result<StorageResult, Box<dyn Error>> {
+    let meso = match character.meso.checked_add(amount) {
+        Some(meso) if meso >= 0 => meso,
+        _ => return Ok(StorageResult::NotEnoughMesos),
+    };
+
+    let character_id = character.id;
+    let result = storage.change(|stored, _| match stored.meso.checked_sub(amount) {
+        Some(stored_meso) if stored_meso >= 0 => {
+            stored.meso = stored_meso;
+            let character_update = CharacterUpdate {
+                character_id,
+                meso,
+                inventory: None,
+            };
+            Ok((StorageResult::MesoMoved, Some(character_update)))
+        }
+        _ => Ok((StorageResult::NotEnoughMesos, None)),
+    })?;
+    if result != StorageResult::MesoMoved {
+        return Ok(result);
+    }
+
+    character.meso = meso;
+    meso::log(
+        character,
+        amount,
+        MesoReason::Storage,
+        &format!("moved mesos to or from storage at NPC {}", storage.npc_id),
+    );
+    Ok(result)
+}
+
+/// The slots of the storage of the account of the character in its world.
+pub fn slots(character: &Character) -> Result<i16, Box<dyn Error>> {
+    let (storage, _) = Storage::get_or_create(character.user_id, character.world_id, DEFAULT_STORAGE_SLOTS)?;
+    Ok(storage.slots)
+}
+
+/// Adds `slots` slots to the storage of the account for `cost` mesos, returning whether the
+/// character could pay for them and the storage had room to grow.
+pub fn expand(character: &mut Character, npc_id: i32, slots: i16, cost: i32) -> Result<bool, Box<dyn Error>> {
+    if slots < 1 || cost < 0 || character.meso < cost {
+        return Ok(false);
+    }
+
+    let fees = StorageFees { store: 0, take: 0 };
+    let mut storage = AccountStorage::load(character, npc_id, fees)?;
+    let character_update = CharacterUpdate {
+        character_id: character.id,
+        meso: character.meso - cost,
+        inventory: None,
+    };
+    let expanded = storage.change(|stored, _| match stored.slots + slots <= MAX_STORAGE_SLOTS {
+        true => {
+            stored.slots += slots;
+            Ok((true, Some(character_update)))
+        }
+        false => Ok((false, None)),
+    })?;
+    if !expanded {
+        return Ok(false);
+    }
+
+    character.meso -= cost;
+    if cost > 0 {
+        meso::log(
+            character,
+            -cost,
+            MesoReason::Storage,
+            &format!("expanded storage by {} slots at NPC {}", slots, npc_id),
+        );
+    }
+    Ok(true)
+}
@@ -0,0 +1,561 @@
+use crate::db::model::character::{Character, CharacterUpdate};
+use crate::db::model::meso_log::MesoReason;
+use crate::db::model::trade_log::{NewTradeLog, TradeLog};
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::inventory::{CharacterInventory, InventoryType, Item};
+use crate::game::item::{self, InventoryOperation};
+use crate::game::merchant;
+use crate::game::meso;
+use crate::net::client::{Client, PacketSender};
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::interaction as interaction_packet;
+use crate::net::packet::item::create_modify_inventory;
+use crate::net::packet::message::{create_server_message, ServerMessageType};
+use log::{info, warn};
+use std::convert::TryInto;
+use std::error::Error;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// Slots of the trade window each trader can offer an item in.
+const MAX_TRADE_SLOTS: u8 = 9;
+
+/// How a trade ended, by its code in the packet closing the trade window.
+#[derive(Clone, Copy)]
+pub enum TradeResult {
+    Cancelled = 2,
+    Completed = 6,
+    Failed = 7,
+}
+
+struct Trader {
+    character_id: i32,
+    name: String,
+    /// 0 for the character that opened the trade, 1 for its partner.
+    position: u8,
+    visitor: Vec<u8>,
+    sender: PacketSender,
+    items: Vec<(u8, Item)>,
+    meso: i32,
+    confirmed: bool,
+}
+
+/// A trade between two characters in the same map. What they offer is taken out of their
+/// inventories until the trade ends.
+pub struct Trade {
+    traders: Vec<Trader>,
+    /// The character invited to join, until it does.
+    invited_id: Option<i32>,
+}
+
+impl Trade {
+    fn trader(&self, character_id: i32) -> Option<&Trader> {
+        self.traders.iter().find(|trader| trader.character_id == character_id)
+    }
+
+    fn trader_mut(&mut self, character_id: i32) -> Option<&mut Trader> {
+        self.traders.iter_mut().find(|trader| trader.character_id == character_id)
+    }
+
+    fn partner(&self, character_id: i32) -> Option<&Trader> {
+        self.traders.iter().find(|trader| trader.character_id != character_id)
+    }
+}
+
+fn lock_trade(trade: &Mutex<Trade>) -> Result<MutexGuard<'_, Trade>, Box<dyn Error>> {
+    match trade.lock() {
+        Ok(guard) => Ok(guard),
+        Err(error) => Err(format!("Unable to lock Trade Mutex [{}]", error).into()),
+    }
+}
+
+/// The trade of the character, which has to have a partner.
+fn joined_trade(character_id: i32) -> Result<Arc<Mutex<Trade>>, Box<dyn Error>> {
+    match Channel::get()?.trade(character_id) {
+        Some(trade) => Ok(trade),
+        None => Err(format!("Character {} is not trading", character_id).into()),
+    }
+}
+
+/// Opens a trade window the character can invite another character to.
+pub fn open(client: &Client, character: &Character, inventory: &CharacterInventory) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    if channel.trade(character.id).is_some() || channel.room_of(character.id).is_some() {
+        return Err(format!("{} is already trading", character.name).into());
+    }
+    let sender = match client.sender() {
+        Some(sender) => sender,
+        None => return Err("Unable to trade with a disconnected client".into()),
+    };
+
+    let visitor = interaction_packet::encode_visitor(character, inventory.get(InventoryType::Equipped));
+    sender.send(interaction_packet::create_trade_room(0, &[(0, &visitor)]));
+    let trade = Trade {
+        traders: vec![Trader {
+            character_id: character.id,
+            name: character.name.clone(),
+            position: 0,
+            visitor,
+            sender,
+            items: Vec::new(),
+            meso: 0,
+            confirmed: false,
+        }],
+        invited_id: None,
+    };
+    channel.add_trade(character.id, Arc::new(Mutex::new(trade)))
+}
+
+/// Invites `target_id`, who has to be in the same map and not trading, to the trade the
+/// character opened.
+pub fn invite(client: &Client, character: &Character, target_id: i32) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let trade = joined_trade(character.id)?;
+
+    let target = channel.player(target_id).filter(|target| target.character_id != character.id);
+    let in_map = match channel.map(character.map_id)?.lock() {
+        Ok(map_guard) => map_guard.player(target_id).is_some(),
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+    let target = match target {
+        Some(target) if in_map => target,
+        _ => return Err(format!("{} cannot invite character {} to trade", character.name, target_id).into()),
+    };
+    if channel.trade(target_id).is_some() || channel.room_of(target_id).is_some() {
+        client.send(create_server_message(
+            ServerMessageType::PinkText,
+            &format!("{} is busy. Please try again later.", target.name),
+        ));
+        return Ok(());
+    }
+
+    let mut trade = lock_trade(&trade)?;
+    if trade.traders.len() != 1 || trade.trader(character.id).is_none() {
+        return Err(format!("{} cannot invite anyone to the trade", character.name).into());
+    }
+    trade.invited_id = Some(target_id);
+    target.sender.send(interaction_packet::create_trade_invite(&character.name, character.id));
+    Ok(())
+}
+
+/// Turns down the invitation to the trade opened by `trade_id`.
+pub fn decline(character: &Character, trade_id: i32) -> Result<(), Box<dyn Error>> {
+    let trade = match Channel::get()?.trade(trade_id) {
+        Some(trade) => trade,
+        None => return Ok(()),
+    };
+
+    let mut trade = lock_trade(&trade)?;
+    if trade.invited_id != Some(character.id) {
+        return Err(format!("{} was not invited to trade with character {}", character.name, trade_id).into());
+    }
+    trade.invited_id = None;
+    if let Some(opener) = trade.traders.first() {
+        opener.sender.send(create_server_message(
+            ServerMessageType::PinkText,
+            &format!("{} has declined your trade request.", character.name),
+        ));
+    }
+    Ok(())
+}
+
+/// Joins the trade opened by `trade_id`, which the character has to be invited to.
+pub fn join(
+    client: &Client,
+    character: &Character,
+    inventory: &CharacterInventory,
+    trade_id: i32,
+) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let trade_mutex = match channel.trade(trade_id) {
+        Some(trade) => trade,
+        None => {
+            client.send(create_server_message(ServerMessageType::PinkText, "The trade has been closed."));
+            return Ok(());
+        }
+    };
+    if channel.trade(character.id).is_some() || channel.room_of(character.id).is_some() {
+        return Err(format!("{} is already trading", character.name).into());
+    }
+    let sender = match client.sender() {
+        Some(sender) => sender,
+        None => return Err("Unable to trade with a disconnected client".into()),
+    };
+
+    let mut trade = lock_trade(&trade_mutex)?;
+    if trade.invited_id != Some(character.id) || trade.traders.len() != 1 {
+        return Err(format!("{} was not invited to trade with character {}", character.name, trade_id).into());
+    }
+
+    let visitor = interaction_packet::encode_visitor(character, inventory.get(InventoryType::Equipped));
+    let opener = &trade.traders[0];
+    opener.sender.send(interaction_packet::create_visit(1, &visitor));
+    sender.send(interaction_packet::create_trade_room(1, &[(0, &opener.visitor), (1, &visitor)]));
+
+    trade.invited_id = None;
+    trade.traders.push(Trader {
+        character_id: character.id,
+        name: character.name.clone(),
+        position: 1,
+        visitor,
+        sender,
+        items: Vec::new(),
+        meso: 0,
+        confirmed: false,
+    });
+    channel.add_trade(character.id, trade_mutex.clone())
+}
+
+/// Shows a chat message of the character to both traders.
+pub fn chat(character_id: i32, text: &str) -> Result<(), Box<dyn Error>> {
+    let trade = joined_trade(character_id)?;
+    let trade = lock_trade(&trade)?;
+    let name = match trade.trader(character_id) {
+        Some(trader) => trader.name.clone(),
+        None => return Ok(()),
+    };
+
+    for trader in &trade.traders {
+        let position = (trader.character_id != character_id) as u8;
+        trader.sender.send(interaction_packet::create_room_chat(position, &name, text));
+    }
+    Ok(())
+}
+
+/// Offers `quantity` of the item at `source` in `slot` of the trade window, taking it out of
+/// the inventory until the trade ends.
+pub fn offer_item(
+    client: &Client,
+    character: &Character,
+    inventory: &mut CharacterInventory,
+    inventory_type: InventoryType,
+    source: i16,
+    quantity: i16,
+    slot: u8,
+) -> Result<(), Box<dyn Error>> {
+    let trade = joined_trade(character.id)?;
+    let mut trade = lock_trade(&trade)?;
+    match trade.trader(character.id) {
+        Some(trader) if trade.traders.len() == 2 && !trader.confirmed => {
+            if !(1..=MAX_TRADE_SLOTS).contains(&slot) || trader.items.iter().any(|(used, _)| *used == slot) {
+                return Err(format!("{} cannot offer an item in trade slot {}", character.name, slot).into());
+            }
+        }
+        _ => return Err(format!("{} cannot offer items in the trade", character.name).into()),
+    }
+
+    match inventory.get(inventory_type).get(source) {
+        Some(item) if item::is_tradeable(item) => {}
+        Some(item) => return Err(format!("{} tried to trade untradeable item {}", character.name, item.item_id).into()),
+        None => return Err(format!("{} has no item at position {}", character.name, source).into()),
+    }
+    let (operations, item) = item::take(inventory, inventory_type, source, quantity)?;
+    client.send(create_modify_inventory(&operations, true));
+
+    for trader in &trade.traders {
+        let position = (trader.character_id != character.id) as u8;
+        trader.sender.send(interaction_packet::create_trade_item(position, slot, &item));
+    }
+    if let Some(trader) = trade.trader_mut(character.id) {
+        trader.items.push((slot, item));
+    }
+    Ok(())
+}
+
+/// Adds `amount` mesos to the offer of the character, taking them until the trade ends.
+pub fn offer_meso(client: &Client, character: &mut Character, amount: i32) -> Result<(), Box<dyn Error>> {
+    let trade = joined_trade(character.id)?;
+    let mut trade = lock_trade(&trade)?;
+    if trade.traders.len() != 2 {
+        return Err(format!("{} offered mesos with no one to trade with", character.name).into());
+    }
+    if amount <= 0 || amount > character.meso {
+        return Err(format!("{} cannot offer {} mesos", character.name, amount).into());
+    }
+    let trader = match trade.trader_mut(character.id) {
+        Some(trader) if !trader.confirmed => trader,
+        _ => return Err(format!("{} cannot offer mesos in the trade", character.name).into()),
+    };
+    // The offered mesos are held by the trade, so what remains on the character caps the offer.
+    let total = trader
+        .meso
+        .checked_add(amount)
+        .ok_or_else(|| format!("{} offered too many mesos", character.name))?;
+
+    trader.meso = total;
+    character.meso -= amount;
+    client.send(create_update_stats(character, &[Stat::Meso], true));
+    for trader in &trade.traders {
+        let position = (trader.character_id != character.id) as u8;
+        trader.sender.send(interaction_packet::create_trade_meso(position, total));
+    }
+    Ok(())
+}
+
+/// Confirms the offers of the trade, which is completed once both traders confirmed. Nothing
+/// may be locked by the caller, as completing the trade locks both traders.
+pub fn confirm(character_id: i32) -> Result<(), Box<dyn Error>> {
+    let trade_mutex = joined_trade(character_id)?;
+    let completed = {
+        let mut trade = lock_trade(&trade_mutex)?;
+        if trade.traders.len() != 2 {
+            return Err(format!("Character {} confirmed a trade with no partner", character_id).into());
+        }
+        if let Some(trader) = trade.trader_mut(character_id) {
+            trader.confirmed = true;
+        }
+        if let Some(partner) = trade.partner(character_id) {
+            partner.sender.send(interaction_packet::create_trade_confirm());
+        }
+        trade.traders.iter().all(|trader| trader.confirmed)
+    };
+
+    match completed {
+        true => complete(&trade_mutex),
+        false => Ok(()),
+    }
+}
+
+/// Whether the character was invited to the trade opened by `trade_id`.
+pub fn is_invited(character_id: i32, trade_id: i32) -> Result<bool, Box<dyn Error>> {
+    match Channel::get()?.trade(trade_id) {
+        Some(trade) => Ok(lock_trade(&trade)?.invited_id == Some(character_id)),
+        None => Ok(false),
+    }
+}
+
+pub fn is_trading(character_id: i32) -> Result<bool, Box<dyn Error>> {
+    Ok(Channel::get()?.trade(character_id).is_some())
+}
+
+/// Leaves the trade of the character, if it is trading, giving both traders back what they
+/// offered. Nothing may be locked by the caller, as the traders are locked in turn.
+pub fn leave(character_id: i32) -> Result<(), Box<dyn Error>> {
+    match Channel::get()?.trade(character_id) {
+        Some(trade) => cancel(&trade, TradeResult::Cancelled),
+        None => Ok(()),
+    }
+}
+
+fn cancel(trade: &Mutex<Trade>, result: TradeResult) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let traders = std::mem::take(&mut lock_trade(trade)?.traders);
+
+    for trader in traders {
+        channel.remove_trade(trader.character_id);
+        let player = match channel.player(trader.character_id) {
+            Some(player) => player,
+            None => {
+                let items = trader.items.into_iter().map(|(_, item)| item).collect();
+                if let Err(error) = merchant::stash(trader.character_id, trader.meso, items, None) {
+                    warn!("Unable to leave the offer of {} with Fredrick [{}]", trader.name, error);
+                }
+                continue;
+            }
+        };
+
+        let name = trader.name.clone();
+        let returned = character::with_character(&player.client, |client, character| {
+            let mut inventory = character::lock_inventory(client)?;
+            give_back(client, character, &mut inventory, trader, result)
+        });
+        if let Err(error) = returned {
+            warn!("Unable to return the offer of {} [{}]", name, error);
+        }
+    }
+    Ok(())
+}
+
+/// Returns what the trader offered and closes its trade window. What no longer fits, as the
+/// character may have picked up items in the meantime, waits at Fredrick instead, stored in the
+/// same transaction as the character.
+fn give_back(
+    client: &Client,
+    character: &mut Character,
+    inventory: &mut CharacterInventory,
+    trader: Trader,
+    result: TradeResult,
+) -> Result<(), Box<dyn Error>> {
+    let mut returned_inventory = inventory.clone();
+    let mut operations = Vec::new();
+    let mut unreturned = Vec::new();
+    for (_, item) in trader.items {
+        match item::add_item(&mut returned_inventory, item.clone()) {
+            Ok(mut added) => operations.append(&mut added),
+            Err(_) => unreturned.push(item),
+        }
+    }
+    let (meso, unreturned_meso) = match character.meso.checked_add(trader.meso) {
+        Some(meso) => (meso, 0),
+        None => (character.meso, trader.meso),
+    };
+
+    // What fits is returned even if the rest cannot be stashed.
+    let stashing = !unreturned.is_empty() || unreturned_meso > 0;
+    let stashed = match stashing {
+        true => {
+            let owner = CharacterUpdate {
+                character_id: character.id,
+                meso,
+                inventory: Some(returned_inventory.rows(character.id)),
+            };
+            merchant::stash(character.id, unreturned_meso, unreturned, Some(&owner))
+        }
+        false => Ok(()),
+    };
+    *inventory = returned_inventory;
+    character.meso = meso;
+    client.send(create_modify_inventory(&operations, true));
+    client.send(create_update_stats(character, &[Stat::Meso], true));
+    client.send(interaction_packet::create_trade_exit(trader.position, result));
+    if stashing && stashed.is_ok() {
+        client.send(create_server_message(
+            ServerMessageType::PinkText,
+            "Your inventory is full. What you offered in the trade waits at Fredrick.",
+        ));
+    }
+    stashed
+}
+
+/// Exchanges the offers of both traders once both confirmed and have room for what they
+/// receive. The exchange is stored in a single database transaction before it is made in
+/// memory, so a trade that cannot be stored gives both traders their offers back.
+fn complete(trade_mutex: &Mutex<Trade>) -> Result<(), Box<dyn Error>> {
+    let channel = Channel::get()?;
+    let mut character_ids: Vec<i32> = lock_trade(trade_mutex)?
+        .traders
+        .iter()
+        .map(|trader| trader.character_id)
+        .collect();
+    // Both traders are locked in the order of their ids, the only order two clients are ever
+    // locked in.
+    character_ids.sort_unstable();
+    let players: Vec<_> = character_ids.iter().filter_map(|id| channel.player(*id)).collect();
+    if players.len() != 2 {
+        return cancel(trade_mutex, TradeResult::Failed);
+    }
+
+    let first_client = character::lock_client(&players[0].client)?;
+    let second_client = character::lock_client(&players[1].client)?;
+    let mut first_character = character::lock_character(&first_client)?;
+    let mut second_character = character::lock_character(&second_client)?;
+    let mut first_inventory = character::lock_inventory(&first_client)?;
+    let mut second_inventory = character::lock_inventory(&second_client)?;
+
+    let mut trade = lock_trade(trade_mutex)?;
+    if trade.traders.len() != 2 || !trade.traders.iter().all(|trader| trader.confirmed) {
+        // The trade was cancelled in the meantime.
+        return Ok(());
+    }
+    let mut traders = std::mem::take(&mut trade.traders);
+    drop(trade);
+    for trader in &traders {
+        channel.remove_trade(trader.character_id);
+    }
+    traders.sort_unstable_by_key(|trader| trader.character_id);
+    let [first, second]: [Trader; 2] = match traders.try_into() {
+        Ok(traders) => traders,
+        Err(_) => return Err("A trade lost a trader".into()),
+    };
+
+    let exchanged = match (
+        first_character.meso.checked_add(second.meso),
+        second_character.meso.checked_add(first.meso),
+    ) {
+        (Some(first_meso), Some(second_meso)) => exchange(&first_inventory, &second)
+            .zip(exchange(&second_inventory, &first))
+            .map(|(first_received, second_received)| (first_meso, first_received, second_meso, second_received)),
+        _ => None,
+    };
+    let (first_meso, (first_received, first_operations), second_meso, (second_received, second_operations)) =
+        match exchanged {
+            Some(exchanged) => exchanged,
+            None => {
+                for (client, character) in [(&first_client, &first_character), (&second_client, &second_character)] {
+                    client.send(create_server_message(
+                        ServerMessageType::PinkText,
+                        &format!("{} does not have enough room for the trade.", character.name),
+                    ));
+                }
+                give_back(&first_client, &mut first_character, &mut first_inventory, first, TradeResult::Failed)?;
+                give_back(&second_client, &mut second_character, &mut second_inventory, second, TradeResult::Failed)?;
+                return Ok(());
+            }
+        };
+
+    let logs = [log_of(&first, second.character_id), log_of(&second, first.character_id)];
+    let first_update = CharacterUpdate {
+        character_id: first_character.id,
+        meso: first_meso,
+        inventory: Some(first_received.rows(first_character.id)),
+    };
+    let second_update = CharacterUpdate {
+        character_id: second_character.id,
+        meso: second_meso,
+        inventory: Some(second_received.rows(second_character.id)),
+    };
+    if let Err(error) = TradeLog::save_exchange([&first_update, &second_update], &logs) {
+        give_back(&first_client, &mut first_character, &mut first_inventory, first, TradeResult::Failed)?;
+        give_back(&second_client, &mut second_character, &mut second_inventory, second, TradeResult::Failed)?;
+        return Err(format!(
+            "Unable to store the trade of {} and {} [{}]",
+            first_character.name, second_character.name, error
+        )
+        .into());
+    }
+
+    let (first_given, second_given) = (first.meso, second.meso);
+    *first_inventory = first_received;
+    *second_inventory = second_received;
+    first_character.meso = first_meso;
+    second_character.meso = second_meso;
+    receive(&first_client, &first_character, &first_operations, &second, first_given);
+    receive(&second_client, &second_character, &second_operations, &first, second_given);
+    info!("{} and {} completed a trade", first_character.name, second_character.name);
+    Ok(())
+}
+
+/// Adds the offer of `partner` to a copy of the inventory, or None if it does not fit.
+fn exchange(inventory: &CharacterInventory, partner: &Trader) -> Option<(CharacterInventory, Vec<InventoryOperation>)> {
+    let mut inventory = inventory.clone();
+    let mut operations = Vec::new();
+    for (_, item) in &partner.items {
+        operations.append(&mut item::add_item(&mut inventory, item.clone()).ok()?);
+    }
+    Some((inventory, operations))
+}
+
+/// Shows the character the offer of `partner` it received, having given `given` mesos in return.
+fn receive(client: &Client, character: &Character, operations: &[InventoryOperation], partner: &Trader, given: i32) {
+    if partner.meso != given {
+        meso::log(
+            character,
+            partner.meso - given,
+            MesoReason::Trade,
+            &format!("trade with {}", partner.name),
+        );
+    }
+
+    client.send(create_modify_inventory(operations, true));
+    client.send(create_update_stats(character, &[Stat::Meso], true));
+    client.send(interaction_packet::create_trade_exit(
+        (partner.position == 0) as u8,
+        TradeResult::Completed,
+    ));
+}
+
+fn log_of(trader: &Trader, partner_id: i32) -> NewTradeLog {
+    NewTradeLog {
+        character_id: trader.character_id,
+        partner_id,
+        meso: trader.meso,
+        items: trader
+            .items
+            .iter()
+            .map(|(_, item)| format!("{} x {}", item.item_id, item.quantity))
+            .collect::<Vec<_>>()
+            .join(", "),
+        log_date: SystemTime::now(),
+    }
+}
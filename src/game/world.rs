@@ -0,0 +1,840 @@
+use crate::db::model::guild as guild_model;
+use crate::game::guild::{self, Guild, GuildChange, GuildMember, GuildRequest, GuildResult};
+use crate::game::party::{Party, PartyChange, PartyMember, PartyRequest, PartyResult, MAX_PARTY_MEMBERS};
+use crate::net::interserver::{self, ChannelMessage, PlayerStatus, WorldMessage};
+use crate::net::packet::message::ChatGroup;
+use log::{debug, warn};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// Job of beginners, who cannot create parties.
+const BEGINNER_JOB: i16 = 0;
+
+/// What the world server keeps track of for its channel servers.
+#[derive(Default)]
+struct WorldState {
+    players: HashMap<i32, PlayerStatus>,
+    parties: HashMap<i32, Party>,
+    /// The party of every character in one, online or not.
+    party_ids: HashMap<i32, i32>,
+    /// The party each invited character was invited to, and who invited it.
+    invitations: HashMap<i32, (i32, i32)>,
+    last_party_id: i32,
+    /// The buddies every online character accepted.
+    buddies: HashMap<i32, Vec<i32>>,
+    /// Guilds loaded from the database since one of their members logged in.
+    guilds: HashMap<i32, Guild>,
+    /// The guild of every member of a loaded guild.
+    guild_ids: HashMap<i32, i32>,
+    /// The guild each invited character was invited to, and who invited it.
+    guild_invitations: HashMap<i32, (i32, i32)>,
+}
+
+impl WorldState {
+    /// Whether both characters are online and have each other on their buddy lists.
+    fn are_buddies(&self, character_id: i32, buddy_id: i32) -> bool {
+        let has = |owner_id: i32, id: i32| self.buddies.get(&owner_id).is_some_and(|ids| ids.contains(&id));
+        has(character_id, buddy_id) && has(buddy_id, character_id)
+    }
+}
+
+/// The connection of a channel server, told apart from the ones it replaced by its id.
+struct ChannelLink {
+    id: u64,
+    stream: TcpStream,
+}
+
+/// The world server, which keeps the state its channel servers share.
+pub struct World {
+    world_id: u8,
+    /// Locked after `state` whenever both are needed.
+    channels: Mutex<HashMap<u8, ChannelLink>>,
+    last_link_id: AtomicU64,
+    state: Mutex<WorldState>,
+}
+
+static WORLD_INSTANCE: OnceCell<World> = OnceCell::new();
+
+impl PartyMember {
+    fn from_status(status: &PlayerStatus) -> PartyMember {
+        PartyMember {
+            character_id: status.character_id,
+            name: status.name.clone(),
+            job: status.job,
+            level: status.level,
+            channel_id: Some(status.channel_id),
+            map_id: status.map_id,
+        }
+    }
+}
+
+impl GuildMember {
+    fn from_status(status: &PlayerStatus, rank: u8) -> GuildMember {
+        GuildMember {
+            character_id: status.character_id,
+            name: status.name.clone(),
+            job: status.job,
+            level: status.level,
+            rank,
+            online: true,
+        }
+    }
+}
+
+impl World {
+    pub fn get() -> Result<&'static World, Box<dyn Error>> {
+        match WORLD_INSTANCE.get() {
+            Some(world) => Ok(world),
+            None => Err("World not initialized".into()),
+        }
+    }
+
+    pub fn init(world_id: u8) -> Result<(), Box<dyn Error>> {
+        match WORLD_INSTANCE.set(World {
+            world_id,
+            channels: Mutex::new(HashMap::new()),
+            last_link_id: AtomicU64::new(0),
+            state: Mutex::new(WorldState::default()),
+        }) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("World already initialized".into()),
+        }
+    }
+
+    fn lock_state(&self) -> Option<MutexGuard<'_, WorldState>> {
+        match self.state.lock() {
+            Ok(guard) => Some(guard),
+            Err(error) => {
+                warn!("Unable to lock WorldState Mutex [{}]", error);
+                None
+            }
+        }
+    }
+
+    /// Sends the messages of the channel through `stream` from now on, closing the connection
+    /// it replaces. Returns the id the connection is removed with.
+    pub fn add_channel(&self, channel_id: u8, stream: TcpStream) -> u64 {
+        let id = self.last_link_id.fetch_add(1, Ordering::Relaxed) + 1;
+        match self.channels.lock() {
+            Ok(mut channels) => {
+                if let Some(replaced) = channels.insert(channel_id, ChannelLink { id, stream }) {
+                    let _ = replaced.stream.shutdown(Shutdown::Both);
+                }
+            }
+            Err(error) => warn!("Unable to lock channels Mutex [{}]", error),
+        }
+        id
+    }
+
+    /// Forgets the channel, along with the players that were on it, unless the connection
+    /// `link_id` was already replaced by a newer one.
+    pub fn remove_channel(&self, channel_id: u8, link_id: u64) {
+        match self.channels.lock() {
+            Ok(mut channels) => match channels.get(&channel_id) {
+                Some(link) if link.id == link_id => {
+                    channels.remove(&channel_id);
+                }
+                _ => return,
+            },
+            Err(error) => return warn!("Unable to lock channels Mutex [{}]", error),
+        }
+
+        let mut state = match self.lock_state() {
+            Some(state) => state,
+            None => return,
+        };
+        let players: Vec<i32> = state
+            .players
+            .values()
+            .filter(|status| status.channel_id == channel_id)
+            .map(|status| status.character_id)
+            .collect();
+        for character_id in players {
+            self.player_offline(&mut state, character_id);
+        }
+    }
+
+    fn send(&self, channel_id: u8, message: &WorldMessage) {
+        let mut channels = match self.channels.lock() {
+            Ok(channels) => channels,
+            Err(error) => return warn!("Unable to lock channels Mutex [{}]", error),
+        };
+
+        if let Some(link) = channels.get_mut(&channel_id) {
+            if let Err(error) = interserver::write_message(&mut link.stream, &message.encode()) {
+                warn!("Unable to send message to channel {} [{}]", channel_id, error);
+            }
+        }
+    }
+
+    fn broadcast(&self, message: &WorldMessage) {
+        let channel_ids: Vec<u8> = match self.channels.lock() {
+            Ok(channels) => channels.keys().copied().collect(),
+            Err(error) => return warn!("Unable to lock channels Mutex [{}]", error),
+        };
+
+        for channel_id in channel_ids {
+            self.send(channel_id, message);
+        }
+    }
+
+    /// Sends the message to the channel `character_id` plays on, if it is online.
+    fn send_to_player(&self, state: &WorldState, character_id: i32, message: &WorldMessage) {
+        if let Some(status) = state.players.get(&character_id) {
+            self.send(status.channel_id, message);
+        }
+    }
+
+    fn update_party(&self, party: &Party, change: PartyChange) {
+        self.broadcast(&WorldMessage::PartyUpdate(party.clone(), change));
+    }
+
+    fn update_guild(&self, guild: &Guild, change: GuildChange) {
+        self.broadcast(&WorldMessage::GuildUpdate(guild.clone(), change));
+    }
+
+    pub fn handle(&self, message: ChannelMessage) {
+        let mut state = match self.lock_state() {
+            Some(state) => state,
+            None => return,
+        };
+
+        match message {
+            ChannelMessage::RegisterChannel(channel_id, _) => {
+                warn!("Channel {} registered itself twice", channel_id)
+            }
+            ChannelMessage::PlayerOnline(status) => self.player_online(&mut state, status),
+            ChannelMessage::PlayerOffline(character_id) => self.player_offline(&mut state, character_id),
+            ChannelMessage::Party(character_id, request) => {
+                if let Err(error) = self.party_request(&mut state, character_id, request) {
+                    warn!("Rejected party request, possibly a hack attempt [{}]", error);
+                }
+            }
+            ChannelMessage::PartyChat(character_id, text) => self.party_chat(&state, character_id, text),
+            ChannelMessage::Guild(character_id, request) => {
+                let paid = matches!(request, GuildRequest::Create(_) | GuildRequest::ChangeEmblem(_));
+                if let Err(error) = self.guild_request(&mut state, character_id, request) {
+                    warn!("Rejected guild request, possibly a hack attempt [{}]", error);
+                    // The channel gives back what the character paid for the request.
+                    if paid {
+                        self.send_to_player(&state, character_id, &WorldMessage::GuildFailed(character_id));
+                    }
+                }
+            }
+            ChannelMessage::GuildChat(character_id, text) => self.guild_chat(&state, character_id, text),
+            ChannelMessage::Buddies(character_id, buddy_ids) => self.update_buddies(&mut state, character_id, buddy_ids),
+            ChannelMessage::BuddyRequest {
+                character_id,
+                from_id,
+                from_name,
+            } => {
+                if let Some(channel_id) = state.players.get(&from_id).map(|status| status.channel_id) {
+                    let request = WorldMessage::BuddyRequest {
+                        character_id,
+                        from_id,
+                        from_name,
+                        channel_id,
+                    };
+                    self.send_to_player(&state, character_id, &request);
+                }
+            }
+        }
+    }
+
+    fn player_online(&self, state: &mut WorldState, status: PlayerStatus) {
+        let character_id = status.character_id;
+        let party_id = state.party_ids.get(&character_id).copied();
+        if let Some(party) = party_id.and_then(|party_id| state.parties.get_mut(&party_id)) {
+            if let Some(member) = party.member_mut(character_id) {
+                *member = PartyMember::from_status(&status);
+            }
+            self.update_party(party, PartyChange::Silent);
+        }
+
+        let logged_in = !state.players.contains_key(&character_id);
+        if let Err(error) = self.guild_member_online(state, &status, logged_in) {
+            warn!("Unable to update the guild of {} [{}]", status.name, error);
+        }
+        state.players.insert(character_id, status);
+    }
+
+    /// Loads the guild of a character that just logged in and shows it online, and shows the new
+    /// job or level of a member to its guild.
+    fn guild_member_online(
+        &self,
+        state: &mut WorldState,
+        status: &PlayerStatus,
+        logged_in: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let character_id = status.character_id;
+        if logged_in && !state.guild_ids.contains_key(&character_id) {
+            if let Some(member) = guild_model::GuildMember::get_by_character(character_id)? {
+                if let Some(guild) = Guild::load(member.guild_id)? {
+                    for member in &guild.members {
+                        state.guild_ids.insert(member.character_id, guild.id);
+                    }
+                    state.guilds.insert(guild.id, guild);
+                }
+            }
+        }
+
+        let guild_id = state.guild_ids.get(&character_id).copied();
+        let guild = match guild_id.and_then(|guild_id| state.guilds.get_mut(&guild_id)) {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+        let member = match guild.member_mut(character_id) {
+            Some(member) => member,
+            None => return Ok(()),
+        };
+
+        let updated = member.job != status.job || member.level != status.level;
+        if updated {
+            member.job = status.job;
+            member.level = status.level;
+            guild_model::GuildMember::update(character_id, status.job, status.level, member.rank as i16)?;
+        }
+        if logged_in {
+            member.online = true;
+            self.update_guild(guild, GuildChange::MemberOnline(character_id, true));
+        }
+        if updated {
+            self.update_guild(guild, GuildChange::MemberUpdated(character_id));
+        }
+        Ok(())
+    }
+
+    fn player_offline(&self, state: &mut WorldState, character_id: i32) {
+        self.update_buddies(state, character_id, Vec::new());
+        state.buddies.remove(&character_id);
+        state.players.remove(&character_id);
+        state.invitations.remove(&character_id);
+        state.guild_invitations.remove(&character_id);
+
+        let party_id = state.party_ids.get(&character_id).copied();
+        if let Some(party) = party_id.and_then(|party_id| state.parties.get_mut(&party_id)) {
+            if let Some(member) = party.member_mut(character_id) {
+                member.channel_id = None;
+            }
+            self.update_party(party, PartyChange::Silent);
+        }
+
+        let guild_id = state.guild_ids.get(&character_id).copied();
+        if let Some(guild) = guild_id.and_then(|guild_id| state.guilds.get_mut(&guild_id)) {
+            if let Some(member) = guild.member_mut(character_id) {
+                member.online = false;
+            }
+            self.update_guild(guild, GuildChange::MemberOnline(character_id, false));
+        }
+    }
+
+    fn party_request(
+        &self,
+        state: &mut WorldState,
+        character_id: i32,
+        request: PartyRequest,
+    ) -> Result<(), Box<dyn Error>> {
+        let status = match state.players.get(&character_id) {
+            Some(status) => status,
+            None => return Err(format!("Character {} is not online", character_id).into()),
+        };
+        let party_id = state.party_ids.get(&character_id).copied();
+
+        let result = match (request, party_id) {
+            (PartyRequest::Create, Some(_)) => PartyResult::AlreadyJoined,
+            (PartyRequest::Create, None) if status.job == BEGINNER_JOB => PartyResult::BeginnerCannotCreate,
+            (PartyRequest::Create, None) => {
+                state.last_party_id += 1;
+                let party = Party {
+                    id: state.last_party_id,
+                    leader_id: character_id,
+                    members: vec![PartyMember::from_status(status)],
+                };
+                self.update_party(&party, PartyChange::Created);
+                state.party_ids.insert(character_id, party.id);
+                state.parties.insert(party.id, party);
+                return Ok(());
+            }
+            (PartyRequest::Accept(invited_party_id), _) => {
+                return self.accept_invitation(state, character_id, invited_party_id);
+            }
+            (PartyRequest::Deny(inviter_name), _) => {
+                match state.invitations.remove(&character_id) {
+                    Some((_, inviter_id)) => {
+                        let denied = WorldMessage::PartyResult(inviter_id, PartyResult::Denied(status.name.clone()));
+                        self.send_to_player(state, inviter_id, &denied);
+                    }
+                    None => debug!("{} denied an invitation of {} that expired", status.name, inviter_name),
+                }
+                return Ok(());
+            }
+            (_, None) => PartyResult::NotInParty,
+            (request, Some(party_id)) => return self.member_request(state, character_id, party_id, request),
+        };
+
+        self.send_to_player(state, character_id, &WorldMessage::PartyResult(character_id, result));
+        Ok(())
+    }
+
+    fn accept_invitation(
+        &self,
+        state: &mut WorldState,
+        character_id: i32,
+        party_id: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        match state.invitations.remove(&character_id) {
+            Some((invited_party_id, _)) if invited_party_id == party_id => {}
+            _ => return Err(format!("Character {} was not invited to party {}", character_id, party_id).into()),
+        }
+
+        let result = match (state.party_ids.contains_key(&character_id), state.parties.get_mut(&party_id)) {
+            (true, _) => PartyResult::AlreadyJoined,
+            (false, None) => PartyResult::NotFound,
+            (false, Some(party)) if party.members.len() >= MAX_PARTY_MEMBERS => PartyResult::Full,
+            (false, Some(party)) => {
+                if let Some(status) = state.players.get(&character_id) {
+                    party.members.push(PartyMember::from_status(status));
+                    self.update_party(party, PartyChange::Joined(character_id));
+                    state.party_ids.insert(character_id, party_id);
+                }
+                return Ok(());
+            }
+        };
+
+        self.send_to_player(state, character_id, &WorldMessage::PartyResult(character_id, result));
+        Ok(())
+    }
+
+    /// Applies a request of a member of the party.
+    fn member_request(
+        &self,
+        state: &mut WorldState,
+        character_id: i32,
+        party_id: i32,
+        request: PartyRequest,
+    ) -> Result<(), Box<dyn Error>> {
+        let party = match state.parties.get_mut(&party_id) {
+            Some(party) => party,
+            None => return Err(format!("Party {} does not exist", party_id).into()),
+        };
+        let leader = party.leader_id == character_id;
+
+        match request {
+            PartyRequest::Leave if leader => {
+                let party = match state.parties.remove(&party_id) {
+                    Some(party) => party,
+                    None => return Ok(()),
+                };
+                for member in &party.members {
+                    state.party_ids.remove(&member.character_id);
+                }
+                state.invitations.retain(|_, (invited_party_id, _)| *invited_party_id != party_id);
+                self.update_party(&party, PartyChange::Disbanded);
+            }
+            PartyRequest::Leave => self.remove_member(state, party_id, character_id, false),
+            PartyRequest::Expel(target_id) if leader && target_id != character_id => {
+                if party.member(target_id).is_none() {
+                    return Err(format!("Character {} is not in party {}", target_id, party_id).into());
+                }
+                self.remove_member(state, party_id, target_id, true);
+            }
+            PartyRequest::ChangeLeader(target_id) if leader => {
+                match party.member(target_id) {
+                    Some(member) if member.channel_id.is_some() => {}
+                    _ => return Err(format!("Character {} cannot lead party {}", target_id, party_id).into()),
+                }
+                party.leader_id = target_id;
+                self.update_party(party, PartyChange::LeaderChanged);
+            }
+            PartyRequest::Invite(name) if leader => {
+                let result = match state.players.values().find(|status| status.name.eq_ignore_ascii_case(&name)) {
+                    None => Some(PartyResult::NotFound),
+                    Some(target) if state.party_ids.contains_key(&target.character_id) => {
+                        Some(PartyResult::AlreadyJoined)
+                    }
+                    Some(_) if party.members.len() >= MAX_PARTY_MEMBERS => Some(PartyResult::Full),
+                    Some(target) => {
+                        let target_id = target.character_id;
+                        let inviter_name = party.member(character_id).map_or(String::new(), |member| member.name.clone());
+                        state.invitations.insert(target_id, (party_id, character_id));
+                        let invitation = WorldMessage::PartyInvitation {
+                            character_id: target_id,
+                            party_id,
+                            inviter_name,
+                        };
+                        self.send_to_player(state, target_id, &invitation);
+                        None
+                    }
+                };
+
+                if let Some(result) = result {
+                    self.send_to_player(state, character_id, &WorldMessage::PartyResult(character_id, result));
+                }
+            }
+            _ => return Err(format!("Character {} may not do that in party {}", character_id, party_id).into()),
+        }
+        Ok(())
+    }
+
+    fn remove_member(&self, state: &mut WorldState, party_id: i32, character_id: i32, expelled: bool) {
+        let party = match state.parties.get_mut(&party_id) {
+            Some(party) => party,
+            None => return,
+        };
+        let name = match party.member(character_id) {
+            Some(member) => member.name.clone(),
+            None => return,
+        };
+
+        party.members.retain(|member| member.character_id != character_id);
+        state.party_ids.remove(&character_id);
+        self.update_party(
+            party,
+            PartyChange::Left {
+                character_id,
+                name,
+                expelled,
+            },
+        );
+    }
+
+    /// Passes the message on to the other members of the party of `character_id` that are online.
+    fn party_chat(&self, state: &WorldState, character_id: i32, text: String) {
+        let party = match state
+            .party_ids
+            .get(&character_id)
+            .and_then(|party_id| state.parties.get(party_id))
+        {
+            Some(party) => party,
+            None => return,
+        };
+        let name = match party.member(character_id) {
+            Some(member) => member.name.clone(),
+            None => return,
+        };
+
+        let recipients = party
+            .members
+            .iter()
+            .filter(|member| member.character_id != character_id && member.channel_id.is_some())
+            .map(|member| member.character_id)
+            .collect();
+        self.broadcast(&WorldMessage::MultiChat {
+            group: ChatGroup::Party,
+            recipients,
+            name,
+            text,
+        });
+    }
+
+    fn guild_request(
+        &self,
+        state: &mut WorldState,
+        character_id: i32,
+        request: GuildRequest,
+    ) -> Result<(), Box<dyn Error>> {
+        let status = match state.players.get(&character_id) {
+            Some(status) => status,
+            None => return Err(format!("Character {} is not online", character_id).into()),
+        };
+        let guild_id = state.guild_ids.get(&character_id).copied();
+
+        let result = match (request, guild_id) {
+            (GuildRequest::Create(_), Some(_)) => GuildResult::AlreadyJoined,
+            (GuildRequest::Create(name), None) => {
+                if !guild::is_valid_name(&name) {
+                    return Err(format!("{} is not a valid guild name", name).into());
+                }
+
+                let new_guild = guild_model::NewGuild {
+                    world_id: self.world_id as i16,
+                    name,
+                    leader_id: character_id,
+                    rank_titles: guild::DEFAULT_RANK_TITLES.join(","),
+                    capacity: guild::DEFAULT_CAPACITY as i16,
+                };
+                let leader = guild_model::NewGuildMember {
+                    guild_id: 0,
+                    character_id,
+                    name: status.name.clone(),
+                    job: status.job,
+                    level: status.level,
+                    rank: guild::MASTER_RANK as i16,
+                };
+                match guild_model::Guild::create(&new_guild, leader)? {
+                    Some(record) => {
+                        let leader = GuildMember::from_status(status, guild::MASTER_RANK);
+                        let guild = Guild::from_record(record, vec![leader]);
+                        self.update_guild(&guild, GuildChange::Created);
+                        state.guild_ids.insert(character_id, guild.id);
+                        state.guilds.insert(guild.id, guild);
+                        return Ok(());
+                    }
+                    None => GuildResult::NameInUse,
+                }
+            }
+            (GuildRequest::Accept(invited_guild_id), _) => {
+                return self.accept_guild_invitation(state, character_id, invited_guild_id);
+            }
+            (GuildRequest::Deny(inviter_name), _) => {
+                match state.guild_invitations.remove(&character_id) {
+                    Some((_, inviter_id)) => {
+                        let denied = WorldMessage::GuildResult(inviter_id, GuildResult::Denied(status.name.clone()));
+                        self.send_to_player(state, inviter_id, &denied);
+                    }
+                    None => debug!("{} denied a guild invitation of {} that expired", status.name, inviter_name),
+                }
+                return Ok(());
+            }
+            (_, None) => return Err(format!("Character {} is not in a guild", character_id).into()),
+            (request, Some(guild_id)) => return self.guild_member_request(state, character_id, guild_id, request),
+        };
+
+        self.send_to_player(state, character_id, &WorldMessage::GuildResult(character_id, result));
+        Ok(())
+    }
+
+    fn accept_guild_invitation(
+        &self,
+        state: &mut WorldState,
+        character_id: i32,
+        guild_id: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        match state.guild_invitations.remove(&character_id) {
+            Some((invited_guild_id, _)) if invited_guild_id == guild_id => {}
+            _ => return Err(format!("Character {} was not invited to guild {}", character_id, guild_id).into()),
+        }
+
+        let result = match (state.guild_ids.contains_key(&character_id), state.guilds.get_mut(&guild_id)) {
+            (true, _) => GuildResult::AlreadyJoined,
+            (false, None) => GuildResult::NotFound,
+            (false, Some(guild)) if guild.is_full() => GuildResult::Full,
+            (false, Some(guild)) => {
+                if let Some(status) = state.players.get(&character_id) {
+                    let member = GuildMember::from_status(status, guild::MEMBER_RANK);
+                    guild_model::GuildMember::create(&guild_model::NewGuildMember {
+                        guild_id,
+                        character_id,
+                        name: member.name.clone(),
+                        job: member.job,
+                        level: member.level,
+                        rank: member.rank as i16,
+                    })?;
+                    guild.members.push(member);
+                    self.update_guild(guild, GuildChange::Joined(character_id));
+                    state.guild_ids.insert(character_id, guild_id);
+                }
+                return Ok(());
+            }
+        };
+
+        self.send_to_player(state, character_id, &WorldMessage::GuildResult(character_id, result));
+        Ok(())
+    }
+
+    /// Applies a request of a member of the guild, which its rank has to allow.
+    fn guild_member_request(
+        &self,
+        state: &mut WorldState,
+        character_id: i32,
+        guild_id: i32,
+        request: GuildRequest,
+    ) -> Result<(), Box<dyn Error>> {
+        let guild = match state.guilds.get_mut(&guild_id) {
+            Some(guild) => guild,
+            None => return Err(format!("Guild {} is not loaded", guild_id).into()),
+        };
+        let rank = match guild.member(character_id) {
+            Some(member) => member.rank,
+            None => return Err(format!("Character {} is not in guild {}", character_id, guild_id).into()),
+        };
+        let master = rank == guild::MASTER_RANK;
+        let officer = rank <= guild::JR_MASTER_RANK;
+
+        match request {
+            GuildRequest::Leave if !master => self.remove_guild_member(state, guild_id, character_id, false)?,
+            GuildRequest::Expel(target_id) if officer => {
+                match guild.member(target_id) {
+                    Some(target) if target.rank > rank => {}
+                    _ => return Err(format!("Character {} cannot expel {}", character_id, target_id).into()),
+                }
+                self.remove_guild_member(state, guild_id, target_id, true)?;
+            }
+            GuildRequest::Invite(name) if officer => {
+                let result = match state.players.values().find(|status| status.name.eq_ignore_ascii_case(&name)) {
+                    None => Some(GuildResult::NotFound),
+                    Some(target) if state.guild_ids.contains_key(&target.character_id) => {
+                        Some(GuildResult::AlreadyJoined)
+                    }
+                    Some(_) if guild.is_full() => Some(GuildResult::Full),
+                    Some(target) => {
+                        let target_id = target.character_id;
+                        let inviter_name = guild.member(character_id).map_or(String::new(), |member| member.name.clone());
+                        state.guild_invitations.insert(target_id, (guild_id, character_id));
+                        let invitation = WorldMessage::GuildInvitation {
+                            character_id: target_id,
+                            guild_id,
+                            inviter_name,
+                        };
+                        self.send_to_player(state, target_id, &invitation);
+                        None
+                    }
+                };
+
+                if let Some(result) = result {
+                    self.send_to_player(state, character_id, &WorldMessage::GuildResult(character_id, result));
+                }
+            }
+            GuildRequest::ChangeRankTitles(rank_titles) if master && rank_titles.len() == guild.rank_titles.len() => {
+                // The titles are stored separated by commas.
+                if rank_titles.iter().any(|title| title.contains(',')) {
+                    return Err(format!("Character {} asked for invalid rank titles", character_id).into());
+                }
+                guild.rank_titles = rank_titles;
+                guild.save()?;
+                self.update_guild(guild, GuildChange::RankTitlesChanged);
+            }
+            GuildRequest::ChangeRank(target_id, new_rank)
+                if officer && new_rank > rank && new_rank <= guild::MEMBER_RANK =>
+            {
+                let target = match guild.member_mut(target_id) {
+                    Some(target) if target.rank > rank => target,
+                    _ => return Err(format!("Character {} cannot change the rank of {}", character_id, target_id).into()),
+                };
+                guild_model::GuildMember::update(target_id, target.job, target.level, new_rank as i16)?;
+                target.rank = new_rank;
+                self.update_guild(guild, GuildChange::RankChanged(target_id));
+            }
+            GuildRequest::ChangeEmblem(emblem) if master => {
+                guild.emblem = emblem;
+                guild.save()?;
+                self.update_guild(guild, GuildChange::EmblemChanged);
+            }
+            GuildRequest::ChangeNotice(notice) if officer && notice.len() <= guild::MAX_NOTICE_LENGTH => {
+                guild.notice = notice;
+                guild.save()?;
+                self.update_guild(guild, GuildChange::NoticeChanged);
+            }
+            GuildRequest::GainPoints(amount) => {
+                guild.points = guild.points.saturating_add(amount).max(0);
+                guild.save()?;
+                self.update_guild(guild, GuildChange::PointsChanged);
+            }
+            GuildRequest::Disband if master => {
+                guild_model::Guild::delete(guild_id)?;
+                let guild = match state.guilds.remove(&guild_id) {
+                    Some(guild) => guild,
+                    None => return Ok(()),
+                };
+                for member in &guild.members {
+                    state.guild_ids.remove(&member.character_id);
+                }
+                state.guild_invitations.retain(|_, (invited_guild_id, _)| *invited_guild_id != guild_id);
+                self.update_guild(&guild, GuildChange::Disbanded);
+            }
+            _ => return Err(format!("Character {} may not do that in guild {}", character_id, guild_id).into()),
+        }
+        Ok(())
+    }
+
+    fn remove_guild_member(
+        &self,
+        state: &mut WorldState,
+        guild_id: i32,
+        character_id: i32,
+        expelled: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let guild = match state.guilds.get_mut(&guild_id) {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+        let name = match guild.member(character_id) {
+            Some(member) => member.name.clone(),
+            None => return Ok(()),
+        };
+
+        guild_model::GuildMember::delete(character_id)?;
+        guild.members.retain(|member| member.character_id != character_id);
+        state.guild_ids.remove(&character_id);
+        self.update_guild(
+            guild,
+            GuildChange::Left {
+                character_id,
+                name,
+                expelled,
+            },
+        );
+        Ok(())
+    }
+
+    /// Passes the message on to the other members of the guild of `character_id` that are online.
+    fn guild_chat(&self, state: &WorldState, character_id: i32, text: String) {
+        let guild = match state
+            .guild_ids
+            .get(&character_id)
+            .and_then(|guild_id| state.guilds.get(guild_id))
+        {
+            Some(guild) => guild,
+            None => return,
+        };
+        let name = match guild.member(character_id) {
+            Some(member) => member.name.clone(),
+            None => return,
+        };
+
+        let recipients = guild
+            .members
+            .iter()
+            .filter(|member| member.character_id != character_id && member.online)
+            .map(|member| member.character_id)
+            .collect();
+        self.broadcast(&WorldMessage::MultiChat {
+            group: ChatGroup::Guild,
+            recipients,
+            name,
+            text,
+        });
+    }
+
+    /// Shows the character and the buddies it shares with others to each other, and shows it
+    /// offline to those it no longer shares.
+    fn update_buddies(&self, state: &mut WorldState, character_id: i32, buddy_ids: Vec<i32>) {
+        let channel_id = state.players.get(&character_id).map(|status| status.channel_id);
+
+        let previous = state.buddies.get(&character_id).cloned().unwrap_or_default();
+        for buddy_id in previous.iter().filter(|buddy_id| !buddy_ids.contains(buddy_id)) {
+            if state.are_buddies(character_id, *buddy_id) {
+                let offline = WorldMessage::BuddyChannel {
+                    character_id: *buddy_id,
+                    buddy_id: character_id,
+                    channel_id: None,
+                };
+                self.send_to_player(state, *buddy_id, &offline);
+            }
+        }
+
+        state.buddies.insert(character_id, buddy_ids.clone());
+        for buddy_id in buddy_ids {
+            if !state.are_buddies(character_id, buddy_id) {
+                continue;
+            }
+
+            let buddy_channel = WorldMessage::BuddyChannel {
+                character_id,
+                buddy_id,
+                channel_id: state.players.get(&buddy_id).map(|status| status.channel_id),
+            };
+            self.send_to_player(state, character_id, &buddy_channel);
+            let own_channel = WorldMessage::BuddyChannel {
+                character_id: buddy_id,
+                buddy_id: character_id,
+                channel_id,
+            };
+            self.send_to_player(state, buddy_id, &own_channel);
+        }
+    }
+}
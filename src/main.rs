@@ -6,13 +6,56 @@ use ini::Ini;
 
 use std::env;
 use std::fs::File;
+use std::path::Path;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
 
 use log::*;
 use simplelog::*;
 
+mod data;
 mod db;
 mod defaults;
+mod game;
 mod net;
+mod script;
+
+/// Reads `key` from `section` of the general settings, falling back to `default`.
+fn setting<T: FromStr + Display>(general_settings: &Ini, section: &str, key: &str, default: T) -> T
+where
+    T::Err: Display,
+{
+    match general_settings
+        .section(Some(section))
+        .and_then(|section| section.get(key))
+    {
+        Some(value) => match value.parse::<T>() {
+            Ok(parsed_value) => parsed_value,
+            Err(error) => {
+                warn!("could not use the given value for the key `{}` [{}]", key, error);
+                info!("using the default value for `{}` ({})", key, default);
+                default
+            }
+        },
+        None => {
+            warn!("Unable to determine '{}' from general settings", key);
+            info!("using the default value for `{}` ({})", key, default);
+            default
+        }
+    }
+}
+
+/// The secret channel servers register with at their world server, which must be set.
+fn interserver_secret(general_settings: &Ini) -> String {
+    match general_settings
+        .section(Some("Security"))
+        .and_then(|section| section.get("interserver_secret"))
+    {
+        Some(secret) if !secret.is_empty() => secret.to_string(),
+        _ => panic!("Unable to determine 'interserver_secret' from general settings"),
+    }
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -94,6 +137,65 @@ fn main() {
         }
     };
 
+    let ban_list_reload_seconds: u64 = setting(
+        &general_settings,
+        "Security",
+        "ban_list_reload_seconds",
+        defaults::DEFAULT_BAN_LIST_RELOAD_SECONDS,
+    );
+
+    let connection_limits = net::throttle::ConnectionLimits {
+        max_connections: setting(
+            &general_settings,
+            "Security",
+            "max_connections",
+            defaults::DEFAULT_MAX_CONNECTIONS,
+        ),
+        max_connections_per_ip: setting(
+            &general_settings,
+            "Security",
+            "max_connections_per_ip",
+            defaults::DEFAULT_MAX_CONNECTIONS_PER_IP,
+        ),
+        connections_per_ip_per_minute: setting(
+            &general_settings,
+            "Security",
+            "connections_per_ip_per_minute",
+            defaults::DEFAULT_CONNECTIONS_PER_IP_PER_MINUTE,
+        ),
+    };
+
+    net::throttle::AttemptLimiter::init(
+        setting(
+            &general_settings,
+            "Security",
+            "max_login_attempts",
+            defaults::DEFAULT_MAX_LOGIN_ATTEMPTS,
+        ),
+        Duration::from_secs(setting(
+            &general_settings,
+            "Security",
+            "login_block_seconds",
+            defaults::DEFAULT_LOGIN_BLOCK_SECONDS,
+        )),
+    );
+
+    net::handler::login::pin::PinPolicy::init(net::handler::login::pin::PinPolicy {
+        enabled: setting(&general_settings, "Login", "pin_enabled", true),
+        max_attempts: setting(
+            &general_settings,
+            "Login",
+            "pin_max_attempts",
+            defaults::DEFAULT_PIN_MAX_ATTEMPTS,
+        ),
+        lock_duration: Duration::from_secs(setting(
+            &general_settings,
+            "Login",
+            "pin_lock_seconds",
+            defaults::DEFAULT_PIN_LOCK_SECONDS,
+        )),
+    });
+
     match args.len() < 2 {
         true => {
             // bootstrap
@@ -169,10 +271,127 @@ fn main() {
                 Err(error) => panic!("{}", error),
             };
 
+            match net::ban_list::BanList::reload() {
+                Ok(count) => info!("ban list loaded with {} entries", count),
+                Err(error) => panic!("{}", error),
+            };
+            net::ban_list::BanList::spawn_reloader(Duration::from_secs(ban_list_reload_seconds));
+
+            // The world server and its channel servers talk on an internal address, never the one
+            // clients connect to.
+            let interserver_address = format!(
+                "{}:{}",
+                setting(
+                    &specific_settings,
+                    "Infrastructure",
+                    "interserver_address",
+                    defaults::DEFAULT_INTERSERVER_ADDRESS.to_string(),
+                ),
+                setting(
+                    &specific_settings,
+                    "Infrastructure",
+                    "interserver_port",
+                    defaults::DEFAULT_INTERSERVER_PORT,
+                )
+            );
+
+            if server_type == "world" {
+                let world_id = match infrastructure_section.get("id") {
+                    Some(textual_id) => match textual_id.parse::<u8>() {
+                        Ok(id) => id,
+                        Err(error) => panic!("{}", error),
+                    },
+                    None => panic!("Unable to determine world id from instance specific settings"),
+                };
+                match game::world::World::init(world_id) {
+                    Ok(_) => {}
+                    Err(error) => panic!("{}", error),
+                };
+                match net::interserver::listen(
+                    interserver_address.parse().unwrap(),
+                    interserver_secret(&general_settings),
+                ) {
+                    Ok(_) => info!("listening for channel servers on {}", interserver_address),
+                    Err(error) => panic!("{}", error),
+                };
+            }
+
+            if server_type == "channel" {
+                let world_id = match infrastructure_section.get("id") {
+                    Some(textual_id) => match textual_id.parse::<u8>() {
+                        Ok(id) => id,
+                        Err(error) => panic!("{}", error),
+                    },
+                    None => panic!("Unable to determine world id from instance specific settings"),
+                };
+
+                let rates = game::channel::Rates {
+                    exp: setting(&specific_settings, "Game", "exp_rate", 1),
+                    meso: setting(&specific_settings, "Game", "meso_rate", 1),
+                    drop: setting(&specific_settings, "Game", "drop_rate", 1),
+                };
+
+                match game::channel::Channel::init(
+                    world_id,
+                    sequence_number.unwrap_or(0) as u8,
+                    rates,
+                ) {
+                    Ok(_) => {}
+                    Err(error) => panic!("{}", error),
+                };
+                // Merchants left open by the last run go to Fredrick, as nothing minds them anymore.
+                let channel_id = sequence_number.unwrap_or(0) as i16;
+                match db::model::merchant::Merchant::close_channel(world_id as i16, channel_id) {
+                    Ok(count) => info!("closed {} merchants left open on the channel", count),
+                    Err(error) => panic!("{}", error),
+                };
+                match db::model::session::Session::end_channel(world_id as i16, channel_id) {
+                    Ok(count) => info!("ended {} sessions left on the channel", count),
+                    Err(error) => panic!("{}", error),
+                };
+                match game::channel::Channel::get() {
+                    Ok(channel) => {
+                        channel.spawn_map_updater(Duration::from_millis(
+                            defaults::MAP_UPDATE_INTERVAL_MILLISECONDS,
+                        ));
+                        channel.spawn_buff_updater(Duration::from_millis(
+                            defaults::BUFF_UPDATE_INTERVAL_MILLISECONDS,
+                        ));
+                        channel.spawn_merchant_updater(Duration::from_millis(
+                            defaults::MERCHANT_UPDATE_INTERVAL_MILLISECONDS,
+                        ));
+                    }
+                    Err(error) => panic!("{}", error),
+                };
+
+                let nx_directory: String =
+                    setting(&general_settings, "Data", "nx_directory", "data".to_string());
+                match data::nx::NxFiles::init(Path::new(&nx_directory)) {
+                    Ok(_) => {}
+                    Err(error) => panic!("{}", error),
+                };
+
+                let script_directory: String =
+                    setting(&general_settings, "Data", "script_directory", "scripts".to_string());
+                match script::Scripts::init(Path::new(&script_directory)) {
+                    Ok(_) => {}
+                    Err(error) => panic!("{}", error),
+                };
+
+                match net::interserver::WorldLink::init(
+                    interserver_address.parse().unwrap(),
+                    interserver_secret(&general_settings),
+                ) {
+                    Ok(_) => {}
+                    Err(error) => panic!("{}", error),
+                };
+            }
+
             let server = net::server::ServerBuilder::new()
                 .server_type(server_type)
                 .clients_threads(clients_threads)
                 .client_workers(client_workers)
+                .connection_limits(connection_limits)
                 .spawn();
 
             let mut server = match server {
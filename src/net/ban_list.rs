@@ -0,0 +1,160 @@
+use crate::db::model::infraction::Infraction;
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::thread;
+use std::time::Duration;
+
+/// An IP address or a CIDR network such as `10.0.0.0/8`.
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix_length: u8,
+}
+
+impl FromStr for IpNetwork {
+    type Err = Box<dyn Error>;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_length) = match value.split_once('/') {
+            Some((address, prefix_length)) => (address.parse::<IpAddr>()?, Some(prefix_length.parse::<u8>()?)),
+            None => (value.parse::<IpAddr>()?, None),
+        };
+
+        let max_prefix_length = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        match prefix_length {
+            Some(prefix_length) if prefix_length > max_prefix_length => {
+                Err(format!("prefix length of {} is out of range", value).into())
+            }
+            _ => Ok(IpNetwork {
+                address,
+                prefix_length: prefix_length.unwrap_or(max_prefix_length),
+            }),
+        }
+    }
+}
+
+impl IpNetwork {
+    pub fn contains(&self, address: IpAddr) -> bool {
+        match (self.address, address.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_length as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_length as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Reduces the different MAC address notations clients send to bare uppercase hex digits.
+pub fn normalize_mac_address(mac_address: &str) -> String {
+    mac_address
+        .chars()
+        .filter(|character| character.is_ascii_hexdigit())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// In-memory copy of the active IP, MAC and hardware id bans, so connections can be refused
+/// before they cost a client thread pool.
+#[derive(Default)]
+pub struct BanList {
+    networks: Vec<(IpNetwork, Infraction)>,
+    mac_addresses: HashMap<String, Infraction>,
+    hardware_ids: HashMap<String, Infraction>,
+}
+
+static BAN_LIST_INSTANCE: OnceCell<RwLock<BanList>> = OnceCell::new();
+
+impl BanList {
+    fn instance() -> &'static RwLock<BanList> {
+        BAN_LIST_INSTANCE.get_or_init(|| RwLock::new(BanList::default()))
+    }
+
+    fn add(&mut self, infraction: &Infraction) {
+        if let Some(ip_address) = &infraction.ip_address {
+            match ip_address.parse::<IpNetwork>() {
+                Ok(network) => self.networks.push((network, infraction.clone())),
+                Err(error) => warn!("Ignoring infraction {} with invalid address {} [{}]", infraction.id, ip_address, error),
+            }
+        }
+
+        if let Some(mac_address) = &infraction.mac_address {
+            self.mac_addresses
+                .insert(normalize_mac_address(mac_address), infraction.clone());
+        }
+
+        if let Some(hardware_id) = &infraction.hardware_id {
+            self.hardware_ids.insert(hardware_id.clone(), infraction.clone());
+        }
+    }
+
+    /// Replaces the list with the active bans currently stored in the database.
+    pub fn reload() -> Result<usize, Box<dyn Error>> {
+        let infractions = Infraction::get_active_machine_bans()?;
+        let mut ban_list = BanList::default();
+
+        for infraction in &infractions {
+            ban_list.add(infraction);
+        }
+
+        match Self::instance().write() {
+            Ok(mut guard) => *guard = ban_list,
+            Err(error) => return Err(format!("Unable to lock BanList RwLock [{}]", error).into()),
+        };
+
+        Ok(infractions.len())
+    }
+
+    pub fn insert(infraction: &Infraction) {
+        match Self::instance().write() {
+            Ok(mut guard) => guard.add(infraction),
+            Err(error) => warn!("Unable to lock BanList RwLock [{}]", error),
+        }
+    }
+
+    pub fn banned_address(address: IpAddr) -> Option<Infraction> {
+        match Self::instance().read() {
+            Ok(guard) => guard
+                .networks
+                .iter()
+                .find(|(network, infraction)| network.contains(address) && infraction.is_active())
+                .map(|(_, infraction)| infraction.clone()),
+            Err(_) => None,
+        }
+    }
+
+    pub fn banned_machine(mac_addresses: &[String], hardware_id: Option<&str>) -> Option<Infraction> {
+        match Self::instance().read() {
+            Ok(guard) => mac_addresses
+                .iter()
+                .filter_map(|mac_address| guard.mac_addresses.get(&normalize_mac_address(mac_address)))
+                .chain(hardware_id.and_then(|hardware_id| guard.hardware_ids.get(hardware_id)))
+                .find(|infraction| infraction.is_active())
+                .cloned(),
+            Err(_) => None,
+        }
+    }
+
+    /// Reloads the list every `interval`, picking up bans issued by the other servers.
+    pub fn spawn_reloader(interval: Duration) {
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            match Self::reload() {
+                Ok(count) => info!("ban list reloaded with {} entries", count),
+                Err(error) => warn!("Unable to reload ban list [{}]", error),
+            }
+        });
+    }
+}
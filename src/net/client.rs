@@ -1,6 +1,14 @@
-use crate::db::model::user;
+use crate::db::model::{character, infraction, user};
+use crate::game::buddy::BuddyList;
+use crate::game::buff::CharacterBuffs;
+use crate::game::inventory::CharacterInventory;
+use crate::game::quest::CharacterQuests;
+use crate::game::shop::NpcShop;
+use crate::game::skill::CharacterSkills;
+use crate::game::storage::AccountStorage;
 use crate::defaults;
 use crate::net::crypto;
+use crate::script::npc::NpcAnswer;
 use bytes::{BufMut, BytesMut};
 use log::*;
 use rand::prelude::*;
@@ -8,7 +16,7 @@ use rayon;
 use std::error;
 use std::io::{Read, Write};
 use std::mem::size_of;
-use std::net::TcpStream;
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -18,8 +26,81 @@ use super::handler::CommonHandler;
 
 pub struct Client {
     pub user: Option<Mutex<user::User>>,
+    pub character: Option<Mutex<character::Character>>,
+    /// Locked after `character` whenever both are needed.
+    pub inventory: Option<Mutex<CharacterInventory>>,
+    /// Locked after `inventory` whenever both are needed.
+    pub skills: Option<Mutex<CharacterSkills>>,
+    /// Locked after `skills` whenever both are needed.
+    pub buffs: Option<Mutex<CharacterBuffs>>,
+    /// Locked after `buffs` whenever both are needed.
+    pub quests: Option<Mutex<CharacterQuests>>,
+    /// Locked after `quests` whenever both are needed.
+    pub buddies: Option<Mutex<BuddyList>>,
+    pub mute: Option<infraction::Infraction>,
+    /// The shop of the NPC the player is trading with, locked after `character` and before
+    /// `inventory`.
+    pub npc_shop: Mutex<Option<NpcShop>>,
+    /// The storage the player opened from a storage keeper, locked after `character` and before
+    /// `inventory`.
+    pub storage: Mutex<Option<AccountStorage>>,
+    /// Where the answers of the player go while they talk to a scripted NPC.
+    pub conversation: Mutex<Option<Sender<NpcAnswer>>>,
+    pub pin_verified: bool,
     pub ponged: bool,
+    sender: Option<PacketSender>,
+    stream: Option<TcpStream>,
+    address: Option<SocketAddr>,
 }
+
+/// A cloneable handle for queueing encrypted packets to a client without locking it.
+#[derive(Clone)]
+pub struct PacketSender {
+    sender: Sender<SendableMessage>,
+}
+
+impl PacketSender {
+    pub fn send(&self, buffer: Vec<u8>) {
+        if let Err(error) = self.sender.send(SendableMessage {
+            buffer,
+            encrypted: true,
+        }) {
+            debug!("mpsc channel hung up [{}]", error);
+        }
+    }
+}
+
+impl Client {
+    pub fn send(&self, buffer: Vec<u8>) {
+        match &self.sender {
+            Some(sender) => sender.send(buffer),
+            None => debug!("Attempted to send a packet to a disconnected client"),
+        }
+    }
+
+    pub fn sender(&self) -> Option<PacketSender> {
+        self.sender.clone()
+    }
+
+    pub fn address(&self) -> Option<IpAddr> {
+        self.address.map(|address| address.ip())
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.mute
+            .as_ref()
+            .is_some_and(|mute| mute.is_active())
+    }
+
+    pub fn disconnect(&self) {
+        if let Some(stream) = &self.stream {
+            if let Err(error) = stream.shutdown(Shutdown::Both) {
+                debug!("Unable to shutdown TcpStream [{}]", error);
+            }
+        }
+    }
+}
+
 pub struct LowLevelClient {
     pub client: Arc<Mutex<Client>>,
     read_stream: Arc<Mutex<Option<TcpStream>>>,
@@ -69,6 +150,22 @@ impl LowLevelClient {
             Err(error) => panic!("Unable to lock TcpStream Mutex [{}]", error),
         };
 
+        let client_stream = match stream.try_clone() {
+            Ok(cloned_stream) => cloned_stream,
+            Err(error) => panic!("could not copy TcpStream [{}]", error),
+        };
+
+        match self.client.lock() {
+            Ok(mut client_guard) => {
+                client_guard.sender = Some(PacketSender {
+                    sender: sender.clone(),
+                });
+                client_guard.address = client_stream.peer_addr().ok();
+                client_guard.stream = Some(client_stream);
+            }
+            Err(error) => panic!("Unable to lock Client Mutex [{}]", error),
+        };
+
         let mut send_sequence: [u8; defaults::USER_SEQUENCE_SIZE] = Default::default();
         let mut receive_sequence: [u8; defaults::USER_SEQUENCE_SIZE] = Default::default();
 
@@ -163,6 +260,16 @@ impl LowLevelClient {
                 }
             }
         }
+
+        self.packet_handler.disconnect(self.client.clone());
+
+        match self.client.lock() {
+            Ok(mut client_guard) => {
+                client_guard.sender = None;
+                client_guard.stream = None;
+            }
+            Err(error) => error!("Unable to lock Client Mutex [{}]", error),
+        };
     }
 
     fn create_handshake(
@@ -336,7 +443,21 @@ impl<'a> ClientBuilder<'a> {
         Ok(LowLevelClient {
             client: Arc::new(Mutex::new(Client {
                 ponged: true,
-                user: None
+                user: None,
+                character: None,
+                inventory: None,
+                skills: None,
+                buffs: None,
+                quests: None,
+                buddies: None,
+                mute: None,
+                npc_shop: Mutex::new(None),
+                storage: Mutex::new(None),
+                conversation: Mutex::new(None),
+                pin_verified: false,
+                sender: None,
+                stream: None,
+                address: None,
             })),
             workers_count: self.workers_count,
             packet_handler: client_packet_handler,
@@ -0,0 +1,270 @@
+use crate::db::model::cheat_flag::CheatKind;
+use crate::game::channel::{Channel, Rates};
+use crate::game::character;
+use crate::game::cheat;
+use crate::game::combat::{self, Attack, AttackKind, AttackTarget};
+use crate::game::experience;
+use crate::game::inventory::{CharacterInventory, InventoryType};
+use crate::game::item::InventoryOperation;
+use crate::game::map::{DropContent, DropOwnership, Map};
+use crate::game::mob::Mob;
+use crate::game::party;
+use crate::game::quest;
+use crate::net::client::Client;
+use crate::net::packet::combat::create_attack;
+use crate::net::packet::item::create_modify_inventory;
+use bytes::Buf;
+use log::warn;
+use rand::Rng;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// Spells that are charged before they are cast, which adds the charge time to the packet.
+const CHARGED_SKILLS: [i32; 3] = [2121001, 2221001, 2321001];
+/// Chance for a killed mob to drop mesos.
+const MESO_DROP_CHANCE: f64 = 0.6;
+
+pub fn close_range_attack(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    let (attack, projectile_slot) = parse_attack(buffer, AttackKind::CloseRange)?;
+    handle_attack(client, attack, projectile_slot)
+}
+
+pub fn ranged_attack(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    let (attack, projectile_slot) = parse_attack(buffer, AttackKind::Ranged)?;
+    handle_attack(client, attack, projectile_slot)
+}
+
+pub fn magic_attack(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    let (attack, projectile_slot) = parse_attack(buffer, AttackKind::Magic)?;
+    handle_attack(client, attack, projectile_slot)
+}
+
+/// Reads an attack packet, along with the slot of the projectile for ranged attacks.
+fn parse_attack(buffer: &mut &[u8], kind: AttackKind) -> Option<(Attack, Option<i16>)> {
+    if buffer.remaining() < 6 {
+        return None;
+    }
+
+    buffer.advance(1);
+    let counts = buffer.get_u8();
+    let skill_id = buffer.get_i32_le();
+    if CHARGED_SKILLS.contains(&skill_id) {
+        if buffer.remaining() < 4 {
+            return None;
+        }
+        buffer.advance(4); // charge time
+    }
+
+    if buffer.remaining() < 4 {
+        return None;
+    }
+    let display = buffer.get_u8();
+    let direction = buffer.get_u8();
+    buffer.advance(1); // weapon class
+    let speed = buffer.get_u8();
+
+    let projectile_slot = match kind {
+        AttackKind::Ranged => {
+            if buffer.remaining() < 5 {
+                return None;
+            }
+            let slot = buffer.get_i16_le();
+            buffer.advance(3); // cash projectile slot, shoot range
+            Some(slot)
+        }
+        _ => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            buffer.advance(4);
+            None
+        }
+    };
+
+    let target_count = counts >> 4;
+    let lines = counts & 0xF;
+    let mut targets = Vec::with_capacity(target_count as usize);
+    for _ in 0..target_count {
+        if buffer.remaining() < 18 + 4 * lines as usize {
+            return None;
+        }
+
+        let object_id = buffer.get_i32_le();
+        buffer.advance(14); // hit action, positions and delay
+        targets.push(AttackTarget {
+            object_id,
+            damage: (0..lines).map(|_| buffer.get_i32_le()).collect(),
+        });
+    }
+
+    Some((
+        Attack {
+            kind,
+            skill_id,
+            display,
+            direction,
+            speed,
+            lines,
+            targets,
+        },
+        projectile_slot,
+    ))
+}
+
+fn handle_attack(
+    client: Arc<Mutex<Client>>,
+    attack: Attack,
+    projectile_slot: Option<i16>,
+) -> Option<(Vec<u8>, usize)> {
+    let result = character::with_character(&client, |client, character| {
+        let mut inventory = character::lock_inventory(client)?;
+        let skills = character::lock_skills(client)?;
+        let buffs = character::lock_buffs(client)?;
+
+        let equipped = inventory.get(InventoryType::Equipped);
+        if let Err(reason) = combat::validate(character, equipped, &skills, &buffs, &attack) {
+            cheat::flag(character, CheatKind::Damage, &reason);
+            return Ok((Vec::new(), Vec::new()));
+        }
+
+        let projectile_id = match projectile_slot {
+            Some(slot) => use_projectiles(client, &mut inventory, slot, attack.lines)?,
+            None => 0,
+        };
+        let skill_level = skills.level(attack.skill_id);
+
+        let map = Channel::get()?.map(character.map_id)?;
+        let mut map_guard = match map.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+
+        map_guard.broadcast(
+            &create_attack(character.id, &attack, skill_level, projectile_id),
+            Some(character.id),
+        );
+
+        let rates = Channel::get()?.rates();
+        let mut rewards = Vec::new();
+        let mut kills = Vec::new();
+        for target in &attack.targets {
+            let damage = target.damage.iter().fold(0i32, |total, damage| total.saturating_add(*damage));
+            if let Some(mob) = map_guard.damage_mob(target.object_id, character.id, damage) {
+                reward_kill(&mut map_guard, &mob, rates);
+                rewards.extend(experience::kill_rewards(&mob, rates.exp));
+                kills.extend(mob.damage_by.keys().map(|character_id| (*character_id, mob.mob_id())));
+            }
+        }
+
+        let rewards = party::share_exp(rewards, |character_id| map_guard.player(character_id).is_some());
+        Ok((rewards, kills))
+    });
+
+    match result {
+        Ok((rewards, kills)) => {
+            give_exp(rewards);
+            record_kills(kills);
+        }
+        Err(error) => warn!("Unable to handle attack [{}]", error),
+    }
+    None
+}
+
+/// Gives every attacker its share of EXP, once the lock on the client of the killer is released.
+fn give_exp(rewards: Vec<(i32, i32, bool)>) {
+    let channel = match Channel::get() {
+        Ok(channel) => channel,
+        Err(error) => return warn!("Unable to give EXP [{}]", error),
+    };
+
+    for (character_id, amount, white) in rewards {
+        let player = match channel.player(character_id) {
+            Some(player) => player,
+            None => continue,
+        };
+
+        let result = character::with_character(&player.client, |client, character| {
+            experience::gain_exp(client, character, amount, white)
+        });
+        if let Err(error) = result {
+            warn!("Unable to give {} EXP to character {} [{}]", amount, character_id, error);
+        }
+    }
+}
+
+/// Counts the kills of every attacker towards their quests, once the lock on the client of the
+/// killer is released.
+fn record_kills(kills: Vec<(i32, i32)>) {
+    let channel = match Channel::get() {
+        Ok(channel) => channel,
+        Err(error) => return warn!("Unable to record kills [{}]", error),
+    };
+
+    for (character_id, mob_id) in kills {
+        let player = match channel.player(character_id) {
+            Some(player) => player,
+            None => continue,
+        };
+
+        let result = character::with_character(&player.client, |client, _| {
+            quest::record_kill(client, &mut *character::lock_quests(client)?, mob_id);
+            Ok(())
+        });
+        if let Err(error) = result {
+            warn!("Unable to record kill of mob {} by character {} [{}]", mob_id, character_id, error);
+        }
+    }
+}
+
+/// Takes the stars, arrows or bullets a ranged attack fired from `slot`, returning their id.
+fn use_projectiles(
+    client: &Client,
+    inventory: &mut CharacterInventory,
+    slot: i16,
+    count: u8,
+) -> Result<i32, Box<dyn Error>> {
+    let tab = inventory.get_mut(InventoryType::Use);
+    let projectile = match tab.get_mut(slot) {
+        Some(projectile) if matches!(projectile.item_id / 10000, 206 | 207 | 233) => projectile,
+        _ => return Err(format!("No projectile at position {}", slot).into()),
+    };
+
+    let count = count.max(1) as i16;
+    if projectile.quantity < count {
+        return Err(format!("Not enough projectiles at position {}", slot).into());
+    }
+
+    projectile.quantity -= count;
+    let projectile_id = projectile.item_id;
+    let operation = match projectile.quantity == 0 && !projectile.is_rechargeable() {
+        true => {
+            tab.remove(slot);
+            InventoryOperation::Remove(InventoryType::Use, slot)
+        }
+        false => InventoryOperation::Quantity(InventoryType::Use, slot, projectile.quantity),
+    };
+
+    client.send(create_modify_inventory(&[operation], false));
+    Ok(projectile_id)
+}
+
+/// Drops the reward of a killed mob, owned by whoever dealt it the most damage.
+fn reward_kill(map: &mut Map, mob: &Mob, rates: Rates) {
+    let owner_id = match mob.top_attacker() {
+        Some(owner_id) => owner_id,
+        None => return,
+    };
+
+    let mut rng = rand::thread_rng();
+    if rng.gen_bool((MESO_DROP_CHANCE * rates.drop as f64).min(1.0)) {
+        let level = mob.data.level.max(1) as i32;
+        let amount = level * rng.gen_range(5..=10);
+        map.spawn_drop(
+            DropContent::Meso(amount.saturating_mul(rates.meso as i32)),
+            DropOwnership::of_character(owner_id, Channel::get().ok().and_then(|channel| channel.party_id(owner_id))),
+            mob.object_id,
+            mob.position,
+            mob.position,
+        );
+    }
+}
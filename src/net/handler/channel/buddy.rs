@@ -0,0 +1,53 @@
+use crate::game::buddy::{self, DEFAULT_GROUP};
+use crate::game::character;
+use crate::net::client::Client;
+use crate::net::packet::get_maple_string;
+use bytes::Buf;
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+const ADD_BUDDY: u8 = 1;
+const ACCEPT_REQUEST: u8 = 2;
+const DELETE_BUDDY: u8 = 3;
+
+pub fn modify_buddy_list(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if !buffer.has_remaining() {
+        return None;
+    }
+
+    let operation = buffer.get_u8();
+    let (name, group, buddy_id) = match operation {
+        ADD_BUDDY => {
+            let name = get_maple_string(buffer)?;
+            // Only clients that know buddy groups send one.
+            let group = match buffer.has_remaining() {
+                true => get_maple_string(buffer).filter(|group| !group.is_empty()),
+                false => None,
+            };
+            (name, group.unwrap_or_else(|| DEFAULT_GROUP.to_string()), 0)
+        }
+        ACCEPT_REQUEST | DELETE_BUDDY => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            (String::new(), String::new(), buffer.get_i32_le())
+        }
+        _ => {
+            warn!("Received unknown buddy list operation {}", operation);
+            return None;
+        }
+    };
+
+    let result = character::with_character(&client, |client, character| {
+        let mut buddies = character::lock_buddies(client)?;
+        match operation {
+            ADD_BUDDY => buddy::add(client, character, &mut buddies, &name, group),
+            ACCEPT_REQUEST => buddy::accept(client, character, &mut buddies, buddy_id),
+            _ => buddy::delete(client, character, &mut buddies, buddy_id),
+        }
+    });
+    if let Err(error) = result {
+        warn!("Rejected buddy list operation, possibly a hack attempt [{}]", error);
+    }
+    None
+}
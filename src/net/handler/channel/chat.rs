@@ -0,0 +1,122 @@
+use crate::game::channel::Channel;
+use crate::game::command;
+use crate::game::{character, guild, party};
+use crate::net::client::Client;
+use crate::net::packet::get_maple_string;
+use crate::net::packet::message::{self, ChatGroup, ServerMessageType};
+use bytes::Buf;
+use log::{error, warn};
+use std::sync::{Arc, Mutex};
+
+pub fn general_chat(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    let text = get_maple_string(buffer)?;
+    let show = buffer.has_remaining() && buffer.get_u8() != 0;
+
+    let (character_id, map_id, gm_level, muted) = {
+        let client_guard = match client.lock() {
+            Ok(guard) => guard,
+            Err(error) => {
+                warn!("Unable to lock Client Mutext [{}]", error);
+                return None;
+            }
+        };
+
+        match (&client_guard.user, &client_guard.character) {
+            (Some(user_mutex), Some(character_mutex)) => {
+                match (user_mutex.lock(), character_mutex.lock()) {
+                    (Ok(user), Ok(character)) => (
+                        character.id,
+                        character.map_id,
+                        command::gm_level(&user, &character),
+                        client_guard.is_muted(),
+                    ),
+                    _ => {
+                        warn!("Unable to lock User or Character Mutex");
+                        return None;
+                    }
+                }
+            }
+            _ => {
+                error!("Received authenticated packet from non-authenticated user");
+                return None;
+            }
+        }
+    };
+
+    if gm_level > 0 && text.starts_with(command::COMMAND_PREFIX) {
+        command::execute(client, &text);
+        return None;
+    }
+
+    if muted {
+        let response =
+            message::create_server_message(ServerMessageType::PinkText, "You have been muted.");
+        let response_length = response.len();
+        return Some((response, response_length));
+    }
+
+    let map = match Channel::get().and_then(|channel| channel.map(map_id)) {
+        Ok(map) => map,
+        Err(error) => {
+            error!("{}", error);
+            return None;
+        }
+    };
+
+    match map.lock() {
+        Ok(map_guard) => map_guard.broadcast(
+            &message::create_chat_text(character_id, gm_level > 0, &text, show),
+            None,
+        ),
+        Err(error) => warn!("Unable to lock Map Mutex [{}]", error),
+    };
+
+    None
+}
+
+pub fn multi_chat(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 2 {
+        return None;
+    }
+
+    let chat_type = buffer.get_u8();
+    let count = buffer.get_u8() as usize;
+    // The client lists the recipients, but the world server knows better who they are.
+    if buffer.remaining() < count * 4 {
+        return None;
+    }
+    buffer.advance(count * 4);
+    let text = get_maple_string(buffer)?;
+
+    let group = match ChatGroup::from_u8(chat_type) {
+        Some(group) => group,
+        None => {
+            warn!("Received multi chat of unsupported type {}", chat_type);
+            return None;
+        }
+    };
+
+    let (character_id, muted) =
+        match character::with_character(&client, |client, character| Ok((character.id, client.is_muted()))) {
+            Ok(result) => result,
+            Err(error) => {
+                warn!("{}", error);
+                return None;
+            }
+        };
+
+    if muted {
+        let response = message::create_server_message(ServerMessageType::PinkText, "You have been muted.");
+        let response_length = response.len();
+        return Some((response, response_length));
+    }
+
+    let result = match group {
+        ChatGroup::Party => party::say(character_id, text),
+        ChatGroup::Guild => guild::say(character_id, text),
+    };
+    if let Err(error) = result {
+        warn!("Unable to send multi chat [{}]", error);
+    }
+    None
+}
@@ -0,0 +1,359 @@
+use crate::db::model::character::Character;
+use crate::db::model::infraction::{Infraction, InfractionKind};
+use crate::db::model::migration::CharacterMigration;
+use crate::db::model::session::{NewSession, Session};
+use crate::db::model::user::User;
+use crate::game::buddy::{self, BuddyList};
+use crate::game::buff::CharacterBuffs;
+use crate::game::channel::{Channel, Player};
+use crate::game::character;
+use crate::game::inventory::{CharacterInventory, InventoryType};
+use crate::game::quest::CharacterQuests;
+use crate::game::skill::CharacterSkills;
+use crate::game::room;
+use crate::game::trade;
+use crate::net::ban_list::BanList;
+use crate::net::client::Client;
+use crate::net::interserver::{ChannelMessage, WorldLink};
+use crate::net::packet::field;
+use bytes::Buf;
+use log::{error, info, warn};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+pub fn player_login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 4 {
+        return None;
+    }
+    let character_id = buffer.get_i32_le();
+
+    let channel = match Channel::get() {
+        Ok(channel) => channel,
+        Err(error) => {
+            error!("{}", error);
+            return None;
+        }
+    };
+
+    let character = match Character::get_by_id(character_id) {
+        Ok(Some(character)) => character,
+        Ok(None) => {
+            warn!(
+                "Client tried to log in with an unknown character {}",
+                character_id
+            );
+            return None;
+        }
+        Err(error) => {
+            warn!("Problem querying the database [{}]", error);
+            return None;
+        }
+    };
+
+    if character.world_id != channel.world_id() as i16 {
+        warn!("Character {} does not belong to this world", character.name);
+        return None;
+    }
+
+    // Only the account that selected the character on the login server may enter with it.
+    let address = match client.lock() {
+        Ok(client_guard) => client_guard.address(),
+        Err(error) => {
+            warn!("Unable to lock Client Mutext [{}]", error);
+            return None;
+        }
+    };
+    let ip_address = match address {
+        Some(address) => address.to_string(),
+        None => {
+            warn!("Unable to determine the address of the client entering as {}", character.name);
+            disconnect(&client);
+            return None;
+        }
+    };
+    match CharacterMigration::consume(character.id, &ip_address) {
+        Ok(Some(migration)) if migration.user_id == character.user_id => {}
+        Ok(_) => {
+            warn!("{} tried to enter as {} without selecting it", ip_address, character.name);
+            disconnect(&client);
+            return None;
+        }
+        Err(error) => {
+            warn!("Problem querying the database [{}]", error);
+            disconnect(&client);
+            return None;
+        }
+    };
+
+    let user = match User::get_by_id(character.user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            warn!("Character {} has no account", character.name);
+            return None;
+        }
+        Err(error) => {
+            warn!("Problem querying the database [{}]", error);
+            return None;
+        }
+    };
+
+    // Bans issued after the account went through the login server take effect here.
+    match Infraction::get_active(InfractionKind::Ban, Some(user.id), Some(&ip_address)) {
+        Ok(None) => {}
+        Ok(Some(ban)) => {
+            warn!("{} is banned (infraction {})", user.username, ban.id);
+            disconnect(&client);
+            return None;
+        }
+        Err(error) => {
+            warn!("Problem querying the database [{}]", error);
+            disconnect(&client);
+            return None;
+        }
+    };
+
+    // The machine is the one the account selected the character from, as the handoff requires.
+    let mac_addresses: Vec<String> = user
+        .mac_addresses
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|mac_address| !mac_address.is_empty())
+        .map(String::from)
+        .collect();
+    if let Some(ban) = address
+        .and_then(BanList::banned_address)
+        .or_else(|| BanList::banned_machine(&mac_addresses, user.hardware_id.as_deref()))
+    {
+        warn!("{} is connecting from a banned address or machine (infraction {})", user.username, ban.id);
+        disconnect(&client);
+        return None;
+    }
+
+    let inventory = match CharacterInventory::load(&character) {
+        Ok(inventory) => inventory,
+        Err(error) => {
+            warn!("Unable to load inventory of {} [{}]", character.name, error);
+            return None;
+        }
+    };
+
+    let skills = match CharacterSkills::load(character.id) {
+        Ok(skills) => skills,
+        Err(error) => {
+            warn!("Unable to load skills of {} [{}]", character.name, error);
+            return None;
+        }
+    };
+
+    let quests = match CharacterQuests::load(character.id) {
+        Ok(quests) => quests,
+        Err(error) => {
+            warn!("Unable to load quests of {} [{}]", character.name, error);
+            return None;
+        }
+    };
+
+    let buddies = match BuddyList::load(&character) {
+        Ok(buddies) => buddies,
+        Err(error) => {
+            warn!("Unable to load buddies of {} [{}]", character.name, error);
+            return None;
+        }
+    };
+
+    let mut client_guard = match client.lock() {
+        Ok(guard) => guard,
+        Err(error) => {
+            warn!("Unable to lock Client Mutext [{}]", error);
+            return None;
+        }
+    };
+
+    let sender = client_guard.sender()?;
+
+    // A second login would load its own copy of the character, and both copies would be saved.
+    match Session::begin(&NewSession {
+        user_id: user.id,
+        character_id: character.id,
+        world_id: channel.world_id() as i16,
+        channel_id: channel.channel_id() as i16,
+        login_date: SystemTime::now(),
+    }) {
+        Ok(true) => {}
+        Ok(false) => {
+            warn!("{} is already online", user.username);
+            client_guard.disconnect();
+            return None;
+        }
+        Err(error) => {
+            warn!("Unable to begin the session of {} [{}]", user.username, error);
+            client_guard.disconnect();
+            return None;
+        }
+    };
+
+    sender.send(field::create_character_info(
+        channel.channel_id(),
+        &character,
+        &inventory,
+        &skills,
+        &quests,
+    ));
+
+    match channel.add_player(Player {
+        character_id: character.id,
+        user_id: user.id,
+        name: character.name.clone(),
+        client: client.clone(),
+        sender: sender.clone(),
+    }) {
+        Ok(()) => {}
+        Err(error) => {
+            error!("Unable to register player [{}]", error);
+            end_session(user.id);
+            client_guard.disconnect();
+            return None;
+        }
+    };
+
+    match character::enter_map(&sender, &character, inventory.get(InventoryType::Equipped)) {
+        Ok(()) => {}
+        Err(error) => warn!("Unable to enter map {} [{}]", character.map_id, error),
+    };
+    buddy::enter(&sender, character.id, &buddies);
+
+    client_guard.mute = match Infraction::get_active(InfractionKind::Mute, Some(user.id), Some(&ip_address)) {
+        Ok(mute) => mute,
+        Err(error) => {
+            warn!(
+                "Unable to query active mute of {} [{}]",
+                character.name, error
+            );
+            None
+        }
+    };
+
+    info!(
+        "{} entered channel {}",
+        character.name,
+        channel.channel_id()
+    );
+    client_guard.user = Some(Mutex::new(user));
+    client_guard.character = Some(Mutex::new(character));
+    client_guard.inventory = Some(Mutex::new(inventory));
+    client_guard.skills = Some(Mutex::new(skills));
+    client_guard.buffs = Some(Mutex::new(CharacterBuffs::default()));
+    client_guard.quests = Some(Mutex::new(quests));
+    client_guard.buddies = Some(Mutex::new(buddies));
+
+    None
+}
+
+pub fn player_logout(client: Arc<Mutex<Client>>) {
+    // The trade and the shop give back what was offered before the character is stored.
+    if let Ok(character_id) = character::with_character(&client, |_, character| Ok(character.id)) {
+        if let Err(error) = trade::leave(character_id) {
+            warn!("Unable to cancel trade of character {} [{}]", character_id, error);
+        }
+        if let Err(error) = room::leave(character_id) {
+            warn!("Unable to leave the room of character {} [{}]", character_id, error);
+        }
+    }
+
+    let mut client_guard = match client.lock() {
+        Ok(guard) => guard,
+        Err(error) => {
+            warn!("Unable to lock Client Mutext [{}]", error);
+            return;
+        }
+    };
+
+    let character = match client_guard.character.take() {
+        Some(character_mutex) => match character_mutex.into_inner() {
+            Ok(character) => character,
+            Err(error) => {
+                warn!("Unable to take Character out of its Mutex [{}]", error);
+                return;
+            }
+        },
+        None => return,
+    };
+
+    let inventory = match client_guard.inventory.take() {
+        Some(inventory_mutex) => match inventory_mutex.into_inner() {
+            Ok(inventory) => Some(inventory),
+            Err(error) => {
+                warn!("Unable to take CharacterInventory out of its Mutex [{}]", error);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let skills = match client_guard.skills.take() {
+        Some(skills_mutex) => match skills_mutex.into_inner() {
+            Ok(skills) => Some(skills),
+            Err(error) => {
+                warn!("Unable to take CharacterSkills out of its Mutex [{}]", error);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let quests = match client_guard.quests.take() {
+        Some(quests_mutex) => match quests_mutex.into_inner() {
+            Ok(quests) => Some(quests),
+            Err(error) => {
+                warn!("Unable to take CharacterQuests out of its Mutex [{}]", error);
+                None
+            }
+        },
+        None => None,
+    };
+    // Buddies are stored as soon as they change, so there is nothing left to save.
+    client_guard.buddies = None;
+
+    match character::leave_map(&character) {
+        Ok(()) => {}
+        Err(error) => warn!("Unable to leave map {} [{}]", character.map_id, error),
+    };
+
+    match Channel::get() {
+        Ok(channel) => {
+            channel.remove_player(character.id);
+        }
+        Err(error) => error!("{}", error),
+    };
+
+    match WorldLink::get().and_then(|link| link.send(&ChannelMessage::PlayerOffline(character.id))) {
+        Ok(()) => {}
+        Err(error) => warn!("Unable to report {} offline to the world server [{}]", character.name, error),
+    };
+
+    let inventory = inventory.map(|inventory| inventory.rows(character.id));
+    let skills = skills.map(|skills| skills.rows(character.id));
+    let quests = quests.map(|quests| quests.rows(character.id));
+    match character.save(inventory.as_deref(), skills.as_deref(), quests.as_deref()) {
+        Ok(()) => info!("{} left the channel", character.name),
+        Err(error) => error!("Unable to save character {} [{}]", character.name, error),
+    };
+
+    // Only once the character is stored may the account enter again.
+    end_session(character.user_id);
+}
+
+fn end_session(user_id: i32) {
+    match Session::end(user_id) {
+        Ok(_) => {}
+        Err(error) => warn!("Unable to end the session of account {} [{}]", user_id, error),
+    };
+}
+
+fn disconnect(client: &Mutex<Client>) {
+    match client.lock() {
+        Ok(client_guard) => client_guard.disconnect(),
+        Err(error) => warn!("Unable to lock Client Mutext [{}]", error),
+    };
+}
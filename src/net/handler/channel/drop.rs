@@ -0,0 +1,145 @@
+use crate::db::model::meso_log::MesoReason;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::item;
+use crate::game::map::{DropContent, DropOwnership};
+use crate::game::meso;
+use crate::game::reactor::Reactor;
+use crate::net::client::Client;
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::item::create_modify_inventory;
+use crate::net::packet::message::{self, ServerMessageType};
+use bytes::Buf;
+use log::{debug, warn};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+const MIN_MESO_DROP: i32 = 10;
+const MAX_MESO_DROP: i32 = 50000;
+
+pub fn drop_meso(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 8 {
+        return None;
+    }
+
+    buffer.advance(4); // timestamp
+    let amount = buffer.get_i32_le();
+
+    let result = character::with_character(&client, |client, character| {
+        if !(MIN_MESO_DROP..=MAX_MESO_DROP).contains(&amount) || amount > character.meso {
+            return Err(format!("{} cannot drop {} mesos", character.name, amount).into());
+        }
+
+        spawn_player_drop(character.id, character.map_id, DropContent::Meso(amount))?;
+        character.meso -= amount;
+        meso::log(character, -amount, MesoReason::Drop, "dropped on the ground");
+        client.send(create_update_stats(character, &[Stat::Meso], true));
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected meso drop, possibly a hack attempt [{}]", error);
+        enable_actions(&client);
+    }
+    None
+}
+
+pub fn pick_up(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 13 {
+        return None;
+    }
+
+    buffer.advance(4); // timestamp
+    buffer.advance(1);
+    buffer.advance(4); // position
+    let object_id = buffer.get_i32_le();
+
+    let result = character::with_character(&client, |client, character| {
+        let mut inventory = character::lock_inventory(client)?;
+        let map = Channel::get()?.map(character.map_id)?;
+        let mut map_guard = match map.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+
+        let map_drop = match map_guard.map_drop(object_id) {
+            Some(map_drop) => map_drop,
+            None => {
+                debug!("Drop {} is already gone", object_id);
+                client.send(create_modify_inventory(&[], true));
+                return Ok(());
+            }
+        };
+
+        if !map_drop.can_pick_up(character.id, Channel::get()?.party_id(character.id)) {
+            return Err(format!("{} does not own drop {}", character.name, object_id).into());
+        }
+
+        match &map_drop.content {
+            DropContent::Meso(amount) => {
+                character.meso = character.meso.saturating_add(*amount);
+                meso::log(
+                    character,
+                    *amount,
+                    MesoReason::PickUp,
+                    &format!("picked up from {}", map_drop.dropper_id),
+                );
+                client.send(create_update_stats(character, &[Stat::Meso], true));
+            }
+            DropContent::Item(item) => match item::add_item(&mut inventory, item.clone()) {
+                Ok(operations) => client.send(create_modify_inventory(&operations, true)),
+                Err(error) => {
+                    debug!("{} cannot pick up drop {} [{}]", character.name, object_id, error);
+                    client.send(message::create_server_message(
+                        ServerMessageType::PinkText,
+                        "You cannot carry this item.",
+                    ));
+                    client.send(create_modify_inventory(&[], true));
+                    return Ok(());
+                }
+            },
+        }
+
+        map_guard.pick_up_drop(object_id, character.id);
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected pickup, possibly a hack attempt [{}]", error);
+        enable_actions(&client);
+    }
+    None
+}
+
+/// Drops `content` at the position of the player; anyone may pick it up. Returns the reactor the
+/// drop destroyed, if it was the item one waited for.
+pub fn spawn_player_drop(
+    character_id: i32,
+    map_id: i32,
+    content: DropContent,
+) -> Result<Option<Reactor>, Box<dyn Error>> {
+    let map = Channel::get()?.map(map_id)?;
+    let mut map_guard = match map.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+    };
+
+    let position = match map_guard.player(character_id) {
+        Some(player) => player.position,
+        None => return Err(format!("Character {} is not in map {}", character_id, map_id).into()),
+    };
+
+    let object_id = map_guard.spawn_drop(content, DropOwnership::FreeForAll, character_id, position, position);
+    Ok(map_guard.drop_on_reactor(object_id))
+}
+
+pub fn enable_actions(client: &Arc<Mutex<Client>>) {
+    let result = character::with_character(client, |client, character| {
+        client.send(create_update_stats(character, &[], true));
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Unable to enable actions [{}]", error);
+    }
+}
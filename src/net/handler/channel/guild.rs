@@ -0,0 +1,109 @@
+use crate::game::character;
+use crate::game::guild::{self, GuildEmblem, GuildRequest};
+use crate::net::client::Client;
+use crate::net::packet::get_maple_string;
+use bytes::Buf;
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+const CREATE_GUILD: u8 = 0x02;
+const INVITE_PLAYER: u8 = 0x05;
+const ACCEPT_INVITATION: u8 = 0x06;
+const LEAVE_GUILD: u8 = 0x07;
+const EXPEL_MEMBER: u8 = 0x08;
+const CHANGE_RANK_TITLES: u8 = 0x0D;
+const CHANGE_RANK: u8 = 0x0E;
+const CHANGE_EMBLEM: u8 = 0x0F;
+const CHANGE_NOTICE: u8 = 0x10;
+
+pub fn guild_operation(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if !buffer.has_remaining() {
+        return None;
+    }
+
+    let operation = buffer.get_u8();
+    let request = match operation {
+        CREATE_GUILD => GuildRequest::Create(get_maple_string(buffer)?),
+        INVITE_PLAYER => GuildRequest::Invite(get_maple_string(buffer)?),
+        ACCEPT_INVITATION => {
+            if buffer.remaining() < 8 {
+                return None;
+            }
+            let guild_id = buffer.get_i32_le();
+            buffer.advance(4);
+            GuildRequest::Accept(guild_id)
+        }
+        LEAVE_GUILD => GuildRequest::Leave,
+        EXPEL_MEMBER => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            GuildRequest::Expel(buffer.get_i32_le())
+        }
+        CHANGE_RANK_TITLES => {
+            let mut rank_titles = Vec::with_capacity(guild::DEFAULT_RANK_TITLES.len());
+            for _ in 0..guild::DEFAULT_RANK_TITLES.len() {
+                rank_titles.push(get_maple_string(buffer)?);
+            }
+            GuildRequest::ChangeRankTitles(rank_titles)
+        }
+        CHANGE_RANK => {
+            if buffer.remaining() < 5 {
+                return None;
+            }
+            GuildRequest::ChangeRank(buffer.get_i32_le(), buffer.get_u8())
+        }
+        CHANGE_EMBLEM => {
+            if buffer.remaining() < 6 {
+                return None;
+            }
+            GuildRequest::ChangeEmblem(GuildEmblem {
+                background: buffer.get_i16_le(),
+                background_color: buffer.get_u8(),
+                logo: buffer.get_i16_le(),
+                logo_color: buffer.get_u8(),
+            })
+        }
+        CHANGE_NOTICE => GuildRequest::ChangeNotice(get_maple_string(buffer)?),
+        _ => {
+            warn!("Received unknown guild operation {}", operation);
+            return None;
+        }
+    };
+
+    let result = character::with_character(&client, |client, character| {
+        // The fees are taken before the world server sees the request, and given back if it
+        // turns the request down.
+        match &request {
+            GuildRequest::Create(name) if !guild::is_valid_name(name) => {
+                Err(format!("{} asked for the invalid guild name {}", character.name, name).into())
+            }
+            GuildRequest::Create(_) => {
+                guild::request_paid(client, character, request, guild::GUILD_CREATION_COST, "guild creation")
+            }
+            GuildRequest::ChangeEmblem(_) => {
+                guild::request_paid(client, character, request, guild::EMBLEM_CHANGE_COST, "guild emblem change")
+            }
+            _ => guild::request(character.id, request),
+        }
+    });
+    if let Err(error) = result {
+        warn!("Unable to handle guild operation [{}]", error);
+    }
+    None
+}
+
+pub fn deny_guild_request(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if !buffer.has_remaining() {
+        return None;
+    }
+    buffer.advance(1);
+    let inviter_name = get_maple_string(buffer)?;
+
+    let result = character::with_character(&client, |_, character| Ok(character.id))
+        .and_then(|character_id| guild::request(character_id, GuildRequest::Deny(inviter_name)));
+    if let Err(error) = result {
+        warn!("Unable to deny guild invitation [{}]", error);
+    }
+    None
+}
@@ -0,0 +1,89 @@
+use crate::db::model::cheat_flag::CheatKind;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::cheat;
+use crate::game::combat::{DamageSource, MAP_DAMAGE_SOURCE};
+use crate::game::health;
+use crate::net::client::Client;
+use crate::net::packet::combat::create_damage_player;
+use bytes::Buf;
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+pub fn take_damage(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 10 {
+        return None;
+    }
+
+    buffer.advance(4); // timestamp
+    let attack = buffer.get_i8();
+    buffer.advance(1); // element
+    let damage = buffer.get_i32_le();
+    let source = match attack {
+        MAP_DAMAGE_SOURCE => DamageSource::Map,
+        attack => {
+            if buffer.remaining() < 9 {
+                return None;
+            }
+            DamageSource::Mob {
+                attack,
+                mob_id: buffer.get_i32_le(),
+                object_id: buffer.get_i32_le(),
+                direction: buffer.get_u8(),
+            }
+        }
+    };
+
+    let result = character::with_character(&client, |client, character| {
+        if damage < 0 {
+            return Err(format!("{} took negative damage {}", character.name, damage).into());
+        }
+
+        let map = Channel::get()?.map(character.map_id)?;
+        match map.lock() {
+            Ok(map_guard) => {
+                if let DamageSource::Mob { mob_id, object_id, .. } = source {
+                    if map_guard.mob(object_id).map(|mob| mob.mob_id()) != Some(mob_id) {
+                        return Err(format!("Mob {} is not in map {}", mob_id, character.map_id).into());
+                    }
+                }
+                map_guard.broadcast(
+                    &create_damage_player(character.id, source, damage),
+                    Some(character.id),
+                );
+            }
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+
+        health::take_damage(client, character, damage)
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected damage taken [{}]", error);
+    }
+    None
+}
+
+pub fn heal_over_time(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 8 {
+        return None;
+    }
+
+    buffer.advance(4);
+    let hp = buffer.get_i16_le();
+    let mp = buffer.get_i16_le();
+
+    let result = character::with_character(&client, |client, character| {
+        let validation = health::validate_recovery(&mut *character::lock_buffs(client)?, hp, mp);
+        match validation {
+            Ok(()) => health::recover(client, character, hp, mp),
+            Err(reason) => cheat::flag(character, CheatKind::Recovery, &reason),
+        }
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Unable to recover HP and MP [{}]", error);
+    }
+    None
+}
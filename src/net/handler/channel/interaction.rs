@@ -0,0 +1,280 @@
+use crate::game::character;
+use crate::game::inventory::InventoryType;
+use crate::game::minigame;
+use crate::game::player_shop;
+use crate::game::room::{self, RoomType};
+use crate::game::trade;
+use crate::net::client::Client;
+use crate::net::packet::get_maple_string;
+use crate::net::packet::message::{create_server_message, ServerMessageType};
+use bytes::Buf;
+use log::warn;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+const CREATE: u8 = 0x00;
+const INVITE: u8 = 0x02;
+const DECLINE: u8 = 0x03;
+const VISIT: u8 = 0x04;
+const CHAT: u8 = 0x06;
+const EXIT: u8 = 0x0A;
+const SET_ITEMS: u8 = 0x0E;
+const SET_MESO: u8 = 0x0F;
+const CONFIRM: u8 = 0x10;
+const OPEN: u8 = 0x0B;
+const PUT_ITEM: u8 = 0x13;
+const BUY: u8 = 0x14;
+const REMOVE_ITEM: u8 = 0x18;
+const BAN: u8 = 0x19;
+const CLOSE_MERCHANT: u8 = 0x26;
+const REQUEST_TIE: u8 = 0x2C;
+const ANSWER_TIE: u8 = 0x2D;
+const GIVE_UP: u8 = 0x2E;
+const EXIT_AFTER_GAME: u8 = 0x32;
+const CANCEL_EXIT: u8 = 0x33;
+const READY: u8 = 0x34;
+const UNREADY: u8 = 0x35;
+const EXPEL: u8 = 0x36;
+const START: u8 = 0x37;
+const SKIP: u8 = 0x39;
+const MOVE_OMOK: u8 = 0x3A;
+const SELECT_CARD: u8 = 0x3E;
+
+const TRADE_ROOM: u8 = 3;
+
+pub fn player_interaction(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if !buffer.has_remaining() {
+        return None;
+    }
+
+    let mode = buffer.get_u8();
+    let result: Result<(), Box<dyn Error>> = match mode {
+        CREATE => {
+            if !buffer.has_remaining() {
+                return None;
+            }
+            let room_type = buffer.get_u8();
+            if room_type == TRADE_ROOM {
+                return handle(character::with_character(&client, |client, character| {
+                    let inventory = character::lock_inventory(client)?;
+                    trade::open(client, character, &inventory)
+                }));
+            }
+            let room_type = match RoomType::from_u8(room_type) {
+                Some(room_type) => room_type,
+                None => {
+                    warn!("Received unknown interaction room type {}", room_type);
+                    return None;
+                }
+            };
+            let title = get_maple_string(buffer)?;
+            match room_type {
+                RoomType::Omok | RoomType::MatchCard => {
+                    if !buffer.has_remaining() {
+                        return None;
+                    }
+                    let password = match buffer.get_u8() {
+                        0 => None,
+                        _ => Some(get_maple_string(buffer)?),
+                    };
+                    if !buffer.has_remaining() {
+                        return None;
+                    }
+                    let piece = buffer.get_u8();
+                    character::with_character(&client, |client, character| {
+                        let inventory = character::lock_inventory(client)?;
+                        minigame::create(client, character, &inventory, room_type, title, password, piece)
+                    })
+                }
+                RoomType::PlayerShop | RoomType::HiredMerchant => {
+                    if buffer.remaining() < 7 {
+                        return None;
+                    }
+                    buffer.advance(1);
+                    let slot = buffer.get_i16_le();
+                    let permit_id = buffer.get_i32_le();
+                    character::with_character(&client, |client, character| {
+                        let inventory = character::lock_inventory(client)?;
+                        player_shop::create(client, character, &inventory, room_type, title, slot, permit_id)
+                    })
+                }
+            }
+        }
+        INVITE => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            let target_id = buffer.get_i32_le();
+            character::with_character(&client, |client, character| trade::invite(client, character, target_id))
+        }
+        DECLINE => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            let trade_id = buffer.get_i32_le();
+            character::with_character(&client, |_, character| trade::decline(character, trade_id))
+        }
+        VISIT => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            let room_id = buffer.get_i32_le();
+            // Trades are known by the id of the character that opened them, rooms by their own.
+            let password = match buffer.has_remaining() && buffer.get_u8() != 0 {
+                true => Some(get_maple_string(buffer)?),
+                false => None,
+            };
+            character::with_character(&client, |client, character| {
+                let inventory = character::lock_inventory(client)?;
+                match trade::is_invited(character.id, room_id)? {
+                    true => trade::join(client, character, &inventory, room_id),
+                    false => room::visit(client, character, &inventory, room_id, password),
+                }
+            })
+        }
+        CHAT => {
+            let text = get_maple_string(buffer)?;
+            character::with_character(&client, |client, character| match client.is_muted() {
+                true => {
+                    client.send(create_server_message(ServerMessageType::PinkText, "You have been muted."));
+                    Ok(None)
+                }
+                false => Ok(Some(character.id)),
+            })
+            .and_then(|character_id| match character_id {
+                Some(character_id) => match trade::is_trading(character_id)? {
+                    true => trade::chat(character_id, &text),
+                    false => room::chat(character_id, &text),
+                },
+                None => Ok(()),
+            })
+        }
+        EXIT => with_character_id(&client).and_then(|character_id| match trade::is_trading(character_id)? {
+            true => trade::leave(character_id),
+            false => room::leave(character_id),
+        }),
+        SET_ITEMS => {
+            if buffer.remaining() < 6 {
+                return None;
+            }
+            let inventory_type = InventoryType::from_i16(buffer.get_u8() as i16)?;
+            let source = buffer.get_i16_le();
+            let quantity = buffer.get_i16_le();
+            let slot = buffer.get_u8();
+            character::with_character(&client, |client, character| {
+                let mut inventory = character::lock_inventory(client)?;
+                trade::offer_item(client, character, &mut inventory, inventory_type, source, quantity, slot)
+            })
+        }
+        SET_MESO => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            let amount = buffer.get_i32_le();
+            character::with_character(&client, |client, character| trade::offer_meso(client, character, amount))
+        }
+        CONFIRM => with_character_id(&client).and_then(trade::confirm),
+        OPEN => character::with_character(&client, |client, character| {
+            let inventory = character::lock_inventory(client)?;
+            player_shop::open(character, &inventory)
+        }),
+        PUT_ITEM => {
+            if buffer.remaining() < 11 {
+                return None;
+            }
+            let inventory_type = InventoryType::from_i16(buffer.get_u8() as i16)?;
+            let source = buffer.get_i16_le();
+            let quantity = buffer.get_i16_le();
+            let bundles = buffer.get_i16_le();
+            let price = buffer.get_i32_le();
+            character::with_character(&client, |client, character| {
+                let mut inventory = character::lock_inventory(client)?;
+                player_shop::put_item(
+                    client,
+                    character,
+                    &mut inventory,
+                    inventory_type,
+                    source,
+                    quantity,
+                    bundles,
+                    price,
+                )
+            })
+        }
+        BUY => {
+            if buffer.remaining() < 3 {
+                return None;
+            }
+            let index = buffer.get_u8();
+            let bundles = buffer.get_i16_le();
+            with_character_id(&client).and_then(|character_id| player_shop::buy(character_id, index, bundles))
+        }
+        REMOVE_ITEM => {
+            if buffer.remaining() < 2 {
+                return None;
+            }
+            let index = buffer.get_i16_le();
+            character::with_character(&client, |client, character| {
+                let mut inventory = character::lock_inventory(client)?;
+                player_shop::remove_item(client, character, &mut inventory, index)
+            })
+        }
+        BAN => {
+            let name = get_maple_string(buffer)?;
+            with_character_id(&client).and_then(|character_id| room::ban(character_id, &name))
+        }
+        CLOSE_MERCHANT => with_character_id(&client).and_then(player_shop::close_merchant),
+        REQUEST_TIE => with_character_id(&client).and_then(minigame::offer_tie),
+        ANSWER_TIE => {
+            if !buffer.has_remaining() {
+                return None;
+            }
+            let accepted = buffer.get_u8() != 0;
+            with_character_id(&client).and_then(|character_id| minigame::answer_tie(character_id, accepted))
+        }
+        GIVE_UP => with_character_id(&client).and_then(minigame::give_up),
+        EXIT_AFTER_GAME | CANCEL_EXIT => with_character_id(&client)
+            .and_then(|character_id| minigame::leave_after_game(character_id, mode == EXIT_AFTER_GAME)),
+        READY | UNREADY => {
+            with_character_id(&client).and_then(|character_id| minigame::ready(character_id, mode == READY))
+        }
+        EXPEL => with_character_id(&client).and_then(minigame::expel),
+        START => with_character_id(&client).and_then(minigame::start),
+        SKIP => with_character_id(&client).and_then(minigame::skip),
+        MOVE_OMOK => {
+            if buffer.remaining() < 8 {
+                return None;
+            }
+            let x = buffer.get_i32_le();
+            let y = buffer.get_i32_le();
+            with_character_id(&client).and_then(|character_id| minigame::move_omok(character_id, x, y))
+        }
+        SELECT_CARD => {
+            if buffer.remaining() < 2 {
+                return None;
+            }
+            let first = buffer.get_u8() != 0;
+            let index = buffer.get_u8();
+            with_character_id(&client).and_then(|character_id| minigame::select_card(character_id, first, index))
+        }
+        _ => {
+            warn!("Received unknown player interaction {}", mode);
+            return None;
+        }
+    };
+
+    handle(result)
+}
+
+fn handle(result: Result<(), Box<dyn Error>>) -> Option<(Vec<u8>, usize)> {
+    if let Err(error) = result {
+        warn!("Unable to handle player interaction [{}]", error);
+    }
+    None
+}
+
+/// The id of the character of the client, for actions that lock the characters they involve
+/// themselves.
+fn with_character_id(client: &Arc<Mutex<Client>>) -> Result<i32, Box<dyn Error>> {
+    character::with_character(client, |_, character| Ok(character.id))
+}
@@ -0,0 +1,120 @@
+use crate::game::character;
+use crate::game::inventory::InventoryType;
+use crate::game::item::{self, InventoryOperation};
+use crate::game::map::DropContent;
+use crate::game::reactor;
+use crate::net::client::Client;
+use crate::net::handler::channel::drop;
+use crate::net::packet::item::create_modify_inventory;
+use bytes::Buf;
+use log::warn;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+pub fn move_item(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 11 {
+        return None;
+    }
+
+    buffer.advance(4); // timestamp
+    let inventory_type = InventoryType::from_i16(buffer.get_u8() as i16);
+    let source = buffer.get_i16_le();
+    let destination = buffer.get_i16_le();
+    let quantity = buffer.get_i16_le();
+
+    let inventory_type = match inventory_type {
+        Some(InventoryType::Equip) if source < 0 => InventoryType::Equipped,
+        Some(inventory_type) => inventory_type,
+        None => return None,
+    };
+
+    let mut destroyed_reactor = None;
+    let result = character::with_character(&client, |client, character| {
+        let mut inventory = character::lock_inventory(client)?;
+
+        let operations = match destination {
+            0 => {
+                // The item only leaves the inventory once it lies on the map.
+                let mut dropped_inventory = inventory.clone();
+                let (operations, dropped_item) =
+                    item::take_for_drop(&mut dropped_inventory, inventory_type, source, quantity)?;
+                if let Some(dropped_item) = dropped_item {
+                    destroyed_reactor =
+                        drop::spawn_player_drop(character.id, character.map_id, DropContent::Item(dropped_item))?
+                            .map(|reactor| (character.id, character.map_id, reactor));
+                }
+                *inventory = dropped_inventory;
+                operations
+            }
+            _ => item::move_item(
+                character,
+                &mut inventory,
+                inventory_type,
+                source,
+                destination,
+                quantity,
+            )?,
+        };
+
+        client.send(create_modify_inventory(&operations, true));
+        if changes_equipped(&operations) {
+            character::update_look(character, inventory.get(InventoryType::Equipped))?;
+        }
+
+        Ok(())
+    });
+
+    reject_on_error(&client, result, "move item");
+    if let Some((character_id, map_id, destroyed)) = destroyed_reactor {
+        if let Err(error) = reactor::destroyed(&client, character_id, map_id, &destroyed) {
+            warn!("Unable to reward destroying reactor {} [{}]", destroyed.reactor_id(), error);
+        }
+    }
+    None
+}
+
+/// Merges the stacks of a tab and packs them into the first slots.
+pub fn gather_items(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    rearrange_items(client, buffer, false)
+}
+
+/// Same as gathering, but also orders the tab by item id.
+pub fn sort_items(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    rearrange_items(client, buffer, true)
+}
+
+fn rearrange_items(client: Arc<Mutex<Client>>, buffer: &mut &[u8], sort: bool) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 5 {
+        return None;
+    }
+
+    buffer.advance(4); // timestamp
+    let inventory_type = InventoryType::from_i16(buffer.get_u8() as i16)?;
+
+    let result = character::with_character(&client, |client, _character| {
+        let mut inventory = character::lock_inventory(client)?;
+        let operations = item::rearrange(&mut inventory, inventory_type, sort)?;
+        client.send(create_modify_inventory(&operations, true));
+        Ok(())
+    });
+
+    reject_on_error(&client, result, "rearrange items");
+    None
+}
+
+fn changes_equipped(operations: &[InventoryOperation]) -> bool {
+    operations.iter().any(|operation| match operation {
+        InventoryOperation::Move(_, source, destination) => *source < 0 || *destination < 0,
+        InventoryOperation::Remove(_, position) => *position < 0,
+        _ => false,
+    })
+}
+
+/// Inventory packets are validated by the client as well, so a rejected one is either a desync
+/// or a modified client. Either way the client gets its actions back without any change.
+fn reject_on_error(client: &Arc<Mutex<Client>>, result: Result<(), Box<dyn Error>>, action: &str) {
+    if let Err(error) = result {
+        warn!("Rejected request to {}, possibly a hack attempt [{}]", action, error);
+        drop::enable_actions(client);
+    }
+}
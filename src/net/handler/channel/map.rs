@@ -0,0 +1,66 @@
+use crate::game::character;
+use crate::game::health;
+use crate::net::client::Client;
+use crate::net::handler::channel::drop;
+use crate::net::packet::get_maple_string;
+use crate::script;
+use bytes::Buf;
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+/// Target map the client sends when it leaves through a portal rather than by respawning.
+const PORTAL_TARGET: i32 = -1;
+
+pub fn change_map(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 5 {
+        return None;
+    }
+
+    buffer.advance(1); // cause
+    let target = buffer.get_i32_le();
+    let portal_name = match target {
+        PORTAL_TARGET => Some(get_maple_string(buffer)?),
+        _ => None,
+    };
+
+    let result = character::with_character(&client, |client, character| match &portal_name {
+        Some(portal_name) => character::use_portal(client, character, portal_name),
+        None => health::respawn(client, character).map(|_| None),
+    });
+
+    finish_portal(&client, result);
+    None
+}
+
+pub fn use_scripted_portal(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 1 {
+        return None;
+    }
+
+    buffer.advance(1); // cause
+    let portal_name = get_maple_string(buffer)?;
+
+    let result = character::with_character(&client, |client, character| {
+        character::use_portal(client, character, &portal_name)
+    });
+
+    finish_portal(&client, result);
+    None
+}
+
+/// Runs the script of the used portal once the client is unlocked, then lets the player move on.
+fn finish_portal(client: &Arc<Mutex<Client>>, result: Result<Option<String>, Box<dyn std::error::Error>>) {
+    match result {
+        Ok(Some(script_name)) => {
+            if let Err(error) = script::field::run_portal(client, &script_name) {
+                warn!("Unable to run portal script [{}]", error);
+            }
+            drop::enable_actions(client);
+        }
+        Ok(None) => {}
+        Err(error) => {
+            warn!("Rejected map change, possibly a hack attempt [{}]", error);
+            drop::enable_actions(client);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::movement;
+use crate::net::client::Client;
+use crate::net::packet::mob;
+use bytes::Buf;
+use log::{debug, warn};
+use std::sync::{Arc, Mutex};
+
+/// Movement of a mob, reported by the client controlling it.
+pub fn move_mob(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 21 {
+        return None;
+    }
+
+    let object_id = buffer.get_i32_le();
+    let move_id = buffer.get_i16_le();
+    let use_skill = buffer.get_u8();
+    let mut skill = vec![use_skill];
+    skill.extend_from_slice(&buffer[..5]);
+    buffer.advance(5);
+    buffer.advance(5);
+    let start = (buffer.get_i16_le(), buffer.get_i16_le());
+
+    let movement = match movement::parse(buffer, start) {
+        Ok(movement) => movement,
+        Err(error) => {
+            warn!("Unable to parse mob movement [{}]", error);
+            return None;
+        }
+    };
+
+    let result = character::with_character(&client, |client, character| {
+        let map = Channel::get()?.map(character.map_id)?;
+        let mut map_guard = match map.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+
+        let mob = map_guard.move_mob(character.id, object_id, &skill, start, &movement)?;
+        client.send(mob::create_move_mob_response(mob, move_id, use_skill != 0));
+        Ok(())
+    });
+
+    // Controllers change hands while movements are in flight, so this is expected now and then.
+    if let Err(error) = result {
+        debug!("Ignored mob movement [{}]", error);
+    }
+    None
+}
@@ -0,0 +1,78 @@
+mod attack;
+mod buddy;
+mod chat;
+mod connect;
+mod drop;
+mod guild;
+mod health;
+mod interaction;
+mod inventory;
+mod map;
+mod mob;
+mod movement;
+mod npc;
+mod party;
+mod quest;
+mod reactor;
+mod skill;
+mod stat;
+
+use crate::net::client::Client;
+use crate::net::handler::GenericHandler;
+use bytes::Buf;
+use std::sync::{Arc, Mutex};
+
+pub struct ChannelHandler {}
+
+impl GenericHandler for ChannelHandler {
+    fn handle(
+        &self,
+        client: Arc<Mutex<Client>>,
+        buffer: Vec<u8>,
+        _buffer_size: usize,
+    ) -> Option<(Vec<u8>, usize)> {
+        let mut bytes = &buffer.clone()[..];
+
+        match bytes.get_u16_le() {
+            0x14u16 => connect::player_login(client, &mut bytes),
+            0x23u16 => map::change_map(client, &mut bytes),
+            0x26u16 => movement::move_player(client, &mut bytes),
+            0x29u16 => attack::close_range_attack(client, &mut bytes),
+            0x2Au16 => attack::ranged_attack(client, &mut bytes),
+            0x2Bu16 => attack::magic_attack(client, &mut bytes),
+            0x2Du16 => health::take_damage(client, &mut bytes),
+            0x2Eu16 => chat::general_chat(client, &mut bytes),
+            0x36u16 => npc::talk(client, &mut bytes),
+            0x38u16 => npc::talk_more(client, &mut bytes),
+            0x39u16 => npc::shop_action(client, &mut bytes),
+            0x3Au16 => npc::storage_action(client, &mut bytes),
+            0x40u16 => inventory::gather_items(client, &mut bytes),
+            0x41u16 => inventory::sort_items(client, &mut bytes),
+            0x42u16 => inventory::move_item(client, &mut bytes),
+            0x50u16 => stat::distribute_ap(client, &mut bytes),
+            0x51u16 => health::heal_over_time(client, &mut bytes),
+            0x52u16 => stat::distribute_sp(client, &mut bytes),
+            0x53u16 => skill::use_skill(client, &mut bytes),
+            0x54u16 => skill::cancel_buff(client, &mut bytes),
+            0x56u16 => drop::drop_meso(client, &mut bytes),
+            0x58u16 => stat::auto_distribute_ap(client, &mut bytes),
+            0x5Cu16 => map::use_scripted_portal(client, &mut bytes),
+            0x62u16 => quest::quest_action(client, &mut bytes),
+            0x6Bu16 => chat::multi_chat(client, &mut bytes),
+            0x6Du16 => interaction::player_interaction(client, &mut bytes),
+            0x6Eu16 => party::party_operation(client, &mut bytes),
+            0x6Fu16 => party::deny_party_request(client, &mut bytes),
+            0x70u16 => guild::guild_operation(client, &mut bytes),
+            0x71u16 => guild::deny_guild_request(client, &mut bytes),
+            0x76u16 => buddy::modify_buddy_list(client, &mut bytes),
+            0x9Du16 => mob::move_mob(client, &mut bytes),
+            0xABu16 => drop::pick_up(client, &mut bytes),
+            0xAEu16 => reactor::hit_reactor(client, &mut bytes),
+            _ => None,
+        }
+    }
+
+    fn disconnect(&self, client: Arc<Mutex<Client>>) {
+        connect::player_logout(client)
+    }
+}
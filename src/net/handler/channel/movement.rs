@@ -0,0 +1,38 @@
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::movement;
+use crate::net::client::Client;
+use bytes::Buf;
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+pub fn move_player(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 5 {
+        return None;
+    }
+
+    buffer.advance(1); // portal count
+    let start = (buffer.get_i16_le(), buffer.get_i16_le());
+
+    let movement = match movement::parse(buffer, start) {
+        Ok(movement) => movement,
+        Err(error) => {
+            warn!("Unable to parse player movement [{}]", error);
+            return None;
+        }
+    };
+
+    let result = character::with_character(&client, |_client, character| {
+        let map = Channel::get()?.map(character.map_id)?;
+        match map.lock() {
+            Ok(mut map_guard) => map_guard.move_player(character.id, &movement),
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Unable to move player [{}]", error);
+    }
+    None
+}
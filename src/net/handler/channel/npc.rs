@@ -0,0 +1,250 @@
+use crate::data::npc::NpcData;
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::inventory::InventoryType;
+use crate::game::shop::{self, NpcShop};
+use crate::game::storage::{self, AccountStorage, StorageResult};
+use crate::net::client::Client;
+use crate::net::handler::channel::drop;
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::item::create_modify_inventory;
+use crate::net::packet::npc::{
+    create_open_shop, create_open_storage, create_shop_result, create_storage_arranged, create_storage_message,
+    create_storage_meso, create_storage_tab,
+};
+use crate::net::packet::get_maple_string;
+use crate::script;
+use crate::script::npc::NpcAnswer;
+use bytes::Buf;
+use log::{debug, warn};
+use std::sync::{Arc, Mutex};
+
+/// Action the client sends when the player closes a dialog.
+const END_CONVERSATION: i8 = -1;
+
+pub fn talk(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 4 {
+        return None;
+    }
+
+    let object_id = buffer.get_i32_le();
+
+    let client_mutex = client.clone();
+    let result = character::with_character(&client, |client, character| {
+        let map = Channel::get()?.map(character.map_id)?;
+        let npc_id = match map.lock() {
+            Ok(map_guard) => match map_guard.npc(object_id) {
+                Some(npc) => npc.npc_id,
+                None => return Err(format!("NPC {} is not in map {}", object_id, character.map_id).into()),
+            },
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+
+        let storage_fees = NpcData::get(npc_id).and_then(|npc_data| npc_data.storage_fees);
+        match (NpcShop::load(npc_id)?, storage_fees) {
+            (Some(npc_shop), _) => {
+                client.send(create_open_shop(&npc_shop));
+                match client.npc_shop.lock() {
+                    Ok(mut shop_guard) => *shop_guard = Some(npc_shop),
+                    Err(error) => return Err(format!("Unable to lock NpcShop Mutex [{}]", error).into()),
+                };
+            }
+            (None, Some(storage_fees)) => {
+                let storage = AccountStorage::load(character, npc_id, storage_fees)?;
+                client.send(create_open_storage(&storage));
+                match client.storage.lock() {
+                    Ok(mut storage_guard) => *storage_guard = Some(storage),
+                    Err(error) => return Err(format!("Unable to lock AccountStorage Mutex [{}]", error).into()),
+                };
+            }
+            (None, None) => {
+                if !script::npc::start(&client_mutex, client, npc_id)? {
+                    debug!("NPC {} has nothing to say to {}", npc_id, character.name);
+                    client.send(create_update_stats(character, &[], true));
+                }
+            }
+        }
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Unable to talk to NPC [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
+
+pub fn talk_more(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 2 {
+        return None;
+    }
+
+    let message_type = buffer.get_u8();
+    let action = buffer.get_i8();
+    let answer = match (message_type, action) {
+        (_, END_CONVERSATION) => NpcAnswer::End,
+        (0, 1) | (1, 1) => NpcAnswer::Yes,
+        (1, 0) => NpcAnswer::No,
+        (2, 1) => NpcAnswer::Text(get_maple_string(buffer)?),
+        (3, 1) | (4, 1) => match buffer.remaining() {
+            0 => return None,
+            1..=3 => NpcAnswer::Selection(buffer.get_u8() as i32),
+            _ => NpcAnswer::Selection(buffer.get_i32_le()),
+        },
+        // Going back to a previous dialog is not supported, so it ends the conversation as well.
+        _ => NpcAnswer::End,
+    };
+
+    let result = match client.lock() {
+        Ok(client_guard) => script::npc::answer(&client_guard, answer),
+        Err(error) => Err(format!("Unable to lock Client Mutex [{}]", error).into()),
+    };
+
+    if let Err(error) = result {
+        debug!("Unable to continue NPC conversation [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
+
+pub fn shop_action(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 1 {
+        return None;
+    }
+
+    let mode = buffer.get_u8();
+    let request = match mode {
+        0 | 1 if buffer.remaining() >= 8 => (buffer.get_i16_le(), buffer.get_i32_le(), buffer.get_i16_le()),
+        2 if buffer.remaining() >= 2 => (buffer.get_i16_le(), 0, 0),
+        3 => (0, 0, 0),
+        _ => return None,
+    };
+
+    let result = character::with_character(&client, |client, character| {
+        let mut shop_guard = match client.npc_shop.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock NpcShop Mutex [{}]", error).into()),
+        };
+        let npc_shop = match (mode, shop_guard.as_ref()) {
+            (3, _) => {
+                *shop_guard = None;
+                return Ok(());
+            }
+            (_, Some(npc_shop)) => npc_shop,
+            (_, None) => return Err(format!("{} has no shop open", character.name).into()),
+        };
+
+        let mut inventory = character::lock_inventory(client)?;
+        let (position, item_id, quantity) = request;
+        let (result, operations) = match mode {
+            0 => shop::buy(character, &mut inventory, npc_shop, position, item_id, quantity)?,
+            1 => shop::sell(character, &mut inventory, npc_shop, position, item_id, quantity)?,
+            _ => shop::recharge(character, &mut inventory, npc_shop, position)?,
+        };
+
+        if !operations.is_empty() {
+            client.send(create_modify_inventory(&operations, true));
+            client.send(create_update_stats(character, &[Stat::Meso], false));
+        }
+        client.send(create_shop_result(result));
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected shop transaction, possibly a hack attempt [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
+
+const TAKE_ITEM: u8 = 4;
+const STORE_ITEM: u8 = 5;
+const ARRANGE_ITEMS: u8 = 6;
+const MOVE_MESO: u8 = 7;
+const CLOSE_STORAGE: u8 = 8;
+
+pub fn storage_action(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 1 {
+        return None;
+    }
+
+    let mode = buffer.get_u8();
+    match mode {
+        TAKE_ITEM if buffer.remaining() >= 2 => {}
+        STORE_ITEM if buffer.remaining() >= 8 => {}
+        MOVE_MESO if buffer.remaining() >= 4 => {}
+        ARRANGE_ITEMS | CLOSE_STORAGE => {}
+        _ => return None,
+    }
+
+    let result = character::with_character(&client, |client, character| {
+        let mut storage_guard = match client.storage.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock AccountStorage Mutex [{}]", error).into()),
+        };
+        let storage = match (mode, storage_guard.as_mut()) {
+            (CLOSE_STORAGE, _) => {
+                *storage_guard = None;
+                return Ok(());
+            }
+            (_, Some(storage)) => storage,
+            (_, None) => return Err(format!("{} has no storage open", character.name).into()),
+        };
+
+        match mode {
+            TAKE_ITEM | STORE_ITEM => {
+                let mut inventory = character::lock_inventory(client)?;
+                let (result, operations, inventory_type) = match mode {
+                    TAKE_ITEM => {
+                        let inventory_type = match InventoryType::from_i16(buffer.get_u8() as i16) {
+                            Some(InventoryType::Equipped) | None => return Err("Unknown storage tab".into()),
+                            Some(inventory_type) => inventory_type,
+                        };
+                        let index = buffer.get_u8();
+                        let (result, operations) =
+                            storage::take(character, &mut inventory, storage, inventory_type, index)?;
+                        (result, operations, inventory_type)
+                    }
+                    _ => {
+                        let source = buffer.get_i16_le();
+                        let item_id = buffer.get_i32_le();
+                        let quantity = buffer.get_i16_le();
+                        let (result, operations) =
+                            storage::store(character, &mut inventory, storage, source, item_id, quantity)?;
+                        let inventory_type = InventoryType::of_item(item_id).unwrap_or(InventoryType::Etc);
+                        (result, operations, inventory_type)
+                    }
+                };
+
+                if !operations.is_empty() {
+                    client.send(create_modify_inventory(&operations, true));
+                    client.send(create_update_stats(character, &[Stat::Meso], false));
+                }
+                match result {
+                    StorageResult::Took | StorageResult::Stored => {
+                        client.send(create_storage_tab(result, storage, inventory_type))
+                    }
+                    _ => client.send(create_storage_message(result)),
+                }
+            }
+            ARRANGE_ITEMS => {
+                storage::arrange(storage)?;
+                client.send(create_storage_arranged(storage));
+            }
+            _ => match storage::move_meso(character, storage, buffer.get_i32_le())? {
+                StorageResult::MesoMoved => {
+                    client.send(create_update_stats(character, &[Stat::Meso], false));
+                    client.send(create_storage_meso(storage));
+                }
+                result => client.send(create_storage_message(result)),
+            },
+        }
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected storage action, possibly a hack attempt [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
@@ -0,0 +1,63 @@
+use crate::game::character;
+use crate::game::party::{self, PartyRequest};
+use crate::net::client::Client;
+use crate::net::packet::get_maple_string;
+use bytes::Buf;
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+const CREATE_PARTY: u8 = 1;
+const LEAVE_PARTY: u8 = 2;
+const ACCEPT_INVITATION: u8 = 3;
+const INVITE_PLAYER: u8 = 4;
+const EXPEL_MEMBER: u8 = 5;
+const CHANGE_LEADER: u8 = 6;
+
+pub fn party_operation(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if !buffer.has_remaining() {
+        return None;
+    }
+
+    let request = match buffer.get_u8() {
+        CREATE_PARTY => PartyRequest::Create,
+        LEAVE_PARTY => PartyRequest::Leave,
+        INVITE_PLAYER => PartyRequest::Invite(get_maple_string(buffer)?),
+        operation @ (ACCEPT_INVITATION | EXPEL_MEMBER | CHANGE_LEADER) => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            let id = buffer.get_i32_le();
+            match operation {
+                ACCEPT_INVITATION => PartyRequest::Accept(id),
+                EXPEL_MEMBER => PartyRequest::Expel(id),
+                _ => PartyRequest::ChangeLeader(id),
+            }
+        }
+        operation => {
+            warn!("Received unknown party operation {}", operation);
+            return None;
+        }
+    };
+
+    let result = character::with_character(&client, |_, character| Ok(character.id))
+        .and_then(|character_id| party::request(character_id, request));
+    if let Err(error) = result {
+        warn!("Unable to handle party operation [{}]", error);
+    }
+    None
+}
+
+pub fn deny_party_request(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if !buffer.has_remaining() {
+        return None;
+    }
+    buffer.advance(1);
+    let inviter_name = get_maple_string(buffer)?;
+
+    let result = character::with_character(&client, |_, character| Ok(character.id))
+        .and_then(|character_id| party::request(character_id, PartyRequest::Deny(inviter_name)));
+    if let Err(error) = result {
+        warn!("Unable to deny party invitation [{}]", error);
+    }
+    None
+}
@@ -0,0 +1,69 @@
+use crate::data::quest::QuestData;
+use crate::game::character;
+use crate::game::quest;
+use crate::net::client::Client;
+use crate::net::handler::channel::drop;
+use crate::script;
+use bytes::Buf;
+use log::{debug, warn};
+use std::sync::{Arc, Mutex};
+
+const RESTORE_LOST_ITEM: u8 = 0;
+const START_QUEST: u8 = 1;
+const COMPLETE_QUEST: u8 = 2;
+const FORFEIT_QUEST: u8 = 3;
+const START_SCRIPTED_QUEST: u8 = 4;
+const COMPLETE_SCRIPTED_QUEST: u8 = 5;
+
+pub fn quest_action(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 3 {
+        return None;
+    }
+
+    let action = buffer.get_u8();
+    let quest_id = buffer.get_u16_le() as i32;
+    let npc_id = match action {
+        START_QUEST | COMPLETE_QUEST | START_SCRIPTED_QUEST | COMPLETE_SCRIPTED_QUEST => {
+            if buffer.remaining() < 4 {
+                return None;
+            }
+            buffer.get_i32_le()
+        }
+        _ => 0,
+    };
+
+    let client_mutex = client.clone();
+    let result = character::with_character(&client, |client, character| match action {
+        START_QUEST | COMPLETE_QUEST => {
+            let mut inventory = character::lock_inventory(client)?;
+            let mut quests = character::lock_quests(client)?;
+            match action {
+                START_QUEST => quest::start(client, character, &mut inventory, &mut quests, quest_id, npc_id),
+                _ => quest::complete(client, character, &mut inventory, &mut quests, quest_id, npc_id),
+            }
+        }
+        FORFEIT_QUEST => quest::forfeit(client, &mut *character::lock_quests(client)?, quest_id),
+        START_SCRIPTED_QUEST | COMPLETE_SCRIPTED_QUEST => {
+            let completing = action == COMPLETE_SCRIPTED_QUEST;
+            let scripted = QuestData::get(quest_id).is_some_and(|data| match completing {
+                true => data.complete.script.is_some(),
+                false => data.start.script.is_some(),
+            });
+            if !scripted || !script::npc::start_quest(&client_mutex, client, npc_id, quest_id, completing)? {
+                return Err(format!("Quest {} has no script", quest_id).into());
+            }
+            Ok(())
+        }
+        RESTORE_LOST_ITEM => {
+            debug!("{} asked for a lost item of quest {}, which is not handled yet", character.name, quest_id);
+            Ok(())
+        }
+        _ => Err(format!("Unknown quest action {}", action).into()),
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected quest action, possibly a hack attempt [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
@@ -0,0 +1,56 @@
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::reactor;
+use crate::net::client::Client;
+use bytes::Buf;
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+/// How far from a reactor, in pixels along both axes, a character may be to hit it.
+const MAX_HIT_DISTANCE: i32 = 300;
+
+pub fn hit_reactor(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 10 {
+        return None;
+    }
+
+    let object_id = buffer.get_i32_le();
+    buffer.advance(4); // character position
+    let stance = buffer.get_i16_le();
+
+    let result = character::with_character(&client, |_, character| {
+        let map = Channel::get()?.map(character.map_id)?;
+        let mut map_guard = match map.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+
+        let position = match map_guard.player(character.id) {
+            Some(player) => player.position,
+            None => return Err(format!("{} is not in map {}", character.name, character.map_id).into()),
+        };
+        let reactor_position = match map_guard.reactor(object_id) {
+            Some(reactor) => reactor.position,
+            None => return Err(format!("Unknown reactor object {}", object_id).into()),
+        };
+        let distance = (position.0 as i32 - reactor_position.0 as i32).abs()
+            + (position.1 as i32 - reactor_position.1 as i32).abs();
+        if distance > MAX_HIT_DISTANCE {
+            return Err(format!("{} is {} away from reactor {}", character.name, distance, object_id).into());
+        }
+
+        let destroyed = map_guard.hit_reactor(object_id, stance)?;
+        Ok(destroyed.map(|destroyed| (character.id, character.map_id, destroyed)))
+    });
+
+    match result {
+        Ok(Some((character_id, map_id, destroyed))) => {
+            if let Err(error) = reactor::destroyed(&client, character_id, map_id, &destroyed) {
+                warn!("Unable to reward destroying reactor {} [{}]", destroyed.reactor_id(), error);
+            }
+        }
+        Ok(None) => {}
+        Err(error) => warn!("Rejected reactor hit, possibly a hack attempt [{}]", error),
+    }
+    None
+}
@@ -0,0 +1,124 @@
+use crate::data::skill::SkillData;
+use crate::game::buff::{self, Buff};
+use crate::game::channel::Channel;
+use crate::game::character;
+use crate::game::item;
+use crate::game::party;
+use crate::net::client::Client;
+use crate::net::handler::channel::drop;
+use crate::net::packet::buff::{create_give_buff, create_give_foreign_buff};
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::field::{self, Effect};
+use crate::net::packet::item::create_modify_inventory;
+use bytes::Buf;
+use log::{debug, warn};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Casts a skill that is not an attack. Party skills only reach the caster until characters
+/// can form parties.
+pub fn use_skill(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 5 {
+        return None;
+    }
+
+    let skill_id = buffer.get_i32_le();
+    let level = buffer.get_u8();
+
+    let result = character::with_character(&client, |client, character| {
+        let mut inventory = character::lock_inventory(client)?;
+        let skills = character::lock_skills(client)?;
+        let mut buffs = character::lock_buffs(client)?;
+
+        let learned_level = skills.level(skill_id);
+        if learned_level == 0 || level != learned_level {
+            return Err(format!(
+                "{} used skill {} at level {} instead of {}",
+                character.name, skill_id, level, learned_level
+            )
+            .into());
+        }
+
+        let data = match SkillData::get(skill_id) {
+            Some(data) => data,
+            None => return Err(format!("Skill {} does not exist", skill_id).into()),
+        };
+        let level_data = match data.level(level) {
+            Some(level_data) => level_data,
+            None => return Err(format!("Skill {} has no level {}", skill_id, level).into()),
+        };
+
+        let now = Instant::now();
+        if buffs.is_cooling_down(skill_id, now) {
+            return Err(format!("Skill {} is still cooling down", skill_id).into());
+        }
+        if character.mp < level_data.mp_cost || character.hp <= level_data.hp_cost {
+            return Err(format!("{} cannot afford skill {}", character.name, skill_id).into());
+        }
+
+        if let Some((item_id, quantity)) = level_data.item_cost {
+            let operations = item::remove_item(&mut inventory, item_id, quantity)?;
+            client.send(create_modify_inventory(&operations, false));
+        }
+        character.mp -= level_data.mp_cost;
+        character.hp -= level_data.hp_cost;
+        client.send(create_update_stats(character, &[Stat::Hp, Stat::Mp], true));
+        if level_data.hp_cost > 0 {
+            party::update_hp(character, false)?;
+        }
+
+        if let Some(cooldown) = level_data.cooldown {
+            buffs.start_cooldown(skill_id, now, cooldown);
+        }
+
+        let map = Channel::get()?.map(character.map_id)?;
+        let map_guard = match map.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+        };
+
+        client.send(field::create_show_own_effect(Effect::SkillUse(skill_id, level)));
+        map_guard.broadcast(
+            &field::create_show_foreign_effect(character.id, Effect::SkillUse(skill_id, level)),
+            Some(character.id),
+        );
+
+        if let Some(buff) = Buff::from_skill(skill_id, level_data, now) {
+            client.send(create_give_buff(&buff));
+            map_guard.broadcast(&create_give_foreign_buff(character.id, &buff), Some(character.id));
+            buffs.give(buff);
+        }
+
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected skill use, possibly a hack attempt [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
+
+pub fn cancel_buff(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 4 {
+        return None;
+    }
+
+    let skill_id = buffer.get_i32_le();
+
+    let result = character::with_character(&client, |client, character| {
+        let cancelled = character::lock_buffs(client)?.cancel(skill_id);
+        match cancelled {
+            Some(cancelled) => buff::cancel(client, character.id, character.map_id, &cancelled),
+            None => {
+                debug!("Buff of skill {} is already gone", skill_id);
+                Ok(())
+            }
+        }
+    });
+
+    if let Err(error) = result {
+        warn!("Unable to cancel buff [{}]", error);
+    }
+    None
+}
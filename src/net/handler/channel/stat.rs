@@ -0,0 +1,107 @@
+use crate::game::character;
+use crate::game::skill;
+use crate::game::stat;
+use crate::net::client::Client;
+use crate::net::handler::channel::drop;
+use crate::net::packet::character::{create_update_skill, create_update_stats, Stat};
+use bytes::Buf;
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+/// Auto assign never spreads AP over more stats than the client has buttons for.
+const MAX_AUTO_ASSIGN_STATS: u32 = 4;
+
+pub fn distribute_ap(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 8 {
+        return None;
+    }
+
+    buffer.advance(4); // timestamp
+    let stat = Stat::from_ap_mask(buffer.get_u32_le());
+
+    let result = character::with_character(&client, |client, character| {
+        let stat = match stat {
+            Some(stat) => stat,
+            None => return Err("AP cannot be spent on an unknown stat".into()),
+        };
+
+        stat::add_ability_points(character, stat, 1)?;
+        client.send(create_update_stats(character, &[stat, Stat::Ap], true));
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected AP distribution, possibly a hack attempt [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
+
+pub fn auto_distribute_ap(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 8 {
+        return None;
+    }
+
+    buffer.advance(4); // timestamp
+    let count = buffer.get_u32_le();
+    if count > MAX_AUTO_ASSIGN_STATS || buffer.remaining() < 8 * count as usize {
+        return None;
+    }
+    let distribution: Vec<(Option<Stat>, i32)> = (0..count)
+        .map(|_| (Stat::from_ap_mask(buffer.get_u32_le()), buffer.get_i32_le()))
+        .collect();
+
+    let result = character::with_character(&client, |client, character| {
+        let total = distribution.iter().fold(0i64, |total, (_, amount)| total + *amount as i64);
+        if total > character.ap as i64 {
+            return Err(format!("{} cannot spend {} of its {} AP", character.name, total, character.ap).into());
+        }
+
+        let mut stats = vec![Stat::Ap];
+        for (stat, amount) in &distribution {
+            let stat = match stat {
+                Some(stat) => *stat,
+                None => return Err("AP cannot be spent on an unknown stat".into()),
+            };
+            if let Err(error) = stat::add_ability_points(character, stat, *amount as i16) {
+                // The stats raised before this one stay raised, so the client still needs them.
+                client.send(create_update_stats(character, &stats, true));
+                return Err(error);
+            }
+            stats.push(stat);
+        }
+
+        client.send(create_update_stats(character, &stats, true));
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected AP auto assign, possibly a hack attempt [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
+
+pub fn distribute_sp(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 8 {
+        return None;
+    }
+
+    buffer.advance(4); // timestamp
+    let skill_id = buffer.get_i32_le();
+
+    let result = character::with_character(&client, |client, character| {
+        let mut skills = character::lock_skills(client)?;
+
+        let entry = skill::add_skill_point(character, &mut skills, skill_id)?;
+        client.send(create_update_stats(character, &[Stat::Sp], false));
+        client.send(create_update_skill(skill_id, entry));
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        warn!("Rejected SP distribution, possibly a hack attempt [{}]", error);
+        drop::enable_actions(&client);
+    }
+    None
+}
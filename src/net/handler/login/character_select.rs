@@ -0,0 +1,109 @@
+use crate::db::model::character::Character;
+use crate::db::model::migration::{CharacterMigration, NewCharacterMigration};
+use crate::net::ban_list::{self, BanList};
+use crate::net::client::Client;
+use crate::net::packet;
+use bytes::Buf;
+use log::{error, warn};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// The client sends its MAC addresses, and from some builds a hardware id, alongside the
+/// selected character. They are stored on the account and checked against the ban list.
+pub fn select_character(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
+    if buffer.remaining() < 4 {
+        return None;
+    }
+
+    let character_id = buffer.get_i32_le();
+    let mac_addresses: Vec<String> = match packet::get_maple_string(buffer) {
+        Some(value) => value
+            .split(',')
+            .map(ban_list::normalize_mac_address)
+            .filter(|mac_address| !mac_address.is_empty())
+            .collect(),
+        None => Vec::new(),
+    };
+    let hardware_id = packet::get_maple_string(buffer).filter(|value| !value.is_empty());
+
+    let client_guard = match client.lock() {
+        Ok(guard) => guard,
+        Err(error) => {
+            warn!("Unable to lock Client Mutext [{}]", error);
+            return None;
+        }
+    };
+
+    if !client_guard.pin_verified {
+        warn!("Received character selection before the PIN was entered");
+        client_guard.disconnect();
+        return None;
+    }
+
+    let user_mutex = match &client_guard.user {
+        Some(user_mutex) => user_mutex,
+        None => {
+            error!("Received authenticated packet from non-authenticated user");
+            return None;
+        }
+    };
+
+    let mut user = match user_mutex.lock() {
+        Ok(user) => user,
+        Err(error) => {
+            warn!("Unable to lock User Mutext [{}]", error);
+            return None;
+        }
+    };
+
+    match Character::get_by_id(character_id) {
+        Ok(Some(character)) if character.user_id == user.id => {}
+        Ok(_) => {
+            warn!("{} selected character {} which is not theirs", user.username, character_id);
+            client_guard.disconnect();
+            return None;
+        }
+        Err(error) => {
+            warn!("Problem querying the database [{}]", error);
+            return None;
+        }
+    };
+
+    let ip_address = client_guard.address().map(|address| address.to_string());
+    match user.update_machine_identifiers(ip_address.clone(), mac_addresses.join(","), hardware_id.clone()) {
+        Ok(_) => {}
+        Err(error) => warn!("Unable to update User machine identifiers [{}]", error),
+    };
+
+    if let Some(ban) = BanList::banned_machine(&mac_addresses, hardware_id.as_deref()) {
+        warn!("{} is connecting from a banned machine (infraction {})", user.username, ban.id);
+        client_guard.disconnect();
+        return None;
+    }
+
+    // The channel server only lets the character in from the address that selected it.
+    let new_migration = match ip_address {
+        Some(ip_address) => NewCharacterMigration {
+            user_id: user.id,
+            character_id,
+            ip_address,
+            creation_date: SystemTime::now(),
+        },
+        None => {
+            warn!("Unable to determine the address of {}", user.username);
+            client_guard.disconnect();
+            return None;
+        }
+    };
+    match CharacterMigration::create(&new_migration) {
+        Ok(()) => {}
+        Err(error) => {
+            warn!("Unable to store the channel handoff of {} [{}]", user.username, error);
+            client_guard.disconnect();
+            return None;
+        }
+    };
+
+    // Redirecting to the channel server is not implemented yet.
+    None
+}
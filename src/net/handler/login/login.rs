@@ -1,13 +1,15 @@
+use crate::db::model::infraction::{Infraction, InfractionKind};
 use crate::db::model::user::{self, User};
 use crate::net::client::Client;
+use crate::net::throttle::{AttemptKey, AttemptLimiter};
 use bytes::{Buf, BufMut, BytesMut};
-use log::warn;
+use log::{info, warn};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use std::error::Error;
 use std::fmt::Write;
 use std::sync::{Arc, Mutex};
-use std::{env, time::SystemTime};
+use std::{env, time::SystemTime, time::SystemTimeError};
 
 pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
     let username_length = buffer.get_u16_le();
@@ -19,6 +21,33 @@ pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>,
     buffer.copy_to_slice(&mut password);
 
     let mut response = BytesMut::new();
+    let address = match client.lock() {
+        Ok(client_guard) => client_guard.address(),
+        Err(error) => {
+            warn!("Unable to lock Client Mutext [{}]", error);
+            None
+        }
+    };
+    let ip_address = address.map(|address| address.to_string());
+
+    let attempt_keys: Vec<AttemptKey> = address
+        .map(AttemptKey::Address)
+        .into_iter()
+        .chain(std::iter::once(AttemptKey::Account(
+            String::from_utf8_lossy(&username).to_string(),
+        )))
+        .collect();
+
+    if let Some(blocked_for) = AttemptLimiter::blocked_for(&attempt_keys) {
+        info!(
+            "refused login attempt for {} from [{}], blocked for {} more seconds",
+            String::from_utf8_lossy(&username),
+            ip_address.as_deref().unwrap_or("unknown"),
+            blocked_for.as_secs()
+        );
+        create_simple_login_response(&mut response, LoginResponseType::TooManyConnections);
+        return Some((response.to_vec(), response.len()));
+    }
 
     match std::str::from_utf8(&username[..]) {
         Ok(string_username) => match std::str::from_utf8(&password[..]) {
@@ -34,16 +63,24 @@ pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>,
                                             LoginResponseType::AlreadyLoggedIn,
                                         );
                                     } else {
-                                        if user.ban_reset_date > SystemTime::now() {
-                                            match user
-                                                .ban_reset_date
-                                                .duration_since(SystemTime::UNIX_EPOCH)
-                                            {
-                                                Ok(duraion) => {
+                                        let active_ban = Infraction::get_active(
+                                            InfractionKind::Ban,
+                                            Some(user.id),
+                                            ip_address.as_deref(),
+                                        );
+                                        let active_mute = Infraction::get_active(
+                                            InfractionKind::Mute,
+                                            Some(user.id),
+                                            ip_address.as_deref(),
+                                        );
+
+                                        if let Ok(Some(ban)) = active_ban {
+                                            match ban_reset_date(&ban) {
+                                                Ok(reset_date) => {
                                                     create_banned_login_response(
                                                         &mut response,
-                                                        user.ban_reason as u8,
-                                                        duraion.as_secs(),
+                                                        ban.reason as u8,
+                                                        reset_date,
                                                     );
                                                 }
                                                 Err(error) => {
@@ -54,12 +91,22 @@ pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>,
                                                     );
                                                 }
                                             }
+                                        } else if let (Err(error), _) | (_, Err(error)) = (&active_ban, &active_mute) {
+                                            warn!("Problem querying the database [{}]", error);
+                                            create_simple_login_response(
+                                                &mut response,
+                                                LoginResponseType::ServerError,
+                                            );
                                         } else {
                                             match create_login_success_response(
                                                 &mut response,
                                                 &user,
+                                                active_mute.ok().flatten().as_ref(),
                                             ) {
                                                 Ok(()) => {
+                                                    // One correct password says nothing of the other
+                                                    // accounts guessed from the same address.
+                                                    AttemptLimiter::reset(&[AttemptKey::Account(string_username.to_string())]);
                                                     match client.lock() {
                                                         Ok(ref mut client_guard) => {
                                                             client_guard.user =
@@ -87,6 +134,7 @@ pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>,
                                         }
                                     }
                                 } else {
+                                    AttemptLimiter::record_failure(&attempt_keys);
                                     create_simple_login_response(
                                         &mut response,
                                         LoginResponseType::IncorrectPassword,
@@ -123,10 +171,12 @@ pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>,
                                             logged_in: false,
                                             pin_code: None,
                                             creation_date: SystemTime::now(),
-                                            ban_reason: 0,
-                                            ban_reset_date: SystemTime::now(),
-                                            mute_reason: 0,
-                                            mute_reset_date: SystemTime::now(),
+                                            last_ip_address: ip_address.clone(),
+                                            mac_addresses: None,
+                                            hardware_id: None,
+                                            pin_attempts: 0,
+                                            pin_locked_until: None,
+                                            gm_level: 0,
                                             password: hash_obj.to_string(),
                                             salt: hash_obj.get_salt().into(),
                                         }) {
@@ -134,6 +184,7 @@ pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>,
                                                 match create_login_success_response(
                                                     &mut response,
                                                     &user,
+                                                    None,
                                                 ) {
                                                     Ok(()) => {
                                                         match client.lock() {
@@ -182,6 +233,7 @@ pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>,
                                     }
                                 };
                             } else {
+                                AttemptLimiter::record_failure(&attempt_keys);
                                 create_simple_login_response(
                                     &mut response,
                                     LoginResponseType::NotRegistered,
@@ -190,6 +242,7 @@ pub fn login(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>,
                         }
                         Err(error) => {
                             warn!("Unable to read AUTO_REGISTER value from .env [{}]", error);
+                            AttemptLimiter::record_failure(&attempt_keys);
                             create_simple_login_response(
                                 &mut response,
                                 LoginResponseType::NotRegistered,
@@ -229,7 +282,15 @@ fn create_banned_login_response(buffer: &mut BytesMut, ban_reason: u8, ban_reset
     buffer.put_u64_le(ban_reset_date);
 }
 
-fn create_login_success_response(buffer: &mut BytesMut, user: &User) -> Result<(), Box<dyn Error>> {
+/// Bans without an expiry date are reported with the furthest date the client can show.
+fn ban_reset_date(ban: &Infraction) -> Result<u64, SystemTimeError> {
+    match ban.expiry_date {
+        Some(expiry_date) => Ok(expiry_date.duration_since(SystemTime::UNIX_EPOCH)?.as_secs()),
+        None => Ok(u32::MAX as u64),
+    }
+}
+
+fn create_login_success_response(buffer: &mut BytesMut, user: &User, mute: Option<&Infraction>) -> Result<(), Box<dyn Error>> {
     buffer.put_u16_le(0); // OPCODE
     buffer.put_u32_le(LoginResponseType::LoginSuccess as u32);
     buffer.put_u16_le(0);
@@ -237,7 +298,7 @@ fn create_login_success_response(buffer: &mut BytesMut, user: &User) -> Result<(
     buffer.put_u32_le(user.id as u32);
     buffer.put_u8(0);
 
-    buffer.put_u16_le(match user.is_admin {
+    buffer.put_u16_le(match user.is_admin || user.gm_level > 0 {
         true => 0x8001,
         false => 0,
     });
@@ -247,12 +308,16 @@ fn create_login_success_response(buffer: &mut BytesMut, user: &User) -> Result<(
 
     buffer.put_u8(0);
 
-    buffer.put_u8(user.mute_reason as u8);
-    buffer.put_u64_le(
-        user.mute_reset_date
-            .duration_since(SystemTime::UNIX_EPOCH)?
-            .as_secs(),
-    );
+    match mute {
+        Some(mute) => {
+            buffer.put_u8(mute.reason as u8);
+            buffer.put_u64_le(ban_reset_date(mute)?);
+        }
+        None => {
+            buffer.put_u8(0);
+            buffer.put_u64_le(0);
+        }
+    }
 
     buffer.put_u64_le(
         user.creation_date
@@ -271,4 +336,5 @@ enum LoginResponseType {
     NotRegistered = 5,
     ServerError = 6,
     AlreadyLoggedIn = 7,
+    TooManyConnections = 10,
 }
\ No newline at end of file
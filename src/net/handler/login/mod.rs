@@ -1,5 +1,6 @@
+mod character_select;
 mod login;
-mod pin;
+pub mod pin;
 mod world_select;
 
 use crate::net::client::Client;
@@ -24,6 +25,7 @@ impl GenericHandler for LoginHandler {
             0x09u16 => pin::check_pin_code(client, &mut bytes),
             0x0Au16 => pin::insert_pin_code(client, &mut bytes),
             0x0Bu16 => None, // Show Worlds
+            0x13u16 => character_select::select_character(client, &mut bytes),
             _ => None,
         }
     }
@@ -1,7 +1,40 @@
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::defaults;
 use crate::net::client::Client;
+use crate::net::throttle::{AttemptKey, AttemptLimiter};
 use bytes::{Buf, BufMut, BytesMut};
-use log::{warn, error};
+use log::{info, warn, error};
+use once_cell::sync::OnceCell;
+
+/// How the PIN step after login behaves, read from the [Login] section of the general settings.
+pub struct PinPolicy {
+    pub enabled: bool,
+    pub max_attempts: i16,
+    pub lock_duration: Duration,
+}
+
+static PIN_POLICY_INSTANCE: OnceCell<PinPolicy> = OnceCell::new();
+
+impl PinPolicy {
+    pub fn init(policy: PinPolicy) {
+        if PIN_POLICY_INSTANCE.set(policy).is_err() {
+            warn!("PinPolicy has already been initialized");
+        }
+    }
+
+    fn get() -> &'static PinPolicy {
+        PIN_POLICY_INSTANCE.get_or_init(|| PinPolicy {
+            enabled: true,
+            max_attempts: defaults::DEFAULT_PIN_MAX_ATTEMPTS,
+            lock_duration: Duration::from_secs(defaults::DEFAULT_PIN_LOCK_SECONDS),
+        })
+    }
+}
+
+fn is_valid_pin_code(pin_code: &str) -> bool {
+    pin_code.len() == defaults::PIN_CODE_LENGTH && pin_code.chars().all(|character| character.is_ascii_digit())
+}
 
 pub fn insert_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
     let mut response = BytesMut::new();
@@ -36,7 +69,18 @@ pub fn insert_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option
                                 }
                             };
 
-                            match user.update_pin_code(string_pin_code) {
+                            if user.pin_code.is_some() && !client_guard.pin_verified {
+                                warn!("{} tried to replace their PIN without entering it first", user.username);
+                                return None;
+                            }
+
+                            if !is_valid_pin_code(&string_pin_code) {
+                                warn!("{} sent an invalid PIN", user.username);
+                                create_simple_pin_response(&mut response, PinResponseType::InsertNewPin);
+                                return Some((response.to_vec(), response.len()));
+                            }
+
+                            match user.update_pin_code(&string_pin_code) {
                                 Ok(_) => create_simple_pin_response(&mut response, PinResponseType::PinAccepted),
                                 Err(error) => {
                                     warn!("Unable to update User pin code [{}]", error);
@@ -49,6 +93,8 @@ pub fn insert_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option
                             return None;
                         }
                     };
+                    // Choosing the PIN proves knowing it, as entering it would.
+                    client_guard.pin_verified = true;
                 }
                 Err(error) => {
                     warn!("Unable to convert byte array to String [{}]", error);
@@ -66,6 +112,7 @@ pub fn insert_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option
 pub fn check_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<(Vec<u8>, usize)> {
     let mut response = BytesMut::new();
     let sub_stage = buffer.get_u8();
+    let policy = PinPolicy::get();
     let mut client_guard = match client.lock() {
         Ok(guard) => guard,
         Err(error) => {
@@ -85,17 +132,13 @@ pub fn check_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<
 
         if stage == 1 {
             if sub_stage == 1 {
-                match &client_guard.user {
+                let response_type = match &client_guard.user {
                     Some(user_mutex) => match user_mutex.lock() {
-                        Ok(user) => match user.pin_code {
-                            Some(_) => create_simple_pin_response(
-                                &mut response,
-                                PinResponseType::EnterPin,
-                            ),
-                            None => create_simple_pin_response(
-                                &mut response,
-                                PinResponseType::InsertNewPin,
-                            ),
+                        Ok(user) => match (policy.enabled, &user.pin_code) {
+                            (false, _) => PinResponseType::PinAccepted,
+                            (true, Some(_)) if user.is_pin_locked() => PinResponseType::SystemError,
+                            (true, Some(_)) => PinResponseType::EnterPin,
+                            (true, None) => PinResponseType::InsertNewPin,
                         },
                         Err(error) => {
                             warn!("Unable to lock User Mutex [{}]", error);
@@ -107,6 +150,11 @@ pub fn check_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<
                         return None;
                     }
                 };
+
+                if !policy.enabled {
+                    client_guard.pin_verified = true;
+                }
+                create_simple_pin_response(&mut response, response_type);
             }
         } else if stage == 0 {
             buffer.get_u32();
@@ -122,11 +170,32 @@ pub fn check_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<
                 }
             };
 
+            let address_key: Vec<AttemptKey> = client_guard.address().map(AttemptKey::Address).into_iter().collect();
+            let mut pin_verified = client_guard.pin_verified;
+
             match &client_guard.user {
                 Some(user_mutex) => match user_mutex.lock() {
-                    Ok(user) => match &user.pin_code {
-                        Some(db_pin_code) => {
-                            if db_pin_code.eq(&str_pin_code) {
+                    Ok(mut user) => match user.pin_code.is_some() {
+                        true => {
+                            if user.is_pin_locked() || AttemptLimiter::blocked_for(&address_key).is_some() {
+                                info!("refused PIN attempt for {}, PIN entry is locked", user.username);
+                                create_simple_pin_response(
+                                    &mut response,
+                                    PinResponseType::SystemError,
+                                );
+                            } else if user.verify_pin_code(&str_pin_code).unwrap_or(false) {
+                                pin_verified = true;
+                                if user.pin_attempts > 0 {
+                                    if let Err(error) = user.reset_pin_failures() {
+                                        warn!("Unable to reset User PIN attempts [{}]", error);
+                                    }
+                                }
+                                if user.has_legacy_pin_code() {
+                                    if let Err(error) = user.update_pin_code(&str_pin_code) {
+                                        warn!("Unable to hash User legacy PIN [{}]", error);
+                                    }
+                                }
+
                                 if sub_stage == 1 {
                                     create_simple_pin_response(
                                         &mut response,
@@ -141,13 +210,17 @@ pub fn check_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<
                                     return None;
                                 }
                             } else {
+                                AttemptLimiter::record_failure(&address_key);
+                                if let Err(error) = user.record_pin_failure(policy.max_attempts, policy.lock_duration) {
+                                    warn!("Unable to update User PIN attempts [{}]", error);
+                                }
                                 create_simple_pin_response(
                                     &mut response,
                                     PinResponseType::PinFailed,
                                 );
                             }
                         },
-                        None => create_simple_pin_response(&mut response, PinResponseType::InsertNewPin)
+                        false => create_simple_pin_response(&mut response, PinResponseType::InsertNewPin)
                     },
                     Err(error) => {
                         warn!("Unable to lock User Mutext [{}]", error);
@@ -159,6 +232,8 @@ pub fn check_pin_code(client: Arc<Mutex<Client>>, buffer: &mut &[u8]) -> Option<
                     return None;
                 }
             };
+
+            client_guard.pin_verified = pin_verified;
         }
     }
 
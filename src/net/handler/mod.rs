@@ -1,5 +1,5 @@
-mod channel_handler;
-mod login;
+mod channel;
+pub mod login;
 mod world_handler;
 use bytes::Buf;
 use std::sync::{Arc, Mutex};
@@ -14,6 +14,8 @@ pub trait GenericHandler {
         buffer: Vec<u8>,
         buffer_size: usize,
     ) -> Option<(Vec<u8>, usize)>;
+
+    fn disconnect(&self, _client: Arc<Mutex<Client>>) {}
 }
 
 pub struct CommonHandler {
@@ -39,6 +41,10 @@ impl CommonHandler {
         }
     }
 
+    pub fn disconnect(&self, client: Arc<Mutex<Client>>) {
+        self.handler.disconnect(client)
+    }
+
     fn handle_pong(client: Arc<Mutex<Client>>, _buffer: Vec<u8>, _buffer_size: usize) -> Option<(Vec<u8>, usize)> {
         let mut client_guard = match client.lock() {
             Ok(guard) => guard,
@@ -64,9 +70,7 @@ impl Clone for CommonHandler {
 pub fn get_handler_by_name(handler_name: &str) -> Option<CommonHandler> {
     match handler_name {
         "login" => Some(CommonHandler::new(Arc::new(login::LoginHandler {}))),
-        "channel" => Some(CommonHandler::new(Arc::new(
-            channel_handler::ChannelHandler {},
-        ))),
+        "channel" => Some(CommonHandler::new(Arc::new(channel::ChannelHandler {}))),
         "world" => Some(CommonHandler::new(Arc::new(world_handler::WorldHandler {}))),
         _ => None,
     }
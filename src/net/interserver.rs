@@ -0,0 +1,950 @@
+use crate::game::channel::Channel;
+use crate::game::buddy;
+use crate::game::character;
+use crate::game::guild::{self, Guild, GuildChange, GuildEmblem, GuildMember, GuildRequest, GuildResult};
+use crate::game::party::{self, Party, PartyChange, PartyMember, PartyRequest, PartyResult};
+use crate::game::world::World;
+use crate::net::packet::message::{self, ChatGroup};
+use crate::net::packet::{get_maple_string, PacketWriter};
+use bytes::{Buf, BufMut, BytesMut};
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Largest message the servers accept from each other.
+const MAX_MESSAGE_LENGTH: usize = 1 << 20;
+/// How long a channel server waits before connecting to its world server again.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// How long the world server waits for a new connection to register as a channel.
+const REGISTER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a character plays, as channel servers report it to their world server.
+pub struct PlayerStatus {
+    pub character_id: i32,
+    pub name: String,
+    pub job: i16,
+    pub level: i16,
+    pub channel_id: u8,
+    pub map_id: i32,
+}
+
+/// Messages channel servers send to their world server.
+pub enum ChannelMessage {
+    /// The first message of every connection, carrying the secret shared by the servers.
+    RegisterChannel(u8, String),
+    /// Sent when a character logs in, and again whenever its map, job or level changes.
+    PlayerOnline(PlayerStatus),
+    PlayerOffline(i32),
+    Party(i32, PartyRequest),
+    PartyChat(i32, String),
+    /// The buddies a character that is online accepted, sent when it logs in and whenever they
+    /// change.
+    Buddies(i32, Vec<i32>),
+    BuddyRequest {
+        character_id: i32,
+        from_id: i32,
+        from_name: String,
+    },
+    Guild(i32, GuildRequest),
+    GuildChat(i32, String),
+}
+
+/// Messages the world server sends to its channel servers.
+pub enum WorldMessage {
+    PartyUpdate(Party, PartyChange),
+    PartyInvitation {
+        character_id: i32,
+        party_id: i32,
+        inviter_name: String,
+    },
+    PartyResult(i32, PartyResult),
+    /// A chat message of `name` to the members of its group that play on the channel.
+    MultiChat {
+        group: ChatGroup,
+        recipients: Vec<i32>,
+        name: String,
+        text: String,
+    },
+    BuddyRequest {
+        character_id: i32,
+        from_id: i32,
+        from_name: String,
+        channel_id: u8,
+    },
+    /// The buddy of `character_id` moved to `channel_id`, or went offline.
+    BuddyChannel {
+        character_id: i32,
+        buddy_id: i32,
+        channel_id: Option<u8>,
+    },
+    GuildUpdate(Guild, GuildChange),
+    GuildInvitation {
+        character_id: i32,
+        guild_id: i32,
+        inviter_name: String,
+    },
+    GuildResult(i32, GuildResult),
+    /// The guild request of the character was rejected.
+    GuildFailed(i32),
+}
+
+fn get_u8(buffer: &mut &[u8]) -> Option<u8> {
+    match buffer.remaining() >= 1 {
+        true => Some(buffer.get_u8()),
+        false => None,
+    }
+}
+
+fn get_i16(buffer: &mut &[u8]) -> Option<i16> {
+    match buffer.remaining() >= 2 {
+        true => Some(buffer.get_i16_le()),
+        false => None,
+    }
+}
+
+fn get_i32(buffer: &mut &[u8]) -> Option<i32> {
+    match buffer.remaining() >= 4 {
+        true => Some(buffer.get_i32_le()),
+        false => None,
+    }
+}
+
+fn put_ids(buffer: &mut BytesMut, ids: &[i32]) {
+    buffer.put_u8(ids.len() as u8);
+    for id in ids {
+        buffer.put_i32_le(*id);
+    }
+}
+
+fn get_ids(buffer: &mut &[u8]) -> Option<Vec<i32>> {
+    let count = get_u8(buffer)?;
+    let mut ids = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        ids.push(get_i32(buffer)?);
+    }
+    Some(ids)
+}
+
+fn put_strings(buffer: &mut BytesMut, strings: &[String]) {
+    buffer.put_u8(strings.len() as u8);
+    for string in strings {
+        buffer.put_maple_string(string);
+    }
+}
+
+fn get_strings(buffer: &mut &[u8]) -> Option<Vec<String>> {
+    let count = get_u8(buffer)?;
+    let mut strings = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        strings.push(get_maple_string(buffer)?);
+    }
+    Some(strings)
+}
+
+fn put_emblem(buffer: &mut BytesMut, emblem: GuildEmblem) {
+    buffer.put_i16_le(emblem.background);
+    buffer.put_u8(emblem.background_color);
+    buffer.put_i16_le(emblem.logo);
+    buffer.put_u8(emblem.logo_color);
+}
+
+fn get_emblem(buffer: &mut &[u8]) -> Option<GuildEmblem> {
+    Some(GuildEmblem {
+        background: get_i16(buffer)?,
+        background_color: get_u8(buffer)?,
+        logo: get_i16(buffer)?,
+        logo_color: get_u8(buffer)?,
+    })
+}
+
+fn put_guild(buffer: &mut BytesMut, guild: &Guild) {
+    buffer.put_i32_le(guild.id);
+    buffer.put_i16_le(guild.world_id);
+    buffer.put_maple_string(&guild.name);
+    buffer.put_i32_le(guild.leader_id);
+    put_strings(buffer, &guild.rank_titles);
+    buffer.put_u8(guild.capacity);
+    put_emblem(buffer, guild.emblem);
+    buffer.put_maple_string(&guild.notice);
+    buffer.put_i32_le(guild.points);
+    buffer.put_u8(guild.members.len() as u8);
+    for member in &guild.members {
+        buffer.put_i32_le(member.character_id);
+        buffer.put_maple_string(&member.name);
+        buffer.put_i16_le(member.job);
+        buffer.put_i16_le(member.level);
+        buffer.put_u8(member.rank);
+        buffer.put_u8(member.online as u8);
+    }
+}
+
+fn get_guild(buffer: &mut &[u8]) -> Option<Guild> {
+    let id = get_i32(buffer)?;
+    let world_id = get_i16(buffer)?;
+    let name = get_maple_string(buffer)?;
+    let leader_id = get_i32(buffer)?;
+    let rank_titles = get_strings(buffer)?;
+    let capacity = get_u8(buffer)?;
+    let emblem = get_emblem(buffer)?;
+    let notice = get_maple_string(buffer)?;
+    let points = get_i32(buffer)?;
+    let count = get_u8(buffer)?;
+    let mut members = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        members.push(GuildMember {
+            character_id: get_i32(buffer)?,
+            name: get_maple_string(buffer)?,
+            job: get_i16(buffer)?,
+            level: get_i16(buffer)?,
+            rank: get_u8(buffer)?,
+            online: get_u8(buffer)? != 0,
+        });
+    }
+    Some(Guild {
+        id,
+        world_id,
+        name,
+        leader_id,
+        rank_titles,
+        capacity,
+        emblem,
+        notice,
+        points,
+        members,
+    })
+}
+
+fn put_party(buffer: &mut BytesMut, party: &Party) {
+    buffer.put_i32_le(party.id);
+    buffer.put_i32_le(party.leader_id);
+    buffer.put_u8(party.members.len() as u8);
+    for member in &party.members {
+        buffer.put_i32_le(member.character_id);
+        buffer.put_maple_string(&member.name);
+        buffer.put_i16_le(member.job);
+        buffer.put_i16_le(member.level);
+        buffer.put_i16_le(member.channel_id.map_or(-1, |channel_id| channel_id as i16));
+        buffer.put_i32_le(member.map_id);
+    }
+}
+
+fn get_party(buffer: &mut &[u8]) -> Option<Party> {
+    let id = get_i32(buffer)?;
+    let leader_id = get_i32(buffer)?;
+    let count = get_u8(buffer)?;
+    let mut members = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        members.push(PartyMember {
+            character_id: get_i32(buffer)?,
+            name: get_maple_string(buffer)?,
+            job: get_i16(buffer)?,
+            level: get_i16(buffer)?,
+            channel_id: match get_i16(buffer)? {
+                channel_id if channel_id < 0 => None,
+                channel_id => Some(channel_id as u8),
+            },
+            map_id: get_i32(buffer)?,
+        });
+    }
+    Some(Party { id, leader_id, members })
+}
+
+impl ChannelMessage {
+    fn encode(&self) -> Vec<u8> {
+        let mut buffer = BytesMut::new();
+
+        match self {
+            ChannelMessage::RegisterChannel(channel_id, secret) => {
+                buffer.put_u8(0);
+                buffer.put_u8(*channel_id);
+                buffer.put_maple_string(secret);
+            }
+            ChannelMessage::PlayerOnline(status) => {
+                buffer.put_u8(1);
+                buffer.put_i32_le(status.character_id);
+                buffer.put_maple_string(&status.name);
+                buffer.put_i16_le(status.job);
+                buffer.put_i16_le(status.level);
+                buffer.put_u8(status.channel_id);
+                buffer.put_i32_le(status.map_id);
+            }
+            ChannelMessage::PlayerOffline(character_id) => {
+                buffer.put_u8(2);
+                buffer.put_i32_le(*character_id);
+            }
+            ChannelMessage::Party(character_id, request) => {
+                buffer.put_u8(3);
+                buffer.put_i32_le(*character_id);
+                match request {
+                    PartyRequest::Create => buffer.put_u8(0),
+                    PartyRequest::Leave => buffer.put_u8(1),
+                    PartyRequest::Accept(party_id) => {
+                        buffer.put_u8(2);
+                        buffer.put_i32_le(*party_id);
+                    }
+                    PartyRequest::Invite(name) => {
+                        buffer.put_u8(3);
+                        buffer.put_maple_string(name);
+                    }
+                    PartyRequest::Expel(target_id) => {
+                        buffer.put_u8(4);
+                        buffer.put_i32_le(*target_id);
+                    }
+                    PartyRequest::ChangeLeader(target_id) => {
+                        buffer.put_u8(5);
+                        buffer.put_i32_le(*target_id);
+                    }
+                    PartyRequest::Deny(inviter_name) => {
+                        buffer.put_u8(6);
+                        buffer.put_maple_string(inviter_name);
+                    }
+                }
+            }
+            ChannelMessage::PartyChat(character_id, text) => {
+                buffer.put_u8(4);
+                buffer.put_i32_le(*character_id);
+                buffer.put_maple_string(text);
+            }
+            ChannelMessage::Buddies(character_id, buddy_ids) => {
+                buffer.put_u8(5);
+                buffer.put_i32_le(*character_id);
+                put_ids(&mut buffer, buddy_ids);
+            }
+            ChannelMessage::BuddyRequest {
+                character_id,
+                from_id,
+                from_name,
+            } => {
+                buffer.put_u8(6);
+                buffer.put_i32_le(*character_id);
+                buffer.put_i32_le(*from_id);
+                buffer.put_maple_string(from_name);
+            }
+            ChannelMessage::Guild(character_id, request) => {
+                buffer.put_u8(7);
+                buffer.put_i32_le(*character_id);
+                match request {
+                    GuildRequest::Create(name) => {
+                        buffer.put_u8(0);
+                        buffer.put_maple_string(name);
+                    }
+                    GuildRequest::Invite(name) => {
+                        buffer.put_u8(1);
+                        buffer.put_maple_string(name);
+                    }
+                    GuildRequest::Accept(guild_id) => {
+                        buffer.put_u8(2);
+                        buffer.put_i32_le(*guild_id);
+                    }
+                    GuildRequest::Deny(inviter_name) => {
+                        buffer.put_u8(3);
+                        buffer.put_maple_string(inviter_name);
+                    }
+                    GuildRequest::Leave => buffer.put_u8(4),
+                    GuildRequest::Expel(target_id) => {
+                        buffer.put_u8(5);
+                        buffer.put_i32_le(*target_id);
+                    }
+                    GuildRequest::ChangeRankTitles(rank_titles) => {
+                        buffer.put_u8(6);
+                        put_strings(&mut buffer, rank_titles);
+                    }
+                    GuildRequest::ChangeRank(target_id, rank) => {
+                        buffer.put_u8(7);
+                        buffer.put_i32_le(*target_id);
+                        buffer.put_u8(*rank);
+                    }
+                    GuildRequest::ChangeEmblem(emblem) => {
+                        buffer.put_u8(8);
+                        put_emblem(&mut buffer, *emblem);
+                    }
+                    GuildRequest::ChangeNotice(notice) => {
+                        buffer.put_u8(9);
+                        buffer.put_maple_string(notice);
+                    }
+                    GuildRequest::Disband => buffer.put_u8(10),
+                    GuildRequest::GainPoints(amount) => {
+                        buffer.put_u8(11);
+                        buffer.put_i32_le(*amount);
+                    }
+                }
+            }
+            ChannelMessage::GuildChat(character_id, text) => {
+                buffer.put_u8(8);
+                buffer.put_i32_le(*character_id);
+                buffer.put_maple_string(text);
+            }
+        }
+
+        buffer.to_vec()
+    }
+
+    fn decode(mut buffer: &[u8]) -> Option<ChannelMessage> {
+        let buffer = &mut buffer;
+
+        match get_u8(buffer)? {
+            0 => Some(ChannelMessage::RegisterChannel(get_u8(buffer)?, get_maple_string(buffer)?)),
+            1 => Some(ChannelMessage::PlayerOnline(PlayerStatus {
+                character_id: get_i32(buffer)?,
+                name: get_maple_string(buffer)?,
+                job: get_i16(buffer)?,
+                level: get_i16(buffer)?,
+                channel_id: get_u8(buffer)?,
+                map_id: get_i32(buffer)?,
+            })),
+            2 => Some(ChannelMessage::PlayerOffline(get_i32(buffer)?)),
+            3 => {
+                let character_id = get_i32(buffer)?;
+                let request = match get_u8(buffer)? {
+                    0 => PartyRequest::Create,
+                    1 => PartyRequest::Leave,
+                    2 => PartyRequest::Accept(get_i32(buffer)?),
+                    3 => PartyRequest::Invite(get_maple_string(buffer)?),
+                    4 => PartyRequest::Expel(get_i32(buffer)?),
+                    5 => PartyRequest::ChangeLeader(get_i32(buffer)?),
+                    6 => PartyRequest::Deny(get_maple_string(buffer)?),
+                    _ => return None,
+                };
+                Some(ChannelMessage::Party(character_id, request))
+            }
+            4 => Some(ChannelMessage::PartyChat(get_i32(buffer)?, get_maple_string(buffer)?)),
+            5 => Some(ChannelMessage::Buddies(get_i32(buffer)?, get_ids(buffer)?)),
+            6 => Some(ChannelMessage::BuddyRequest {
+                character_id: get_i32(buffer)?,
+                from_id: get_i32(buffer)?,
+                from_name: get_maple_string(buffer)?,
+            }),
+            7 => {
+                let character_id = get_i32(buffer)?;
+                let request = match get_u8(buffer)? {
+                    0 => GuildRequest::Create(get_maple_string(buffer)?),
+                    1 => GuildRequest::Invite(get_maple_string(buffer)?),
+                    2 => GuildRequest::Accept(get_i32(buffer)?),
+                    3 => GuildRequest::Deny(get_maple_string(buffer)?),
+                    4 => GuildRequest::Leave,
+                    5 => GuildRequest::Expel(get_i32(buffer)?),
+                    6 => GuildRequest::ChangeRankTitles(get_strings(buffer)?),
+                    7 => GuildRequest::ChangeRank(get_i32(buffer)?, get_u8(buffer)?),
+                    8 => GuildRequest::ChangeEmblem(get_emblem(buffer)?),
+                    9 => GuildRequest::ChangeNotice(get_maple_string(buffer)?),
+                    10 => GuildRequest::Disband,
+                    11 => GuildRequest::GainPoints(get_i32(buffer)?),
+                    _ => return None,
+                };
+                Some(ChannelMessage::Guild(character_id, request))
+            }
+            8 => Some(ChannelMessage::GuildChat(get_i32(buffer)?, get_maple_string(buffer)?)),
+            _ => None,
+        }
+    }
+}
+
+impl WorldMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = BytesMut::new();
+
+        match self {
+            WorldMessage::PartyUpdate(party, change) => {
+                buffer.put_u8(0);
+                put_party(&mut buffer, party);
+                match change {
+                    PartyChange::Created => buffer.put_u8(0),
+                    PartyChange::Silent => buffer.put_u8(1),
+                    PartyChange::Joined(character_id) => {
+                        buffer.put_u8(2);
+                        buffer.put_i32_le(*character_id);
+                    }
+                    PartyChange::Left {
+                        character_id,
+                        name,
+                        expelled,
+                    } => {
+                        buffer.put_u8(3);
+                        buffer.put_i32_le(*character_id);
+                        buffer.put_maple_string(name);
+                        buffer.put_u8(*expelled as u8);
+                    }
+                    PartyChange::Disbanded => buffer.put_u8(4),
+                    PartyChange::LeaderChanged => buffer.put_u8(5),
+                }
+            }
+            WorldMessage::PartyInvitation {
+                character_id,
+                party_id,
+                inviter_name,
+            } => {
+                buffer.put_u8(1);
+                buffer.put_i32_le(*character_id);
+                buffer.put_i32_le(*party_id);
+                buffer.put_maple_string(inviter_name);
+            }
+            WorldMessage::PartyResult(character_id, result) => {
+                buffer.put_u8(2);
+                buffer.put_i32_le(*character_id);
+                match result {
+                    PartyResult::BeginnerCannotCreate => buffer.put_u8(0),
+                    PartyResult::NotInParty => buffer.put_u8(1),
+                    PartyResult::AlreadyJoined => buffer.put_u8(2),
+                    PartyResult::Full => buffer.put_u8(3),
+                    PartyResult::NotFound => buffer.put_u8(4),
+                    PartyResult::Denied(name) => {
+                        buffer.put_u8(5);
+                        buffer.put_maple_string(name);
+                    }
+                }
+            }
+            WorldMessage::MultiChat {
+                group,
+                recipients,
+                name,
+                text,
+            } => {
+                buffer.put_u8(3);
+                buffer.put_u8(*group as u8);
+                put_ids(&mut buffer, recipients);
+                buffer.put_maple_string(name);
+                buffer.put_maple_string(text);
+            }
+            WorldMessage::BuddyRequest {
+                character_id,
+                from_id,
+                from_name,
+                channel_id,
+            } => {
+                buffer.put_u8(4);
+                buffer.put_i32_le(*character_id);
+                buffer.put_i32_le(*from_id);
+                buffer.put_maple_string(from_name);
+                buffer.put_u8(*channel_id);
+            }
+            WorldMessage::BuddyChannel {
+                character_id,
+                buddy_id,
+                channel_id,
+            } => {
+                buffer.put_u8(5);
+                buffer.put_i32_le(*character_id);
+                buffer.put_i32_le(*buddy_id);
+                buffer.put_i16_le(channel_id.map_or(-1, |channel_id| channel_id as i16));
+            }
+            WorldMessage::GuildUpdate(guild, change) => {
+                buffer.put_u8(6);
+                put_guild(&mut buffer, guild);
+                match change {
+                    GuildChange::Created => buffer.put_u8(0),
+                    GuildChange::MemberOnline(character_id, online) => {
+                        buffer.put_u8(1);
+                        buffer.put_i32_le(*character_id);
+                        buffer.put_u8(*online as u8);
+                    }
+                    GuildChange::MemberUpdated(character_id) => {
+                        buffer.put_u8(2);
+                        buffer.put_i32_le(*character_id);
+                    }
+                    GuildChange::Joined(character_id) => {
+                        buffer.put_u8(3);
+                        buffer.put_i32_le(*character_id);
+                    }
+                    GuildChange::Left {
+                        character_id,
+                        name,
+                        expelled,
+                    } => {
+                        buffer.put_u8(4);
+                        buffer.put_i32_le(*character_id);
+                        buffer.put_maple_string(name);
+                        buffer.put_u8(*expelled as u8);
+                    }
+                    GuildChange::Disbanded => buffer.put_u8(5),
+                    GuildChange::RankTitlesChanged => buffer.put_u8(6),
+                    GuildChange::RankChanged(character_id) => {
+                        buffer.put_u8(7);
+                        buffer.put_i32_le(*character_id);
+                    }
+                    GuildChange::EmblemChanged => buffer.put_u8(8),
+                    GuildChange::NoticeChanged => buffer.put_u8(9),
+                    GuildChange::PointsChanged => buffer.put_u8(10),
+                }
+            }
+            WorldMessage::GuildInvitation {
+                character_id,
+                guild_id,
+                inviter_name,
+            } => {
+                buffer.put_u8(7);
+                buffer.put_i32_le(*character_id);
+                buffer.put_i32_le(*guild_id);
+                buffer.put_maple_string(inviter_name);
+            }
+            WorldMessage::GuildResult(character_id, result) => {
+                buffer.put_u8(8);
+                buffer.put_i32_le(*character_id);
+                match result {
+                    GuildResult::NameInUse => buffer.put_u8(0),
+                    GuildResult::AlreadyJoined => buffer.put_u8(1),
+                    GuildResult::NotFound => buffer.put_u8(2),
+                    GuildResult::Full => buffer.put_u8(3),
+                    GuildResult::Denied(name) => {
+                        buffer.put_u8(4);
+                        buffer.put_maple_string(name);
+                    }
+                }
+            }
+            WorldMessage::GuildFailed(character_id) => {
+                buffer.put_u8(9);
+                buffer.put_i32_le(*character_id);
+            }
+        }
+
+        buffer.to_vec()
+    }
+
+    fn decode(mut buffer: &[u8]) -> Option<WorldMessage> {
+        let buffer = &mut buffer;
+
+        match get_u8(buffer)? {
+            0 => {
+                let party = get_party(buffer)?;
+                let change = match get_u8(buffer)? {
+                    0 => PartyChange::Created,
+                    1 => PartyChange::Silent,
+                    2 => PartyChange::Joined(get_i32(buffer)?),
+                    3 => PartyChange::Left {
+                        character_id: get_i32(buffer)?,
+                        name: get_maple_string(buffer)?,
+                        expelled: get_u8(buffer)? != 0,
+                    },
+                    4 => PartyChange::Disbanded,
+                    5 => PartyChange::LeaderChanged,
+                    _ => return None,
+                };
+                Some(WorldMessage::PartyUpdate(party, change))
+            }
+            1 => Some(WorldMessage::PartyInvitation {
+                character_id: get_i32(buffer)?,
+                party_id: get_i32(buffer)?,
+                inviter_name: get_maple_string(buffer)?,
+            }),
+            2 => {
+                let character_id = get_i32(buffer)?;
+                let result = match get_u8(buffer)? {
+                    0 => PartyResult::BeginnerCannotCreate,
+                    1 => PartyResult::NotInParty,
+                    2 => PartyResult::AlreadyJoined,
+                    3 => PartyResult::Full,
+                    4 => PartyResult::NotFound,
+                    5 => PartyResult::Denied(get_maple_string(buffer)?),
+                    _ => return None,
+                };
+                Some(WorldMessage::PartyResult(character_id, result))
+            }
+            3 => Some(WorldMessage::MultiChat {
+                group: ChatGroup::from_u8(get_u8(buffer)?)?,
+                recipients: get_ids(buffer)?,
+                name: get_maple_string(buffer)?,
+                text: get_maple_string(buffer)?,
+            }),
+            4 => Some(WorldMessage::BuddyRequest {
+                character_id: get_i32(buffer)?,
+                from_id: get_i32(buffer)?,
+                from_name: get_maple_string(buffer)?,
+                channel_id: get_u8(buffer)?,
+            }),
+            5 => Some(WorldMessage::BuddyChannel {
+                character_id: get_i32(buffer)?,
+                buddy_id: get_i32(buffer)?,
+                channel_id: match get_i16(buffer)? {
+                    channel_id if channel_id < 0 => None,
+                    channel_id => Some(channel_id as u8),
+                },
+            }),
+            6 => {
+                let guild = get_guild(buffer)?;
+                let change = match get_u8(buffer)? {
+                    0 => GuildChange::Created,
+                    1 => GuildChange::MemberOnline(get_i32(buffer)?, get_u8(buffer)? != 0),
+                    2 => GuildChange::MemberUpdated(get_i32(buffer)?),
+                    3 => GuildChange::Joined(get_i32(buffer)?),
+                    4 => GuildChange::Left {
+                        character_id: get_i32(buffer)?,
+                        name: get_maple_string(buffer)?,
+                        expelled: get_u8(buffer)? != 0,
+                    },
+                    5 => GuildChange::Disbanded,
+                    6 => GuildChange::RankTitlesChanged,
+                    7 => GuildChange::RankChanged(get_i32(buffer)?),
+                    8 => GuildChange::EmblemChanged,
+                    9 => GuildChange::NoticeChanged,
+                    10 => GuildChange::PointsChanged,
+                    _ => return None,
+                };
+                Some(WorldMessage::GuildUpdate(guild, change))
+            }
+            7 => Some(WorldMessage::GuildInvitation {
+                character_id: get_i32(buffer)?,
+                guild_id: get_i32(buffer)?,
+                inviter_name: get_maple_string(buffer)?,
+            }),
+            8 => {
+                let character_id = get_i32(buffer)?;
+                let result = match get_u8(buffer)? {
+                    0 => GuildResult::NameInUse,
+                    1 => GuildResult::AlreadyJoined,
+                    2 => GuildResult::NotFound,
+                    3 => GuildResult::Full,
+                    4 => GuildResult::Denied(get_maple_string(buffer)?),
+                    _ => return None,
+                };
+                Some(WorldMessage::GuildResult(character_id, result))
+            }
+            9 => Some(WorldMessage::GuildFailed(get_i32(buffer)?)),
+            _ => None,
+        }
+    }
+}
+
+/// Writes a message prefixed with its length.
+pub fn write_message(stream: &mut TcpStream, message: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut buffer = BytesMut::with_capacity(4 + message.len());
+    buffer.put_u32_le(message.len() as u32);
+    buffer.put_slice(message);
+    stream.write_all(&buffer)?;
+    Ok(())
+}
+
+fn read_message(stream: &mut TcpStream) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header)?;
+
+    let length = u32::from_le_bytes(header) as usize;
+    if length > MAX_MESSAGE_LENGTH {
+        return Err(format!("Message of {} bytes is too long", length).into());
+    }
+
+    let mut message = vec![0u8; length];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}
+
+/// The connection of a channel server to its world server.
+pub struct WorldLink {
+    stream: Mutex<Option<TcpStream>>,
+    secret: String,
+}
+
+static WORLD_LINK_INSTANCE: OnceCell<WorldLink> = OnceCell::new();
+
+impl WorldLink {
+    pub fn get() -> Result<&'static WorldLink, Box<dyn Error>> {
+        match WORLD_LINK_INSTANCE.get() {
+            Some(link) => Ok(link),
+            None => Err("World link not initialized".into()),
+        }
+    }
+
+    /// Connects to the world server at `address`, connecting again whenever the connection drops.
+    pub fn init(address: SocketAddr, secret: String) -> Result<(), Box<dyn Error>> {
+        if WORLD_LINK_INSTANCE
+            .set(WorldLink {
+                stream: Mutex::new(None),
+                secret,
+            })
+            .is_err()
+        {
+            return Err("World link already initialized".into());
+        }
+
+        thread::spawn(move || loop {
+            match TcpStream::connect(address) {
+                Ok(stream) => {
+                    info!("connected to world server [{}]", address);
+                    if let Err(error) = Self::run(stream) {
+                        warn!("Lost connection to world server [{}]", error);
+                    }
+                }
+                Err(error) => warn!("Unable to connect to world server {} [{}]", address, error),
+            }
+
+            if let Ok(link) = Self::get() {
+                if let Ok(mut stream) = link.stream.lock() {
+                    *stream = None;
+                }
+            }
+            thread::sleep(RECONNECT_DELAY);
+        });
+        Ok(())
+    }
+
+    /// Registers the channel and its players with the world server, then handles its messages
+    /// until the connection drops.
+    fn run(mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
+        let channel = Channel::get()?;
+        let link = Self::get()?;
+        write_message(
+            &mut stream,
+            &ChannelMessage::RegisterChannel(channel.channel_id(), link.secret.clone()).encode(),
+        )?;
+        match link.stream.lock() {
+            Ok(mut link_stream) => *link_stream = Some(stream.try_clone()?),
+            Err(error) => return Err(format!("Unable to lock TcpStream Mutex [{}]", error).into()),
+        }
+
+        for player in channel.players() {
+            let result = character::with_character(&player.client, |client, character| {
+                party::report(character);
+                buddy::report(character.id, &*character::lock_buddies(client)?);
+                Ok(())
+            });
+            if let Err(error) = result {
+                warn!("Unable to report {} to the world server [{}]", player.name, error);
+            }
+        }
+
+        loop {
+            let message = read_message(&mut stream)?;
+            match WorldMessage::decode(&message) {
+                Some(message) => {
+                    if let Err(error) = handle_world_message(message) {
+                        warn!("Unable to handle message of world server [{}]", error);
+                    }
+                }
+                None => warn!("Received malformed message from world server"),
+            }
+        }
+    }
+
+    pub fn send(&self, message: &ChannelMessage) -> Result<(), Box<dyn Error>> {
+        let mut stream = match self.stream.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock TcpStream Mutex [{}]", error).into()),
+        };
+
+        match stream.as_mut() {
+            Some(stream) => write_message(stream, &message.encode()),
+            None => Err("Not connected to world server".into()),
+        }
+    }
+}
+
+fn handle_world_message(message: WorldMessage) -> Result<(), Box<dyn Error>> {
+    match message {
+        WorldMessage::PartyUpdate(party, change) => party::update(party, change),
+        WorldMessage::PartyInvitation {
+            character_id,
+            party_id,
+            inviter_name,
+        } => party::invite(character_id, party_id, &inviter_name),
+        WorldMessage::PartyResult(character_id, result) => party::result(character_id, &result),
+        WorldMessage::MultiChat {
+            group,
+            recipients,
+            name,
+            text,
+        } => {
+            Channel::get()?.send_to(&recipients, &message::create_multi_chat(group, &name, &text));
+            Ok(())
+        }
+        WorldMessage::BuddyRequest {
+            character_id,
+            from_id,
+            from_name,
+            channel_id,
+        } => buddy::request(character_id, from_id, from_name, channel_id),
+        WorldMessage::BuddyChannel {
+            character_id,
+            buddy_id,
+            channel_id,
+        } => buddy::update_channel(character_id, buddy_id, channel_id),
+        WorldMessage::GuildUpdate(guild, change) => guild::update(guild, change),
+        WorldMessage::GuildInvitation {
+            character_id,
+            guild_id,
+            inviter_name,
+        } => guild::invite(character_id, guild_id, &inviter_name),
+        WorldMessage::GuildResult(character_id, result) => guild::result(character_id, &result),
+        WorldMessage::GuildFailed(character_id) => guild::failed(character_id),
+    }
+}
+
+/// Accepts the connections of the channel servers of the world on `address`, which should
+/// only be reachable from the servers. Channel servers must register with `secret`.
+pub fn listen(address: SocketAddr, secret: String) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(address)?;
+    info!("waiting for channel servers on {}", address);
+
+    let secret = Arc::new(secret);
+    thread::spawn(move || {
+        for connection in listener.incoming() {
+            match connection {
+                Ok(stream) => {
+                    let secret = secret.clone();
+                    thread::spawn(move || serve_channel(stream, &secret));
+                }
+                Err(error) => warn!("Unable to accept channel server [{}]", error),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Compares the secrets without stopping at the first difference, so the time taken does not
+/// tell how much of a guess was right.
+fn secrets_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (given, expected)| difference | (given ^ expected))
+            == 0
+}
+
+fn serve_channel(mut stream: TcpStream, secret: &str) {
+    let address = stream
+        .peer_addr()
+        .map_or_else(|_| "unknown".to_string(), |address| address.to_string());
+    if let Err(error) = stream.set_read_timeout(Some(REGISTER_TIMEOUT)) {
+        return warn!("Unable to set the timeout of channel server {} [{}]", address, error);
+    }
+    let channel_id = match read_message(&mut stream).map(|message| ChannelMessage::decode(&message)) {
+        Ok(Some(ChannelMessage::RegisterChannel(channel_id, given))) if secrets_match(&given, secret) => channel_id,
+        Ok(Some(ChannelMessage::RegisterChannel(channel_id, _))) => {
+            return warn!("{} tried to register channel {} with a wrong secret", address, channel_id)
+        }
+        _ => return warn!("Channel server {} did not register itself", address),
+    };
+    if let Err(error) = stream.set_read_timeout(None) {
+        return warn!("Unable to clear the timeout of channel {} [{}]", channel_id, error);
+    }
+
+    let world = match World::get() {
+        Ok(world) => world,
+        Err(error) => return warn!("{}", error),
+    };
+    let link_id = match stream.try_clone() {
+        Ok(link_stream) => world.add_channel(channel_id, link_stream),
+        Err(error) => return warn!("Unable to register channel {} [{}]", channel_id, error),
+    };
+    info!("channel {} connected from {}", channel_id, address);
+
+    loop {
+        let message = match read_message(&mut stream) {
+            Ok(message) => message,
+            Err(error) => {
+                warn!("Lost connection to channel {} [{}]", channel_id, error);
+                break;
+            }
+        };
+
+        match ChannelMessage::decode(&message) {
+            Some(message) => world.handle(message),
+            None => warn!("Received malformed message from channel {}", channel_id),
+        }
+    }
+
+    world.remove_channel(channel_id, link_id);
+}
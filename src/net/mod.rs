@@ -1,4 +1,8 @@
+pub mod ban_list;
 pub mod client;
 pub mod crypto;
 pub mod handler;
-pub mod server;
\ No newline at end of file
+pub mod interserver;
+pub mod packet;
+pub mod server;
+pub mod throttle;
\ No newline at end of file
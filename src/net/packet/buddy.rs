@@ -0,0 +1,66 @@
+use crate::game::buddy::{BuddyList, BuddyResult};
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+
+/// Length the client reserves for the names of buddies.
+const BUDDY_NAME_LENGTH: usize = 13;
+/// Channel the client shows offline buddies in.
+const OFFLINE_CHANNEL: i32 = -1;
+
+/// Shows the buddies of the list, leaving out requests that are yet to be accepted.
+pub fn create_buddy_list(buddies: &BuddyList) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3C); // OPCODE
+    buffer.put_u8(7);
+    let entries: Vec<_> = buddies.entries.iter().filter(|entry| !entry.pending).collect();
+    buffer.put_u8(entries.len() as u8);
+    for entry in &entries {
+        buffer.put_i32_le(entry.character_id);
+        buffer.put_padded_string(&entry.name, BUDDY_NAME_LENGTH);
+        buffer.put_u8(0);
+        buffer.put_i32_le(entry.channel_id.map_or(OFFLINE_CHANNEL, |channel_id| channel_id as i32));
+    }
+    buffer.put_bytes(0, 4 * entries.len());
+
+    buffer.to_vec()
+}
+
+/// Asks the player to accept the request of `from_name`, who plays on `channel_id`.
+pub fn create_buddy_request(from_id: i32, from_name: &str, channel_id: Option<u8>) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3C); // OPCODE
+    buffer.put_u8(9);
+    buffer.put_i32_le(from_id);
+    buffer.put_maple_string(from_name);
+    buffer.put_i32_le(from_id);
+    buffer.put_padded_string(from_name, BUDDY_NAME_LENGTH);
+    buffer.put_u8(1);
+    buffer.put_i32_le(channel_id.map_or(OFFLINE_CHANNEL, |channel_id| channel_id as i32));
+    buffer.put_u8(0);
+
+    buffer.to_vec()
+}
+
+pub fn create_update_buddy_channel(buddy_id: i32, channel_id: Option<u8>) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3C); // OPCODE
+    buffer.put_u8(0x14);
+    buffer.put_i32_le(buddy_id);
+    buffer.put_u8(0);
+    buffer.put_i32_le(channel_id.map_or(OFFLINE_CHANNEL, |channel_id| channel_id as i32));
+
+    buffer.to_vec()
+}
+
+pub fn create_buddy_result(result: BuddyResult) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3C); // OPCODE
+    buffer.put_u8(result as u8);
+    buffer.put_u8(0);
+
+    buffer.to_vec()
+}
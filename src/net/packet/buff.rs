@@ -0,0 +1,54 @@
+use crate::game::buff::Buff;
+use bytes::{BufMut, BytesMut};
+
+/// Builds a GIVE_BUFF packet, applying the stats of `buff` to the receiving client.
+pub fn create_give_buff(buff: &Buff) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x1B); // OPCODE
+    buffer.put_u64_le(buff.mask());
+    for (_, value) in &buff.stats {
+        buffer.put_i16_le(*value);
+        buffer.put_i32_le(buff.skill_id);
+        buffer.put_i32_le(buff.duration.as_millis() as i32);
+    }
+    buffer.put_i16_le(0); // delay
+    buffer.put_u8(0);
+
+    buffer.to_vec()
+}
+
+pub fn create_cancel_buff(mask: u64) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x1C); // OPCODE
+    buffer.put_u64_le(mask);
+    buffer.put_u8(0);
+
+    buffer.to_vec()
+}
+
+/// Shows the buff of another character in the map, such as its speed.
+pub fn create_give_foreign_buff(character_id: i32, buff: &Buff) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x9A); // OPCODE
+    buffer.put_i32_le(character_id);
+    buffer.put_u64_le(buff.mask());
+    for (_, value) in &buff.stats {
+        buffer.put_i16_le(*value);
+    }
+    buffer.put_i16_le(0); // delay
+
+    buffer.to_vec()
+}
+
+pub fn create_cancel_foreign_buff(character_id: i32, mask: u64) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x9B); // OPCODE
+    buffer.put_i32_le(character_id);
+    buffer.put_u64_le(mask);
+
+    buffer.to_vec()
+}
@@ -0,0 +1,247 @@
+use crate::data::skill::SkillData;
+use crate::db::model::character::Character;
+use crate::game::inventory::{CharacterInventory, Inventory, InventoryType, CASH_EQUIP_OFFSET};
+use crate::game::quest::CharacterQuests;
+use crate::game::skill::{CharacterSkills, SkillEntry};
+use crate::net::packet::item;
+use crate::net::packet::quest;
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+use std::collections::BTreeMap;
+
+/// Equip slot of the cash weapon, which is sent apart from the other equips.
+const CASH_WEAPON_SLOT: i16 = 11;
+
+const INVENTORY_TABS: [InventoryType; 5] = [
+    InventoryType::Equip,
+    InventoryType::Use,
+    InventoryType::Setup,
+    InventoryType::Etc,
+    InventoryType::Cash,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stat {
+    Level = 0x10,
+    Strength = 0x40,
+    Dexterity = 0x80,
+    Intelligence = 0x100,
+    Luck = 0x200,
+    Hp = 0x400,
+    MaxHp = 0x800,
+    Mp = 0x1000,
+    MaxMp = 0x2000,
+    Ap = 0x4000,
+    Sp = 0x8000,
+    Exp = 0x10000,
+    Fame = 0x20000,
+    Meso = 0x40000,
+}
+
+impl Stat {
+    /// The stats AP can be spent on, by the mask the client sends them with.
+    pub fn from_ap_mask(mask: u32) -> Option<Stat> {
+        match mask {
+            0x40 => Some(Stat::Strength),
+            0x80 => Some(Stat::Dexterity),
+            0x100 => Some(Stat::Intelligence),
+            0x200 => Some(Stat::Luck),
+            0x800 => Some(Stat::MaxHp),
+            0x2000 => Some(Stat::MaxMp),
+            _ => None,
+        }
+    }
+
+    pub fn value_of(&self, character: &Character) -> i32 {
+        match self {
+            Stat::Level => character.level as i32,
+            Stat::Strength => character.strength as i32,
+            Stat::Dexterity => character.dexterity as i32,
+            Stat::Intelligence => character.intelligence as i32,
+            Stat::Luck => character.luck as i32,
+            Stat::Hp => character.hp as i32,
+            Stat::MaxHp => character.max_hp as i32,
+            Stat::Mp => character.mp as i32,
+            Stat::MaxMp => character.max_mp as i32,
+            Stat::Ap => character.ap as i32,
+            Stat::Sp => character.sp as i32,
+            Stat::Exp => character.exp,
+            Stat::Fame => character.fame as i32,
+            Stat::Meso => character.meso,
+        }
+    }
+}
+
+/// Builds an UPDATE_STATS packet with the current values of `stats` taken from `character`.
+/// An empty list only re-enables the client's actions.
+pub fn create_update_stats(character: &Character, stats: &[Stat], enable_actions: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+    let mut sorted_stats = stats.to_vec();
+    sorted_stats.sort();
+    sorted_stats.dedup();
+
+    buffer.put_u16_le(0x1A); // OPCODE
+    buffer.put_u8(enable_actions as u8);
+    buffer.put_u32_le(
+        sorted_stats
+            .iter()
+            .fold(0, |mask, stat| mask | *stat as u32),
+    );
+
+    for stat in sorted_stats {
+        let value = stat.value_of(character);
+        match stat {
+            Stat::Level => buffer.put_u8(value as u8),
+            Stat::Strength
+            | Stat::Dexterity
+            | Stat::Intelligence
+            | Stat::Luck
+            | Stat::Hp
+            | Stat::MaxHp
+            | Stat::Mp
+            | Stat::MaxMp
+            | Stat::Ap
+            | Stat::Sp
+            | Stat::Fame => buffer.put_i16_le(value as i16),
+            Stat::Exp | Stat::Meso => buffer.put_i32_le(value),
+        }
+    }
+
+    buffer.to_vec()
+}
+
+/// Builds an UPDATE_SKILLS packet for a single skill whose level changed.
+pub fn create_update_skill(skill_id: i32, entry: SkillEntry) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x1D); // OPCODE
+    buffer.put_u8(1);
+    buffer.put_u16_le(1);
+    buffer.put_i32_le(skill_id);
+    buffer.put_i32_le(entry.level as i32);
+    buffer.put_i32_le(entry.master_level as i32);
+    buffer.put_u8(1);
+
+    buffer.to_vec()
+}
+
+/// Skills that need mastery also carry the master level the character unlocked.
+fn put_skill(buffer: &mut BytesMut, skill_id: i32, entry: SkillEntry) {
+    buffer.put_i32_le(skill_id);
+    buffer.put_i32_le(entry.level as i32);
+    if SkillData::get(skill_id).is_some_and(|data| data.needs_mastery) {
+        buffer.put_i32_le(entry.master_level as i32);
+    }
+}
+
+pub fn put_character_stats(buffer: &mut BytesMut, character: &Character) {
+    buffer.put_i32_le(character.id);
+    buffer.put_padded_string(&character.name, 13);
+    buffer.put_u8(character.gender as u8);
+    buffer.put_u8(character.skin as u8);
+    buffer.put_i32_le(character.face);
+    buffer.put_i32_le(character.hair);
+    buffer.put_u64_le(0); // pet
+    buffer.put_u8(character.level as u8);
+    buffer.put_i16_le(character.job);
+    buffer.put_i16_le(character.strength);
+    buffer.put_i16_le(character.dexterity);
+    buffer.put_i16_le(character.intelligence);
+    buffer.put_i16_le(character.luck);
+    buffer.put_i16_le(character.hp);
+    buffer.put_i16_le(character.max_hp);
+    buffer.put_i16_le(character.mp);
+    buffer.put_i16_le(character.max_mp);
+    buffer.put_i16_le(character.ap);
+    buffer.put_i16_le(character.sp);
+    buffer.put_i32_le(character.exp);
+    buffer.put_i16_le(character.fame);
+    buffer.put_i32_le(character.map_id);
+    buffer.put_u8(character.spawn_point as u8);
+}
+
+/// Writes the appearance of the character, where cash equips are shown over the regular
+/// equips in the same slot.
+pub fn put_character_look(buffer: &mut BytesMut, character: &Character, equipped: &Inventory) {
+    let mut visible_equips = BTreeMap::new();
+    let mut covered_equips = BTreeMap::new();
+    let mut cash_weapon = 0;
+
+    for (position, item) in equipped.items().filter(|(position, _)| *position > -CASH_EQUIP_OFFSET) {
+        visible_equips.insert(-position, item.item_id);
+    }
+
+    for (position, item) in equipped.items().filter(|(position, _)| *position <= -CASH_EQUIP_OFFSET) {
+        let slot = -position - CASH_EQUIP_OFFSET;
+        if slot == CASH_WEAPON_SLOT {
+            cash_weapon = item.item_id;
+        } else if let Some(covered_item_id) = visible_equips.insert(slot, item.item_id) {
+            covered_equips.insert(slot, covered_item_id);
+        }
+    }
+
+    buffer.put_u8(character.gender as u8);
+    buffer.put_u8(character.skin as u8);
+    buffer.put_i32_le(character.face);
+    buffer.put_u8(1);
+    buffer.put_i32_le(character.hair);
+    for (slot, item_id) in visible_equips {
+        buffer.put_u8(slot as u8);
+        buffer.put_i32_le(item_id);
+    }
+    buffer.put_u8(0xFF);
+    for (slot, item_id) in covered_equips {
+        buffer.put_u8(slot as u8);
+        buffer.put_i32_le(item_id);
+    }
+    buffer.put_u8(0xFF);
+    buffer.put_i32_le(cash_weapon);
+    buffer.put_i32_le(0); // pet
+}
+
+pub fn put_character_info(
+    buffer: &mut BytesMut,
+    character: &Character,
+    inventory: &CharacterInventory,
+    skills: &CharacterSkills,
+    quests: &CharacterQuests,
+) {
+    buffer.put_i64_le(-1);
+    put_character_stats(buffer, character);
+    buffer.put_u8(character.buddy_capacity as u8);
+    buffer.put_i32_le(character.meso);
+
+    for inventory_type in INVENTORY_TABS {
+        buffer.put_u8(inventory.get(inventory_type).slot_limit() as u8);
+    }
+
+    let equipped = inventory.get(InventoryType::Equipped);
+    for (position, equip) in equipped.items().filter(|(position, _)| *position > -CASH_EQUIP_OFFSET) {
+        item::put_slot_item(buffer, position, equip);
+    }
+    buffer.put_u8(0);
+    for (position, equip) in equipped.items().filter(|(position, _)| *position <= -CASH_EQUIP_OFFSET) {
+        item::put_slot_item(buffer, position, equip);
+    }
+    buffer.put_u8(0);
+
+    for inventory_type in INVENTORY_TABS {
+        for (position, inventory_item) in inventory.get(inventory_type).items() {
+            item::put_slot_item(buffer, position, inventory_item);
+        }
+        buffer.put_u8(0);
+    }
+
+    let learned_skills: Vec<(i32, SkillEntry)> = skills.iter().filter(|(_, entry)| entry.level > 0).collect();
+    buffer.put_u16_le(learned_skills.len() as u16);
+    for (skill_id, entry) in learned_skills {
+        put_skill(buffer, skill_id, entry);
+    }
+    quest::put_quests(buffer, quests);
+    buffer.put_u16_le(0); // mini games
+    buffer.put_u16_le(0); // rings
+
+    for _ in 0..5 {
+        buffer.put_i32_le(999999999); // teleport rock maps
+    }
+}
@@ -0,0 +1,65 @@
+use crate::game::combat::{Attack, AttackKind, DamageSource, MAP_DAMAGE_SOURCE};
+use bytes::{BufMut, BytesMut};
+
+/// Shows the attack of a player to the others in the map.
+pub fn create_attack(character_id: i32, attack: &Attack, skill_level: u8, projectile_id: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(match attack.kind {
+        AttackKind::CloseRange => 0x8E,
+        AttackKind::Ranged => 0x8F,
+        AttackKind::Magic => 0x90,
+    }); // OPCODE
+    buffer.put_i32_le(character_id);
+    buffer.put_u8(((attack.targets.len() as u8) << 4) | attack.lines);
+    match attack.skill_id {
+        0 => buffer.put_u8(0),
+        skill_id => {
+            buffer.put_u8(skill_level);
+            buffer.put_i32_le(skill_id);
+        }
+    }
+    buffer.put_u8(attack.display);
+    buffer.put_u8(attack.direction);
+    buffer.put_u8(attack.speed);
+    buffer.put_i32_le(projectile_id);
+
+    for target in &attack.targets {
+        buffer.put_i32_le(target.object_id);
+        buffer.put_u8(0x06);
+        for damage in &target.damage {
+            buffer.put_i32_le(*damage);
+        }
+    }
+
+    buffer.to_vec()
+}
+
+/// Shows the damage a player took to the others in the map.
+pub fn create_damage_player(character_id: i32, source: DamageSource, damage: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x93); // OPCODE
+    buffer.put_i32_le(character_id);
+    match source {
+        DamageSource::Map => {
+            buffer.put_i8(MAP_DAMAGE_SOURCE);
+            buffer.put_i32_le(damage);
+        }
+        DamageSource::Mob {
+            attack,
+            mob_id,
+            direction,
+            ..
+        } => {
+            buffer.put_i8(attack);
+            buffer.put_i32_le(damage);
+            buffer.put_i32_le(mob_id);
+            buffer.put_u8(direction);
+            buffer.put_u8(0); // stance
+        }
+    }
+    buffer.put_i32_le(damage);
+
+    buffer.to_vec()
+}
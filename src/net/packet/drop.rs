@@ -0,0 +1,83 @@
+use crate::game::map::{DropContent, DropOwnership, MapDrop};
+use crate::net::packet::item::PERMANENT_FILETIME;
+use bytes::{BufMut, BytesMut};
+
+#[derive(Clone, Copy)]
+pub enum DropAnimation {
+    /// Falls from the dropper to its position.
+    Dropping = 1,
+    /// Already lying on the ground, for players entering the map.
+    Existing = 2,
+}
+
+pub enum RemoveDropAnimation {
+    /// Fades out, for drops that stayed on the ground too long.
+    Expired,
+    /// Flies to the character that picked it up.
+    PickedUp(i32),
+}
+
+pub fn create_drop(map_drop: &MapDrop, animation: DropAnimation) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xCD); // OPCODE
+    buffer.put_u8(animation as u8);
+    buffer.put_i32_le(map_drop.object_id);
+    match &map_drop.content {
+        DropContent::Item(item) => {
+            buffer.put_u8(0);
+            buffer.put_i32_le(item.item_id);
+        }
+        DropContent::Meso(amount) => {
+            buffer.put_u8(1);
+            buffer.put_i32_le(*amount);
+        }
+    }
+    match map_drop.ownership {
+        DropOwnership::Character(character_id) => {
+            buffer.put_i32_le(character_id);
+            buffer.put_u8(0);
+        }
+        DropOwnership::Party(party_id) => {
+            buffer.put_i32_le(party_id);
+            buffer.put_u8(1);
+        }
+        DropOwnership::FreeForAll => {
+            buffer.put_i32_le(map_drop.dropper_id);
+            buffer.put_u8(2);
+        }
+    }
+    buffer.put_i16_le(map_drop.position.0);
+    buffer.put_i16_le(map_drop.position.1);
+    buffer.put_i32_le(map_drop.dropper_id);
+    if let DropAnimation::Dropping = animation {
+        buffer.put_i16_le(map_drop.dropped_from.0);
+        buffer.put_i16_le(map_drop.dropped_from.1);
+        buffer.put_i16_le(0); // delay
+    }
+    if let DropContent::Item(_) = map_drop.content {
+        buffer.put_u64_le(PERMANENT_FILETIME);
+    }
+    buffer.put_u8(1); // pets may pick it up
+
+    buffer.to_vec()
+}
+
+pub fn create_remove_drop(object_id: i32, animation: RemoveDropAnimation) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xCE); // OPCODE
+    match animation {
+        RemoveDropAnimation::Expired => {
+            buffer.put_u8(0);
+            buffer.put_i32_le(object_id);
+        }
+        RemoveDropAnimation::PickedUp(character_id) => {
+            buffer.put_u8(2);
+            buffer.put_i32_le(object_id);
+            buffer.put_i32_le(character_id);
+        }
+    }
+
+    buffer.to_vec()
+}
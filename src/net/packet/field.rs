@@ -0,0 +1,196 @@
+use crate::db::model::character::Character;
+use crate::game::guild::Guild;
+use crate::game::inventory::{CharacterInventory, Inventory};
+use crate::game::quest::CharacterQuests;
+use crate::game::skill::CharacterSkills;
+use crate::net::packet::character;
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+use rand::prelude::*;
+use std::time::SystemTime;
+
+/// Filetime value of 1/1/1970, used by the client to convert unix timestamps.
+const FILETIME_UNIX_EPOCH: u64 = 116444736000000000;
+
+pub fn to_filetime(time: SystemTime) -> u64 {
+    match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(duration) => FILETIME_UNIX_EPOCH + (duration.as_nanos() / 100) as u64,
+        Err(_) => FILETIME_UNIX_EPOCH,
+    }
+}
+
+/// Builds the first WARP_TO_MAP packet sent after a player connects to the channel,
+/// carrying the whole character information.
+pub fn create_character_info(
+    channel_id: u8,
+    character: &Character,
+    inventory: &CharacterInventory,
+    skills: &CharacterSkills,
+    quests: &CharacterQuests,
+) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+    let mut prng: StdRng = StdRng::from_entropy();
+
+    buffer.put_u16_le(0x5C); // OPCODE
+    buffer.put_u32_le(channel_id as u32);
+    buffer.put_u8(1);
+    buffer.put_u8(1);
+    for _ in 0..3 {
+        buffer.put_u32_le(prng.gen());
+    }
+
+    character::put_character_info(&mut buffer, character, inventory, skills, quests);
+    buffer.put_u64_le(to_filetime(SystemTime::now()));
+
+    buffer.to_vec()
+}
+
+pub fn create_change_map(channel_id: u8, character: &Character) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x5C); // OPCODE
+    buffer.put_u32_le(channel_id as u32);
+    buffer.put_u16_le(2);
+    buffer.put_u16_le(0);
+    buffer.put_i32_le(character.map_id);
+    buffer.put_u8(character.spawn_point as u8);
+    buffer.put_i16_le(character.hp);
+    buffer.put_u8(0);
+    buffer.put_u64_le(to_filetime(SystemTime::now()));
+
+    buffer.to_vec()
+}
+
+pub fn create_spawn_player(
+    character: &Character,
+    equipped: &Inventory,
+    position: (i16, i16),
+    stance: u8,
+    foothold: i16,
+    guild: Option<&Guild>,
+) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x78); // OPCODE
+    buffer.put_i32_le(character.id);
+    buffer.put_maple_string(&character.name);
+    let emblem = guild.map(|guild| guild.emblem).unwrap_or_default();
+    buffer.put_maple_string(guild.map_or("", |guild| guild.name.as_str()));
+    buffer.put_i16_le(emblem.background);
+    buffer.put_u8(emblem.background_color);
+    buffer.put_i16_le(emblem.logo);
+    buffer.put_u8(emblem.logo_color);
+    buffer.put_u64_le(0); // foreign buffs
+    buffer.put_i16_le(character.job);
+    character::put_character_look(&mut buffer, character, equipped);
+    buffer.put_u32_le(0); // item effect
+    buffer.put_u32_le(0); // chair
+    buffer.put_i16_le(position.0);
+    buffer.put_i16_le(position.1);
+    buffer.put_u8(stance);
+    buffer.put_i16_le(foothold);
+    buffer.put_u8(0); // pets
+    buffer.put_u8(0); // mini room
+    buffer.put_u8(0); // rings
+
+    buffer.to_vec()
+}
+
+pub fn create_remove_player(character_id: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x79); // OPCODE
+    buffer.put_i32_le(character_id);
+
+    buffer.to_vec()
+}
+
+pub fn create_move_player(character_id: i32, movement: &[u8]) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x8D); // OPCODE
+    buffer.put_i32_le(character_id);
+    buffer.put_i32_le(0);
+    buffer.put_slice(movement);
+
+    buffer.to_vec()
+}
+
+pub fn create_update_look(character: &Character, equipped: &Inventory) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x98); // OPCODE
+    buffer.put_i32_le(character.id);
+    buffer.put_u8(1);
+    character::put_character_look(&mut buffer, character, equipped);
+    buffer.put_u8(0); // rings
+    buffer.put_u16_le(0);
+
+    buffer.to_vec()
+}
+
+#[derive(Clone, Copy)]
+pub enum Effect {
+    LevelUp,
+    /// Casting a skill at a level.
+    SkillUse(i32, u8),
+    QuestComplete,
+}
+
+fn put_effect(buffer: &mut BytesMut, effect: Effect) {
+    match effect {
+        Effect::LevelUp => buffer.put_u8(0),
+        Effect::SkillUse(skill_id, level) => {
+            buffer.put_u8(1);
+            buffer.put_i32_le(skill_id);
+            buffer.put_u8(level);
+        }
+        Effect::QuestComplete => buffer.put_u8(9),
+    }
+}
+
+/// Plays `effect` on the character of the receiving client.
+pub fn create_show_own_effect(effect: Effect) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xA1); // OPCODE
+    put_effect(&mut buffer, effect);
+
+    buffer.to_vec()
+}
+
+/// Plays `effect` on another character in the map.
+pub fn create_show_foreign_effect(character_id: i32, effect: Effect) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x99); // OPCODE
+    buffer.put_i32_le(character_id);
+    put_effect(&mut buffer, effect);
+
+    buffer.to_vec()
+}
+
+/// Effects shown to everyone in the map, by the path of their data.
+pub enum FieldEffect<'a> {
+    /// An animation drawn over the screen, such as `maplemap/enter/10000`.
+    Screen(&'a str),
+    Sound(&'a str),
+}
+
+pub fn create_field_effect(effect: FieldEffect) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x68); // OPCODE
+    match effect {
+        FieldEffect::Screen(path) => {
+            buffer.put_u8(3);
+            buffer.put_maple_string(path);
+        }
+        FieldEffect::Sound(path) => {
+            buffer.put_u8(4);
+            buffer.put_maple_string(path);
+        }
+    }
+
+    buffer.to_vec()
+}
@@ -0,0 +1,227 @@
+use crate::game::guild::{Guild, GuildEmblem, GuildMember, GuildResult};
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+
+/// Length the client reserves for the names of guild members.
+const MEMBER_NAME_LENGTH: usize = 13;
+
+fn put_member(buffer: &mut BytesMut, member: &GuildMember) {
+    buffer.put_padded_string(&member.name, MEMBER_NAME_LENGTH);
+    buffer.put_i32_le(member.job as i32);
+    buffer.put_i32_le(member.level as i32);
+    buffer.put_i32_le(member.rank as i32);
+    buffer.put_i32_le(member.online as i32);
+    buffer.put_i32_le(3); // signature
+}
+
+fn put_emblem(buffer: &mut BytesMut, emblem: GuildEmblem) {
+    buffer.put_i16_le(emblem.background);
+    buffer.put_u8(emblem.background_color);
+    buffer.put_i16_le(emblem.logo);
+    buffer.put_u8(emblem.logo_color);
+}
+
+/// Opens the dialog the guild name is entered in, for a guild NPC.
+pub fn create_guild_name_dialog() -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(1);
+
+    buffer.to_vec()
+}
+
+/// Opens the dialog the guild emblem is designed in, for a guild NPC.
+pub fn create_guild_emblem_dialog() -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x11);
+
+    buffer.to_vec()
+}
+
+pub fn create_guild_invite(guild_id: i32, inviter_name: &str) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(5);
+    buffer.put_i32_le(guild_id);
+    buffer.put_maple_string(inviter_name);
+
+    buffer.to_vec()
+}
+
+/// Shows everything about the guild of the player, or that it has none.
+pub fn create_guild_info(guild: Option<&Guild>) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x1A);
+    let guild = match guild {
+        Some(guild) => guild,
+        None => {
+            buffer.put_u8(0);
+            return buffer.to_vec();
+        }
+    };
+
+    buffer.put_u8(1);
+    buffer.put_i32_le(guild.id);
+    buffer.put_maple_string(&guild.name);
+    for rank in 0..5 {
+        buffer.put_maple_string(guild.rank_titles.get(rank).map_or("", |title| title.as_str()));
+    }
+    buffer.put_u8(guild.members.len() as u8);
+    for member in &guild.members {
+        buffer.put_i32_le(member.character_id);
+    }
+    for member in &guild.members {
+        put_member(&mut buffer, member);
+    }
+    buffer.put_i32_le(guild.capacity as i32);
+    put_emblem(&mut buffer, guild.emblem);
+    buffer.put_maple_string(&guild.notice);
+    buffer.put_i32_le(guild.points);
+
+    buffer.to_vec()
+}
+
+pub fn create_new_member(guild_id: i32, member: &GuildMember) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x27);
+    buffer.put_i32_le(guild_id);
+    buffer.put_i32_le(member.character_id);
+    put_member(&mut buffer, member);
+
+    buffer.to_vec()
+}
+
+pub fn create_member_left(guild_id: i32, character_id: i32, name: &str, expelled: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(match expelled {
+        true => 0x2F,
+        false => 0x2C,
+    });
+    buffer.put_i32_le(guild_id);
+    buffer.put_i32_le(character_id);
+    buffer.put_maple_string(name);
+
+    buffer.to_vec()
+}
+
+pub fn create_disband_guild(guild_id: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x32);
+    buffer.put_i32_le(guild_id);
+    buffer.put_u8(1);
+
+    buffer.to_vec()
+}
+
+pub fn create_update_member(guild_id: i32, member: &GuildMember) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x3C);
+    buffer.put_i32_le(guild_id);
+    buffer.put_i32_le(member.character_id);
+    buffer.put_i32_le(member.level as i32);
+    buffer.put_i32_le(member.job as i32);
+
+    buffer.to_vec()
+}
+
+pub fn create_member_online(guild_id: i32, character_id: i32, online: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x3D);
+    buffer.put_i32_le(guild_id);
+    buffer.put_i32_le(character_id);
+    buffer.put_u8(online as u8);
+
+    buffer.to_vec()
+}
+
+pub fn create_change_rank_titles(guild_id: i32, rank_titles: &[String]) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x3E);
+    buffer.put_i32_le(guild_id);
+    for rank in 0..5 {
+        buffer.put_maple_string(rank_titles.get(rank).map_or("", |title| title.as_str()));
+    }
+
+    buffer.to_vec()
+}
+
+pub fn create_change_rank(guild_id: i32, member: &GuildMember) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x40);
+    buffer.put_i32_le(guild_id);
+    buffer.put_i32_le(member.character_id);
+    buffer.put_u8(member.rank);
+
+    buffer.to_vec()
+}
+
+pub fn create_change_emblem(guild_id: i32, emblem: GuildEmblem) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x42);
+    buffer.put_i32_le(guild_id);
+    put_emblem(&mut buffer, emblem);
+
+    buffer.to_vec()
+}
+
+pub fn create_change_notice(guild_id: i32, notice: &str) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x44);
+    buffer.put_i32_le(guild_id);
+    buffer.put_maple_string(notice);
+
+    buffer.to_vec()
+}
+
+pub fn create_update_points(guild_id: i32, points: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    buffer.put_u8(0x48);
+    buffer.put_i32_le(guild_id);
+    buffer.put_i32_le(points);
+
+    buffer.to_vec()
+}
+
+pub fn create_guild_result(result: &GuildResult) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3E); // OPCODE
+    match result {
+        GuildResult::NameInUse => buffer.put_u8(0x1C),
+        GuildResult::Full => buffer.put_u8(0x26),
+        GuildResult::AlreadyJoined => buffer.put_u8(0x28),
+        GuildResult::NotFound => buffer.put_u8(0x2A),
+        GuildResult::Denied(name) => {
+            buffer.put_u8(0x37);
+            buffer.put_maple_string(name);
+        }
+    }
+
+    buffer.to_vec()
+}
@@ -0,0 +1,502 @@
+use crate::db::model::character::Character;
+use crate::db::model::minigame_record::MinigameRecord;
+use crate::game::inventory::{Inventory, Item};
+use crate::game::minigame::{GameResult, Play};
+use crate::game::player_shop::Shop;
+use crate::game::room::{Room, RoomContent, RoomError, RoomExit, RoomType};
+use crate::game::trade::TradeResult;
+use crate::net::packet::character::put_character_look;
+use crate::net::packet::item::put_item_info;
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+
+const INVITE: u8 = 0x02;
+const VISIT: u8 = 0x04;
+const ROOM: u8 = 0x05;
+const CHAT: u8 = 0x06;
+const EXIT: u8 = 0x0A;
+const SET_ITEMS: u8 = 0x0E;
+const SET_MESO: u8 = 0x0F;
+const CONFIRM: u8 = 0x10;
+const SHOP_ITEMS: u8 = 0x17;
+const SHOP_SOLD: u8 = 0x1A;
+const REQUEST_TIE: u8 = 0x2C;
+const ANSWER_TIE: u8 = 0x2D;
+const READY: u8 = 0x34;
+const UNREADY: u8 = 0x35;
+const START: u8 = 0x37;
+const GAME_RESULT: u8 = 0x38;
+const SKIP: u8 = 0x39;
+const MOVE_OMOK: u8 = 0x3A;
+const SELECT_CARD: u8 = 0x3E;
+
+const TRADE_ROOM: u8 = 3;
+const TRADE_CAPACITY: u8 = 2;
+/// Chat of the room, as opposed to its notices.
+const ROOM_CHAT: u8 = 8;
+/// How many items a shop can list.
+const SHOP_ITEM_LIMIT: u8 = 16;
+/// The points mini-game records show, which the client ranks players by.
+const GAME_POINTS: i32 = 2000;
+
+/// The look and name of a character as rooms show their visitors, which is written into the
+/// packets of the room as is.
+pub fn encode_visitor(character: &Character, equipped: &Inventory) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    put_character_look(&mut buffer, character, equipped);
+    buffer.put_maple_string(&character.name);
+
+    buffer.to_vec()
+}
+
+/// Opens the trade window, with the player at `position` among `visitors`.
+pub fn create_trade_room(position: u8, visitors: &[(u8, &[u8])]) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(ROOM);
+    buffer.put_u8(TRADE_ROOM);
+    buffer.put_u8(TRADE_CAPACITY);
+    buffer.put_u8(position);
+    for (visitor_position, visitor) in visitors {
+        buffer.put_u8(*visitor_position);
+        buffer.put_slice(visitor);
+    }
+    buffer.put_u8(0xFF);
+
+    buffer.to_vec()
+}
+
+pub fn create_trade_invite(inviter_name: &str, trade_id: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(INVITE);
+    buffer.put_u8(TRADE_ROOM);
+    buffer.put_maple_string(inviter_name);
+    buffer.put_i32_le(trade_id);
+
+    buffer.to_vec()
+}
+
+/// Shows the visitor that joined the room at `position`.
+pub fn create_visit(position: u8, visitor: &[u8]) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(VISIT);
+    buffer.put_u8(position);
+    buffer.put_slice(visitor);
+
+    buffer.to_vec()
+}
+
+pub fn create_room_chat(position: u8, name: &str, text: &str) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(CHAT);
+    buffer.put_u8(ROOM_CHAT);
+    buffer.put_u8(position);
+    buffer.put_maple_string(&format!("{} : {}", name, text));
+
+    buffer.to_vec()
+}
+
+/// Closes the trade window of the player at `position`, telling it how the trade ended.
+pub fn create_trade_exit(position: u8, result: TradeResult) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(EXIT);
+    buffer.put_u8(position);
+    buffer.put_u8(result as u8);
+
+    buffer.to_vec()
+}
+
+/// Shows an item offered in `slot` of the trade window, by the player itself when `position`
+/// is 0 and by its partner when it is 1.
+pub fn create_trade_item(position: u8, slot: u8, item: &Item) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(SET_ITEMS);
+    buffer.put_u8(position);
+    buffer.put_u8(slot);
+    put_item_info(&mut buffer, item);
+
+    buffer.to_vec()
+}
+
+/// Shows the mesos offered in total, by the player itself when `position` is 0 and by its
+/// partner when it is 1.
+pub fn create_trade_meso(position: u8, meso: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(SET_MESO);
+    buffer.put_u8(position);
+    buffer.put_i32_le(meso);
+
+    buffer.to_vec()
+}
+
+/// Tells the player its partner confirmed the trade.
+pub fn create_trade_confirm() -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(CONFIRM);
+
+    buffer.to_vec()
+}
+
+/// Opens the window of the room, with the player at `position` in it.
+pub fn create_room(room: &Room, position: u8) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(ROOM);
+    buffer.put_u8(room.room_type as u8);
+    buffer.put_u8(room.room_type.capacity());
+    buffer.put_u8(position);
+    if let RoomContent::Shop(shop) = &room.content {
+        if room.room_type == RoomType::HiredMerchant {
+            buffer.put_i32_le(shop.permit_id);
+            buffer.put_maple_string("Hired Merchant");
+        }
+    }
+    for (visitor_position, visitor) in room.visitors() {
+        buffer.put_u8(visitor_position);
+        buffer.put_slice(&visitor.look);
+    }
+    buffer.put_u8(0xFF);
+
+    match &room.content {
+        RoomContent::Game(game) => {
+            buffer.put_u8(0);
+            for (visitor_position, _) in room.visitors() {
+                buffer.put_u8(visitor_position);
+                put_record(&mut buffer, &game.records[visitor_position as usize]);
+            }
+            buffer.put_u8(0xFF);
+            buffer.put_maple_string(&room.title);
+            buffer.put_u8(game.piece);
+            buffer.put_u8(0);
+        }
+        RoomContent::Shop(shop) => {
+            if room.room_type == RoomType::HiredMerchant {
+                buffer.put_u16_le(0);
+                buffer.put_maple_string(&room.owner_name);
+            }
+            buffer.put_maple_string(&room.title);
+            buffer.put_u8(SHOP_ITEM_LIMIT);
+            put_shop_items(&mut buffer, shop);
+        }
+    }
+
+    buffer.to_vec()
+}
+
+/// Tells the player why it could not enter a room.
+pub fn create_room_error(error: RoomError) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(ROOM);
+    buffer.put_u8(0);
+    buffer.put_u8(error as u8);
+
+    buffer.to_vec()
+}
+
+/// Shows the visitor at `position` to the others in the room, with its record in mini-games.
+pub fn create_room_visit(room: &Room, position: u8) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(VISIT);
+    buffer.put_u8(position);
+    if let Some(visitor) = room.visitor(position) {
+        buffer.put_slice(&visitor.look);
+    }
+    if let RoomContent::Game(game) = &room.content {
+        put_record(&mut buffer, &game.records[position as usize]);
+    }
+
+    buffer.to_vec()
+}
+
+/// Removes the visitor at `position` from the window of the others in the room.
+pub fn create_room_leave(position: u8) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(EXIT);
+    buffer.put_u8(position);
+
+    buffer.to_vec()
+}
+
+/// Closes the room window of the player at `position`, telling it why.
+pub fn create_room_exit(position: u8, exit: RoomExit) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(EXIT);
+    buffer.put_u8(position);
+    buffer.put_u8(exit as u8);
+
+    buffer.to_vec()
+}
+
+/// Shows the balloon of a player shop or mini-game room over the head of its owner.
+pub fn create_room_balloon(room: &Room) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x7D); // OPCODE
+    buffer.put_i32_le(room.owner_id);
+    buffer.put_u8(room.room_type as u8);
+    put_balloon(&mut buffer, room);
+
+    buffer.to_vec()
+}
+
+pub fn create_remove_room_balloon(owner_id: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x7D); // OPCODE
+    buffer.put_i32_le(owner_id);
+    buffer.put_u8(0);
+
+    buffer.to_vec()
+}
+
+/// Spawns the hired merchant minding the room where its owner opened it.
+pub fn create_spawn_merchant(room: &Room, position: (i16, i16)) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xCA); // OPCODE
+    buffer.put_i32_le(room.owner_id);
+    buffer.put_i32_le(match &room.content {
+        RoomContent::Shop(shop) => shop.permit_id,
+        RoomContent::Game(_) => 0,
+    });
+    buffer.put_i16_le(position.0);
+    buffer.put_i16_le(position.1);
+    buffer.put_i16_le(0); // foothold
+    buffer.put_maple_string(&room.owner_name);
+    buffer.put_u8(RoomType::HiredMerchant as u8);
+    put_balloon(&mut buffer, room);
+
+    buffer.to_vec()
+}
+
+pub fn create_remove_merchant(owner_id: i32) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xCB); // OPCODE
+    buffer.put_i32_le(owner_id);
+
+    buffer.to_vec()
+}
+
+/// Updates the visitor count over the hired merchant minding the room.
+pub fn create_update_merchant(room: &Room) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xCC); // OPCODE
+    buffer.put_i32_le(room.owner_id);
+    buffer.put_u8(RoomType::HiredMerchant as u8);
+    put_balloon(&mut buffer, room);
+
+    buffer.to_vec()
+}
+
+/// Shows the items the shop lists, and for hired merchants the mesos they made.
+pub fn create_shop_items(shop: &Shop) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(SHOP_ITEMS);
+    put_shop_items(&mut buffer, shop);
+
+    buffer.to_vec()
+}
+
+/// Tells the owner of a shop that `buyer_name` bought `bundles` of the item at `index`.
+pub fn create_shop_sold(index: u8, bundles: i16, buyer_name: &str) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(SHOP_SOLD);
+    buffer.put_u8(index);
+    buffer.put_i16_le(bundles);
+    buffer.put_maple_string(buyer_name);
+
+    buffer.to_vec()
+}
+
+pub fn create_game_ready(ready: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(match ready {
+        true => READY,
+        false => UNREADY,
+    });
+
+    buffer.to_vec()
+}
+
+/// Starts the game that was just set up, with the cards in their shuffled order in match
+/// cards.
+pub fn create_game_start(first: u8, play: &Play) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(START);
+    buffer.put_u8(first);
+    if let Play::MatchCard { cards, .. } = play {
+        buffer.put_u8(cards.len() as u8);
+        for card in cards {
+            buffer.put_i32_le(*card as i32);
+        }
+    }
+
+    buffer.to_vec()
+}
+
+/// Places a stone of the player whose stones are `stone` on the board.
+pub fn create_omok_move(x: i32, y: i32, stone: u8) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(MOVE_OMOK);
+    buffer.put_i32_le(x);
+    buffer.put_i32_le(y);
+    buffer.put_u8(stone);
+
+    buffer.to_vec()
+}
+
+/// Turns the first card of a turn face up.
+pub fn create_first_card(index: u8) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(SELECT_CARD);
+    buffer.put_u8(1);
+    buffer.put_u8(index);
+
+    buffer.to_vec()
+}
+
+/// Turns the second card of a turn face up, which the player at `position` either matched with
+/// the first one or turned both back down.
+pub fn create_second_card(index: u8, first_index: u8, position: u8, matched: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(SELECT_CARD);
+    buffer.put_u8(0);
+    buffer.put_u8(index);
+    buffer.put_u8(first_index);
+    buffer.put_u8(match matched {
+        true => position + 2,
+        false => position,
+    });
+
+    buffer.to_vec()
+}
+
+/// Passes the turn on from the player at `position`.
+pub fn create_game_skip(position: u8) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(SKIP);
+    buffer.put_u8(position);
+
+    buffer.to_vec()
+}
+
+pub fn create_tie_request() -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(REQUEST_TIE);
+
+    buffer.to_vec()
+}
+
+pub fn create_tie_declined() -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(ANSWER_TIE);
+
+    buffer.to_vec()
+}
+
+/// Ends the game with its result and the records of both players after it.
+pub fn create_game_result(result: GameResult, records: &[MinigameRecord; 2]) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF5); // OPCODE
+    buffer.put_u8(GAME_RESULT);
+    match result {
+        GameResult::Win(winner) => {
+            buffer.put_u8(0);
+            buffer.put_u8(winner);
+        }
+        GameResult::Tie => buffer.put_u8(1),
+        GameResult::Forfeit(winner) => {
+            buffer.put_u8(2);
+            buffer.put_u8(winner);
+        }
+    }
+    for record in records {
+        put_record(&mut buffer, record);
+    }
+
+    buffer.to_vec()
+}
+
+fn put_record(buffer: &mut BytesMut, record: &MinigameRecord) {
+    buffer.put_i32_le(record.game as i32);
+    buffer.put_i32_le(record.wins);
+    buffer.put_i32_le(record.ties);
+    buffer.put_i32_le(record.losses);
+    buffer.put_i32_le(GAME_POINTS);
+}
+
+/// What the balloon of a room shows: its title, whether it is locked, its piece or permit and
+/// how full it is.
+fn put_balloon(buffer: &mut BytesMut, room: &Room) {
+    buffer.put_i32_le(room.room_id);
+    buffer.put_maple_string(&room.title);
+    buffer.put_u8(room.password.is_some() as u8);
+    buffer.put_u8(room.kind());
+    buffer.put_u8(room.visitors().count() as u8);
+    buffer.put_u8(room.room_type.capacity());
+    buffer.put_u8(match &room.content {
+        RoomContent::Game(game) => game.play.is_some() as u8,
+        RoomContent::Shop(_) => 0,
+    });
+}
+
+fn put_shop_items(buffer: &mut BytesMut, shop: &Shop) {
+    if let Some(merchant) = &shop.merchant {
+        buffer.put_i32_le(merchant.meso);
+    }
+    buffer.put_u8(shop.listings.len() as u8);
+    for listing in &shop.listings {
+        buffer.put_i16_le(listing.bundles);
+        buffer.put_i16_le(listing.item.quantity);
+        buffer.put_i32_le(listing.price);
+        put_item_info(buffer, &listing.item);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::game::inventory::{InventoryType, Item, CASH_EQUIP_OFFSET};
+use crate::game::item::InventoryOperation;
+use crate::net::packet::field;
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+
+/// Expiration the client shows as permanent.
+pub const PERMANENT_FILETIME: u64 = 150842304000000000;
+
+const ITEM_TYPE_EQUIP: u8 = 1;
+const ITEM_TYPE_BUNDLE: u8 = 2;
+
+/// Writes the byte position of an inventory slot, folding equipped positions back to the
+/// positive slot numbers and cash equips onto the slot they cover.
+pub fn put_item_position(buffer: &mut BytesMut, position: i16) {
+    match position {
+        position if position <= -CASH_EQUIP_OFFSET => buffer.put_u8((-position - CASH_EQUIP_OFFSET) as u8),
+        position if position < 0 => buffer.put_u8(-position as u8),
+        position => buffer.put_u8(position as u8),
+    }
+}
+
+pub fn put_item_info(buffer: &mut BytesMut, item: &Item) {
+    buffer.put_u8(match &item.equip {
+        Some(_) => ITEM_TYPE_EQUIP,
+        None => ITEM_TYPE_BUNDLE,
+    });
+    buffer.put_i32_le(item.item_id);
+    buffer.put_u8(0); // cash serial number
+    buffer.put_u64_le(match item.expiration_date {
+        Some(expiration_date) => field::to_filetime(expiration_date),
+        None => PERMANENT_FILETIME,
+    });
+
+    match &item.equip {
+        Some(stats) => {
+            buffer.put_u8(stats.upgrade_slots as u8);
+            buffer.put_u8(stats.upgrades as u8);
+            buffer.put_i16_le(stats.strength);
+            buffer.put_i16_le(stats.dexterity);
+            buffer.put_i16_le(stats.intelligence);
+            buffer.put_i16_le(stats.luck);
+            buffer.put_i16_le(stats.hp);
+            buffer.put_i16_le(stats.mp);
+            buffer.put_i16_le(stats.weapon_attack);
+            buffer.put_i16_le(stats.magic_attack);
+            buffer.put_i16_le(stats.weapon_defense);
+            buffer.put_i16_le(stats.magic_defense);
+            buffer.put_i16_le(stats.accuracy);
+            buffer.put_i16_le(stats.avoidability);
+            buffer.put_i16_le(stats.hands);
+            buffer.put_i16_le(stats.speed);
+            buffer.put_i16_le(stats.jump);
+            buffer.put_maple_string(&item.owner);
+            buffer.put_u8(item.flags as u8);
+            buffer.put_u8(0);
+            buffer.put_u64_le(0);
+        }
+        None => {
+            buffer.put_i16_le(item.quantity);
+            buffer.put_maple_string(&item.owner);
+            buffer.put_i16_le(item.flags);
+            if item.is_rechargeable() {
+                buffer.put_u64_le(0); // recharge serial number
+            }
+        }
+    }
+}
+
+pub fn put_slot_item(buffer: &mut BytesMut, position: i16, item: &Item) {
+    put_item_position(buffer, position);
+    put_item_info(buffer, item);
+}
+
+/// Builds a MODIFY_INVENTORY packet applying `operations` to the client's inventory.
+pub fn create_modify_inventory(operations: &[InventoryOperation], enable_actions: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+    let mut equip_change = None;
+
+    buffer.put_u16_le(0x18); // OPCODE
+    buffer.put_u8(enable_actions as u8);
+    buffer.put_u8(operations.len() as u8);
+
+    for operation in operations {
+        match operation {
+            InventoryOperation::Add(inventory_type, position, item) => {
+                buffer.put_u8(0);
+                buffer.put_u8(packet_inventory_type(*inventory_type));
+                buffer.put_i16_le(*position);
+                put_item_info(&mut buffer, item);
+            }
+            InventoryOperation::Quantity(inventory_type, position, quantity) => {
+                buffer.put_u8(1);
+                buffer.put_u8(packet_inventory_type(*inventory_type));
+                buffer.put_i16_le(*position);
+                buffer.put_i16_le(*quantity);
+            }
+            InventoryOperation::Move(inventory_type, source, destination) => {
+                buffer.put_u8(2);
+                buffer.put_u8(packet_inventory_type(*inventory_type));
+                buffer.put_i16_le(*source);
+                buffer.put_i16_le(*destination);
+                if *source < 0 {
+                    equip_change = Some(1);
+                } else if *destination < 0 {
+                    equip_change = Some(2);
+                }
+            }
+            InventoryOperation::Remove(inventory_type, position) => {
+                buffer.put_u8(3);
+                buffer.put_u8(packet_inventory_type(*inventory_type));
+                buffer.put_i16_le(*position);
+                if *position < 0 {
+                    equip_change = Some(2);
+                }
+            }
+        }
+    }
+
+    if let Some(equip_change) = equip_change {
+        buffer.put_u8(equip_change);
+    }
+
+    buffer.to_vec()
+}
+
+/// Equipped items are addressed as the equip tab with negative positions.
+fn packet_inventory_type(inventory_type: InventoryType) -> u8 {
+    match inventory_type {
+        InventoryType::Equipped => InventoryType::Equip as u8,
+        inventory_type => inventory_type as u8,
+    }
+}
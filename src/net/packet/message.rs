@@ -0,0 +1,73 @@
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+
+pub enum ServerMessageType {
+    Notice = 0,
+    PinkText = 5,
+    LightBlueText = 6,
+}
+
+/// The groups a chat message can be sent to besides the map.
+#[derive(Clone, Copy)]
+pub enum ChatGroup {
+    Party = 1,
+    Guild = 2,
+}
+
+impl ChatGroup {
+    pub fn from_u8(value: u8) -> Option<ChatGroup> {
+        match value {
+            1 => Some(ChatGroup::Party),
+            2 => Some(ChatGroup::Guild),
+            _ => None,
+        }
+    }
+}
+
+pub fn create_chat_text(character_id: i32, is_gm: bool, text: &str, show: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x7A); // OPCODE
+    buffer.put_i32_le(character_id);
+    buffer.put_u8(is_gm as u8);
+    buffer.put_maple_string(text);
+    buffer.put_u8(show as u8);
+
+    buffer.to_vec()
+}
+
+pub fn create_server_message(message_type: ServerMessageType, message: &str) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x41); // OPCODE
+    buffer.put_u8(message_type as u8);
+    buffer.put_maple_string(message);
+
+    buffer.to_vec()
+}
+
+/// The yellow or white EXP gain shown at the bottom right of the screen.
+pub fn create_exp_gain(amount: i32, white: bool, in_chat: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x20); // OPCODE
+    buffer.put_u8(3);
+    buffer.put_u8(white as u8);
+    buffer.put_i32_le(amount);
+    buffer.put_u8(in_chat as u8);
+    buffer.put_i32_le(0); // party bonus
+
+    buffer.to_vec()
+}
+
+/// A chat message sent to a group, shown in its own color.
+pub fn create_multi_chat(group: ChatGroup, name: &str, text: &str) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x6B); // OPCODE
+    buffer.put_u8(group as u8);
+    buffer.put_maple_string(name);
+    buffer.put_maple_string(text);
+
+    buffer.to_vec()
+}
@@ -0,0 +1,82 @@
+use crate::game::mob::Mob;
+use bytes::{BufMut, BytesMut};
+
+#[derive(Clone, Copy)]
+pub enum MobAppearance {
+    /// Fades in, for mobs that just spawned.
+    New = -2,
+    /// Shown as is, for mobs already in the map.
+    Existing = -1,
+}
+
+fn put_mob(buffer: &mut BytesMut, mob: &Mob, appearance: MobAppearance) {
+    buffer.put_i32_le(mob.object_id);
+    buffer.put_u8(5); // control status
+    buffer.put_i32_le(mob.mob_id());
+    buffer.put_i32_le(0); // status effects
+    buffer.put_i16_le(mob.position.0);
+    buffer.put_i16_le(mob.position.1);
+    buffer.put_u8(mob.stance);
+    buffer.put_i16_le(0); // origin foothold
+    buffer.put_i16_le(mob.foothold);
+    buffer.put_i8(appearance as i8);
+    buffer.put_i32_le(0);
+}
+
+pub fn create_spawn_mob(mob: &Mob, appearance: MobAppearance) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xAF); // OPCODE
+    put_mob(&mut buffer, mob, appearance);
+
+    buffer.to_vec()
+}
+
+pub fn create_kill_mob(object_id: i32, animate: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xB0); // OPCODE
+    buffer.put_i32_le(object_id);
+    buffer.put_u8(animate as u8);
+
+    buffer.to_vec()
+}
+
+/// Makes the receiving client move the mob and report its movement.
+pub fn create_control_mob(mob: &Mob) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xB1); // OPCODE
+    buffer.put_u8(1);
+    put_mob(&mut buffer, mob, MobAppearance::Existing);
+
+    buffer.to_vec()
+}
+
+pub fn create_move_mob(object_id: i32, skill: &[u8], start: (i16, i16), movement: &[u8]) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xB2); // OPCODE
+    buffer.put_i32_le(object_id);
+    buffer.put_slice(skill);
+    buffer.put_i16_le(start.0);
+    buffer.put_i16_le(start.1);
+    buffer.put_slice(movement);
+
+    buffer.to_vec()
+}
+
+/// Acknowledges a movement of the controller, which waits for it before moving the mob again.
+pub fn create_move_mob_response(mob: &Mob, move_id: i16, use_skills: bool) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xB3); // OPCODE
+    buffer.put_i32_le(mob.object_id);
+    buffer.put_i16_le(move_id);
+    buffer.put_u8(use_skills as u8);
+    buffer.put_i16_le(mob.mp.min(i16::MAX as i32) as i16);
+    buffer.put_u8(0); // skill id
+    buffer.put_u8(0); // skill level
+
+    buffer.to_vec()
+}
@@ -0,0 +1,53 @@
+pub mod buff;
+pub mod buddy;
+pub mod character;
+pub mod combat;
+pub mod drop;
+pub mod field;
+pub mod guild;
+pub mod interaction;
+pub mod item;
+pub mod message;
+pub mod mob;
+pub mod npc;
+pub mod party;
+pub mod quest;
+pub mod reactor;
+
+use bytes::{Buf, BufMut, BytesMut};
+
+pub trait PacketWriter {
+    fn put_maple_string(&mut self, value: &str);
+    fn put_padded_string(&mut self, value: &str, length: usize);
+}
+
+impl PacketWriter for BytesMut {
+    fn put_maple_string(&mut self, value: &str) {
+        self.put_u16_le(value.len() as u16);
+        self.put_slice(value.as_bytes());
+    }
+
+    fn put_padded_string(&mut self, value: &str, length: usize) {
+        let bytes = value.as_bytes();
+        let written = bytes.len().min(length);
+
+        self.put_slice(&bytes[..written]);
+        self.put_bytes(0, length - written);
+    }
+}
+
+pub fn get_maple_string(buffer: &mut &[u8]) -> Option<String> {
+    if buffer.remaining() < 2 {
+        return None;
+    }
+
+    let length = buffer.get_u16_le() as usize;
+    if buffer.remaining() < length {
+        return None;
+    }
+
+    let mut bytes = vec![0u8; length];
+    buffer.copy_to_slice(&mut bytes);
+
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,206 @@
+use crate::data::item::ItemData;
+use crate::game::map::MapNpc;
+use crate::game::inventory::{InventoryType, Item};
+use crate::game::shop::{NpcShop, ShopResult};
+use crate::game::storage::{AccountStorage, StorageResult};
+use crate::net::packet::item::put_item_info;
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+
+pub fn create_spawn_npc(npc: &MapNpc) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xC2); // OPCODE
+    buffer.put_i32_le(npc.object_id);
+    buffer.put_i32_le(npc.npc_id);
+    buffer.put_i16_le(npc.position.0);
+    buffer.put_i16_le(npc.position.1);
+    buffer.put_u8(!npc.flipped as u8);
+    buffer.put_i16_le(npc.foothold);
+    buffer.put_i16_le(npc.range.0);
+    buffer.put_i16_le(npc.range.1);
+    buffer.put_u8(1);
+
+    buffer.to_vec()
+}
+
+/// Opens the shop window; rechargeable items also carry their price per star or bullet.
+pub fn create_open_shop(shop: &NpcShop) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xEE); // OPCODE
+    buffer.put_i32_le(shop.npc_id);
+    buffer.put_u16_le(shop.items.len() as u16);
+    for shop_item in &shop.items {
+        let item_data = ItemData::get(shop_item.item_id);
+        let slot_max = item_data.as_ref().map_or(1, |item_data| item_data.slot_max);
+
+        buffer.put_i32_le(shop_item.item_id);
+        buffer.put_i32_le(shop_item.price);
+        match shop_item.item_id / 10000 {
+            207 | 233 => {
+                let unit_price = item_data.as_ref().map_or(0.0, |item_data| item_data.unit_price);
+                buffer.put_i16_le(0);
+                buffer.put_i32_le(0);
+                // The client only reads the upper bits of the double.
+                buffer.put_u16_le((unit_price.to_bits() >> 48) as u16);
+            }
+            _ => buffer.put_i16_le(1), // quantity
+        }
+        buffer.put_i16_le(slot_max);
+    }
+
+    buffer.to_vec()
+}
+
+pub fn create_shop_result(result: ShopResult) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xEF); // OPCODE
+    buffer.put_u8(result as u8);
+
+    buffer.to_vec()
+}
+
+/// Dialogs an NPC conversation shows, each answered by the player through NPC_TALK_MORE.
+pub enum NpcMessage<'a> {
+    /// Text with a "Next" button.
+    Say(&'a str),
+    YesNo(&'a str),
+    AskText(&'a str),
+    /// Text with a number field holding a default value and its bounds.
+    AskNumber(&'a str, i32, i32, i32),
+    /// Text holding `#L<index>#...#l` options to pick from.
+    AskMenu(&'a str),
+}
+
+pub fn create_npc_talk(npc_id: i32, message: &NpcMessage) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xED); // OPCODE
+    buffer.put_u8(4);
+    buffer.put_i32_le(npc_id);
+    match message {
+        NpcMessage::Say(text) => {
+            buffer.put_u8(0);
+            buffer.put_maple_string(text);
+            buffer.put_u8(0); // previous
+            buffer.put_u8(1); // next
+        }
+        NpcMessage::YesNo(text) => {
+            buffer.put_u8(1);
+            buffer.put_maple_string(text);
+        }
+        NpcMessage::AskText(text) => {
+            buffer.put_u8(2);
+            buffer.put_maple_string(text);
+            buffer.put_maple_string(""); // default
+            buffer.put_i16_le(0); // min length
+            buffer.put_i16_le(0); // max length
+        }
+        NpcMessage::AskNumber(text, default, min, max) => {
+            buffer.put_u8(3);
+            buffer.put_maple_string(text);
+            buffer.put_i32_le(*default);
+            buffer.put_i32_le(*min);
+            buffer.put_i32_le(*max);
+        }
+        NpcMessage::AskMenu(text) => {
+            buffer.put_u8(4);
+            buffer.put_maple_string(text);
+        }
+    }
+
+    buffer.to_vec()
+}
+
+/// Flag of the storage mesos in the contents a storage packet carries.
+const STORAGE_MESO: u16 = 0x02;
+/// Flags of every tab in the contents a storage packet carries.
+const STORAGE_TABS: u16 = 0x7C;
+
+/// Flag of a tab in the contents a storage packet carries.
+fn storage_tab(inventory_type: InventoryType) -> u16 {
+    2 << inventory_type as u16
+}
+
+fn put_storage_items<'a>(buffer: &mut BytesMut, items: impl Iterator<Item = &'a Item>) {
+    let items: Vec<&Item> = items.collect();
+    buffer.put_u8(items.len() as u8);
+    for item in items {
+        put_item_info(buffer, item);
+    }
+}
+
+pub fn create_open_storage(storage: &AccountStorage) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF0); // OPCODE
+    buffer.put_u8(0x16);
+    buffer.put_i32_le(storage.npc_id);
+    buffer.put_u8(storage.slots as u8);
+    buffer.put_u16_le(STORAGE_MESO | STORAGE_TABS);
+    buffer.put_u16_le(0);
+    buffer.put_i32_le(0);
+    buffer.put_i32_le(storage.meso);
+    buffer.put_u16_le(0);
+    put_storage_items(&mut buffer, storage.items.iter());
+    buffer.put_u16_le(0);
+    buffer.put_u8(0);
+
+    buffer.to_vec()
+}
+
+/// Shows the items of the tab an item was stored in or taken from.
+pub fn create_storage_tab(result: StorageResult, storage: &AccountStorage, inventory_type: InventoryType) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF0); // OPCODE
+    buffer.put_u8(result as u8);
+    buffer.put_u8(storage.slots as u8);
+    buffer.put_u16_le(storage_tab(inventory_type));
+    buffer.put_u16_le(0);
+    buffer.put_i32_le(0);
+    put_storage_items(&mut buffer, storage.items_of(inventory_type));
+
+    buffer.to_vec()
+}
+
+/// Shows every item of the storage once it was arranged.
+pub fn create_storage_arranged(storage: &AccountStorage) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF0); // OPCODE
+    buffer.put_u8(StorageResult::Arranged as u8);
+    buffer.put_u8(storage.slots as u8);
+    buffer.put_u8(STORAGE_TABS as u8);
+    buffer.put_slice(&[0; 10]);
+    put_storage_items(&mut buffer, storage.items.iter());
+    buffer.put_u8(0);
+
+    buffer.to_vec()
+}
+
+pub fn create_storage_meso(storage: &AccountStorage) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF0); // OPCODE
+    buffer.put_u8(StorageResult::MesoMoved as u8);
+    buffer.put_u8(storage.slots as u8);
+    buffer.put_u16_le(STORAGE_MESO);
+    buffer.put_u16_le(0);
+    buffer.put_i32_le(0);
+    buffer.put_i32_le(storage.meso);
+
+    buffer.to_vec()
+}
+
+/// Tells the player why a storage action failed.
+pub fn create_storage_message(result: StorageResult) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xF0); // OPCODE
+    buffer.put_u8(result as u8);
+
+    buffer.to_vec()
+}
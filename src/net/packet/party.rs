@@ -0,0 +1,150 @@
+use crate::game::party::{Party, PartyChange, PartyResult, MAX_PARTY_MEMBERS};
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+
+/// Length the client reserves for the names of party members.
+const MEMBER_NAME_LENGTH: usize = 13;
+/// Map id the client takes for no mystic door.
+const NO_DOOR_MAP: i32 = 999999999;
+/// Channel the client shows offline members in.
+const OFFLINE_CHANNEL: i32 = -2;
+
+/// Writes the members of the party padded to a full party, as seen from `channel_id`, which
+/// only shows the maps of members on the same channel.
+fn put_party_status(buffer: &mut BytesMut, channel_id: u8, party: &Party) {
+    let members = &party.members[..party.members.len().min(MAX_PARTY_MEMBERS)];
+    let padding = MAX_PARTY_MEMBERS - members.len();
+
+    for member in members {
+        buffer.put_i32_le(member.character_id);
+    }
+    buffer.put_bytes(0, 4 * padding);
+    for member in members {
+        buffer.put_padded_string(&member.name, MEMBER_NAME_LENGTH);
+    }
+    buffer.put_bytes(0, MEMBER_NAME_LENGTH * padding);
+    for member in members {
+        buffer.put_i32_le(member.job as i32);
+    }
+    buffer.put_bytes(0, 4 * padding);
+    for member in members {
+        buffer.put_i32_le(member.level as i32);
+    }
+    buffer.put_bytes(0, 4 * padding);
+    for member in members {
+        buffer.put_i32_le(member.channel_id.map_or(OFFLINE_CHANNEL, |channel_id| channel_id as i32));
+    }
+    for _ in 0..padding {
+        buffer.put_i32_le(OFFLINE_CHANNEL);
+    }
+    buffer.put_i32_le(party.leader_id);
+    for member in members {
+        match member.channel_id == Some(channel_id) {
+            true => buffer.put_i32_le(member.map_id),
+            false => buffer.put_i32_le(0),
+        }
+    }
+    buffer.put_bytes(0, 4 * padding);
+    for _ in 0..MAX_PARTY_MEMBERS {
+        buffer.put_i32_le(NO_DOOR_MAP);
+        buffer.put_i32_le(NO_DOOR_MAP);
+        buffer.put_i32_le(-1);
+        buffer.put_i32_le(-1);
+    }
+}
+
+/// Shows how the party changed to one of its members playing on `channel_id`.
+pub fn create_update_party(channel_id: u8, party: &Party, change: &PartyChange) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3B); // OPCODE
+    match change {
+        PartyChange::Created => {
+            buffer.put_u8(8);
+            buffer.put_i32_le(party.id);
+            buffer.put_i32_le(NO_DOOR_MAP);
+            buffer.put_i32_le(NO_DOOR_MAP);
+            buffer.put_i32_le(0);
+        }
+        PartyChange::Silent => {
+            buffer.put_u8(7);
+            buffer.put_i32_le(party.id);
+            put_party_status(&mut buffer, channel_id, party);
+        }
+        PartyChange::Joined(character_id) => {
+            buffer.put_u8(0xF);
+            buffer.put_i32_le(party.id);
+            buffer.put_maple_string(party.member(*character_id).map_or("", |member| &member.name));
+            put_party_status(&mut buffer, channel_id, party);
+        }
+        PartyChange::Left {
+            character_id,
+            name,
+            expelled,
+        } => {
+            buffer.put_u8(0xC);
+            buffer.put_i32_le(party.id);
+            buffer.put_i32_le(*character_id);
+            buffer.put_u8(1);
+            buffer.put_u8(*expelled as u8);
+            buffer.put_maple_string(name);
+            put_party_status(&mut buffer, channel_id, party);
+        }
+        PartyChange::Disbanded => {
+            buffer.put_u8(0xC);
+            buffer.put_i32_le(party.id);
+            buffer.put_i32_le(party.leader_id);
+            buffer.put_u8(0);
+            buffer.put_i32_le(party.id);
+        }
+        PartyChange::LeaderChanged => {
+            buffer.put_u8(0x1B);
+            buffer.put_i32_le(party.leader_id);
+            buffer.put_u8(0);
+        }
+    }
+
+    buffer.to_vec()
+}
+
+pub fn create_party_invite(party_id: i32, inviter_name: &str) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3B); // OPCODE
+    buffer.put_u8(4);
+    buffer.put_i32_le(party_id);
+    buffer.put_maple_string(inviter_name);
+    buffer.put_u8(0);
+
+    buffer.to_vec()
+}
+
+pub fn create_party_result(result: &PartyResult) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x3B); // OPCODE
+    match result {
+        PartyResult::BeginnerCannotCreate => buffer.put_u8(10),
+        PartyResult::NotInParty => buffer.put_u8(12),
+        PartyResult::AlreadyJoined => buffer.put_u8(16),
+        PartyResult::Full => buffer.put_u8(17),
+        PartyResult::NotFound => buffer.put_u8(19),
+        PartyResult::Denied(name) => {
+            buffer.put_u8(23);
+            buffer.put_maple_string(name);
+        }
+    }
+
+    buffer.to_vec()
+}
+
+pub fn create_update_party_hp(character_id: i32, hp: i16, max_hp: i16) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xA2); // OPCODE
+    buffer.put_i32_le(character_id);
+    buffer.put_i32_le(hp as i32);
+    buffer.put_i32_le(max_hp as i32);
+
+    buffer.to_vec()
+}
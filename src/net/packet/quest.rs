@@ -0,0 +1,66 @@
+use crate::game::quest::{CharacterQuests, QuestEntry, QuestState};
+use crate::net::packet::field::to_filetime;
+use crate::net::packet::PacketWriter;
+use bytes::{BufMut, BytesMut};
+use std::time::SystemTime;
+
+/// Shows the new state of a quest in the quest log; `None` for a forfeited quest.
+pub fn create_update_quest(quest_id: i32, entry: Option<&QuestEntry>) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0x20); // OPCODE
+    buffer.put_u8(1);
+    buffer.put_u16_le(quest_id as u16);
+    match entry {
+        Some(entry) => {
+            buffer.put_u8(entry.state as u8);
+            match entry.state {
+                QuestState::Started => buffer.put_maple_string(&entry.progress),
+                QuestState::Completed => buffer.put_u64_le(to_filetime(entry.completed_at.unwrap_or(SystemTime::now()))),
+                QuestState::NotStarted => buffer.put_u8(0),
+            }
+        }
+        None => {
+            buffer.put_u8(QuestState::NotStarted as u8);
+            buffer.put_u8(0);
+        }
+    }
+
+    buffer.to_vec()
+}
+
+/// Confirms a quest was started or completed at `npc_id`, opening the dialog of the quest that
+/// follows it if any.
+pub fn create_quest_result(quest_id: i32, npc_id: i32, next_quest: Option<i32>) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xA6); // OPCODE
+    buffer.put_u8(8);
+    buffer.put_u16_le(quest_id as u16);
+    buffer.put_i32_le(npc_id);
+    buffer.put_u16_le(next_quest.unwrap_or(0) as u16);
+
+    buffer.to_vec()
+}
+
+pub fn put_quests(buffer: &mut BytesMut, quests: &CharacterQuests) {
+    let started: Vec<(i32, &QuestEntry)> = quests
+        .iter()
+        .filter(|(_, entry)| entry.state == QuestState::Started)
+        .collect();
+    buffer.put_u16_le(started.len() as u16);
+    for (quest_id, entry) in started {
+        buffer.put_u16_le(quest_id as u16);
+        buffer.put_maple_string(&entry.progress);
+    }
+
+    let completed: Vec<(i32, &QuestEntry)> = quests
+        .iter()
+        .filter(|(_, entry)| entry.state == QuestState::Completed)
+        .collect();
+    buffer.put_u16_le(completed.len() as u16);
+    for (quest_id, entry) in completed {
+        buffer.put_u16_le(quest_id as u16);
+        buffer.put_u64_le(to_filetime(entry.completed_at.unwrap_or(SystemTime::now())));
+    }
+}
@@ -0,0 +1,45 @@
+use crate::game::reactor::Reactor;
+use bytes::{BufMut, BytesMut};
+
+pub fn create_spawn_reactor(reactor: &Reactor) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xD8); // OPCODE
+    buffer.put_i32_le(reactor.object_id);
+    buffer.put_i32_le(reactor.reactor_id());
+    buffer.put_u8(reactor.state);
+    buffer.put_i16_le(reactor.position.0);
+    buffer.put_i16_le(reactor.position.1);
+    buffer.put_u8(reactor.flipped as u8);
+
+    buffer.to_vec()
+}
+
+/// Plays the animation of the reactor moving on to its current state.
+pub fn create_hit_reactor(reactor: &Reactor, stance: i16) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xD6); // OPCODE
+    buffer.put_i32_le(reactor.object_id);
+    buffer.put_u8(reactor.state);
+    buffer.put_i16_le(reactor.position.0);
+    buffer.put_i16_le(reactor.position.1);
+    buffer.put_i16_le(stance);
+    buffer.put_u8(0);
+    buffer.put_u8(5); // frame delay
+
+    buffer.to_vec()
+}
+
+/// Plays the breaking animation of the final state of the reactor and removes it.
+pub fn create_destroy_reactor(reactor: &Reactor) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u16_le(0xD9); // OPCODE
+    buffer.put_i32_le(reactor.object_id);
+    buffer.put_u8(reactor.state);
+    buffer.put_i16_le(reactor.position.0);
+    buffer.put_i16_le(reactor.position.1);
+
+    buffer.to_vec()
+}
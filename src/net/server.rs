@@ -1,6 +1,9 @@
 use crate::defaults;
+use crate::net::ban_list::BanList;
 use crate::net::client;
 use crate::net::handler;
+use crate::net::throttle::{ConnectionLimiter, ConnectionLimits};
+use log::info;
 use std::error;
 use std::net::SocketAddr;
 use std::net::TcpListener;
@@ -11,6 +14,7 @@ pub struct Server {
     packet_handler: CommonHandler,
     connection_threads: usize,
     client_workers: usize,
+    connection_limiter: ConnectionLimiter,
 }
 
 impl Server {
@@ -23,13 +27,25 @@ impl Server {
         let thread_pool = ThreadPool::new(self.connection_threads);
 
         for connection in listener.incoming() {
-            let stream = match connection {
+            let (stream, permit) = match connection {
                 Ok(unwrapped_stream) => {
-                    match unwrapped_stream.peer_addr() {
-                        Ok(peer_addr) => on_new_connection(peer_addr),
-                        Err(error) => return Err(error.into()),
+                    let peer_addr = unwrapped_stream.peer_addr()?;
+
+                    if let Some(ban) = BanList::banned_address(peer_addr.ip()) {
+                        info!("refused connection from banned address [{}] (infraction {})", peer_addr, ban.id);
+                        continue;
+                    }
+
+                    let permit = match self.connection_limiter.acquire(peer_addr.ip()) {
+                        Ok(permit) => permit,
+                        Err(refusal) => {
+                            info!("refused connection from [{}] ({})", peer_addr, refusal);
+                            continue;
+                        }
                     };
-                    unwrapped_stream
+
+                    on_new_connection(peer_addr);
+                    (unwrapped_stream, permit)
                 }
                 Err(error) => {
                     return Err(error.into());
@@ -47,6 +63,7 @@ impl Server {
             
             thread_pool.execute(move || {
                 client.start(stream);
+                drop(permit);
             });
         }
         Ok(())
@@ -57,6 +74,7 @@ pub struct ServerBuilder<'a> {
     server_packet_handler: Option<&'a str>,
     client_main_thread_count: usize,
     client_workers_count: usize,
+    connection_limits: ConnectionLimits,
 }
 
 impl<'a> ServerBuilder<'a> {
@@ -65,6 +83,11 @@ impl<'a> ServerBuilder<'a> {
             server_packet_handler: None,
             client_main_thread_count: defaults::DEFAULT_CLIENT_WORKERS,
             client_workers_count: defaults::DEFAULT_CLIENT_WORKERS,
+            connection_limits: ConnectionLimits {
+                max_connections: defaults::DEFAULT_MAX_CONNECTIONS,
+                max_connections_per_ip: defaults::DEFAULT_MAX_CONNECTIONS_PER_IP,
+                connections_per_ip_per_minute: defaults::DEFAULT_CONNECTIONS_PER_IP_PER_MINUTE,
+            },
         }
     }
 
@@ -83,6 +106,11 @@ impl<'a> ServerBuilder<'a> {
         self
     }
 
+    pub fn connection_limits(&mut self, limits: ConnectionLimits) -> &mut Self {
+        self.connection_limits = limits;
+        self
+    }
+
     pub fn spawn(&self) -> Result<Server, Box<dyn error::Error>> {
         let matched_packet_handler: CommonHandler = match self.server_packet_handler {
             Some(name) => match handler::get_handler_by_name(name) {
@@ -95,6 +123,7 @@ impl<'a> ServerBuilder<'a> {
             packet_handler: matched_packet_handler,
             connection_threads: self.client_main_thread_count,
             client_workers: self.client_workers_count,
+            connection_limiter: ConnectionLimiter::new(self.connection_limits),
         })
     }
 }
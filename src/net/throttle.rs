@@ -0,0 +1,237 @@
+use log::warn;
+use once_cell::sync::OnceCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const ACCEPT_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits applied to accepted sockets; a limit of 0 disables that check.
+#[derive(Clone, Copy)]
+pub struct ConnectionLimits {
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    pub connections_per_ip_per_minute: usize,
+}
+
+pub enum ConnectionRefusal {
+    TooManyConnections,
+    TooManyConnectionsFromAddress,
+    AcceptRateExceeded,
+}
+
+impl fmt::Display for ConnectionRefusal {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionRefusal::TooManyConnections => write!(formatter, "server is full"),
+            ConnectionRefusal::TooManyConnectionsFromAddress => {
+                write!(formatter, "too many open connections from this address")
+            }
+            ConnectionRefusal::AcceptRateExceeded => {
+                write!(formatter, "too many new connections from this address")
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct ConnectionState {
+    total: usize,
+    open: HashMap<IpAddr, usize>,
+    accepted: HashMap<IpAddr, VecDeque<Instant>>,
+}
+
+pub struct ConnectionLimiter {
+    limits: ConnectionLimits,
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(limits: ConnectionLimits) -> ConnectionLimiter {
+        ConnectionLimiter {
+            limits,
+            state: Arc::new(Mutex::new(ConnectionState::default())),
+        }
+    }
+
+    /// Admits a new connection from `address`, which stays counted until the permit is dropped.
+    pub fn acquire(&self, address: IpAddr) -> Result<ConnectionPermit, ConnectionRefusal> {
+        let mut state = match self.state.lock() {
+            Ok(guard) => guard,
+            Err(error) => error.into_inner(),
+        };
+        let now = Instant::now();
+
+        state.accepted.retain(|_, accept_times| {
+            while let Some(accept_time) = accept_times.front() {
+                match now.duration_since(*accept_time) > ACCEPT_RATE_WINDOW {
+                    true => accept_times.pop_front(),
+                    false => break,
+                };
+            }
+            !accept_times.is_empty()
+        });
+
+        let recent_accepts = state.accepted.get(&address).map_or(0, |accept_times| accept_times.len());
+        let open_connections = state.open.get(&address).copied().unwrap_or(0);
+
+        if self.limits.connections_per_ip_per_minute > 0
+            && recent_accepts >= self.limits.connections_per_ip_per_minute
+        {
+            return Err(ConnectionRefusal::AcceptRateExceeded);
+        }
+
+        if self.limits.max_connections > 0 && state.total >= self.limits.max_connections {
+            return Err(ConnectionRefusal::TooManyConnections);
+        }
+
+        if self.limits.max_connections_per_ip > 0
+            && open_connections >= self.limits.max_connections_per_ip
+        {
+            return Err(ConnectionRefusal::TooManyConnectionsFromAddress);
+        }
+
+        // Only accepted connections count towards the rate, so retrying against a full server
+        // does not lock the address out as well.
+        state.accepted.entry(address).or_default().push_back(now);
+        state.total += 1;
+        *state.open.entry(address).or_insert(0) += 1;
+
+        Ok(ConnectionPermit {
+            address,
+            state: self.state.clone(),
+        })
+    }
+}
+
+pub struct ConnectionPermit {
+    address: IpAddr,
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut state = match self.state.lock() {
+            Ok(guard) => guard,
+            Err(error) => error.into_inner(),
+        };
+
+        state.total = state.total.saturating_sub(1);
+        if let Some(open_connections) = state.open.get_mut(&self.address) {
+            *open_connections -= 1;
+            if *open_connections == 0 {
+                state.open.remove(&self.address);
+            }
+        }
+    }
+}
+
+/// What failed credential checks are counted against.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum AttemptKey {
+    Address(IpAddr),
+    Account(String),
+}
+
+struct AttemptState {
+    failures: u32,
+    last_failure: Instant,
+    blocked_until: Option<Instant>,
+}
+
+/// Counts failed password and PIN checks per address and per account, blocking further
+/// attempts for a while once too many fail in a row.
+pub struct AttemptLimiter {
+    max_attempts: u32,
+    block_duration: Duration,
+    attempts: Mutex<HashMap<AttemptKey, AttemptState>>,
+}
+
+static ATTEMPT_LIMITER_INSTANCE: OnceCell<AttemptLimiter> = OnceCell::new();
+
+impl AttemptLimiter {
+    pub fn init(max_attempts: u32, block_duration: Duration) {
+        if ATTEMPT_LIMITER_INSTANCE
+            .set(AttemptLimiter {
+                max_attempts,
+                block_duration,
+                attempts: Mutex::new(HashMap::new()),
+            })
+            .is_err()
+        {
+            warn!("AttemptLimiter has already been initialized");
+        }
+    }
+
+    fn get() -> Option<&'static AttemptLimiter> {
+        match ATTEMPT_LIMITER_INSTANCE.get() {
+            Some(limiter) if limiter.max_attempts > 0 => Some(limiter),
+            _ => None,
+        }
+    }
+
+    /// How long until any of `keys` may try again, if one of them is blocked.
+    pub fn blocked_for(keys: &[AttemptKey]) -> Option<Duration> {
+        let limiter = Self::get()?;
+        let attempts = match limiter.attempts.lock() {
+            Ok(guard) => guard,
+            Err(error) => error.into_inner(),
+        };
+        let now = Instant::now();
+
+        keys.iter()
+            .filter_map(|key| attempts.get(key)?.blocked_until)
+            .filter(|blocked_until| *blocked_until > now)
+            .map(|blocked_until| blocked_until - now)
+            .max()
+    }
+
+    pub fn record_failure(keys: &[AttemptKey]) {
+        let limiter = match Self::get() {
+            Some(limiter) => limiter,
+            None => return,
+        };
+        let mut attempts = match limiter.attempts.lock() {
+            Ok(guard) => guard,
+            Err(error) => error.into_inner(),
+        };
+        let now = Instant::now();
+
+        attempts.retain(|_, state| {
+            now.duration_since(state.last_failure) < limiter.block_duration
+                || state.blocked_until.is_some_and(|blocked_until| blocked_until > now)
+        });
+
+        for key in keys {
+            let state = attempts.entry(key.clone()).or_insert(AttemptState {
+                failures: 0,
+                last_failure: now,
+                blocked_until: None,
+            });
+
+            state.failures += 1;
+            state.last_failure = now;
+
+            if state.failures >= limiter.max_attempts {
+                state.failures = 0;
+                state.blocked_until = Some(now + limiter.block_duration);
+            }
+        }
+    }
+
+    /// Forgets the failures of `keys`, after they passed a check.
+    pub fn reset(keys: &[AttemptKey]) {
+        if let Some(limiter) = Self::get() {
+            let mut attempts = match limiter.attempts.lock() {
+                Ok(guard) => guard,
+                Err(error) => error.into_inner(),
+            };
+
+            for key in keys {
+                attempts.remove(key);
+            }
+        }
+    }
+}
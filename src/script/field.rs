@@ -0,0 +1,174 @@
+use crate::data::item::ItemData;
+use crate::game::channel::Channel;
+use crate::game::map::{DropContent, DropOwnership};
+use crate::game::reactor::Reactor;
+use crate::net::client::Client;
+use crate::net::packet::field::{create_field_effect, FieldEffect};
+use crate::script::player::{self, to_i32, ScriptPlayer};
+use crate::script::{self, Scripts};
+use log::{debug, warn};
+use rhai::{EvalAltResult, INT};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Scripts of portals, maps and the other parts of a map that run without a conversation.
+struct FieldScript {
+    player: Arc<ScriptPlayer>,
+    /// Where the reactor running the script stood, for reactor scripts.
+    origin: Option<(i16, i16)>,
+}
+
+impl FieldScript {
+    /// Shows `effect` to the character, or to everyone in its map when `broadcast` is set.
+    fn show(&self, effect: FieldEffect, broadcast: bool) -> Result<(), Box<EvalAltResult>> {
+        let packet = create_field_effect(effect);
+        self.player.with_character(|client, character| {
+            if !broadcast {
+                client.send(packet);
+                return Ok(());
+            }
+
+            let map = Channel::get()?.map(character.map_id)?;
+            let map_guard = match map.lock() {
+                Ok(guard) => guard,
+                Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+            };
+            map_guard.broadcast(&packet, None);
+            Ok(())
+        })
+    }
+
+    fn spawn_mob(&self, mob_id: INT, x: INT, y: INT) -> Result<(), Box<EvalAltResult>> {
+        let mob_id = to_i32(mob_id)?;
+        let position = (to_i32(x)? as i16, to_i32(y)? as i16);
+        self.player.with_character(|_, character| {
+            let map = Channel::get()?.map(character.map_id)?;
+            let mut map_guard = match map.lock() {
+                Ok(guard) => guard,
+                Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+            };
+            map_guard.spawn_mob(mob_id, position, 0, false, None)?;
+            Ok(())
+        })
+    }
+
+    /// Drops `content` where the reactor running the script stood, owned by the character and its
+    /// party.
+    fn drop(&self, content: DropContent) -> Result<(), Box<EvalAltResult>> {
+        let origin = match self.origin {
+            Some(origin) => origin,
+            None => return Err("Only reactor scripts can drop items".into()),
+        };
+        self.player.with_character(|_, character| {
+            let channel = Channel::get()?;
+            let map = channel.map(character.map_id)?;
+            let mut map_guard = match map.lock() {
+                Ok(guard) => guard,
+                Err(error) => return Err(format!("Unable to lock Map Mutex [{}]", error).into()),
+            };
+            let ownership = DropOwnership::of_character(character.id, channel.party_id(character.id));
+            map_guard.spawn_drop(content, ownership, character.id, origin, origin);
+            Ok(())
+        })
+    }
+
+    fn drop_item(&self, item_id: INT, quantity: INT) -> Result<(), Box<EvalAltResult>> {
+        let item_id = to_i32(item_id)?;
+        let item_data = match ItemData::get(item_id) {
+            Some(item_data) => item_data,
+            None => return Err(format!("Unknown item {}", item_id).into()),
+        };
+        let quantity = match i16::try_from(quantity) {
+            Ok(quantity) if quantity > 0 && quantity <= item_data.slot_max.max(1) => quantity,
+            _ => return Err(format!("Cannot drop {} of item {}", quantity, item_id).into()),
+        };
+        self.drop(DropContent::Item(item_data.create_item(quantity)))
+    }
+
+    fn drop_meso(&self, amount: INT) -> Result<(), Box<EvalAltResult>> {
+        match to_i32(amount)? {
+            amount if amount > 0 => self.drop(DropContent::Meso(amount)),
+            amount => Err(format!("Cannot drop {} mesos", amount).into()),
+        }
+    }
+}
+
+/// Runs the script `name` of `kind` for the character of the client, returning `false` if there
+/// is no such script. No lock on the client may be held while it runs.
+fn run(
+    client: &Arc<Mutex<Client>>,
+    kind: &str,
+    name: &str,
+    origin: Option<(i16, i16)>,
+) -> Result<bool, Box<dyn Error>> {
+    let ast = match Scripts::get(kind, name)? {
+        Some(ast) => ast,
+        None => return Ok(false),
+    };
+
+    let context = Arc::new(FieldScript {
+        player: Arc::new(ScriptPlayer::new(client.clone(), format!("script {}/{}", kind, name))),
+        origin,
+    });
+    let mut engine = script::new_engine();
+    let effect_context = context.clone();
+    engine.register_fn("show_effect", move |path: &str| effect_context.show(FieldEffect::Screen(path), false));
+    let effect_context = context.clone();
+    engine.register_fn("show_map_effect", move |path: &str| effect_context.show(FieldEffect::Screen(path), true));
+    let effect_context = context.clone();
+    engine.register_fn("play_sound", move |path: &str| effect_context.show(FieldEffect::Sound(path), false));
+    let mob_context = context.clone();
+    engine.register_fn("spawn_mob", move |mob_id: INT, x: INT, y: INT| mob_context.spawn_mob(mob_id, x, y));
+    let drop_context = context.clone();
+    engine.register_fn("drop_item", move |item_id: INT, quantity: INT| drop_context.drop_item(item_id, quantity));
+    let drop_context = context.clone();
+    engine.register_fn("drop_meso", move |amount: INT| drop_context.drop_meso(amount));
+    player::register(&mut engine, context.player.clone());
+
+    match engine.run_ast(&ast) {
+        Ok(()) => Ok(true),
+        Err(error) => Err(format!("Script {}/{} failed [{}]", kind, name, error).into()),
+    }
+}
+
+/// Runs the script of a portal the character of the client used.
+pub fn run_portal(client: &Arc<Mutex<Client>>, name: &str) -> Result<(), Box<dyn Error>> {
+    match run(client, "portal", name, None)? {
+        true => Ok(()),
+        false => Err(format!("Portal script {} was not found", name).into()),
+    }
+}
+
+/// Runs the scripts of the map the character just entered. They run on their own thread, since
+/// the character is entered while its client is still locked.
+pub fn enter_map(character_id: i32, scripts: Vec<(&'static str, String)>) {
+    if scripts.is_empty() {
+        return;
+    }
+
+    thread::spawn(move || {
+        let client = match Channel::get().ok().and_then(|channel| channel.player(character_id)) {
+            Some(player) => player.client,
+            None => return debug!("Character {} left before its map scripts ran", character_id),
+        };
+
+        for (kind, name) in scripts {
+            match run(&client, kind, &name, None) {
+                Ok(true) => {}
+                Ok(false) => debug!("Map script {}/{} was not found", kind, name),
+                Err(error) => warn!("{}", error),
+            }
+        }
+    });
+}
+
+/// Runs the script of a reactor the character of the client destroyed, if it has one.
+pub fn run_reactor(client: &Arc<Mutex<Client>>, reactor: &Reactor) -> Result<(), Box<dyn Error>> {
+    let name = reactor.data.script_name();
+    if !run(client, "reactor", &name, Some(reactor.position))? {
+        debug!("Reactor {} has no script {}", reactor.reactor_id(), name);
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+pub mod field;
+pub mod npc;
+pub mod player;
+
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use rhai::{Engine, AST};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Keeps scripts stuck in a loop from running forever; waiting for the player costs nothing.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// An engine with the limits every script runs under, before any API is registered.
+pub fn new_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine
+}
+
+/// Compiled scripts, kept along with the modification time of their file so edited scripts are
+/// compiled again on their next use without restarting the server.
+pub struct Scripts {
+    directory: PathBuf,
+    cache: Mutex<HashMap<PathBuf, (SystemTime, Arc<AST>)>>,
+}
+
+static SCRIPTS_INSTANCE: OnceCell<Scripts> = OnceCell::new();
+
+impl Scripts {
+    pub fn init(directory: &Path) -> Result<(), Box<dyn Error>> {
+        if !directory.is_dir() {
+            warn!("Script directory {} was not found", directory.display());
+        }
+
+        match SCRIPTS_INSTANCE.set(Scripts {
+            directory: directory.to_path_buf(),
+            cache: Mutex::new(HashMap::new()),
+        }) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Scripts already initialized".into()),
+        }
+    }
+
+    /// The script `name` in the `kind` subdirectory, such as `npc/9010000.rhai`; `None` if there
+    /// is no such script.
+    pub fn get(kind: &str, name: &str) -> Result<Option<Arc<AST>>, Box<dyn Error>> {
+        let scripts = match SCRIPTS_INSTANCE.get() {
+            Some(scripts) => scripts,
+            None => return Err("Scripts are not initialized".into()),
+        };
+
+        let path = scripts.directory.join(kind).join(format!("{}.rhai", name));
+        let modified = match fs::metadata(&path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("Unable to read {} [{}]", path.display(), error).into()),
+        };
+
+        let mut cache = match scripts.cache.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock Scripts Mutex [{}]", error).into()),
+        };
+        if let Some((compiled_at, ast)) = cache.get(&path) {
+            if *compiled_at == modified {
+                return Ok(Some(ast.clone()));
+            }
+        }
+
+        let ast = match Engine::new().compile_file(path.clone()) {
+            Ok(ast) => Arc::new(ast),
+            Err(error) => return Err(format!("Unable to compile {} [{}]", path.display(), error).into()),
+        };
+        info!("compiled {}", path.display());
+        cache.insert(path, (modified, ast.clone()));
+        Ok(Some(ast))
+    }
+}
@@ -0,0 +1,349 @@
+use crate::game::character;
+use crate::game::guild::{self, GuildRequest};
+use crate::game::merchant;
+use crate::game::quest;
+use crate::game::storage;
+use crate::net::client::{Client, PacketSender};
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::guild as guild_packet;
+use crate::net::packet::npc::{create_npc_talk, NpcMessage};
+use crate::script::player::{self, to_i32, ScriptPlayer};
+use crate::script::{self, Scripts};
+use log::{debug, warn};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Position, Scope, AST, INT};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Conversations the player leaves unanswered for this long are ended.
+const CONVERSATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// What the player answered to the last dialog of a conversation.
+pub enum NpcAnswer {
+    /// The player closed the dialog.
+    End,
+    Yes,
+    No,
+    Selection(i32),
+    Text(String),
+}
+
+/// A conversation between a player and an NPC, driven by the script of the NPC on its own thread.
+struct Conversation {
+    npc_id: i32,
+    player: Arc<ScriptPlayer>,
+    sender: PacketSender,
+    answers: Mutex<Receiver<NpcAnswer>>,
+}
+
+impl Conversation {
+    /// Shows `message` and waits for the answer of the player, ending the script if they closed
+    /// the dialog, walked away or took too long.
+    fn ask(&self, message: NpcMessage) -> Result<NpcAnswer, Box<EvalAltResult>> {
+        self.sender.send(create_npc_talk(self.npc_id, &message));
+
+        let answers = match self.answers.lock() {
+            Ok(guard) => guard,
+            Err(error) => return Err(format!("Unable to lock Receiver Mutex [{}]", error).into()),
+        };
+        match answers.recv_timeout(CONVERSATION_TIMEOUT) {
+            Ok(NpcAnswer::End) => Err(ended()),
+            Ok(answer) => Ok(answer),
+            Err(RecvTimeoutError::Timeout) => {
+                debug!("Conversation with NPC {} timed out", self.npc_id);
+                Err(ended())
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(ended()),
+        }
+    }
+
+    fn say(&self, text: &str) -> Result<(), Box<EvalAltResult>> {
+        match self.ask(NpcMessage::Say(text))? {
+            NpcAnswer::Yes => Ok(()),
+            _ => Err(ended()),
+        }
+    }
+
+    fn yes_no(&self, text: &str) -> Result<bool, Box<EvalAltResult>> {
+        match self.ask(NpcMessage::YesNo(text))? {
+            NpcAnswer::Yes => Ok(true),
+            NpcAnswer::No => Ok(false),
+            _ => Err("Expected a yes or no answer".into()),
+        }
+    }
+
+    /// Lists `options` below `text` and returns the index of the chosen one.
+    fn ask_menu(&self, text: &str, options: Array) -> Result<INT, Box<EvalAltResult>> {
+        let mut menu = text.to_string();
+        for (index, option) in options.iter().enumerate() {
+            menu.push_str(&format!("\r\n#L{}#{}#l", index, option));
+        }
+
+        match self.ask(NpcMessage::AskMenu(&menu))? {
+            NpcAnswer::Selection(selection) if selection >= 0 && (selection as usize) < options.len() => {
+                Ok(selection as INT)
+            }
+            _ => Err(format!("Expected one of {} options", options.len()).into()),
+        }
+    }
+
+    fn ask_number(&self, text: &str, default: INT, min: INT, max: INT) -> Result<INT, Box<EvalAltResult>> {
+        let (default, min, max) = (to_i32(default)?, to_i32(min)?, to_i32(max)?);
+        match self.ask(NpcMessage::AskNumber(text, default, min, max))? {
+            NpcAnswer::Selection(number) if (min..=max).contains(&number) => Ok(number as INT),
+            _ => Err(format!("Expected a number between {} and {}", min, max).into()),
+        }
+    }
+
+    fn ask_text(&self, text: &str) -> Result<String, Box<EvalAltResult>> {
+        match self.ask(NpcMessage::AskText(text))? {
+            NpcAnswer::Text(answer) => Ok(answer),
+            _ => Err("Expected a text answer".into()),
+        }
+    }
+
+    /// Starts or completes the quest the way talking to the NPC in the quest log would, returning
+    /// whether the character met its requirements.
+    fn update_quest(&self, quest_id: INT, completing: bool) -> Result<bool, Box<EvalAltResult>> {
+        let quest_id = to_i32(quest_id)?;
+        let npc_id = self.npc_id;
+        self.player.with_character(|client, character| {
+            let mut inventory = character::lock_inventory(client)?;
+            let mut quests = character::lock_quests(client)?;
+            let result = match completing {
+                true => quest::complete(client, character, &mut inventory, &mut quests, quest_id, npc_id),
+                false => quest::start(client, character, &mut inventory, &mut quests, quest_id, npc_id),
+            };
+            match result {
+                Ok(()) => Ok(true),
+                Err(error) => {
+                    debug!("Script of NPC {} could not update quest {} [{}]", npc_id, quest_id, error);
+                    Ok(false)
+                }
+            }
+        })
+    }
+
+    fn forfeit_quest(&self, quest_id: INT) -> Result<bool, Box<EvalAltResult>> {
+        let quest_id = to_i32(quest_id)?;
+        self.player.with_character(|client, _| {
+            let mut quests = character::lock_quests(client)?;
+            Ok(quest::forfeit(client, &mut quests, quest_id).is_ok())
+        })
+    }
+
+    /// Lets the player name the guild it creates. The client asks the world server to create it
+    /// once the name is entered.
+    fn open_guild_creation(&self) {
+        self.sender.send(guild_packet::create_guild_name_dialog());
+    }
+
+    fn open_guild_emblem_editor(&self) {
+        self.sender.send(guild_packet::create_guild_emblem_dialog());
+    }
+
+    /// Disbands the guild of the player, returning whether it is the guild master.
+    fn disband_guild(&self) -> Result<bool, Box<EvalAltResult>> {
+        let character_id = self.player.with_character(|_, character| Ok(character.id))?;
+        let result = guild::rank(character_id).and_then(|rank| match rank == guild::MASTER_RANK {
+            true => guild::request(character_id, GuildRequest::Disband).map(|_| true),
+            false => Ok(false),
+        });
+        result.map_err(|error| error.to_string().into())
+    }
+
+    fn storage_slots(&self) -> Result<INT, Box<EvalAltResult>> {
+        self.player
+            .with_character(|_, character| storage::slots(character).map(|slots| slots as INT))
+    }
+
+    /// Adds `slots` slots to the storage of the account of the player for `cost` mesos, returning
+    /// whether it could pay for them and the storage had room to grow.
+    fn expand_storage(&self, slots: INT, cost: INT) -> Result<bool, Box<EvalAltResult>> {
+        let slots = match i16::try_from(slots) {
+            Ok(slots) => slots,
+            Err(_) => return Ok(false),
+        };
+        let cost = to_i32(cost)?;
+        let npc_id = self.npc_id;
+        self.player.with_character(|client, character| {
+            let expanded = storage::expand(character, npc_id, slots, cost)?;
+            if expanded {
+                client.send(create_update_stats(character, &[Stat::Meso], false));
+            }
+            Ok(expanded)
+        })
+    }
+
+    /// Where the hired merchant of the player is: 0 when it has none, 1 when it is open and 2
+    /// when its items and mesos are waiting here.
+    fn merchant_status(&self) -> Result<INT, Box<EvalAltResult>> {
+        self.player
+            .with_character(|_, character| merchant::status(character.id).map(|status| status as INT))
+    }
+
+    /// Gives the player what its closed merchant held, returning whether anything was waiting
+    /// and the player had room for it.
+    fn retrieve_merchant(&self) -> Result<bool, Box<EvalAltResult>> {
+        self.player.with_character(|client, character| {
+            let mut inventory = character::lock_inventory(client)?;
+            merchant::retrieve(client, character, &mut inventory)
+        })
+    }
+}
+
+/// The error scripts are stopped with once their conversation is over.
+fn ended() -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorTerminated(Dynamic::UNIT, Position::NONE))
+}
+
+fn is_ended(error: &EvalAltResult) -> bool {
+    match error {
+        EvalAltResult::ErrorTerminated(..) => true,
+        EvalAltResult::ErrorInFunctionCall(_, _, inner, _) => is_ended(inner),
+        _ => false,
+    }
+}
+
+fn create_engine(conversation: Arc<Conversation>) -> Engine {
+    let mut engine = script::new_engine();
+
+    let context = conversation.clone();
+    engine.register_fn("say", move |text: &str| context.say(text));
+    let context = conversation.clone();
+    engine.register_fn("yes_no", move |text: &str| context.yes_no(text));
+    let context = conversation.clone();
+    engine.register_fn("ask_menu", move |text: &str, options: Array| context.ask_menu(text, options));
+    let context = conversation.clone();
+    engine.register_fn("ask_number", move |text: &str, default: INT, min: INT, max: INT| {
+        context.ask_number(text, default, min, max)
+    });
+    let context = conversation.clone();
+    engine.register_fn("ask_text", move |text: &str| context.ask_text(text));
+    let context = conversation.clone();
+    engine.register_fn("start_quest", move |quest_id: INT| context.update_quest(quest_id, false));
+    let context = conversation.clone();
+    engine.register_fn("complete_quest", move |quest_id: INT| context.update_quest(quest_id, true));
+    let context = conversation.clone();
+    engine.register_fn("forfeit_quest", move |quest_id: INT| context.forfeit_quest(quest_id));
+    let context = conversation.clone();
+    engine.register_fn("open_guild_creation", move || context.open_guild_creation());
+    let context = conversation.clone();
+    engine.register_fn("open_guild_emblem_editor", move || context.open_guild_emblem_editor());
+    let context = conversation.clone();
+    engine.register_fn("disband_guild", move || context.disband_guild());
+    let context = conversation.clone();
+    engine.register_fn("storage_slots", move || context.storage_slots());
+    let context = conversation.clone();
+    engine.register_fn("expand_storage", move |slots: INT, cost: INT| context.expand_storage(slots, cost));
+    let context = conversation.clone();
+    engine.register_fn("merchant_status", move || context.merchant_status());
+    let context = conversation.clone();
+    engine.register_fn("retrieve_merchant", move || context.retrieve_merchant());
+    player::register(&mut engine, conversation.player.clone());
+
+    engine
+}
+
+/// Runs a conversation with `npc_id` on its own thread and makes the client send its answers
+/// there. Quest scripts are entered through their `start` or `end` function, which is given the
+/// quest id.
+fn converse(
+    client_mutex: &Arc<Mutex<Client>>,
+    client: &Client,
+    npc_id: i32,
+    ast: Arc<AST>,
+    entry: Option<(&'static str, i32)>,
+) -> Result<(), Box<dyn Error>> {
+    let sender = match client.sender() {
+        Some(sender) => sender,
+        None => return Err("Unable to talk to NPC with a disconnected client".into()),
+    };
+
+    let (answer_sender, answer_receiver) = mpsc::channel();
+    let conversation = Arc::new(Conversation {
+        npc_id,
+        player: Arc::new(ScriptPlayer::new(client_mutex.clone(), format!("NPC {}", npc_id))),
+        sender,
+        answers: Mutex::new(answer_receiver),
+    });
+
+    let client_mutex = client_mutex.clone();
+    thread::spawn(move || {
+        let engine = create_engine(conversation);
+        let result = match entry {
+            Some((function, quest_id)) => engine
+                .call_fn::<Dynamic>(&mut Scope::new(), &ast, function, (quest_id as INT,))
+                .map(|_| ()),
+            None => engine.run_ast(&ast),
+        };
+
+        match result {
+            Ok(()) => {}
+            Err(error) if is_ended(&error) => debug!("Conversation with NPC {} ended", npc_id),
+            Err(error) => {
+                warn!("Script of NPC {} failed [{}]", npc_id, error);
+                let result = character::with_character(&client_mutex, |client, character| {
+                    client.send(create_update_stats(character, &[], true));
+                    Ok(())
+                });
+                if let Err(error) = result {
+                    warn!("Unable to enable actions [{}]", error);
+                }
+            }
+        }
+    });
+
+    match client.conversation.lock() {
+        Ok(mut conversation) => *conversation = Some(answer_sender),
+        Err(error) => return Err(format!("Unable to lock Conversation Mutex [{}]", error).into()),
+    };
+    Ok(())
+}
+
+/// Starts the script of `npc_id`, returning `false` if the NPC has none.
+pub fn start(client_mutex: &Arc<Mutex<Client>>, client: &Client, npc_id: i32) -> Result<bool, Box<dyn Error>> {
+    match Scripts::get("npc", &npc_id.to_string())? {
+        Some(ast) => converse(client_mutex, client, npc_id, ast, None).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// Starts the script of a scripted quest at `npc_id`, returning `false` if there is none.
+pub fn start_quest(
+    client_mutex: &Arc<Mutex<Client>>,
+    client: &Client,
+    npc_id: i32,
+    quest_id: i32,
+    completing: bool,
+) -> Result<bool, Box<dyn Error>> {
+    let function = match completing {
+        true => "end",
+        false => "start",
+    };
+    match Scripts::get("quest", &quest_id.to_string())? {
+        Some(ast) => converse(client_mutex, client, npc_id, ast, Some((function, quest_id))).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// Passes the answer of the player on to the conversation they are in.
+pub fn answer(client: &Client, answer: NpcAnswer) -> Result<(), Box<dyn Error>> {
+    let mut conversation = match client.conversation.lock() {
+        Ok(guard) => guard,
+        Err(error) => return Err(format!("Unable to lock Conversation Mutex [{}]", error).into()),
+    };
+
+    let ending = matches!(answer, NpcAnswer::End);
+    let result = match conversation.as_ref() {
+        Some(answers) => answers.send(answer).map_err(|_| "Conversation is already over".into()),
+        None => Err("Not in a conversation".into()),
+    };
+    if ending || result.is_err() {
+        *conversation = None;
+    }
+    result
+}
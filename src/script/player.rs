@@ -0,0 +1,180 @@
+use crate::data::item::ItemData;
+use crate::data::map::MapData;
+use crate::db::model::character::Character;
+use crate::db::model::meso_log::MesoReason;
+use crate::game::character;
+use crate::game::experience;
+use crate::game::guild::{self, GuildRequest};
+use crate::game::item;
+use crate::game::meso;
+use crate::net::client::Client;
+use crate::net::packet::character::{create_update_stats, Stat};
+use crate::net::packet::item::create_modify_inventory;
+use crate::net::packet::message::{create_server_message, ServerMessageType};
+use log::debug;
+use rhai::{Engine, EvalAltResult, INT};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// The character a script runs for, along with the part of the scripting API every kind of
+/// script shares.
+pub struct ScriptPlayer {
+    client: Arc<Mutex<Client>>,
+    /// What runs the script, such as `NPC 9010000`, for the meso log.
+    source: String,
+}
+
+pub fn to_i32(value: INT) -> Result<i32, Box<EvalAltResult>> {
+    i32::try_from(value).map_err(|_| format!("{} is out of range", value).into())
+}
+
+impl ScriptPlayer {
+    pub fn new(client: Arc<Mutex<Client>>, source: String) -> ScriptPlayer {
+        ScriptPlayer { client, source }
+    }
+
+    pub fn with_character<T>(
+        &self,
+        action: impl FnOnce(&Client, &mut Character) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<EvalAltResult>> {
+        character::with_character(&self.client, action).map_err(|error| error.to_string().into())
+    }
+
+    /// Warps the character to the portal named `portal` of the map, or its first portal when
+    /// there is no such portal.
+    fn warp(&self, map_id: INT, portal: &str) -> Result<(), Box<EvalAltResult>> {
+        let map_id = to_i32(map_id)?;
+        let spawn_point = MapData::get(map_id)
+            .and_then(|data| data.portal(portal).map(|portal| portal.id))
+            .unwrap_or(0);
+        self.with_character(|client, character| character::change_map(client, character, map_id, spawn_point))
+    }
+
+    /// Gives `quantity` of the item, or takes it away when `quantity` is negative. Returns
+    /// whether the inventory had room for it, or held enough of it.
+    fn give_item(&self, item_id: INT, quantity: INT) -> Result<bool, Box<EvalAltResult>> {
+        let item_id = to_i32(item_id)?;
+        let item_data = match ItemData::get(item_id) {
+            Some(item_data) => item_data,
+            None => return Err(format!("Unknown item {}", item_id).into()),
+        };
+        let quantity = match i16::try_from(quantity) {
+            Ok(quantity) if quantity != 0 && quantity <= item_data.slot_max.max(1) => quantity,
+            _ => return Err(format!("Cannot give {} of item {}", quantity, item_id).into()),
+        };
+
+        self.with_character(|client, character| {
+            let mut inventory = character::lock_inventory(client)?;
+            let result = match quantity > 0 {
+                true => item::add_item(&mut inventory, item_data.create_item(quantity)),
+                false => item::remove_item(&mut inventory, item_id, -quantity),
+            };
+            match result {
+                Ok(operations) => {
+                    client.send(create_modify_inventory(&operations, false));
+                    Ok(true)
+                }
+                Err(error) => {
+                    debug!("Unable to give {} of item {} to {} [{}]", quantity, item_id, character.name, error);
+                    Ok(false)
+                }
+            }
+        })
+    }
+
+    fn has_item(&self, item_id: INT) -> Result<bool, Box<EvalAltResult>> {
+        let item_id = to_i32(item_id)?;
+        self.with_character(|client, _| Ok(item::has_item(&*character::lock_inventory(client)?, item_id)))
+    }
+
+    fn give_exp(&self, amount: INT) -> Result<(), Box<EvalAltResult>> {
+        let amount = to_i32(amount)?;
+        if amount <= 0 {
+            return Err(format!("Cannot give {} EXP", amount).into());
+        }
+        self.with_character(|client, character| experience::gain_exp(client, character, amount, true))
+    }
+
+    /// Gives mesos, or takes them away when `amount` is negative. Returns whether the character
+    /// could hold, or had, that many.
+    fn give_meso(&self, amount: INT) -> Result<bool, Box<EvalAltResult>> {
+        let amount = to_i32(amount)?;
+        self.with_character(|client, character| {
+            let meso = match character.meso.checked_add(amount) {
+                Some(meso) if meso >= 0 => meso,
+                _ => return Ok(false),
+            };
+
+            character.meso = meso;
+            meso::log(character, amount, MesoReason::Script, &format!("given by {}", self.source));
+            client.send(create_update_stats(character, &[Stat::Meso], false));
+            Ok(true)
+        })
+    }
+
+    fn quest_state(&self, quest_id: INT) -> Result<INT, Box<EvalAltResult>> {
+        let quest_id = to_i32(quest_id)?;
+        self.with_character(|client, _| Ok(character::lock_quests(client)?.state(quest_id) as INT))
+    }
+
+    /// The rank of the character in its guild, from 1 for the master down to 5, or 0 when it has
+    /// none.
+    fn guild_rank(&self) -> Result<INT, Box<EvalAltResult>> {
+        let character_id = self.with_character(|_, character| Ok(character.id))?;
+        guild::rank(character_id).map(|rank| rank as INT).map_err(|error| error.to_string().into())
+    }
+
+    /// Gives guild points to the guild of the character, returning whether it has one.
+    fn give_guild_points(&self, amount: INT) -> Result<bool, Box<EvalAltResult>> {
+        let amount = to_i32(amount)?;
+        if self.guild_rank()? == 0 {
+            return Ok(false);
+        }
+        let character_id = self.with_character(|_, character| Ok(character.id))?;
+        match guild::request(character_id, GuildRequest::GainPoints(amount)) {
+            Ok(()) => Ok(true),
+            Err(error) => Err(error.to_string().into()),
+        }
+    }
+
+    fn message(&self, text: &str) -> Result<(), Box<EvalAltResult>> {
+        self.with_character(|client, _| {
+            client.send(create_server_message(ServerMessageType::PinkText, text));
+            Ok(())
+        })
+    }
+}
+
+pub fn register(engine: &mut Engine, player: Arc<ScriptPlayer>) {
+    let context = player.clone();
+    engine.register_fn("warp", move |map_id: INT| context.warp(map_id, ""));
+    let context = player.clone();
+    engine.register_fn("warp", move |map_id: INT, portal: &str| context.warp(map_id, portal));
+    let context = player.clone();
+    engine.register_fn("give_item", move |item_id: INT, quantity: INT| context.give_item(item_id, quantity));
+    let context = player.clone();
+    engine.register_fn("has_item", move |item_id: INT| context.has_item(item_id));
+    let context = player.clone();
+    engine.register_fn("give_exp", move |amount: INT| context.give_exp(amount));
+    let context = player.clone();
+    engine.register_fn("give_meso", move |amount: INT| context.give_meso(amount));
+    let context = player.clone();
+    engine.register_fn("quest_state", move |quest_id: INT| context.quest_state(quest_id));
+    let context = player.clone();
+    engine.register_fn("guild_rank", move || context.guild_rank());
+    let context = player.clone();
+    engine.register_fn("give_guild_points", move |amount: INT| context.give_guild_points(amount));
+    let context = player.clone();
+    engine.register_fn("message", move |text: &str| context.message(text));
+    let context = player.clone();
+    engine.register_fn("level", move || context.with_character(|_, character| Ok(character.level as INT)));
+    let context = player.clone();
+    engine.register_fn("job", move || context.with_character(|_, character| Ok(character.job as INT)));
+    let context = player.clone();
+    engine.register_fn("meso", move || context.with_character(|_, character| Ok(character.meso as INT)));
+    let context = player.clone();
+    engine.register_fn("map", move || context.with_character(|_, character| Ok(character.map_id as INT)));
+    let context = player;
+    engine.register_fn("name", move || context.with_character(|_, character| Ok(character.name.clone())));
+}